@@ -0,0 +1,183 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{Bytes, H256, U256},
+};
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    bot::{
+        executor::{FreshnessGuard, GasBudget},
+        executor_manager::DryRunExecutor,
+        signer_pool::{required_funds, SignerPool},
+    },
+    types::{Action, Executor},
+    utils::config::skip_if_writes_disabled,
+};
+
+/// One ready-to-relay arb, as handed to an external submitter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileBundle {
+    pub tx_hash: H256,
+    /// Signed raw transaction, RLP encoded.
+    pub raw_tx: Bytes,
+    /// Block the tx should land in: the one after the latest when it was signed.
+    pub target_block: u64,
+    pub quoted_at_block: u64,
+    /// The tx being backrun, for relay bids.
+    pub opp_tx_hash: Option<H256>,
+    /// Raw units of the profit token, as a hex quantity.
+    pub bid_amount: Option<U256>,
+}
+
+/// Signs arb actions and writes them as JSON bundles instead of sending them, for users who relay through
+/// their own tooling. If `target` is a directory each bundle goes to its own `<target_block>-<tx_hash>.json`,
+/// written under a temporary name and renamed so watchers never see a partial file. Otherwise (e.g. a
+/// named pipe) bundles are appended to it one JSON object per line.
+///
+/// Stale quotes are dropped and the gas budget is honoured as in `PublicTxExecutor`, and txs are signed by the
+/// same `SignerPool`, so bundles and public sends share each wallet's nonces.
+pub struct FileBundleSubmitter {
+    client: Provider<Http>,
+    signers: Arc<SignerPool>,
+    freshness: FreshnessGuard,
+    gas_budget: Arc<GasBudget>,
+    target: PathBuf,
+}
+
+impl FileBundleSubmitter {
+    pub fn new(
+        rpc_url: &str,
+        signers: Arc<SignerPool>,
+        max_quote_age_blocks: u64,
+        gas_budget: Arc<GasBudget>,
+        target: PathBuf,
+    ) -> Result<Self> {
+        Ok(Self {
+            client: Provider::<Http>::try_from(rpc_url)?,
+            signers,
+            freshness: FreshnessGuard::new(max_quote_age_blocks),
+            gas_budget,
+            target,
+        })
+    }
+}
+
+/// Write `bundle` to `target` as `FileBundleSubmitter` describes; returns the file written.
+fn write_bundle(target: &Path, bundle: &FileBundle) -> Result<PathBuf> {
+    if target.is_dir() {
+        let name = format!("{}-{:?}.json", bundle.target_block, bundle.tx_hash);
+        let path = target.join(&name);
+        let tmp = target.join(format!(".{name}.tmp"));
+        fs::write(&tmp, serde_json::to_vec_pretty(bundle)?).with_context(|| format!("write {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("rename to {}", path.display()))?;
+        return Ok(path);
+    }
+
+    let mut line = serde_json::to_vec(bundle)?;
+    line.push(b'\n');
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(target)
+        .and_then(|mut file| file.write_all(&line))
+        .with_context(|| format!("append to {}", target.display()))?;
+    Ok(target.to_path_buf())
+}
+
+#[async_trait]
+impl Executor<Action> for FileBundleSubmitter {
+    fn name(&self) -> &str {
+        "FileBundleSubmitter"
+    }
+
+    async fn execute(&self, action: Action) -> Result<()> {
+        let (quoted, opp_tx_hash, bid_amount) = match action {
+            Action::ExecutePublicTx(quoted) => (quoted, None, None),
+            Action::MevRelaySubmitBid((quoted, bid_amount, opp_tx_hash)) => {
+                (quoted, Some(opp_tx_hash), Some(bid_amount))
+            }
+            Action::NotifyViaTelegram(_) => return Ok(()),
+        };
+
+        let quoted_at_block = quoted.quoted_at_block;
+        let latest_block = self.client.get_block_number().await?.as_u64();
+        self.freshness.check_at(quoted_at_block, latest_block)?;
+        self.gas_budget.check(latest_block)?;
+
+        let tx = quoted.typed_tx();
+        let signer = self.signers.next_funded_with(required_funds(&tx), quoted.own_funds).await?;
+        let Some((_, tx_hash, raw_tx)) = skip_if_writes_disabled(signer.sign_transaction(tx).await)? else {
+            return Ok(());
+        };
+        let bundle = FileBundle {
+            tx_hash,
+            raw_tx,
+            target_block: latest_block + 1,
+            quoted_at_block,
+            opp_tx_hash,
+            bid_amount,
+        };
+        let path = write_bundle(&self.target, &bundle)?;
+        info!(?tx_hash, target_block = bundle.target_block, path = %path.display(), "Wrote arb bundle");
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DryRunExecutor for FileBundleSubmitter {
+    async fn dry_run(&self, action: &Action) -> Result<()> {
+        let Some(quoted) = action.quoted_tx() else {
+            return Ok(());
+        };
+        self.freshness.check(&self.client, quoted.quoted_at_block).await?;
+        let mut tx = quoted.typed_tx();
+        tx.set_from(self.signers.primary());
+        self.client.estimate_gas(&tx, None).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_bundle_to_dir_and_pipe() {
+        let dir = std::env::temp_dir().join(format!("bundles-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let bundle = FileBundle {
+            tx_hash: H256::from_low_u64_be(1),
+            raw_tx: Bytes::from(vec![0x02, 0xf8]),
+            target_block: 101,
+            quoted_at_block: 100,
+            opp_tx_hash: Some(H256::from_low_u64_be(2)),
+            // 20 WAVAX, more than a u64 holds
+            bid_amount: Some(U256::exp10(18) * 20),
+        };
+
+        let path = write_bundle(&dir, &bundle).unwrap();
+        assert!(path.file_name().unwrap().to_str().unwrap().starts_with("101-"));
+        let written: FileBundle = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(written, bundle);
+        // no temp files left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        let stream = dir.join("stream");
+        write_bundle(&stream, &bundle).unwrap();
+        write_bundle(&stream, &bundle).unwrap();
+        assert_eq!(fs::read_to_string(&stream).unwrap().lines().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
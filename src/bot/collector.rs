@@ -1,5 +1,5 @@
 use crate::engine::{async_trait, Collector, CollectorStream};
-use eyre::Result;
+use eyre::{Result, WrapErr};
 use futures::stream::StreamExt;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -36,7 +36,7 @@ impl Collector<Event> for AvaxMempoolCollector {
     async fn get_event_stream(&self) -> Result<CollectorStream<'_, Event>> {
         let (ws_stream, _) = tokio_tungstenite::connect_async(&self.ws_url)
             .await
-            .expect("Failed to connect to AVAX WebSocket");
+            .wrap_err("Failed to connect to AVAX WebSocket")?;
 
         // 订阅pending交易
         let (mut sink, read) = ws_stream.split();
@@ -48,7 +48,7 @@ impl Collector<Event> for AvaxMempoolCollector {
         });
         
         futures::SinkExt::send(&mut sink, Message::Text(subscribe_msg.to_string())).await
-            .expect("Failed to send subscription");
+            .wrap_err("Failed to send subscription")?;
 
         let stream = async_stream::stream! {
             pin!(read);
@@ -79,3 +79,27 @@ impl Collector<Event> for AvaxMempoolCollector {
         Ok(Box::pin(stream))
     }
 }
+
+/// Turns SIGUSR1 (`kill -USR1 <pid>`) into `Event::DumpRequested`, so the strategy dumps its in-flight
+/// opportunities when the signal arrives rather than on the next mempool event.
+pub struct DumpSignalCollector;
+
+#[async_trait]
+impl Collector<Event> for DumpSignalCollector {
+    fn name(&self) -> &str {
+        "DumpSignalCollector"
+    }
+
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, Event>> {
+        let mut signals = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined1())
+            .wrap_err("Failed to install SIGUSR1 handler")?;
+
+        let stream = async_stream::stream! {
+            while signals.recv().await.is_some() {
+                yield Event::DumpRequested;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
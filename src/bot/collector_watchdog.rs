@@ -0,0 +1,161 @@
+use std::time::{Duration, Instant};
+
+use ethers::providers::{Http, Middleware, Provider};
+use eyre::Result;
+use futures::stream::StreamExt;
+use tracing::{info, warn};
+
+use crate::engine::{async_trait, Collector, CollectorStream};
+
+/// Wait before resubscribing after a failed subscription, so a down endpoint isn't hammered.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// Event-flow liveness of one subscription. A subscription is stalled when no event has arrived for
+/// `stall_timeout` although the chain moved past the block at which it went quiet; a quiet but halted
+/// chain (e.g. a paused local fork) isn't a stall.
+#[derive(Debug)]
+struct Liveness {
+    stall_timeout: Duration,
+    last_event: Instant,
+    quiet_since_block: Option<u64>,
+}
+
+impl Liveness {
+    fn new(stall_timeout: Duration, now: Instant) -> Self {
+        Self {
+            stall_timeout,
+            last_event: now,
+            quiet_since_block: None,
+        }
+    }
+
+    fn on_event(&mut self, now: Instant) {
+        self.last_event = now;
+        self.quiet_since_block = None;
+    }
+
+    /// Record the chain head seen at `now`; `true` if the subscription is stalled.
+    fn on_tick(&mut self, now: Instant, block: u64) -> bool {
+        let Some(quiet_since_block) = self.quiet_since_block else {
+            self.quiet_since_block = Some(block);
+            return false;
+        };
+        now.duration_since(self.last_event) >= self.stall_timeout && block > quiet_since_block
+    }
+}
+
+enum Next<E> {
+    Event(E),
+    Ended,
+    Tick,
+}
+
+/// Wraps a collector and rebuilds its subscription when it ends, or goes silent while the chain keeps
+/// advancing. Some providers stop delivering on a subscription without closing or erroring it; only
+/// actual event flow tells that apart from a healthy connection.
+pub struct CollectorWatchdog<C> {
+    inner: C,
+    provider: Provider<Http>,
+    stall_timeout: Duration,
+}
+
+impl<C> CollectorWatchdog<C> {
+    pub fn new(inner: C, rpc_url: &str, stall_timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            inner,
+            provider: Provider::<Http>::try_from(rpc_url)?,
+            stall_timeout,
+        })
+    }
+}
+
+#[async_trait]
+impl<E, C> Collector<E> for CollectorWatchdog<C>
+where
+    E: Send + 'static,
+    C: Collector<E>,
+{
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, E>> {
+        let name = self.inner.name();
+        let mut events = self.inner.get_event_stream().await?;
+
+        let stream = async_stream::stream! {
+            loop {
+                let mut liveness = Liveness::new(self.stall_timeout, Instant::now());
+                let mut ticks = tokio::time::interval((self.stall_timeout / 4).max(Duration::from_millis(100)));
+
+                loop {
+                    let next = tokio::select! {
+                        event = events.next() => event.map_or(Next::Ended, Next::Event),
+                        _ = ticks.tick() => Next::Tick,
+                    };
+                    match next {
+                        Next::Event(event) => {
+                            liveness.on_event(Instant::now());
+                            yield event;
+                        }
+                        Next::Ended => {
+                            warn!(collector = name, "event stream ended, resubscribing");
+                            break;
+                        }
+                        Next::Tick => match self.provider.get_block_number().await {
+                            Ok(block) if liveness.on_tick(Instant::now(), block.as_u64()) => {
+                                warn!(
+                                    collector = name,
+                                    silent_for = ?liveness.last_event.elapsed(),
+                                    block = block.as_u64(),
+                                    "no events while the chain advanced, resubscribing"
+                                );
+                                break;
+                            }
+                            Ok(_) => {}
+                            Err(error) => warn!(collector = name, ?error, "failed to fetch block number for liveness"),
+                        },
+                    }
+                }
+
+                events = loop {
+                    match self.inner.get_event_stream().await {
+                        Ok(events) => break events,
+                        Err(error) => {
+                            warn!(collector = name, "resubscribe failed: {error:#}");
+                            tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+                        }
+                    }
+                };
+                info!(collector = name, "resubscribed");
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stall_needs_silence_and_chain_progress() {
+        let start = Instant::now();
+        let timeout = Duration::from_secs(30);
+        let mut liveness = Liveness::new(timeout, start);
+
+        // first tick only records where the quiet period started
+        assert!(!liveness.on_tick(start + Duration::from_secs(10), 100));
+        // chain moved, but not silent for long enough
+        assert!(!liveness.on_tick(start + Duration::from_secs(20), 102));
+        // silent long enough, but the chain is halted
+        assert!(!liveness.on_tick(start + Duration::from_secs(40), 100));
+        assert!(liveness.on_tick(start + Duration::from_secs(40), 103));
+
+        // an event resets both the clock and the reference block
+        liveness.on_event(start + Duration::from_secs(41));
+        assert!(!liveness.on_tick(start + Duration::from_secs(80), 110));
+        assert!(liveness.on_tick(start + Duration::from_secs(80), 111));
+    }
+}
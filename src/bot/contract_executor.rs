@@ -8,6 +8,8 @@ use tracing::{info, warn};
 
 // 导入合约绑定
 use crate::bindings::avaxarbexecutor::{AvaxArbExecutor, ArbParams};
+use crate::dex::{PoolReserves, UniswapV2Calculator};
+use crate::utils::config::ensure_writes_enabled;
 
 /// 套利路径编码器
 pub struct SwapDataEncoder;
@@ -43,6 +45,15 @@ impl SwapDataEncoder {
         data
     }
     
+    /// 编码区块构建者报酬：合约解包 `amount` 的 wrapped native 并付给 `block.coinbase`
+    pub fn encode_coinbase_payment(wrapped_native: Address, amount: U256) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.push(4u8); // 构建者报酬类型
+        data.extend_from_slice(wrapped_native.as_bytes());
+        data.extend_from_slice(&amount.to_be_bytes_vec());
+        data
+    }
+    
     /// 组合多个操作
     pub fn encode_multi_swap(operations: Vec<Vec<u8>>) -> Bytes {
         let mut result = Vec::new();
@@ -56,6 +67,31 @@ impl SwapDataEncoder {
     }
 }
 
+/// `allowance(address,address)` selector.
+const ALLOWANCE: [u8; 4] = [0xdd, 0x62, 0xed, 0x3e];
+
+/// Approve amounts, in order, that let a spender with `current` allowance pull `amount`. A fresh allowance is
+/// set straight to max; a non-zero but insufficient one is reset to zero first, because USDT-style tokens
+/// revert on changing one non-zero allowance to another. Standard tokens accept both steps.
+pub fn approval_steps(current: U256, amount: U256) -> Vec<U256> {
+    if current >= amount {
+        vec![]
+    } else if current.is_zero() {
+        vec![U256::MAX]
+    } else {
+        vec![U256::zero(), U256::MAX]
+    }
+}
+
+/// One UniswapV2-style hop of an arb path, with the pair's reserves oriented in the trade direction.
+#[derive(Debug, Clone)]
+pub struct V2Hop {
+    pub pair: Address,
+    pub token_in: Address,
+    pub token_out: Address,
+    pub reserves: PoolReserves,
+}
+
 /// 套利参数构建器
 #[derive(Debug, Clone)]
 pub struct ArbParamsBuilder {
@@ -84,6 +120,25 @@ impl ArbParamsBuilder {
         self
     }
     
+    /// Swap `amount_in` through `hops` in order: pay each pair its input, then take the quoted output.
+    pub fn add_v2_path(mut self, hops: &[V2Hop]) -> Result<Self> {
+        let mut amount = self.amount_in;
+        for hop in hops {
+            let amount_out = UniswapV2Calculator::get_amount_out(amount, &hop.reserves)?;
+            // pairs order their tokens by address
+            let (amount0_out, amount1_out) = if hop.token_out < hop.token_in {
+                (amount_out, U256::zero())
+            } else {
+                (U256::zero(), amount_out)
+            };
+            self = self
+                .add_transfer(hop.token_in, hop.pair, amount)
+                .add_v2_swap(hop.pair, amount0_out, amount1_out);
+            amount = amount_out;
+        }
+        Ok(self)
+    }
+    
     pub fn add_transfer(mut self, token: Address, to: Address, amount: U256) -> Self {
         self.swap_operations.push(SwapDataEncoder::encode_transfer(token, to, amount));
         self
@@ -94,6 +149,24 @@ impl ArbParamsBuilder {
         self
     }
     
+    /// Approve `spender` for `amount` of `token` inside the arb tx, given the contract's `current` allowance.
+    /// Add it before the swap that pulls the token.
+    pub fn ensure_approval(mut self, token: Address, spender: Address, current: U256, amount: U256) -> Self {
+        for step in approval_steps(current, amount) {
+            self = self.add_approve(token, spender, step);
+        }
+        self
+    }
+    
+    /// Pay the block builder `amount` wei out of the arb, unwrapped from `wrapped_native`. Add it last: the
+    /// contract checks `min_profit` after it, so an arb that can't afford the payment reverts without paying.
+    pub fn pay_coinbase(mut self, wrapped_native: Address, amount: U256) -> Self {
+        if !amount.is_zero() {
+            self.swap_operations.push(SwapDataEncoder::encode_coinbase_payment(wrapped_native, amount));
+        }
+        self
+    }
+    
     pub fn min_profit(mut self, min_profit: U256) -> Self {
         self.min_profit = min_profit;
         self
@@ -133,6 +206,7 @@ impl<M: Middleware> ContractArbExecutor<M> {
         info!("执行套利交易: token_in={:?}, amount_in={}, profit_token={:?}", 
               params.token_in, params.amount_in, params.profit_token);
         
+        ensure_writes_enabled("send executeArb")?;
         let call = self.contract.execute_arb(params);
         let pending_tx = call.send().await?;
         let receipt = pending_tx.await?;
@@ -154,6 +228,7 @@ impl<M: Middleware> ContractArbExecutor<M> {
         info!("执行闪电贷套利: token_in={:?}, amount_in={}, profit_token={:?}", 
               params.token_in, params.amount_in, params.profit_token);
         
+        ensure_writes_enabled("send executeArbWithFlash")?;
         let call = self.contract.execute_arb_with_flash(params);
         let pending_tx = call.send().await?;
         let receipt = pending_tx.await?;
@@ -170,6 +245,36 @@ impl<M: Middleware> ContractArbExecutor<M> {
         }
     }
     
+    /// Allowance the arb contract has given `spender` on `token`.
+    pub async fn allowance(&self, token: Address, spender: Address) -> Result<U256> {
+        let mut data = ALLOWANCE.to_vec();
+        data.extend(ethers::abi::encode(&[
+            ethers::abi::Token::Address(self.contract.address()),
+            ethers::abi::Token::Address(spender),
+        ]));
+        let tx: TypedTransaction = TransactionRequest::new().to(token).data(data).into();
+        let output = self.client.call(&tx, None).await.map_err(|e| eyre::eyre!("allowance of {token:?}: {e}"))?;
+        eyre::ensure!(output.len() >= 32, "short allowance output from {token:?}");
+        Ok(U256::from_big_endian(&output[..32]))
+    }
+    
+    /// Add the approvals the arb contract is missing for each `(token, spender, amount)` to `builder`, so a
+    /// first-touch token is approved in the same tx instead of reverting it.
+    pub async fn with_approvals(
+        &self,
+        mut builder: ArbParamsBuilder,
+        approvals: &[(Address, Address, U256)],
+    ) -> Result<ArbParamsBuilder> {
+        for &(token, spender, amount) in approvals {
+            let current = self.allowance(token, spender).await?;
+            if current < amount {
+                info!(?token, ?spender, %current, "approving in arb tx");
+            }
+            builder = builder.ensure_approval(token, spender, current, amount);
+        }
+        Ok(builder)
+    }
+    
     /// 获取合约owner
     pub async fn get_owner(&self) -> Result<Address> {
         let owner = self.contract.owner().call().await?;
@@ -180,6 +285,7 @@ impl<M: Middleware> ContractArbExecutor<M> {
     pub async fn emergency_withdraw(&self, token: Address) -> Result<TransactionReceipt> {
         warn!("执行紧急提取: token={:?}", token);
         
+        ensure_writes_enabled("send emergencyWithdraw")?;
         let call = self.contract.emergency_withdraw(token);
         let pending_tx = call.send().await?;
         let receipt = pending_tx.await?;
@@ -225,6 +331,24 @@ mod tests {
         assert!(encoded.len() > 1);
     }
     
+    #[test]
+    fn test_approval_steps() {
+        let amount = U256::from(1000);
+        assert!(approval_steps(U256::MAX, amount).is_empty());
+        assert!(approval_steps(amount, amount).is_empty());
+        assert_eq!(approval_steps(U256::zero(), amount), vec![U256::MAX]);
+        // USDT-style: reset to zero before setting a new non-zero allowance
+        assert_eq!(approval_steps(U256::from(1), amount), vec![U256::zero(), U256::MAX]);
+        
+        let token = Address::from_low_u64_be(1);
+        let spender = Address::from_low_u64_be(2);
+        let builder = ArbParamsBuilder::new(token, amount, token).ensure_approval(token, spender, U256::from(1), amount);
+        assert_eq!(builder.swap_operations, vec![
+            SwapDataEncoder::encode_approve(token, spender, U256::zero()),
+            SwapDataEncoder::encode_approve(token, spender, U256::MAX),
+        ]);
+    }
+    
     #[test]
     fn test_arb_params_builder() {
         let token_in = Address::from_low_u64_be(1);
@@ -242,4 +366,36 @@ mod tests {
         assert_eq!(params.profit_token, token_out);
         assert_eq!(params.min_profit, U256::from(100));
     }
+    
+    #[test]
+    fn test_add_v2_path() {
+        let wavax = Address::from_low_u64_be(1);
+        let usdc = Address::from_low_u64_be(2);
+        let pair_a = Address::from_low_u64_be(3);
+        let pair_b = Address::from_low_u64_be(4);
+        let amount = U256::from(1000);
+        let hops = [
+            V2Hop { pair: pair_a, token_in: wavax, token_out: usdc, reserves: PoolReserves::new(U256::from(100_000), U256::from(200_000), 30) },
+            V2Hop { pair: pair_b, token_in: usdc, token_out: wavax, reserves: PoolReserves::new(U256::from(150_000), U256::from(100_000), 30) },
+        ];
+        
+        let builder = ArbParamsBuilder::new(wavax, amount, wavax).add_v2_path(&hops).unwrap();
+        let out_a = UniswapV2Calculator::get_amount_out(amount, &hops[0].reserves).unwrap();
+        let out_b = UniswapV2Calculator::get_amount_out(out_a, &hops[1].reserves).unwrap();
+        assert_eq!(builder.swap_operations, vec![
+            SwapDataEncoder::encode_transfer(wavax, pair_a, amount),
+            // usdc sorts after wavax, so it is token1
+            SwapDataEncoder::encode_v2_swap(pair_a, U256::zero(), out_a),
+            SwapDataEncoder::encode_transfer(usdc, pair_b, out_a),
+            SwapDataEncoder::encode_v2_swap(pair_b, out_b, U256::zero()),
+        ]);
+        
+        let wrapped_native = Address::from_low_u64_be(5);
+        let paid = builder.clone().pay_coinbase(wrapped_native, U256::from(7));
+        assert_eq!(paid.swap_operations.last(), Some(&SwapDataEncoder::encode_coinbase_payment(wrapped_native, U256::from(7))));
+        assert_eq!(builder.clone().pay_coinbase(wrapped_native, U256::zero()).swap_operations, builder.swap_operations);
+        
+        let empty = V2Hop { reserves: PoolReserves::new(U256::zero(), U256::zero(), 30), ..hops[0].clone() };
+        assert!(ArbParamsBuilder::new(wavax, amount, wavax).add_v2_path(&[empty]).is_err());
+    }
 }
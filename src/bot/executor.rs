@@ -7,11 +7,23 @@ use ethers::{
     providers::{Http, Middleware, Provider},
     signers::{LocalWallet, Signer},
 };
-use std::sync::Arc;
-use tracing::info;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
 
 use crate::contract_executor::{ContractArbExecutor, ArbParamsBuilder};
 use crate::bindings::avaxarbexecutor::ArbParams;
+use crate::bot::executor_manager::DryRunExecutor;
+use crate::bot::signer_pool::{required_funds, SignerPool};
+use crate::common::webhook::{WebhookEvent, WebhookNotifier};
+use crate::engine::executors::mempool_executor::{GasPriceStrategy, MempoolExecutor};
+use crate::types::Action;
+use crate::utils::config::{ensure_writes_enabled, skip_if_writes_disabled};
 
 /// 套利执行动作类型
 #[derive(Debug, Clone)]
@@ -23,31 +35,316 @@ pub enum ArbAction {
         token_in: Address,
         amount_in: U256,
         swap_path: Vec<(Address, U256, U256)>, // (pair, amount0_out, amount1_out)
+        /// `(token, spender, amount)` allowances the swaps pull with `transferFrom`; missing ones are
+        /// approved inside the arb tx.
+        approvals: Vec<(Address, Address, U256)>,
         profit_token: Address,
         min_profit: U256,
         use_flash: bool,
     },
 }
 
+/// 报价新鲜度检查：丢弃基于超过 `max_block_age` 个区块之前储备报价的交易
+pub struct FreshnessGuard {
+    max_block_age: u64,
+    dropped: AtomicU64,
+}
+
+impl FreshnessGuard {
+    pub fn new(max_block_age: u64) -> Self {
+        Self {
+            max_block_age,
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_fresh(&self, quoted_at_block: u64, latest_block: u64) -> bool {
+        latest_block.saturating_sub(quoted_at_block) <= self.max_block_age
+    }
+
+    /// Returns `Ok(())` if the quote is still fresh, otherwise counts the drop and errors.
+    pub async fn check<M: Middleware>(&self, client: &M, quoted_at_block: u64) -> Result<()>
+    where
+        M::Error: 'static,
+    {
+        let latest_block = client.get_block_number().await?.as_u64();
+        self.check_at(quoted_at_block, latest_block)
+    }
+
+    /// `check` against an already fetched `latest_block`.
+    pub fn check_at(&self, quoted_at_block: u64, latest_block: u64) -> Result<()> {
+        if self.is_fresh(quoted_at_block, latest_block) {
+            return Ok(());
+        }
+
+        let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        warn!(
+            quoted_at_block,
+            latest_block,
+            max_block_age = self.max_block_age,
+            dropped,
+            "Dropping stale action"
+        );
+        eyre::bail!(
+            "stale quote: computed at block {}, latest block {}",
+            quoted_at_block,
+            latest_block
+        )
+    }
+
+    /// Number of actions dropped for staleness so far.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+const GAS_BUDGET_HOUR: Duration = Duration::from_secs(3600);
+
+struct GasBudgetState {
+    block: u64,
+    block_spent: U256,
+    hour_start: Instant,
+    hour_spent: U256,
+    paused: bool,
+}
+
+/// Gas 支出上限：单个区块或一小时内累计花费的 AVAX 超过预算后拒绝发送, 窗口滚动后恢复
+pub struct GasBudget {
+    per_block: Option<U256>,
+    per_hour: Option<U256>,
+    state: Mutex<GasBudgetState>,
+}
+
+impl GasBudget {
+    /// Budgets are in wei; `None` disables that window.
+    pub fn new(per_block: Option<U256>, per_hour: Option<U256>) -> Self {
+        Self {
+            per_block,
+            per_hour,
+            state: Mutex::new(GasBudgetState {
+                block: 0,
+                block_spent: U256::zero(),
+                hour_start: Instant::now(),
+                hour_spent: U256::zero(),
+                paused: false,
+            }),
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(None, None)
+    }
+
+    fn roll(&self, state: &mut GasBudgetState, block: u64, now: Instant) {
+        if block > state.block {
+            state.block = block;
+            state.block_spent = U256::zero();
+        }
+        if now.duration_since(state.hour_start) >= GAS_BUDGET_HOUR {
+            state.hour_start = now;
+            state.hour_spent = U256::zero();
+        }
+    }
+
+    /// Returns `Ok(())` if there is budget left in the current windows.
+    pub fn check(&self, block: u64) -> Result<()> {
+        self.check_at(block, Instant::now())
+    }
+
+    fn check_at(&self, block: u64, now: Instant) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        self.roll(&mut state, block, now);
+
+        let block_exceeded = self.per_block.is_some_and(|budget| state.block_spent >= budget);
+        let hour_exceeded = self.per_hour.is_some_and(|budget| state.hour_spent >= budget);
+        if !block_exceeded && !hour_exceeded {
+            if state.paused {
+                state.paused = false;
+                info!(block, "Gas budget window rolled over, resuming");
+            }
+            return Ok(());
+        }
+
+        if !state.paused {
+            state.paused = true;
+            warn!(
+                block,
+                block_spent = %state.block_spent,
+                hour_spent = %state.hour_spent,
+                per_block = ?self.per_block,
+                per_hour = ?self.per_hour,
+                "Gas budget exhausted, pausing submissions"
+            );
+        }
+        eyre::bail!(
+            "gas budget exhausted: block spent {}, hour spent {}",
+            state.block_spent,
+            state.hour_spent
+        )
+    }
+
+    /// Record the actual `gas_used × effective_gas_price` of a mined tx.
+    pub fn record_receipt(&self, receipt: &TransactionReceipt) {
+        let gas_cost = receipt.gas_used.unwrap_or_default() * receipt.effective_gas_price.unwrap_or_default();
+        let block = receipt.block_number.map(|b| b.as_u64()).unwrap_or_default();
+        self.record_at(block, gas_cost, Instant::now());
+    }
+
+    fn record_at(&self, block: u64, gas_cost: U256, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        self.roll(&mut state, block, now);
+        // a receipt from an older block only counts towards the hour
+        if block == state.block {
+            state.block_spent = state.block_spent.saturating_add(gas_cost);
+        }
+        state.hour_spent = state.hour_spent.saturating_add(gas_cost);
+    }
+}
+
+/// 自有资金仓位上限：非闪电贷套利的 `amount_in` 不得超过资金账户余额的 `max_balance_fraction`
+#[derive(Debug, Clone, Copy)]
+pub struct PositionGuard {
+    max_balance_fraction: f64,
+}
+
+impl PositionGuard {
+    pub fn new(max_balance_fraction: f64) -> Self {
+        Self {
+            max_balance_fraction: max_balance_fraction.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Largest `amount_in` allowed for a funding account holding `balance`.
+    pub fn max_amount_in(&self, balance: U256) -> U256 {
+        // parts-per-million keeps the math in integers
+        let ppm = (self.max_balance_fraction * 1_000_000.0) as u64;
+        balance * U256::from(ppm) / U256::from(1_000_000u64)
+    }
+
+    /// `amount_in` clamped to the allowed position, or an error if nothing can be traded.
+    pub fn clamp(&self, amount_in: U256, balance: U256) -> Result<U256> {
+        let max = self.max_amount_in(balance);
+        eyre::ensure!(!max.is_zero(), "no funds for own-funds trade: balance {}", balance);
+        if amount_in > max {
+            warn!(%amount_in, %balance, %max, "amount_in exceeds position limit, clamping");
+        }
+        Ok(amount_in.min(max))
+    }
+
+    /// Reject `amount_in` above the allowed position. Used when the trade was built for an exact
+    /// amount and cannot be resized.
+    pub fn check(&self, amount_in: U256, balance: U256) -> Result<()> {
+        let max = self.max_amount_in(balance);
+        if amount_in > max {
+            warn!(%amount_in, %balance, %max, "Skipping own-funds arb above position limit");
+            eyre::bail!("amount_in {} exceeds position limit {} (balance {})", amount_in, max, balance);
+        }
+        Ok(())
+    }
+}
+
+/// ERC20 余额（`token` 为零地址时返回原生 AVAX 余额）
+async fn token_balance<M: Middleware>(client: &M, token: Address, account: Address) -> Result<U256>
+where
+    M::Error: 'static,
+{
+    if token.is_zero() {
+        return Ok(client.get_balance(account, None).await?);
+    }
+
+    let mut data = ethers::utils::id("balanceOf(address)").to_vec();
+    data.extend(ethers::abi::encode(&[ethers::abi::Token::Address(account)]));
+    let tx: TypedTransaction = ethers::types::TransactionRequest::new().to(token).data(data).into();
+    let output = client.call(&tx, None).await?;
+    eyre::ensure!(output.len() >= 32, "invalid balanceOf output");
+    Ok(U256::from_big_endian(&output[..32]))
+}
+
+/// Sends actions to the public mempool, rotating over the signer pool so concurrent arbs don't
+/// serialize behind one nonce.
 pub struct PublicTxExecutor {
-    client: SignerMiddleware<Provider<Http>, LocalWallet>,
+    client: Provider<Http>,
+    signers: Arc<SignerPool>,
+    freshness: FreshnessGuard,
+    gas_budget: Arc<GasBudget>,
+    webhook: Option<WebhookNotifier>,
+    position_guard: Option<PositionGuard>,
+    /// Prices txs from recent blocks instead of keeping their quoted gas price; `None` keeps it.
+    gas_pricer: Option<MempoolExecutor<Provider<Http>>>,
 }
 
 impl PublicTxExecutor {
-    pub async fn new(rpc_url: &str, private_key: &str) -> Result<Self> {
-        let provider = Provider::<Http>::try_from(rpc_url)?;
-        let wallet: LocalWallet = private_key.parse()?;
-        let client = SignerMiddleware::new(provider, wallet);
-        
-        Ok(Self { client })
+    pub fn new(
+        rpc_url: &str,
+        signers: Arc<SignerPool>,
+        max_quote_age_blocks: u64,
+        gas_budget: Arc<GasBudget>,
+    ) -> Result<Self> {
+        Ok(Self {
+            client: Provider::<Http>::try_from(rpc_url)?,
+            signers,
+            freshness: FreshnessGuard::new(max_quote_age_blocks),
+            gas_budget,
+            webhook: None,
+            position_guard: None,
+            gas_pricer: None,
+        })
+    }
+
+    /// Re-price every tx right before sending it per `strategy`. `GasPriceStrategy::Node` keeps the quoted price.
+    pub fn with_gas_price_strategy(mut self, strategy: GasPriceStrategy) -> Self {
+        self.gas_pricer = match strategy {
+            GasPriceStrategy::Node => None,
+            strategy => Some(MempoolExecutor::with_gas_price_strategy(Arc::new(self.client.clone()), strategy)),
+        };
+        self
+    }
+
+    /// Skip own-funds arbs whose `amount_in` exceeds `position_guard`'s share of the sending signer's balance.
+    pub fn with_position_guard(mut self, position_guard: PositionGuard) -> Self {
+        self.position_guard = Some(position_guard);
+        self
+    }
+
+    /// Post every mined tx to `webhook` as a fill.
+    pub fn with_webhook(mut self, webhook: Option<WebhookNotifier>) -> Self {
+        self.webhook = webhook;
+        self
     }
 
     pub async fn execute_tx(&self, tx: TypedTransaction) -> Result<TransactionReceipt> {
-        let pending_tx = self.client.send_transaction(tx, None).await?;
-        let receipt = pending_tx.await?;
-        
+        self.send(tx, None).await
+    }
+
+    /// Send `tx` from the next signer that can fund it. `own_funds` is the `(token_in, amount_in)` an own-funds
+    /// arb spends from its sender: the signer is picked by that balance too, and the position limit is
+    /// checked against it.
+    async fn send(&self, mut tx: TypedTransaction, own_funds: Option<(Address, U256)>) -> Result<TransactionReceipt> {
+        let latest_block = self.client.get_block_number().await?.as_u64();
+        self.gas_budget.check(latest_block)?;
+        if let Some(pricer) = &self.gas_pricer {
+            let gas_price = pricer.gas_price().await.map_err(|e| eyre::eyre!("{e:#}"))?;
+            tx.set_gas_price(gas_price);
+        }
+
+        ensure_writes_enabled(&format!("send tx to {:?}", tx.to()))?;
+        // each wallet pays its own gas and value, so only one that can afford the tx sends it
+        let signer = self.signers.next_funded_with(required_funds(&tx), own_funds).await?;
+        // 自有资金套利：swap 路径按确切的 amount_in 构建，无法缩小，超限直接跳过
+        if let (Some(guard), Some((token_in, amount_in))) = (&self.position_guard, own_funds) {
+            guard.check(amount_in, signer.token_balance(token_in).await?)?;
+        }
+        tx.set_from(signer.address());
+        let receipt = signer.send_transaction(tx).await?;
+
         match receipt {
-            Some(receipt) => Ok(receipt),
+            Some(receipt) => {
+                self.gas_budget.record_receipt(&receipt);
+                if let Some(webhook) = &self.webhook {
+                    webhook.spawn_notify(WebhookEvent::Fill((&receipt).into()));
+                }
+                Ok(receipt)
+            }
             None => eyre::bail!("Transaction failed to get receipt"),
         }
     }
@@ -60,7 +357,9 @@ impl Executor<TypedTransaction> for PublicTxExecutor {
     }
 
     async fn execute(&self, action: TypedTransaction) -> Result<()> {
-        let receipt = self.execute_tx(action).await?;
+        let Some(receipt) = skip_if_writes_disabled(self.execute_tx(action).await)? else {
+            return Ok(());
+        };
         let tx_hash = receipt.transaction_hash;
 
         info!(
@@ -74,16 +373,70 @@ impl Executor<TypedTransaction> for PublicTxExecutor {
     }
 }
 
+#[async_trait]
+impl Executor<Action> for PublicTxExecutor {
+    fn name(&self) -> &str {
+        "AvaxPublicActionExecutor"
+    }
+
+    async fn execute(&self, action: Action) -> Result<()> {
+        let quoted = match action {
+            Action::ExecutePublicTx(quoted) => quoted,
+            Action::MevRelaySubmitBid((quoted, _, _)) => quoted,
+            Action::NotifyViaTelegram(_) => return Ok(()),
+        };
+
+        // 发送前最后一刻检查报价是否过期
+        self.freshness.check(&self.client, quoted.quoted_at_block).await?;
+
+        let Some(receipt) = skip_if_writes_disabled(self.send(quoted.typed_tx(), quoted.own_funds).await)? else {
+            return Ok(());
+        };
+        info!(
+            tx_hash = ?receipt.transaction_hash,
+            status = ?receipt.status,
+            quoted_at_block = quoted.quoted_at_block,
+            "Executed AVAX action"
+        );
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DryRunExecutor for PublicTxExecutor {
+    async fn dry_run(&self, action: &Action) -> Result<()> {
+        let Some(quoted) = action.quoted_tx() else {
+            return Ok(());
+        };
+        self.freshness.check(&self.client, quoted.quoted_at_block).await?;
+        let mut tx = quoted.typed_tx();
+        tx.set_from(self.signers.primary());
+        self.client.estimate_gas(&tx, None).await?;
+        Ok(())
+    }
+}
+
 /// 增强的套利执行器，支持合约和直接交易
 pub struct EnhancedArbExecutor {
     client: Arc<SignerMiddleware<Provider<Http>, LocalWallet>>,
     contract_executor: Option<ContractArbExecutor<SignerMiddleware<Provider<Http>, LocalWallet>>>,
+    contract_address: Option<Address>,
+    gas_budget: Arc<GasBudget>,
+    position_guard: PositionGuard,
 }
 
 impl EnhancedArbExecutor {
-    pub async fn new(rpc_url: &str, private_key: &str, contract_address: Option<Address>) -> Result<Self> {
+    pub async fn new(
+        rpc_url: &str,
+        private_key: &str,
+        contract_address: Option<Address>,
+        gas_budget: Arc<GasBudget>,
+        position_guard: PositionGuard,
+    ) -> Result<Self> {
         let provider = Provider::<Http>::try_from(rpc_url)?;
-        let wallet: LocalWallet = private_key.parse()?;
+        let chain_id = provider.get_chainid().await?.as_u64();
+        let wallet: LocalWallet = private_key.parse::<LocalWallet>()?.with_chain_id(chain_id);
         let client = Arc::new(SignerMiddleware::new(provider, wallet));
         
         let contract_executor = match contract_address {
@@ -91,11 +444,40 @@ impl EnhancedArbExecutor {
             None => None,
         };
         
-        Ok(Self { client, contract_executor })
+        Ok(Self {
+            client,
+            contract_executor,
+            contract_address,
+            gas_budget,
+            position_guard,
+        })
     }
     
     /// 执行套利动作
     pub async fn execute_arb_action(&self, action: ArbAction) -> Result<TransactionReceipt> {
+        let latest_block = self.client.get_block_number().await?.as_u64();
+        self.gas_budget.check(latest_block)?;
+
+        // 自有资金套利：swap 路径按确切的 amount_in 构建，无法缩小，超限直接跳过
+        if let ArbAction::ContractArb {
+            token_in,
+            amount_in,
+            use_flash: false,
+            ..
+        } = &action
+        {
+            let funder = self.contract_address.ok_or_else(|| eyre::eyre!("合约执行器未初始化"))?;
+            let balance = token_balance(&self.client, *token_in, funder).await?;
+            self.position_guard.check(*amount_in, balance)?;
+        }
+
+        let receipt = self.send_arb_action(action).await?;
+        self.gas_budget.record_receipt(&receipt);
+        Ok(receipt)
+    }
+
+    async fn send_arb_action(&self, action: ArbAction) -> Result<TransactionReceipt> {
+        ensure_writes_enabled("send arb action")?;
         match action {
             ArbAction::DirectTx(tx) => {
                 let pending_tx = self.client.send_transaction(tx, None).await?;
@@ -106,6 +488,7 @@ impl EnhancedArbExecutor {
                 token_in,
                 amount_in,
                 swap_path,
+                approvals,
                 profit_token,
                 min_profit,
                 use_flash,
@@ -114,8 +497,10 @@ impl EnhancedArbExecutor {
                     .as_ref()
                     .ok_or_else(|| eyre::eyre!("合约执行器未初始化"))?;
                 
-                let mut builder = ArbParamsBuilder::new(token_in, amount_in, profit_token)
+                let builder = ArbParamsBuilder::new(token_in, amount_in, profit_token)
                     .min_profit(min_profit);
+                // 授权需在拉取代币的交换之前
+                let mut builder = contract_executor.with_approvals(builder, &approvals).await?;
                 
                 // 构建交换路径
                 for (pair, amount0_out, amount1_out) in swap_path {
@@ -141,7 +526,9 @@ impl Executor<ArbAction> for EnhancedArbExecutor {
     }
 
     async fn execute(&self, action: ArbAction) -> Result<()> {
-        let receipt = self.execute_arb_action(action.clone()).await?;
+        let Some(receipt) = skip_if_writes_disabled(self.execute_arb_action(action.clone()).await)? else {
+            return Ok(());
+        };
         let tx_hash = receipt.transaction_hash;
 
         match action {
@@ -167,3 +554,56 @@ impl Executor<ArbAction> for EnhancedArbExecutor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_freshness_guard_window() {
+        let guard = FreshnessGuard::new(2);
+        assert!(guard.is_fresh(100, 100));
+        assert!(guard.is_fresh(100, 102));
+        assert!(!guard.is_fresh(100, 103));
+        // a quote from a block ahead of our view of the chain is never stale
+        assert!(guard.is_fresh(105, 103));
+    }
+
+    #[test]
+    fn test_gas_budget_windows() {
+        let start = Instant::now();
+        let budget = GasBudget::new(Some(U256::from(100)), Some(U256::from(250)));
+
+        budget.check_at(1, start).unwrap();
+        budget.record_at(1, U256::from(100), start);
+        // per-block budget spent
+        assert!(budget.check_at(1, start).is_err());
+
+        // next block resets the block window
+        budget.check_at(2, start).unwrap();
+        budget.record_at(2, U256::from(100), start);
+        budget.check_at(3, start).unwrap();
+        budget.record_at(3, U256::from(50), start);
+        // 250 spent this hour
+        assert!(budget.check_at(4, start).is_err());
+
+        // hour rolls over
+        budget.check_at(4, start + GAS_BUDGET_HOUR).unwrap();
+
+        GasBudget::unlimited().check_at(1, start).unwrap();
+    }
+
+    #[test]
+    fn test_position_guard() {
+        let guard = PositionGuard::new(0.5);
+        let balance = U256::from(1_000);
+
+        assert_eq!(guard.max_amount_in(balance), U256::from(500));
+        assert!(guard.check(U256::from(500), balance).is_ok());
+        assert!(guard.check(U256::from(501), balance).is_err());
+
+        assert_eq!(guard.clamp(U256::from(800), balance).unwrap(), U256::from(500));
+        assert_eq!(guard.clamp(U256::from(100), balance).unwrap(), U256::from(100));
+        assert!(guard.clamp(U256::from(100), U256::zero()).is_err());
+    }
+}
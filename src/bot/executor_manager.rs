@@ -0,0 +1,266 @@
+use std::time::Instant;
+
+use async_trait::async_trait;
+use eyre::{bail, Result};
+use futures::stream::{FuturesUnordered, StreamExt};
+use tracing::{debug, info, warn};
+
+use crate::{
+    strategy::record_arb_outcome,
+    types::{Action, Executor},
+};
+
+/// An action executor that can also check an action without signing or sending it.
+#[async_trait]
+pub trait DryRunExecutor: Executor<Action> {
+    /// `Ok` if `action` would go through this executor right now (fresh quote, tx doesn't revert).
+    async fn dry_run(&self, action: &Action) -> Result<()>;
+}
+
+/// How `ExecutorManager` spreads one action over its executors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SubmitStrategy {
+    /// Try executors one at a time in priority order, stop at the first that succeeds.
+    #[default]
+    FirstSuccess,
+    /// Submit through every executor at once and take the first to succeed. The others are dropped
+    /// mid-flight, so a tx they already sent may still land; they share the signer's nonce, so at most one does.
+    /// Requires a single signer.
+    Race,
+    /// Dry-run the action on every executor, then only the highest-priority one that passed submits it.
+    AllSimulateOneSends,
+}
+
+struct Prioritized {
+    priority: u32,
+    executor: Box<dyn DryRunExecutor>,
+}
+
+/// Submits actions through several executors (e.g. public mempool and a private relay) with explicit,
+/// configurable semantics. Higher priority goes first; equal priorities keep the order they were added in.
+pub struct ExecutorManager {
+    strategy: SubmitStrategy,
+    executors: Vec<Prioritized>,
+}
+
+impl ExecutorManager {
+    pub fn new(strategy: SubmitStrategy) -> Self {
+        Self {
+            strategy,
+            executors: vec![],
+        }
+    }
+
+    pub fn add(&mut self, executor: Box<dyn DryRunExecutor>, priority: u32) {
+        // after every executor of the same or higher priority
+        let at = self.executors.partition_point(|e| e.priority >= priority);
+        self.executors.insert(at, Prioritized { priority, executor });
+    }
+
+    /// Executor names, in the order they are tried.
+    pub fn executor_names(&self) -> Vec<&str> {
+        self.executors.iter().map(|e| e.executor.name()).collect()
+    }
+
+    pub async fn execute_arbitrage(&self, action: Action) -> Result<()> {
+        if self.executors.is_empty() {
+            bail!("no executor configured");
+        }
+
+        // the cooldown runs from submission: the token is held back while the executors wait on the tx
+        let token = action.quoted_tx().and_then(|quoted| quoted.token.clone());
+        let submitted_at = Instant::now();
+        if let Some(token) = &token {
+            record_arb_outcome(token, true, submitted_at);
+        }
+        let result = match self.strategy {
+            SubmitStrategy::FirstSuccess => self.first_success(action).await,
+            SubmitStrategy::Race => self.race(action).await,
+            SubmitStrategy::AllSimulateOneSends => self.all_simulate_one_sends(action).await,
+        };
+        // a tx that was sent but reverted counts as submitted: someone else most likely took the edge
+        if let (Some(token), Err(_)) = (token, &result) {
+            record_arb_outcome(&token, false, submitted_at);
+        }
+        result
+    }
+
+    async fn first_success(&self, action: Action) -> Result<()> {
+        let mut errors = vec![];
+        for Prioritized { executor, .. } in &self.executors {
+            match executor.execute(action.clone()).await {
+                Ok(()) => {
+                    debug!(executor = executor.name(), "action executed");
+                    return Ok(());
+                }
+                Err(error) => {
+                    warn!(executor = executor.name(), "executor failed, falling back: {error:#}");
+                    errors.push(format!("{}: {error:#}", executor.name()));
+                }
+            }
+        }
+        bail!("all executors failed: {}", errors.join("; "))
+    }
+
+    async fn race(&self, action: Action) -> Result<()> {
+        let mut submissions: FuturesUnordered<_> = self
+            .executors
+            .iter()
+            .map(|Prioritized { executor, .. }| {
+                let action = action.clone();
+                async move { (executor.name(), executor.execute(action).await) }
+            })
+            .collect();
+
+        let mut errors = vec![];
+        while let Some((name, result)) = submissions.next().await {
+            match result {
+                Ok(()) => {
+                    info!(executor = name, "won the submission race");
+                    return Ok(());
+                }
+                Err(error) => errors.push(format!("{name}: {error:#}")),
+            }
+        }
+        bail!("all executors failed: {}", errors.join("; "))
+    }
+
+    async fn all_simulate_one_sends(&self, action: Action) -> Result<()> {
+        let dry_runs = futures::future::join_all(self.executors.iter().map(|e| e.executor.dry_run(&action))).await;
+
+        let mut errors = vec![];
+        for (Prioritized { executor, .. }, dry_run) in self.executors.iter().zip(dry_runs) {
+            match dry_run {
+                Ok(()) => return executor.execute(action).await,
+                Err(error) => {
+                    debug!(executor = executor.name(), "dry run failed: {error:#}");
+                    errors.push(format!("{}: {error:#}", executor.name()));
+                }
+            }
+        }
+        bail!("no executor passed the dry run: {}", errors.join("; "))
+    }
+}
+
+#[async_trait]
+impl Executor<Action> for ExecutorManager {
+    fn name(&self) -> &str {
+        "ExecutorManager"
+    }
+
+    async fn execute(&self, action: Action) -> Result<()> {
+        self.execute_arbitrage(action).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use ethers::types::TransactionRequest;
+
+    use super::*;
+    use crate::types::QuotedTx;
+
+    struct MockExecutor {
+        name: &'static str,
+        dry_run_ok: bool,
+        execute_ok: bool,
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Executor<Action> for MockExecutor {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn execute(&self, _action: Action) -> Result<()> {
+            self.calls.lock().unwrap().push(format!("execute {}", self.name));
+            if !self.execute_ok {
+                bail!("{} reverted", self.name);
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl DryRunExecutor for MockExecutor {
+        async fn dry_run(&self, _action: &Action) -> Result<()> {
+            self.calls.lock().unwrap().push(format!("dry_run {}", self.name));
+            if !self.dry_run_ok {
+                bail!("{} would revert", self.name);
+            }
+            Ok(())
+        }
+    }
+
+    fn manager(strategy: SubmitStrategy, executors: &[(&'static str, u32, bool, bool)]) -> (ExecutorManager, Arc<Mutex<Vec<String>>>) {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let mut manager = ExecutorManager::new(strategy);
+        for &(name, priority, dry_run_ok, execute_ok) in executors {
+            let executor = MockExecutor {
+                name,
+                dry_run_ok,
+                execute_ok,
+                calls: calls.clone(),
+            };
+            manager.add(Box::new(executor), priority);
+        }
+        (manager, calls)
+    }
+
+    fn action() -> Action {
+        QuotedTx::new(TransactionRequest::new(), 1).into()
+    }
+
+    #[test]
+    fn test_priority_order() {
+        let (manager, _) = manager(
+            SubmitStrategy::FirstSuccess,
+            &[("public", 1, true, true), ("relay", 10, true, true), ("backup", 1, true, true)],
+        );
+        assert_eq!(manager.executor_names(), ["relay", "public", "backup"]);
+    }
+
+    #[tokio::test]
+    async fn test_first_success_falls_back_in_order() {
+        let (manager, calls) = manager(
+            SubmitStrategy::FirstSuccess,
+            &[("public", 1, true, true), ("relay", 10, true, false)],
+        );
+        manager.execute_arbitrage(action()).await.unwrap();
+        assert_eq!(*calls.lock().unwrap(), ["execute relay", "execute public"]);
+
+        let (manager, _) = manager_failing(SubmitStrategy::FirstSuccess);
+        assert!(manager.execute_arbitrage(action()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_race_takes_any_success() {
+        let (manager, calls) = manager(SubmitStrategy::Race, &[("public", 1, true, true), ("relay", 10, true, false)]);
+        manager.execute_arbitrage(action()).await.unwrap();
+        assert!(calls.lock().unwrap().contains(&"execute public".to_string()));
+
+        let (manager, _) = manager_failing(SubmitStrategy::Race);
+        assert!(manager.execute_arbitrage(action()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_all_simulate_one_sends() {
+        let (manager, calls) = manager(
+            SubmitStrategy::AllSimulateOneSends,
+            &[("public", 1, true, true), ("relay", 10, false, true), ("backup", 0, true, true)],
+        );
+        manager.execute_arbitrage(action()).await.unwrap();
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.iter().filter(|c| c.starts_with("dry_run")).count(), 3);
+        // relay failed its dry run, public outranks backup
+        assert_eq!(calls.iter().filter(|c| c.starts_with("execute")).collect::<Vec<_>>(), ["execute public"]);
+    }
+
+    fn manager_failing(strategy: SubmitStrategy) -> (ExecutorManager, Arc<Mutex<Vec<String>>>) {
+        manager(strategy, &[("public", 1, true, false), ("relay", 10, true, false)])
+    }
+}
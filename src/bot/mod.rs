@@ -1,4 +1,11 @@
+pub mod bundle_submitter;
 pub mod collector;
+pub mod collector_watchdog;
 pub mod executor;
+pub mod executor_manager;
+pub mod reload;
+pub mod signer_pool;
 pub mod contract_executor;
 pub mod start_bot;
+pub mod sweep;
+pub mod validate;
@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+
+use clap::{parser::ValueSource, CommandFactory, FromArgMatches};
+use eyre::{eyre, Result};
+use tracing::{info, warn};
+
+use crate::bot::start_bot::{apply_tunables, WorkerConfig};
+
+/// Split a reload file into command-line tokens: one `--flag value` or `--flag=value` per line, with blank
+/// lines and `#` comments skipped. Values keep their inner spaces.
+fn reload_args(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .flat_map(|line| match line.split_once(char::is_whitespace) {
+            Some((flag, value)) => vec![flag.to_string(), value.trim().to_string()],
+            None => vec![line.to_string()],
+        })
+        .collect()
+}
+
+/// `current` with the flags set in `contents` applied over it. Flags the file leaves out keep their current
+/// value rather than falling back to the environment or defaults.
+fn merge_reload_args(current: &WorkerConfig, contents: &str) -> Result<WorkerConfig> {
+    let args = std::iter::once("reload".to_string()).chain(reload_args(contents));
+    let mut matches = WorkerConfig::command().try_get_matches_from(args)?;
+    let unset: Vec<String> = matches
+        .ids()
+        .filter(|id| matches.value_source(id.as_str()) != Some(ValueSource::CommandLine))
+        .map(|id| id.to_string())
+        .collect();
+    for id in unset {
+        matches.try_clear_id(&id)?;
+    }
+
+    let mut config = current.clone();
+    config.update_from_arg_matches(&matches)?;
+    Ok(config)
+}
+
+/// Parse `path` as worker flags over `current`.
+fn read_reload_file(path: &Path, current: &WorkerConfig) -> Result<WorkerConfig> {
+    let contents = std::fs::read_to_string(path).map_err(|e| eyre!("{}: {e}", path.display()))?;
+    merge_reload_args(current, &contents).map_err(|e| eyre!("{}: {e}", path.display()))
+}
+
+/// Re-read the tunable worker config from `path` on every SIGHUP (`kill -HUP <pid>`), leaving the indexer,
+/// simulators and connections alone. The file only needs the flags it changes, everything else stays as
+/// `config` or the last reload set it. A file that fails to parse changes nothing.
+pub fn spawn_reload_on_sighup(path: PathBuf, mut config: WorkerConfig) {
+    tokio::spawn(async move {
+        let mut signals = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+            Ok(signals) => signals,
+            Err(error) => {
+                warn!(?error, "failed to install SIGHUP handler, config reload disabled");
+                return;
+            }
+        };
+        while signals.recv().await.is_some() {
+            match read_reload_file(&path, &config).and_then(|config| apply_tunables(&config).map(|_| config)) {
+                Ok(reloaded) => {
+                    info!(path = %path.display(), config = ?reloaded, "config reloaded");
+                    config = reloaded;
+                }
+                Err(error) => warn!(path = %path.display(), ?error, "config reload failed, keeping the current config"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn test_reload_args() {
+        let contents = "
+            # tighter filters for the evening
+            --min-spread-bps 30
+            --protocols=TraderJoe,Pangolin
+
+            --log-decisions
+        ";
+        assert_eq!(
+            reload_args(contents),
+            ["--min-spread-bps", "30", "--protocols=TraderJoe,Pangolin", "--log-decisions"]
+        );
+
+        let config = WorkerConfig::try_parse_from(std::iter::once("reload".to_string()).chain(reload_args(contents)))
+            .unwrap();
+        assert_eq!(config.min_spread_bps, Some(30));
+        assert_eq!(config.protocols, ["TraderJoe", "Pangolin"]);
+        assert!(config.log_decisions);
+    }
+
+    #[test]
+    fn test_partial_reload_keeps_current_values() {
+        let current = WorkerConfig::try_parse_from(["start", "--min-spread-bps", "30", "--log-decisions"]).unwrap();
+
+        let config = merge_reload_args(&current, "--protocols TraderJoe").unwrap();
+        assert_eq!(config.protocols, ["TraderJoe"]);
+        assert_eq!(config.min_spread_bps, Some(30));
+        assert!(config.log_decisions);
+
+        assert!(merge_reload_args(&current, "--min-spread-bps thirty").is_err());
+    }
+}
@@ -0,0 +1,496 @@
+use std::{
+    collections::HashSet,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use ethers::{
+    abi::{self, Token},
+    middleware::SignerMiddleware,
+    providers::{Http, Middleware, Provider},
+    signers::{LocalWallet, Signer},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Bytes, TransactionReceipt, TransactionRequest,
+        H256, U256,
+    },
+    utils::{id, keccak256},
+};
+use eyre::{bail, ensure, eyre, Context, Result};
+use once_cell::sync::Lazy;
+use prometheus::{register_int_counter, IntCounter};
+use tracing::{info, warn};
+
+use crate::utils::config::ensure_writes_enabled;
+
+/// Resolve one signer key source: `env:<VAR>` reads the key from an env var, `file:<path>` from a file,
+/// anything else is the hex key itself.
+pub fn parse_key_source(source: &str) -> Result<String> {
+    let source = source.trim();
+    let key = if let Some(var) = source.strip_prefix("env:") {
+        std::env::var(var).map_err(|e| eyre!("key source env:{var}: {e}"))?
+    } else if let Some(path) = source.strip_prefix("file:") {
+        std::fs::read_to_string(path).with_context(|| format!("key source file:{path}"))?
+    } else {
+        source.to_string()
+    };
+    Ok(key.trim().to_string())
+}
+
+/// The wallets behind `sources`, in order. The same key listed twice would share a nonce across two
+/// managers, so duplicates are an error.
+pub fn parse_wallets(sources: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Vec<LocalWallet>> {
+    let mut seen = HashSet::new();
+    let mut wallets = vec![];
+    for (i, source) in sources.into_iter().enumerate() {
+        let wallet = parse_key_source(source.as_ref())?
+            .parse::<LocalWallet>()
+            .map_err(|e| eyre!("invalid private key #{i}: {e}"))?;
+        ensure!(seen.insert(wallet.address()), "signer {:?} configured twice", wallet.address());
+        wallets.push(wallet);
+    }
+    ensure!(!wallets.is_empty(), "no signer configured");
+    Ok(wallets)
+}
+
+/// AVAX a tx needs in its sender's account: its value plus the gas it may burn at its gas price.
+pub fn required_funds(tx: &TypedTransaction) -> U256 {
+    let value = tx.value().copied().unwrap_or_default();
+    let gas = tx.gas().copied().unwrap_or_default();
+    value.saturating_add(gas.saturating_mul(tx.gas_price().unwrap_or_default()))
+}
+
+static TX_RESUBMISSIONS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!("arb_tx_resubmissions_total", "Pending txs re-sent at a higher gas price").unwrap()
+});
+
+static TX_CANCELLATIONS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "arb_tx_cancellations_total",
+        "Nonces cancelled after fee escalation ran out of attempts"
+    )
+    .unwrap()
+});
+
+/// How often a pending tx's receipt is polled while escalating.
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Re-submission policy for a tx still pending after `interval`: the same nonce again at a gas price raised by
+/// `step_bps`, at most `max_attempts` times and never above `cap`. When the attempts run out the nonce is
+/// cancelled with a 0-value self-transfer. Off with 0 attempts: the tx is sent once and awaited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeeEscalation {
+    pub max_attempts: u32,
+    pub step_bps: u32,
+    pub cap: U256,
+    pub interval: Duration,
+}
+
+impl FeeEscalation {
+    pub fn is_enabled(&self) -> bool {
+        self.max_attempts > 0
+    }
+
+    /// Gas price of the attempt after one at `price`; `None` once the cap is reached.
+    pub fn next_price(&self, price: U256) -> Option<U256> {
+        let next = bump(price, self.step_bps).min(self.cap);
+        (next > price).then_some(next)
+    }
+}
+
+/// `price` raised by `step_bps`, and by at least 1 wei.
+fn bump(price: U256, step_bps: u32) -> U256 {
+    let step = price.saturating_mul(U256::from(step_bps)) / 10_000;
+    price.saturating_add(step.max(U256::one()))
+}
+
+/// Hands out the nonces of one signer locally, so concurrent sends don't wait on (or race through)
+/// `eth_getTransactionCount`. A failed send leaves a gap, so the counter is resynced from the chain.
+#[derive(Debug)]
+pub struct NonceManager {
+    next: AtomicU64,
+}
+
+impl NonceManager {
+    pub fn new(next: u64) -> Self {
+        Self {
+            next: AtomicU64::new(next),
+        }
+    }
+
+    pub fn reserve(&self) -> u64 {
+        self.next.fetch_add(1, Ordering::SeqCst)
+    }
+
+    /// Restart from the account's pending tx count.
+    pub fn resync(&self, pending_count: u64) {
+        self.next.store(pending_count, Ordering::SeqCst);
+    }
+}
+
+/// One wallet of the pool, with its own nonce.
+pub struct PooledSigner {
+    client: SignerMiddleware<Provider<Http>, LocalWallet>,
+    nonces: NonceManager,
+    escalation: FeeEscalation,
+}
+
+impl PooledSigner {
+    pub fn address(&self) -> Address {
+        self.client.address()
+    }
+
+    async fn pending_count(&self) -> Result<u64> {
+        let count = self
+            .client
+            .get_transaction_count(self.address(), Some(BlockNumber::Pending.into()))
+            .await?;
+        Ok(count.as_u64())
+    }
+
+    /// Sign `tx` from this wallet with the next local nonce, send it and wait for its receipt, escalating its
+    /// gas price while it stays pending when a `FeeEscalation` is configured.
+    pub async fn send_transaction(&self, mut tx: TypedTransaction) -> Result<Option<TransactionReceipt>> {
+        ensure_writes_enabled(&format!("send tx to {:?}", tx.to()))?;
+        tx.set_from(self.address());
+        tx.set_nonce(self.nonces.reserve());
+        if self.escalation.is_enabled() && tx.gas_price().is_none() {
+            tx.set_gas_price(self.client.get_gas_price().await?);
+        }
+
+        let pending_tx = match self.client.send_transaction(tx.clone(), None).await {
+            Ok(pending_tx) => pending_tx,
+            Err(error) => {
+                self.resync_nonce().await;
+                return Err(error.into());
+            }
+        };
+        if !self.escalation.is_enabled() {
+            return Ok(pending_tx.await?);
+        }
+        self.escalate(tx, *pending_tx).await.map(Some)
+    }
+
+    /// Sign `tx` from this wallet with the next local nonce without sending it, for a relay to submit.
+    /// Returns the filled tx with its hash and raw bytes. The nonce counts as used: a bundle that never
+    /// lands leaves a gap in this wallet's nonces until they are resynced.
+    pub async fn sign_transaction(&self, mut tx: TypedTransaction) -> Result<(TypedTransaction, H256, Bytes)> {
+        ensure_writes_enabled("sign arb bundle")?;
+        tx.set_from(self.address());
+        tx.set_nonce(self.nonces.reserve());
+        if let Err(error) = self.client.fill_transaction(&mut tx, None).await {
+            self.resync_nonce().await;
+            return Err(error.into());
+        }
+        let signature = self.client.signer().sign_transaction(&tx).await?;
+        let raw_tx = tx.rlp_signed(&signature);
+
+        Ok((tx, H256(keccak256(&raw_tx)), raw_tx))
+    }
+
+    /// Restart the local nonces from the chain after a reserved nonce went unused.
+    async fn resync_nonce(&self) {
+        match self.pending_count().await {
+            Ok(count) => self.nonces.resync(count),
+            Err(resync_error) => warn!(signer = ?self.address(), ?resync_error, "failed to resync nonce"),
+        }
+    }
+
+    /// Re-send `tx` (already sent as `first`) at rising gas prices until one of its versions is mined, then
+    /// cancel its nonce once the attempts or the cap run out.
+    async fn escalate(&self, mut tx: TypedTransaction, first: H256) -> Result<TransactionReceipt> {
+        let policy = self.escalation;
+        let mut hashes = vec![first];
+        let mut price = tx.gas_price().unwrap_or_default();
+
+        for attempt in 1..=policy.max_attempts {
+            if let Some(receipt) = self.wait_for_any(&hashes, policy.interval).await? {
+                return Ok(receipt);
+            }
+            let Some(next_price) = policy.next_price(price) else {
+                break;
+            };
+            price = next_price;
+            tx.set_gas_price(price);
+            // safe mode may have been switched on since the first send
+            ensure_writes_enabled(&format!("re-send tx with nonce {:?}", tx.nonce()))?;
+            match self.client.send_transaction(tx.clone(), None).await {
+                Ok(pending_tx) => {
+                    TX_RESUBMISSIONS.inc();
+                    info!(
+                        signer = ?self.address(),
+                        nonce = ?tx.nonce(),
+                        attempt,
+                        %price,
+                        "re-sent pending tx at a higher gas price"
+                    );
+                    hashes.push(*pending_tx);
+                }
+                // e.g. an earlier version was just mined, or the bump was too small for the node
+                Err(error) => warn!(signer = ?self.address(), nonce = ?tx.nonce(), attempt, ?error, "re-send rejected"),
+            }
+        }
+        if let Some(receipt) = self.wait_for_any(&hashes, policy.interval).await? {
+            return Ok(receipt);
+        }
+
+        // give up: a self-transfer on the same nonce, outbidding the last attempt even past the cap, since it
+        // only burns 21k gas and otherwise every later nonce stays stuck behind this one
+        let cancel: TypedTransaction = TransactionRequest::new()
+            .from(self.address())
+            .to(self.address())
+            .value(0)
+            .gas(21_000)
+            .gas_price(bump(price, policy.step_bps.max(1_000)))
+            .nonce(*tx.nonce().ok_or_else(|| eyre!("tx without nonce"))?)
+            .into();
+        let attempts = hashes.len();
+        ensure_writes_enabled(&format!("cancel tx with nonce {:?}", tx.nonce()))?;
+        match self.client.send_transaction(cancel, None).await {
+            Ok(pending_tx) => {
+                TX_CANCELLATIONS.inc();
+                hashes.push(*pending_tx);
+            }
+            Err(error) => warn!(signer = ?self.address(), nonce = ?tx.nonce(), ?error, "cancel rejected"),
+        }
+        match self.wait_for_any(&hashes, policy.interval).await? {
+            // the arb landed after all
+            Some(receipt) if hashes[..attempts].contains(&receipt.transaction_hash) => Ok(receipt),
+            _ => bail!("tx with nonce {:?} still pending after {} attempts, cancelled", tx.nonce(), attempts),
+        }
+    }
+
+    /// The receipt of whichever of `hashes` is mined first within `timeout`.
+    async fn wait_for_any(&self, hashes: &[H256], timeout: Duration) -> Result<Option<TransactionReceipt>> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            for hash in hashes {
+                if let Some(receipt) = self.client.get_transaction_receipt(*hash).await? {
+                    return Ok(Some(receipt));
+                }
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+        }
+    }
+
+    /// This wallet's balance of `token`, native AVAX when zero.
+    pub async fn token_balance(&self, token: Address) -> Result<U256> {
+        if token.is_zero() {
+            return Ok(self.client.get_balance(self.address(), None).await?);
+        }
+        let mut data = id("balanceOf(address)").to_vec();
+        data.extend(abi::encode(&[Token::Address(self.address())]));
+        let call: TypedTransaction = TransactionRequest::new().to(token).data(data).into();
+        let output = self.client.call(&call, None).await?;
+        ensure!(output.len() >= 32, "invalid balanceOf output");
+        Ok(U256::from_big_endian(&output[..32]))
+    }
+
+    /// Whether this wallet holds `required` AVAX for gas and value, plus `amount` of `token` when the tx
+    /// spends `spend = (token, amount)` from it.
+    async fn covers(&self, required: U256, spend: Option<(Address, U256)>) -> Result<bool> {
+        let mut required = required;
+        match spend {
+            Some((token, amount)) if token.is_zero() => required = required.saturating_add(amount),
+            Some((token, amount)) => {
+                let balance = self.token_balance(token).await?;
+                if balance < amount {
+                    warn!(signer = ?self.address(), ?token, %balance, %amount, "Signer can't fund trade, rotating");
+                    return Ok(false);
+                }
+            }
+            None => {}
+        }
+        let balance = self.client.get_balance(self.address(), None).await?;
+        if balance < required {
+            warn!(signer = ?self.address(), %balance, %required, "Signer can't fund tx, rotating");
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Send this wallet's balance of `token` (native AVAX when `None`, less `reserve`) to `to`.
+    async fn sweep(&self, to: Address, token: Option<Address>, reserve: U256) -> Result<Option<TransactionReceipt>> {
+        let tx = match token {
+            None => {
+                let balance = self.client.get_balance(self.address(), None).await?;
+                let amount = balance.saturating_sub(reserve);
+                if amount.is_zero() {
+                    return Ok(None);
+                }
+                TransactionRequest::new().to(to).value(amount)
+            }
+            Some(token) => {
+                let amount = self.token_balance(token).await?;
+                if amount.is_zero() {
+                    return Ok(None);
+                }
+
+                let mut data = id("transfer(address,uint256)").to_vec();
+                data.extend(abi::encode(&[Token::Address(to), Token::Uint(amount)]));
+                TransactionRequest::new().to(token).data(data)
+            }
+        };
+        self.send_transaction(tx.into()).await
+    }
+}
+
+/// `len` indices starting at `start`, wrapping around.
+fn rotation(start: usize, len: usize) -> impl Iterator<Item = usize> {
+    (0..len).map(move |i| (start + i) % len)
+}
+
+/// Signers that workers rotate through round-robin, each with its own nonce, so several arbs can land in
+/// the same block instead of queueing behind one account's nonce.
+pub struct SignerPool {
+    signers: Vec<PooledSigner>,
+    next: AtomicUsize,
+}
+
+impl SignerPool {
+    pub async fn new(rpc_url: &str, wallets: Vec<LocalWallet>) -> Result<Self> {
+        ensure!(!wallets.is_empty(), "no signer configured");
+        let provider = Provider::<Http>::try_from(rpc_url)?;
+        let chain_id = provider.get_chainid().await?.as_u64();
+
+        let mut signers = Vec::with_capacity(wallets.len());
+        for wallet in wallets {
+            let client = SignerMiddleware::new(provider.clone(), wallet.with_chain_id(chain_id));
+            let mut signer = PooledSigner {
+                client,
+                nonces: NonceManager::new(0),
+                escalation: FeeEscalation::default(),
+            };
+            signer.nonces = NonceManager::new(signer.pending_count().await?);
+            signers.push(signer);
+        }
+        info!(signers = ?signers.iter().map(|s| s.address()).collect::<Vec<_>>(), "Signer pool initialized");
+
+        Ok(Self {
+            signers,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Escalate the gas price of every signer's pending txs per `escalation`.
+    pub fn with_fee_escalation(mut self, escalation: FeeEscalation) -> Self {
+        for signer in &mut self.signers {
+            signer.escalation = escalation;
+        }
+        self
+    }
+
+    /// The first configured signer.
+    pub fn primary(&self) -> Address {
+        self.signers[0].address()
+    }
+
+    pub fn addresses(&self) -> Vec<Address> {
+        self.signers.iter().map(|s| s.address()).collect()
+    }
+
+    /// The next signer in rotation whose own balance covers `required`. Wallets are checked one by one,
+    /// so an empty wallet is skipped rather than failing the send.
+    pub async fn next_funded(&self, required: U256) -> Result<&PooledSigner> {
+        self.next_funded_with(required, None).await
+    }
+
+    /// `next_funded`, for a tx that also spends `spend = (token, amount)` from its sender, as an own-funds arb
+    /// does: only a wallet holding that much `token` on top of `required` AVAX can send it.
+    pub async fn next_funded_with(&self, required: U256, spend: Option<(Address, U256)>) -> Result<&PooledSigner> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        for i in rotation(start, self.signers.len()) {
+            let signer = &self.signers[i];
+            if signer.covers(required, spend).await? {
+                return Ok(signer);
+            }
+        }
+        match spend {
+            Some((token, amount)) => {
+                bail!("no signer holds the {} of {:?} and {} wei the tx needs", amount, token, required)
+            }
+            None => bail!("no signer holds the {} wei the tx needs", required),
+        }
+    }
+
+    /// Send each signer's balance of `token` (native AVAX when `None`) to `to`. For AVAX, `reserve` is
+    /// left behind to pay for gas. Returns the receipt or error per signer.
+    pub async fn sweep(
+        &self,
+        to: Address,
+        token: Option<Address>,
+        reserve: U256,
+    ) -> Vec<(Address, Result<Option<TransactionReceipt>>)> {
+        let mut results = vec![];
+        for signer in &self.signers {
+            let result = signer.sweep(to, token, reserve).await;
+            results.push((signer.address(), result));
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_A: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const KEY_B: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+    #[test]
+    fn test_parse_wallets() {
+        std::env::set_var("SIGNER_POOL_TEST_KEY", KEY_B);
+        let wallets = parse_wallets(&[KEY_A.to_string(), "env:SIGNER_POOL_TEST_KEY".to_string()]).unwrap();
+        assert_eq!(wallets.len(), 2);
+        assert_eq!(wallets[1].address(), KEY_B.parse::<LocalWallet>().unwrap().address());
+
+        assert!(parse_wallets(&[KEY_A.to_string(), format!(" {KEY_A} ")]).is_err());
+        assert!(parse_wallets(&["env:SIGNER_POOL_TEST_MISSING".to_string()]).is_err());
+        assert!(parse_wallets(&[]).is_err());
+    }
+
+    #[test]
+    fn test_rotation_and_nonces() {
+        assert_eq!(rotation(4, 3).collect::<Vec<_>>(), [1, 2, 0]);
+
+        let nonces = NonceManager::new(7);
+        assert_eq!(nonces.reserve(), 7);
+        assert_eq!(nonces.reserve(), 8);
+        // the send with nonce 8 failed and never reached the mempool
+        nonces.resync(8);
+        assert_eq!(nonces.reserve(), 8);
+    }
+
+    #[test]
+    fn test_fee_escalation() {
+        let gwei = |n: u64| U256::from(n) * U256::exp10(9);
+        let policy = FeeEscalation {
+            max_attempts: 3,
+            step_bps: 1_250,
+            cap: gwei(30),
+            interval: Duration::from_secs(2),
+        };
+        assert!(policy.is_enabled());
+        assert!(!FeeEscalation::default().is_enabled());
+
+        assert_eq!(policy.next_price(gwei(25)), Some(gwei(28) + gwei(1) / 8));
+        // clamped to the cap, then no further attempt
+        assert_eq!(policy.next_price(gwei(28)), Some(gwei(30)));
+        assert_eq!(policy.next_price(gwei(30)), None);
+        // a zero step still moves by a wei
+        assert_eq!(bump(U256::from(7), 0), U256::from(8));
+    }
+
+    #[test]
+    fn test_required_funds() {
+        let tx: TypedTransaction = TransactionRequest::new()
+            .value(1_000)
+            .gas(21_000)
+            .gas_price(25)
+            .into();
+        assert_eq!(required_funds(&tx), U256::from(1_000 + 21_000 * 25));
+        assert_eq!(required_funds(&TransactionRequest::new().into()), U256::zero());
+    }
+}
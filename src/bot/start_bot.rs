@@ -1,45 +1,140 @@
 use std::{
-    sync::Arc,
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Arc,
+    },
     time::Duration,
 };
 
+use burberry::Engine;
 use clap::Parser;
+use dex_indexer::types::Protocol;
 use eyre::Result;
 use object_pool::ObjectPool;
 use tracing::{info, warn};
 
 use crate::{
-    bot::{collector::AvaxMempoolCollector, executor::EnhancedArbExecutor},
-    simulator::{HttpSimulator, Simulator},
-    strategy::{
-        ArbStrategy,
-        transaction_analyzer::TransactionAnalyzer,
-        arbitrage_analyzer::ArbitrageAnalyzer,
+    bot::{
+        bundle_submitter::FileBundleSubmitter,
+        collector::{AvaxMempoolCollector, DumpSignalCollector},
+        collector_watchdog::CollectorWatchdog,
+        executor::{GasBudget, PositionGuard, PublicTxExecutor},
+        executor_manager::{ExecutorManager, SubmitStrategy},
+        signer_pool::{parse_wallets, FeeEscalation, SignerPool},
+    },
+    common::{
+        price_oracle::PriceFeedCollector,
+        spread_metrics::{parse_spread_pairs, serve_metrics, spawn_spread_monitor},
+        webhook::WebhookNotifier,
     },
-    types::{Action, Event},
-    utils::heartbeat,
+    dex::Defi,
+    engine::executors::mempool_executor::GasPriceStrategy,
+    simulator::{new_fork_simulator, HttpSimulator, MeteredSimulator, PoolUsage, Simulator, TimeoutSimulator},
+    strategy::{ArbStrategy, ChannelFullPolicy},
+    types::{Action, Event, Executor},
+    utils::{config::ProfitToken, heartbeat, secret::Secret, token_config::TokenConfig},
     HttpConfig,
 };
 
-use ethers::types::Address;
+use ethers::types::{Address, U256};
 
 #[derive(Clone, Debug, Parser)]
 pub struct Args {
     #[arg(long, env = "AVAX_PRIVATE_KEY")]
-    pub private_key: String,
+    pub private_key: Secret,
+
+    /// More signers for the public-mempool executor to rotate through with `private_key`, each with its own
+    /// nonce, so several arbs can land in one block. Comma separated key sources: a hex key, `env:<VAR>` or
+    /// `file:<path>`.
+    #[arg(long, env = "SIGNER_KEYS", value_delimiter = ',')]
+    pub signer_keys: Vec<Secret>,
+
+    /// Re-send a public tx still pending after `--fee-escalation-interval-ms` at a higher gas price, at most this
+    /// many times, then cancel its nonce. 0 sends once and waits.
+    #[arg(long, env = "FEE_ESCALATION_ATTEMPTS", default_value_t = 0)]
+    pub fee_escalation_attempts: u32,
+
+    /// Gas price raise per re-send, in bps of the previous attempt's. Nodes reject replacements below 10%.
+    #[arg(long, env = "FEE_ESCALATION_STEP_BPS", default_value_t = 1_250)]
+    pub fee_escalation_step_bps: u32,
+
+    /// Gas price re-sends never exceed, in gwei.
+    #[arg(long, env = "FEE_ESCALATION_CAP_GWEI", default_value_t = 100)]
+    pub fee_escalation_cap_gwei: u64,
+
+    /// How long each attempt may stay pending before the next one.
+    #[arg(long, env = "FEE_ESCALATION_INTERVAL_MS", default_value_t = 2_000)]
+    pub fee_escalation_interval_ms: u64,
+
+    /// Price public txs at this percentile (0-100) of the effective gas prices paid in the last
+    /// `--gas-price-blocks` blocks, plus `--gas-price-tip-gwei`, instead of the quoted price.
+    #[arg(long, env = "GAS_PRICE_PERCENTILE", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub gas_price_percentile: Option<u8>,
+
+    /// Blocks sampled by `--gas-price-percentile`.
+    #[arg(long, env = "GAS_PRICE_BLOCKS", default_value_t = 5)]
+    pub gas_price_blocks: u64,
+
+    /// Added on top of the `--gas-price-percentile` price, in gwei.
+    #[arg(long, env = "GAS_PRICE_TIP_GWEI", default_value_t = 0)]
+    pub gas_price_tip_gwei: u64,
 
     #[arg(long, env = "ARB_CONTRACT_ADDRESS")]
     pub contract_address: Option<String>,
 
+    /// Write signed arb bundles as JSON to this directory (or append them to this file / named pipe) for an
+    /// external relay instead of sending them.
+    #[arg(long, env = "BUNDLE_OUTPUT")]
+    pub bundle_output: Option<std::path::PathBuf>,
+
+    /// POST opportunities and fills as JSON to this URL (see `WebhookPayload` for the schema).
+    #[arg(long, env = "WEBHOOK_URL")]
+    pub webhook_url: Option<String>,
+
+    /// Sign webhook bodies with HMAC-SHA256 under this secret, sent as `X-Arb-Signature`.
+    #[arg(long, env = "WEBHOOK_SECRET")]
+    pub webhook_secret: Option<Secret>,
+
+    /// How actions are spread over the configured executors: `first-success` falls back in priority order,
+    /// `race` submits through all at once, `all-simulate-one-sends` dry-runs on all and sends through one.
+    #[arg(long, env = "SUBMIT_STRATEGY", value_enum, default_value_t = SubmitStrategy::FirstSuccess)]
+    pub submit_strategy: SubmitStrategy,
+
+    /// Priority of the public-mempool executor (higher goes first). Setting it alongside `bundle_output`
+    /// enables both executors; otherwise a bundle output replaces the public mempool.
+    #[arg(long, env = "PUBLIC_EXECUTOR_PRIORITY")]
+    pub public_executor_priority: Option<u32>,
+
+    /// Priority of the bundle-file executor (higher goes first).
+    #[arg(long, env = "BUNDLE_EXECUTOR_PRIORITY", default_value_t = 0)]
+    pub bundle_executor_priority: u32,
+
+    /// Never sign or send a transaction: every executor only logs what it would have done. Stays on until
+    /// explicitly turned off.
+    #[arg(long, env = "SAFE_MODE", default_value_t = false)]
+    pub safe_mode: bool,
+
+    /// Worker flags re-read on SIGHUP, one `--flag value` per line. Only the tunables (filters, probe sizes,
+    /// protocol and fee tables, debug switches) are applied; one missing from the file keeps its current value,
+    /// and the rest of the config needs a restart.
+    #[arg(long, env = "RELOAD_FILE")]
+    pub reload_file: Option<std::path::PathBuf>,
+
+    /// Export tracing spans (event, routing, trials, dry run and submission) to this OTLP/gRPC collector,
+    /// e.g. `http://localhost:4317`.
+    #[arg(long, env = "OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+
     #[command(flatten)]
     pub http_config: HttpConfig,
 
     #[command(flatten)]
-    worker_config: WorkerConfig,
+    pub(crate) worker_config: WorkerConfig,
 }
 
 #[derive(Clone, Debug, Parser)]
-struct WorkerConfig {
+pub(crate) struct WorkerConfig {
     /// Number of workers to process events
     #[arg(long, env = "WORKER_THREADS", default_value_t = 8)]
     pub workers: usize,
@@ -48,122 +143,650 @@ struct WorkerConfig {
     #[arg(long, env = "SIMULATOR_POOL_SIZE", default_value_t = 16)]
     pub num_simulators: usize,
 
-    /// If a new coin comes in and it has been processed within the last `max_recent_arbs` times,
-    /// it will be ignored.
+    /// Warn when every pooled simulator has been busy for this long, i.e. the pool is the bottleneck.
+    #[arg(long, env = "SIMULATOR_POOL_EXHAUSTED_WARN_MS", default_value_t = 1000)]
+    pub simulator_pool_exhausted_warn_ms: u64,
+
+    /// An opportunity (token, pool and block) already sent to the workers among the last `max_recent_arbs`,
+    /// within the last few seconds, is ignored whichever source reports it again.
     #[arg(long, env = "MAX_RECENT_ARBS", default_value_t = 20)]
     pub max_recent_arbs: usize,
+
+    /// Ignore a token's new opportunities for this many ms after one of its arbs was submitted, since the fill
+    /// likely consumed the edge. 0 disables it.
+    #[arg(long, env = "COOLDOWN_AFTER_SUCCESS_MS", default_value_t = 0)]
+    pub cooldown_after_success_ms: u64,
+
+    /// Same after every executor failed to submit the token's arb; usually shorter, so it can be retried soon.
+    #[arg(long, env = "COOLDOWN_AFTER_FAILURE_MS", default_value_t = 0)]
+    pub cooldown_after_failure_ms: u64,
+
+    /// Share of an arb's profit, in bps, paid to the block builder of a private relay that requires one. Arbs
+    /// only go out when the profit left after the payment is positive. The arb contract pays `block.coinbase`
+    /// inside the arb tx, so it needs `--contract-address` and flashloan trades.
+    #[arg(long, env = "BUILDER_PAYMENT_BPS", default_value_t = 0)]
+    pub builder_payment_bps: u32,
+
+    /// Fixed builder payment per arb, in wei of the gas token, on top of `builder_payment_bps`.
+    #[arg(long, env = "BUILDER_PAYMENT_WEI", default_value_t = 0)]
+    pub builder_payment_wei: u64,
+
+    /// Actions quoted more than this many blocks before send time are dropped by the executor.
+    #[arg(long, env = "MAX_QUOTE_AGE_BLOCKS", default_value_t = 2)]
+    pub max_quote_age_blocks: u64,
+
+    /// Stablecoins valued at ~$1, comma separated. Defaults to the built-in peg set when empty.
+    #[arg(long, env = "PEGGED_COINS", value_delimiter = ',')]
+    pub pegged_coins: Vec<String>,
+
+    /// Per-pool fee corrections as `<pool>:<fee_bps>`, comma separated. Take precedence over adapter fees.
+    #[arg(long, env = "POOL_FEE_OVERRIDES", value_delimiter = ',')]
+    pub pool_fee_overrides: Vec<String>,
+
+    /// Fixed routes as `<token>:<pool>/<pool>/...`, comma separated: the pools of a cycle from the token back to
+    /// itself, in trade order. Such a token skips the path search; an override that doesn't check out against
+    /// the index at startup is dropped with a warning and the token is searched as usual.
+    #[arg(long, env = "ROUTE_OVERRIDES", value_delimiter = ',')]
+    pub route_overrides: Vec<String>,
+
+    /// Protocols to search pools on, by name (e.g. `TraderJoe,Pangolin`), comma separated. All supported
+    /// protocols when empty.
+    #[arg(long, env = "PROTOCOLS", value_delimiter = ',')]
+    pub protocols: Vec<String>,
+
+    /// Flashloan premiums as `<provider>:<premium_bps>`, comma separated, where the provider is a protocol
+    /// name (for its flash swaps) or `Aave` (the fallback lender). Defaults to each pool's fee and 9 bps for Aave.
+    #[arg(long, env = "FLASHLOAN_PREMIUMS", value_delimiter = ',')]
+    pub flashloan_premiums: Vec<String>,
+
+    /// Referral address for routers that credit part of the swap fee, appended to the TraderJoe, Pangolin and
+    /// SushiSwap router calls.
+    #[arg(long, env = "REFERRAL_ADDRESS")]
+    pub referral_address: Option<Address>,
+
+    /// Referral rebates as `<protocol>:<share_bps>`, the share of the protocol's swap fee paid back to
+    /// `--referral-address`, comma separated. Counted towards profit on the hops whose call carries the address.
+    #[arg(long, env = "REFERRAL_REBATES", value_delimiter = ',')]
+    pub referral_rebates: Vec<String>,
+
+    /// Trade sizes probed before refining, in raw units of the token traded in, comma separated.
+    /// Defaults to one per decade from 0.01 to 10M AVAX when empty.
+    #[arg(long, env = "PROBE_AMOUNTS", value_delimiter = ',', value_parser = crate::utils::config::parse_amount)]
+    pub probe_amounts: Vec<U256>,
+
+    /// Probe only 0.1, 0.5, 1 and 5 AVAX when `probe_amounts` is empty: fewer simulations per opportunity,
+    /// for pools known to be AVAX-sized.
+    #[arg(long, env = "NARROW_PROBE_AMOUNTS")]
+    pub narrow_probe_amounts: bool,
+
+    /// Per-token probe sizes as `<token>:<amount>/<amount>/...`, comma separated. Take precedence over
+    /// `probe_amounts`.
+    #[arg(long, env = "TOKEN_PROBE_AMOUNTS", value_delimiter = ',')]
+    pub token_probe_amounts: Vec<String>,
+
+    /// Max AVAX spent on gas per block. Submissions pause until the next block once exceeded.
+    #[arg(long, env = "GAS_BUDGET_PER_BLOCK_AVAX")]
+    pub gas_budget_per_block_avax: Option<f64>,
+
+    /// Max AVAX spent on gas per hour. Submissions pause until the window rolls over once exceeded.
+    #[arg(long, env = "GAS_BUDGET_PER_HOUR_AVAX")]
+    pub gas_budget_per_hour_avax: Option<f64>,
+
+    /// Simulate each pending tx and size the backrun against its post-state. Runs the simulators on
+    /// anvil forks instead of plain RPC calls.
+    #[arg(long, env = "SIMULATE_PENDING_TXS", default_value_t = false)]
+    pub simulate_pending_txs: bool,
+
+    /// With `--simulate-pending-txs` but no anvil binary, simulate over plain RPC calls with a warning instead
+    /// of refusing to start.
+    #[arg(long, env = "ALLOW_HTTP_FALLBACK", default_value_t = false)]
+    pub allow_http_fallback: bool,
+
+    /// First anvil port used by fork simulators; each instance takes the next one.
+    #[arg(long, env = "ANVIL_BASE_PORT", default_value_t = 8600)]
+    pub anvil_base_port: u16,
+
+    /// Hard cap on anvil forks running at once, the own simulator included. The simulator pool is shrunk to fit
+    /// and workers share its simulators; extra forks queue for a free slot. Unlimited when unset.
+    #[arg(long, env = "MAX_ANVILS")]
+    pub max_anvils: Option<usize>,
+
+    /// Refuse to spawn an anvil fork while the host has less than this many MB available (Linux only). 0
+    /// disables the check.
+    #[arg(long, env = "ANVIL_MIN_FREE_MB", default_value_t = crate::utils::config::DEFAULT_ANVIL_MIN_FREE_MB)]
+    pub anvil_min_free_mb: usize,
+
+    /// Own-funds (non-flashloan) arbs may use at most this fraction of the funding account's balance.
+    #[arg(long, env = "MAX_POSITION_FRACTION", default_value_t = 0.9)]
+    pub max_position_fraction: f64,
+
+    /// Only simulate this many candidate paths per opportunity, the ones with the deepest weakest pool.
+    /// Keeps tokens with huge pool counts within the block time. All paths when unset.
+    #[arg(long, env = "MAX_PATHS_EVALUATED")]
+    pub max_paths_evaluated: Option<usize>,
+
+    /// Take a pool out of routing once its `getReserves` has found no code this many times in a row
+    /// (self-destructed pools). Reverts don't count. Never when unset.
+    #[arg(long, env = "DELIST_AFTER_FAILURES")]
+    pub delist_after_failures: Option<usize>,
+
+    /// Pools to put back into routing after being delisted, comma separated. Applied on start and on every
+    /// config reload.
+    #[arg(long, env = "RELIST_POOLS", value_delimiter = ',')]
+    pub relist_pools: Vec<Address>,
+
+    /// Drop paths that trade on fewer distinct protocols than this before simulating them; a round trip
+    /// within one DEX can't beat its fees. 1 keeps single-venue paths.
+    #[arg(long, env = "MIN_DISTINCT_PROTOCOLS", default_value_t = crate::utils::config::DEFAULT_MIN_DISTINCT_PROTOCOLS)]
+    pub min_distinct_protocols: usize,
+
+    /// Stop expanding a token's routes after this many ms and keep the ones found so far, so a token with
+    /// a huge pool graph can't hold up the rest of the block. Unbounded when unset.
+    #[arg(long, env = "ROUTE_SEARCH_BUDGET_MS")]
+    pub route_search_budget_ms: Option<u64>,
+
+    /// Drop paths whose marginal spread at the quote block, net of fees and read from reserves, is below this
+    /// many bps before simulating them. Paths through pools without reserves are always simulated. Off when
+    /// unset.
+    #[arg(long, env = "MIN_SPREAD_BPS")]
+    pub min_spread_bps: Option<u32>,
+
+    /// Before the full path search, compare the token's direct WAVAX pools across protocols and trade the 2-leg
+    /// cycle alone when its spread, net of fees, is at least this many bps. Off when unset.
+    #[arg(long, env = "DIRECT_PAIR_SPREAD_BPS")]
+    pub direct_pair_spread_bps: Option<u32>,
+
+    /// Debug: before building each final tx, log pools whose reserves moved since the quote block.
+    #[arg(long, env = "CHECK_RESERVES", default_value_t = false)]
+    pub check_reserves: bool,
+
+    /// Keep trades whose simulated gas cost is zero or negative. By default such a figure is treated as a
+    /// simulation anomaly and the path is discarded, since it would overstate the profit.
+    #[arg(long, env = "TRUST_NONPOSITIVE_GAS_COST", default_value_t = false)]
+    pub trust_nonpositive_gas_cost: bool,
+
+    /// Prune a token's first-hop pools by the output of a simulated swap at a mid-grid trade size instead of
+    /// by liquidity, so the pool cap keeps the best priced pools. One extra simulation per candidate pool.
+    #[arg(long, env = "RANK_POOLS_BY_OUTPUT", default_value_t = false)]
+    pub rank_pools_by_output: bool,
+
+    /// Log one debug record per evaluated opportunity (target `arb::decision`): best path, size, gross out,
+    /// gas, flashloan premium, net profit, price impact, and whether and why it was traded.
+    #[arg(long, env = "LOG_DECISIONS", default_value_t = false)]
+    pub log_decisions: bool,
+
+    /// Also evaluate every opportunity with the sender's own funds instead of a flashloan, and trade
+    /// whichever nets more. Only useful when the sender holds inventory of the traded tokens.
+    #[arg(long, env = "OWN_FUNDS", default_value_t = false)]
+    pub own_funds: bool,
+
+    /// With `--own-funds`, how much more the own-funds route must net than the flashloan route, in bps of the
+    /// trade size, to be picked over it. Flashloans pay a premium but keep no capital at risk.
+    #[arg(long, env = "FLASHLOAN_PREFERENCE_BPS", default_value_t = crate::utils::config::DEFAULT_FLASHLOAN_PREFERENCE_BPS)]
+    pub flashloan_preference_bps: u32,
+
+    /// Minimum output a swap tx sets for itself below the adapter's quote, in bps. Only applies to swaps that
+    /// don't go through the arb contract's profit check (e.g. Platypus' `swap`).
+    #[arg(long, env = "SWAP_SLIPPAGE_BPS", default_value_t = crate::utils::config::DEFAULT_SWAP_SLIPPAGE_BPS)]
+    pub swap_slippage_bps: u32,
+
+    /// Attach an access list (from `eth_createAccessList` on the simulator) to each arb tx, sending it as
+    /// EIP-2930 to prepay the storage slots multi-hop arbs touch.
+    #[arg(long, env = "USE_ACCESS_LIST", default_value_t = false)]
+    pub use_access_list: bool,
+
+    /// Hard deadline for every simulator call. A timed-out call fails as a transient error.
+    #[arg(long, env = "SIMULATE_TIMEOUT_MS", default_value_t = 3000)]
+    pub simulate_timeout_ms: u64,
+
+    /// Resubscribe the mempool feed when it delivers nothing for this long while blocks keep coming.
+    #[arg(long, env = "COLLECTOR_STALL_TIMEOUT_MS", default_value_t = 30_000)]
+    pub collector_stall_timeout_ms: u64,
+
+    /// What to do with new opportunities while the worker channel is full: `wait` keeps the queued items,
+    /// `replace-oldest` drops the oldest queued items for the newest ones.
+    #[arg(long, env = "CHANNEL_FULL_POLICY", value_enum, default_value_t = ChannelFullPolicy::Wait)]
+    pub channel_full_policy: ChannelFullPolicy,
+
+    /// Confirmations a public tx's block needs before its receipt is acted on; 1 acts at the head. Trades
+    /// latency for safety against shallow reorgs. Pending (mempool) txs are always acted on immediately.
+    #[arg(long, env = "RECEIPT_CONFIRMATIONS", default_value_t = 1)]
+    pub receipt_confirmations: u64,
+
+    /// Ignore txs sent from the bot's own signer or arb contract, so its own swaps don't trigger backruns.
+    #[arg(long, env = "EXCLUDE_OWN_TXS", default_value_t = true, action = clap::ArgAction::Set)]
+    pub exclude_own_txs: bool,
+
+    /// Log 1-in-N per-tx spans in full. Txs that yield an arb opportunity are always logged.
+    #[arg(long, env = "LOG_SAMPLE_RATE", default_value_t = 100)]
+    pub log_sample_rate: u64,
+
+    /// Arbs whose profit is realized in a pegged coin are only notified above this USD value.
+    #[arg(long, env = "MIN_NOTIFY_PROFIT_USD", default_value_t = 1.0)]
+    pub min_notify_profit_usd: f64,
+
+    /// Threshold for WAVAX profit while the AVAX/USD price feed is stale.
+    #[arg(long, env = "MIN_NOTIFY_PROFIT_AVAX", default_value_t = 0.05)]
+    pub min_notify_profit_avax: f64,
+
+    /// Run without the AVAX/USD price feed. WAVAX profit is then gated by `min_notify_profit_avax`.
+    #[arg(long, env = "NO_PRICE_FEED", default_value_t = false)]
+    pub no_price_feed: bool,
+
+    /// The AVAX/USD price is considered stale once the feed's last round (its `updatedAt`) is this many blocks
+    /// old. Chainlink only posts a round on a large enough move or its heartbeat, so keep this above the
+    /// feed's usual update interval.
+    #[arg(long, env = "PRICE_FEED_MAX_AGE_BLOCKS", default_value_t = 1800)]
+    pub price_feed_max_age_blocks: u32,
+
+    /// Wrapped-native contract used for routing and wrapping. Override for forks and test subnets.
+    #[arg(long, env = "WAVAX_ADDRESS", default_value = crate::utils::config::DEFAULT_WAVAX_ADDRESS)]
+    pub wavax_address: String,
+
+    /// Multicall3 deployment batched reserve reads go through. Override on chains where it lives elsewhere.
+    #[arg(long, env = "MULTICALL_ADDRESS", default_value = crate::utils::config::DEFAULT_MULTICALL_ADDRESS)]
+    pub multicall_address: Address,
+
+    /// Read reserves with individual concurrent calls instead, for chains without a Multicall3 deployment.
+    #[arg(long, env = "NO_MULTICALL", default_value_t = false)]
+    pub no_multicall: bool,
+
+    /// Start even when a configured router, WAVAX, multicall or arb contract has no code on the connected chain.
+    #[arg(long, env = "SKIP_CONTRACT_CHECK", default_value_t = false)]
+    pub skip_contract_check: bool,
+
+    /// Wrapped form of the fee currency, for subnets whose gas isn't AVAX. Defaults to the wrapped-native token.
+    #[arg(long, env = "GAS_TOKEN")]
+    pub gas_token: Option<String>,
+
+    /// Token arbs that start and end in it are scored in, with gas converted at `profit_token_avax_price`.
+    /// Defaults to the wrapped-native token.
+    #[arg(long, env = "PROFIT_TOKEN")]
+    pub profit_token: Option<String>,
+
+    /// Price of 1 gas token (AVAX on C-Chain) in whole units of the profit token. Ignored when the profit token
+    /// is the gas token, and replaced by the live AVAX/USD feed for a USD-pegged profit token on C-Chain.
+    #[arg(long, env = "PROFIT_TOKEN_AVAX_PRICE", default_value_t = 1.0)]
+    pub profit_token_avax_price: f64,
+
+    /// Pairs (`<base>/<quote>` token addresses) whose cross-DEX spread is recorded every block, arb or not.
+    #[arg(long, env = "SPREAD_PAIRS", value_delimiter = ',')]
+    pub spread_pairs: Vec<String>,
+
+    /// How often the spread monitor checks for a new block.
+    #[arg(long, env = "SPREAD_INTERVAL_MS", default_value_t = 2000)]
+    pub spread_interval_ms: u64,
+
+    /// Serve Prometheus metrics (e.g. pair spreads) on this address.
+    #[arg(long, env = "METRICS_ADDR")]
+    pub metrics_addr: Option<std::net::SocketAddr>,
+}
+
+/// Apply the part of the worker config that is safe to change while running: filters, probe sizes, protocol
+/// and fee tables, and debug switches. Everything is parsed before anything is set, so an invalid entry leaves
+/// the current config untouched.
+pub(crate) fn apply_tunables(config: &WorkerConfig) -> Result<()> {
+    let pool_fee_overrides = crate::utils::config::parse_pool_fee_overrides(&config.pool_fee_overrides)?;
+    let protocols = crate::utils::config::parse_protocols(&config.protocols)?;
+    let flashloan_premiums = crate::utils::config::parse_flashloan_premiums(&config.flashloan_premiums)?;
+    let referral_rebates = crate::utils::config::parse_referral_rebates(&config.referral_rebates)?;
+    let token_probe_amounts = crate::utils::config::parse_token_probe_amounts(&config.token_probe_amounts)?;
+
+    // one snapshot over the current values, swapped in with a single store so readers never see half a reload
+    let mut tunables = crate::utils::config::Tunables::clone(&crate::utils::config::tunables());
+    tunables.set_pegged_coin_types(&config.pegged_coins);
+    tunables.set_pool_fee_overrides(pool_fee_overrides);
+    tunables.set_enabled_protocols(&protocols);
+    tunables.set_flashloan_premiums(flashloan_premiums);
+    tunables.set_referral_address(config.referral_address);
+    tunables.set_referral_rebates(referral_rebates);
+    tunables.set_check_reserves(config.check_reserves);
+    tunables.set_trust_nonpositive_gas_cost(config.trust_nonpositive_gas_cost);
+    tunables.set_rank_pools_by_output(config.rank_pools_by_output);
+    tunables.set_use_access_list(config.use_access_list);
+    tunables.set_log_decisions(config.log_decisions);
+    tunables.set_own_funds(config.own_funds);
+    tunables.set_flashloan_preference_bps(config.flashloan_preference_bps);
+    tunables.set_swap_slippage_bps(config.swap_slippage_bps);
+    tunables.set_max_paths_evaluated(config.max_paths_evaluated);
+    tunables.set_min_distinct_protocols(config.min_distinct_protocols);
+    tunables.set_delist_after_failures(config.delist_after_failures);
+    tunables.set_route_search_budget_ms(config.route_search_budget_ms);
+    tunables.set_arb_cooldown_ms(config.cooldown_after_success_ms, config.cooldown_after_failure_ms);
+    tunables.set_builder_payment(config.builder_payment_bps, config.builder_payment_wei);
+    tunables.set_min_spread_bps(config.min_spread_bps);
+    tunables.set_direct_pair_spread_bps(config.direct_pair_spread_bps);
+    if config.probe_amounts.is_empty() && config.narrow_probe_amounts {
+        tunables.set_probe_amounts(&crate::utils::config::NARROW_PROBE_AMOUNTS.map(U256::from));
+    } else {
+        tunables.set_probe_amounts(&config.probe_amounts);
+    }
+    tunables.set_token_probe_amounts(token_probe_amounts);
+    crate::utils::config::set_tunables(tunables);
+    crate::dex::relist_pools(&config.relist_pools);
+    Ok(())
+}
+
+/// Fail fast when configured addresses aren't contracts on the connected chain, which otherwise shows up as an
+/// index without pools and no arbs. Routers and factories of disabled protocols are not checked.
+async fn check_contracts(args: &Args, chain_id: u64) -> Result<()> {
+    let mut contracts = vec![];
+    for (router, name) in &TokenConfig::new().dex_routers {
+        let enabled = crate::utils::config::parse_protocol(name)
+            .map(|protocol| crate::utils::config::is_protocol_enabled(&protocol))
+            .unwrap_or(true);
+        if enabled {
+            contracts.push((format!("{name} router"), router.parse::<Address>()?));
+        }
+    }
+    for protocol in [Protocol::TraderJoe, Protocol::Pangolin, Protocol::SushiSwap] {
+        if !crate::utils::config::is_protocol_enabled(&protocol) {
+            continue;
+        }
+        for address in crate::dex::related_contract_addresses(&protocol).await {
+            let address = address.parse::<Address>()?;
+            if contracts.iter().all(|(_, known)| *known != address) {
+                contracts.push((format!("{protocol} contract"), address));
+            }
+        }
+    }
+    contracts.push(("WAVAX".to_string(), args.worker_config.wavax_address.parse()?));
+    if let Some(multicall) = crate::utils::config::multicall_address() {
+        contracts.push(("multicall (--no-multicall to read without it)".to_string(), multicall));
+    }
+    if let Some(contract) = &args.contract_address {
+        contracts.push(("arb contract".to_string(), contract.parse()?));
+    }
+
+    let missing = crate::common::missing_contracts(&args.http_config.rpc_url, &contracts).await?;
+    if missing.is_empty() {
+        return Ok(());
+    }
+    for (label, address) in &missing {
+        warn!(chain_id, ?address, "{label} has no code on the connected chain");
+    }
+    eyre::ensure!(
+        args.worker_config.skip_contract_check,
+        "{} configured contract(s) not deployed on chain {chain_id}, wrong RPC or addresses for this chain? \
+         (--skip-contract-check to start anyway)",
+        missing.len()
+    );
+    Ok(())
+}
+
+/// Simulators to pool under an anvil cap: one fork is kept for the own simulator, and the pool gets the rest,
+/// shared by the workers.
+fn simulator_pool_size(requested: usize, max_anvils: Option<usize>) -> Result<usize> {
+    let Some(max_anvils) = max_anvils else {
+        return Ok(requested);
+    };
+    eyre::ensure!(
+        max_anvils >= 2,
+        "--max-anvils {max_anvils} leaves no fork for the simulator pool: the own simulator takes one"
+    );
+    let size = requested.min(max_anvils - 1);
+    if size < requested {
+        warn!(requested, size, max_anvils, "simulator pool shrunk to fit --max-anvils");
+    }
+    Ok(size)
 }
 
 pub async fn run(args: Args) -> Result<()> {
     crate::utils::set_panic_hook();
     
     // 初始化日志
-    tracing_subscriber::fmt()
-        .with_env_filter("debug")
-        .init();
+    let _otlp = crate::tools::logger::init_with_otlp("debug", "arbitrage-bot", args.otlp_endpoint.as_deref())?;
 
     info!(
         "Starting AVAX MEV Bot with config: {:#?}",
         args
     );
 
+    crate::utils::config::set_safe_mode(args.safe_mode);
+    if args.safe_mode {
+        warn!("Safe mode: no transaction will be signed or sent");
+    }
+    apply_tunables(&args.worker_config)?;
+    if let Some(reload_file) = &args.reload_file {
+        crate::bot::reload::spawn_reload_on_sighup(reload_file.clone(), args.worker_config.clone());
+    }
+    crate::utils::config::set_wavax_address(&args.worker_config.wavax_address);
+    crate::utils::config::set_route_overrides(crate::utils::config::parse_route_overrides(
+        &args.worker_config.route_overrides,
+    )?);
+    crate::utils::config::set_gas_token(args.worker_config.gas_token.as_deref());
+    crate::utils::config::set_multicall_address(
+        (!args.worker_config.no_multicall).then_some(args.worker_config.multicall_address),
+    );
+    let profit_token = args
+        .worker_config
+        .profit_token
+        .clone()
+        .unwrap_or_else(crate::utils::config::wavax_address);
+    let profit_token_decimals = TokenConfig::new()
+        .get_token_by_address(&profit_token)
+        .map(|t| t.decimals)
+        .ok_or_else(|| eyre::eyre!("unknown profit token {}", profit_token))?;
+    crate::utils::config::set_profit_token(ProfitToken::new(
+        &profit_token,
+        profit_token_decimals,
+        args.worker_config.profit_token_avax_price,
+    ));
+
     let rpc_url = args.http_config.rpc_url.clone();
+    let chain_id = crate::common::get_chain_id(&rpc_url).await?;
+    info!("Connected to chain id {}", chain_id);
+    check_contracts(&args, chain_id).await?;
+
+    let price_feed_collector = if args.worker_config.no_price_feed {
+        let oracle = crate::common::price_oracle::price_oracle();
+        oracle.disable();
+        oracle.log_avax_fallback_once();
+        None
+    } else {
+        Some(PriceFeedCollector::new(
+            &rpc_url,
+            crate::common::price_oracle::AVAX_BLOCK_TIME,
+            args.worker_config.price_feed_max_age_blocks,
+        )?)
+    };
     
-    // 创建模拟器池
+    // 创建模拟器池；回跑需要在受害交易之后的状态上模拟，此时必须使用 anvil fork
+    let simulate_pending_txs = args.worker_config.simulate_pending_txs;
+    let simulate_timeout = Duration::from_millis(args.worker_config.simulate_timeout_ms);
+    let next_anvil_port = Arc::new(AtomicU16::new(args.worker_config.anvil_base_port));
+    crate::utils::config::set_max_anvils(args.worker_config.max_anvils);
+    crate::utils::config::set_anvil_min_free_mb(args.worker_config.anvil_min_free_mb);
+    let num_simulators = simulator_pool_size(
+        args.worker_config.num_simulators,
+        args.worker_config.max_anvils.filter(|_| simulate_pending_txs),
+    )?;
+    let pool_usage = PoolUsage::new(
+        num_simulators,
+        Duration::from_millis(args.worker_config.simulator_pool_exhausted_warn_ms),
+    );
+    let allow_http_fallback = args.worker_config.allow_http_fallback;
     let simulator_pool: ObjectPool<Box<dyn Simulator>> = {
         let rpc_url = rpc_url.clone();
-        ObjectPool::new(args.worker_config.num_simulators, move || {
+        let next_anvil_port = next_anvil_port.clone();
+        ObjectPool::try_new(num_simulators, move || {
+            let pool_usage = pool_usage.clone();
             let rpc_url = rpc_url.clone();
-            tokio::runtime::Runtime::new()
-                .unwrap()
-                .block_on(async { 
-                    Box::new(HttpSimulator::new(&rpc_url).await) as Box<dyn Simulator> 
-                })
-        })
+            let port = next_anvil_port.fetch_add(1, Ordering::Relaxed);
+            tokio::runtime::Runtime::new()?.block_on(async {
+                let simulator = if simulate_pending_txs {
+                    new_fork_simulator(rpc_url, Some(port), None, allow_http_fallback).await?
+                } else {
+                    Box::new(HttpSimulator::new(&rpc_url, Some(chain_id)).await?) as Box<dyn Simulator>
+                };
+                let simulator = Box::new(TimeoutSimulator::new(simulator, simulate_timeout));
+                Ok(Box::new(MeteredSimulator::new(simulator, pool_usage)) as Box<dyn Simulator>)
+            })
+        })?
     };
 
-    // 创建自己的模拟器实例
-    let own_simulator = Arc::new(HttpSimulator::new(&rpc_url).await) as Arc<dyn Simulator>;
+    // 创建自己的模拟器实例；没有 anvil 时按 --allow-http-fallback 退回 HttpSimulator
+    let own_simulator = if simulate_pending_txs {
+        let port = next_anvil_port.fetch_add(1, Ordering::Relaxed);
+        new_fork_simulator(rpc_url.clone(), Some(port), None, allow_http_fallback).await?
+    } else {
+        Box::new(HttpSimulator::new(&rpc_url, Some(chain_id)).await?) as Box<dyn Simulator>
+    };
+    let own_simulator = Arc::new(TimeoutSimulator::new(own_simulator, simulate_timeout)) as Arc<dyn Simulator>;
+
+    info!("Simulator pool initialized with {} instances", num_simulators);
 
-    info!("Simulator pool initialized with {} instances", args.worker_config.num_simulators);
+    let fee_escalation = FeeEscalation {
+        max_attempts: args.fee_escalation_attempts,
+        step_bps: args.fee_escalation_step_bps,
+        cap: U256::from(args.fee_escalation_cap_gwei) * U256::exp10(9),
+        interval: Duration::from_millis(args.fee_escalation_interval_ms),
+    };
+    // attempts past the quote's validity window only pay more for a stale arb
+    let escalation_window = fee_escalation.interval * (fee_escalation.max_attempts + 1);
+    let validity_window = crate::common::price_oracle::AVAX_BLOCK_TIME * args.worker_config.max_quote_age_blocks as u32;
+    if fee_escalation.is_enabled() && escalation_window > validity_window {
+        warn!(
+            ?escalation_window,
+            ?validity_window,
+            "fee escalation outlasts --max-quote-age-blocks; later attempts re-send stale quotes"
+        );
+    }
+    let wallets = parse_wallets(std::iter::once(&args.private_key).chain(&args.signer_keys).map(Secret::expose))?;
+    let signers = Arc::new(SignerPool::new(&rpc_url, wallets).await?.with_fee_escalation(fee_escalation));
+    // racing executors rely on sending from one nonce so that at most one of their txs lands
+    eyre::ensure!(
+        signers.addresses().len() == 1 || args.submit_strategy != SubmitStrategy::Race,
+        "--submit-strategy race needs a single signer, got {}",
+        signers.addresses().len()
+    );
 
     // 创建套利策略
-    let attacker = args.private_key.parse::<ethers::types::Address>()?;
+    // the private key's address, i.e. the first wallet of the pool
+    let attacker = signers.primary();
+    let contract_address = args.contract_address.as_deref().map(|s| s.parse()).transpose()?;
+    let own_addresses = if args.worker_config.exclude_own_txs {
+        signers.addresses().into_iter().chain(contract_address).collect()
+    } else {
+        HashSet::new()
+    };
+    let simulator_pool = Arc::new(simulator_pool);
+    if !crate::utils::config::route_overrides().is_empty() {
+        Defi::new(&rpc_url, simulator_pool.clone()).await?.validate_route_overrides().await;
+    }
+    let spread_pairs = parse_spread_pairs(&args.worker_config.spread_pairs)?;
+    if !spread_pairs.is_empty() {
+        let defi = Defi::new(&rpc_url, simulator_pool.clone()).await?;
+        spawn_spread_monitor(
+            &rpc_url,
+            defi,
+            spread_pairs,
+            Duration::from_millis(args.worker_config.spread_interval_ms),
+        )?;
+    }
+    if let Some(metrics_addr) = args.worker_config.metrics_addr {
+        serve_metrics(metrics_addr).await?;
+    }
+
     let arb_strategy = ArbStrategy::new(
         attacker,
-        Arc::new(simulator_pool),
+        simulator_pool,
         own_simulator,
         args.worker_config.max_recent_arbs,
         &rpc_url,
         args.worker_config.workers,
         None, // AVAX不需要dedicated_simulator
+        args.worker_config.min_notify_profit_usd,
+        args.worker_config.min_notify_profit_avax,
+        simulate_pending_txs,
+        args.worker_config.log_sample_rate,
+        args.worker_config.channel_full_policy,
+        own_addresses,
+        args.worker_config.receipt_confirmations,
     )
     .await;
 
     // 创建收集器
-    let mempool_collector = AvaxMempoolCollector::new(&args.http_config.ws_url);
+    let mempool_collector = CollectorWatchdog::new(
+        AvaxMempoolCollector::new(&args.http_config.ws_url),
+        &rpc_url,
+        Duration::from_millis(args.worker_config.collector_stall_timeout_ms),
+    )?;
     
     // 创建执行器
-    let contract_address = args.contract_address.as_deref().map(|s| s.parse()).transpose()?;
-    let tx_executor = EnhancedArbExecutor::new(&rpc_url, &args.private_key, contract_address).await?;
-
-    info!("Starting mempool monitoring...");
+    let to_wei = |avax: f64| ethers::utils::parse_ether(avax);
+    let gas_budget = Arc::new(GasBudget::new(
+        args.worker_config.gas_budget_per_block_avax.map(to_wei).transpose()?,
+        args.worker_config.gas_budget_per_hour_avax.map(to_wei).transpose()?,
+    ));
+    let webhook = args
+        .webhook_url
+        .as_deref()
+        .map(|url| WebhookNotifier::new(url, args.webhook_secret.as_ref().map(|s| s.expose().to_string())))
+        .transpose()?;
+    let gas_price_strategy = match args.gas_price_percentile {
+        Some(percentile) => GasPriceStrategy::Percentile {
+            percentile,
+            blocks: args.gas_price_blocks,
+            tip: U256::from(args.gas_price_tip_gwei) * U256::exp10(9),
+        },
+        None => GasPriceStrategy::Node,
+    };
+    let mut executor_manager = ExecutorManager::new(args.submit_strategy);
+    if let Some(target) = &args.bundle_output {
+        info!("Writing arb bundles to {}", target.display());
+        executor_manager.add(
+            Box::new(FileBundleSubmitter::new(
+                &rpc_url,
+                signers.clone(),
+                args.worker_config.max_quote_age_blocks,
+                gas_budget.clone(),
+                target.clone(),
+            )?),
+            args.bundle_executor_priority,
+        );
+    }
+    // with a bundle output, the public mempool is only used when explicitly given a priority
+    if args.bundle_output.is_none() || args.public_executor_priority.is_some() {
+        executor_manager.add(
+            Box::new(
+                PublicTxExecutor::new(
+                    &rpc_url,
+                    signers.clone(),
+                    args.worker_config.max_quote_age_blocks,
+                    gas_budget,
+                )?
+                .with_webhook(webhook.clone())
+                .with_position_guard(PositionGuard::new(args.worker_config.max_position_fraction))
+                .with_gas_price_strategy(gas_price_strategy),
+            ),
+            args.public_executor_priority.unwrap_or_default(),
+        );
+    }
+    info!(
+        strategy = ?args.submit_strategy,
+        executors = ?executor_manager.executor_names(),
+        "Action executors configured"
+    );
+    let action_executor: Box<dyn Executor<Action>> = Box::new(executor_manager);
 
     // 启动心跳
     heartbeat::start("avax-mev-bot", Duration::from_secs(30));
 
     info!("AVAX MEV Bot initialized successfully!");
-    info!("Starting event processing loop...");
-
-    // 创建分析器
-    let transaction_analyzer = TransactionAnalyzer::new();
-    let arbitrage_analyzer = ArbitrageAnalyzer::new();
-
-    // 创建事件处理循环
-    use crate::engine::Collector;
-    use futures::StreamExt;
-    
-    let mut event_stream = mempool_collector.get_event_stream().await?;
-    
     info!("Monitoring mempool for arbitrage opportunities...");
-    
-    while let Some(event) = event_stream.next().await {
-        match event {
-            Event::PendingTx(tx) => {
-                // 使用交易分析器提取代币信息
-                if let Some(token_address) = transaction_analyzer.extract_token_from_tx(&tx) {
-                    info!("Processing pending tx: {:?}, token: {:?}", tx.hash, token_address);
-                    
-                    // 使用套利分析器寻找套利机会
-                    match arbitrage_analyzer.find_arbitrage_opportunity(
-                        &arb_strategy, 
-                        &token_address, 
-                        attacker,
-                        &rpc_url
-                    ).await {
-                        Ok(Some(opportunity)) => {
-                            // 使用新的详细显示方法
-                            opportunity.display();
-                            
-                            // 在实际部署中，这里会执行套利交易
-                            // tx_executor.execute(opportunity.tx_data).await?;
-                            info!("Arbitrage opportunity logged (execution disabled in demo mode)");
-                        },
-                        Ok(None) => {
-                            // 没有发现套利机会，这是正常的
-                        },
-                        Err(e) => {
-                            warn!("Error analyzing arbitrage opportunity: {}", e);
-                        }
-                    }
-                }
-            },
-            _ => {
-                // 处理其他类型的事件
-            }
-        }
-    }
 
-    Ok(())
+    // 事件循环：收集器 → 套利策略（workers 通过 submitter 提交动作）→ ExecutorManager
+    let mut engine = Engine::<Event, Action>::default();
+    engine.add_collector(Box::new(mempool_collector));
+    engine.add_collector(Box::new(DumpSignalCollector));
+    if let Some(price_feed_collector) = price_feed_collector {
+        engine.add_collector(Box::new(price_feed_collector));
+    }
+    engine.add_strategy(Box::new(arb_strategy.with_webhook(webhook).with_arb_contract(contract_address)));
+    engine.add_executor(action_executor);
+    engine
+        .run_and_join()
+        .await
+        .map_err(|error| eyre::eyre!("engine stopped: {error}"))
 }
-
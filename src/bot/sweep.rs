@@ -0,0 +1,69 @@
+use clap::Parser;
+use ethers::types::Address;
+use eyre::{bail, Result};
+use tracing::{info, warn};
+
+use crate::{
+    bot::signer_pool::{parse_wallets, SignerPool},
+    utils::{
+        config::{set_safe_mode, WritesDisabled},
+        secret::Secret,
+    },
+    HttpConfig,
+};
+
+#[derive(Clone, Debug, Parser)]
+pub struct Args {
+    #[arg(long, env = "AVAX_PRIVATE_KEY")]
+    pub private_key: Secret,
+
+    /// The other signers to sweep, same format as `start-bot --signer-keys`.
+    #[arg(long, env = "SIGNER_KEYS", value_delimiter = ',')]
+    pub signer_keys: Vec<Secret>,
+
+    /// Cold wallet receiving the funds.
+    #[arg(long, env = "SWEEP_TO")]
+    pub to: Address,
+
+    /// ERC20 to sweep (e.g. WAVAX profit). Native AVAX when unset.
+    #[arg(long)]
+    pub token: Option<Address>,
+
+    /// AVAX left in each signer to keep paying for gas.
+    #[arg(long, default_value_t = 0.5)]
+    pub reserve_avax: f64,
+
+    /// Only log what would be swept, same switch as `start-bot --safe-mode`.
+    #[arg(long, env = "SAFE_MODE", default_value_t = false)]
+    pub safe_mode: bool,
+
+    #[command(flatten)]
+    pub http_config: HttpConfig,
+}
+
+/// Move accumulated profit from every signer of the pool to a cold wallet.
+pub async fn run(args: Args) -> Result<()> {
+    tracing_subscriber::fmt().with_env_filter("info").init();
+    set_safe_mode(args.safe_mode);
+
+    let wallets = parse_wallets(std::iter::once(&args.private_key).chain(&args.signer_keys).map(Secret::expose))?;
+    let signers = SignerPool::new(&args.http_config.rpc_url, wallets).await?;
+    let reserve = ethers::utils::parse_ether(args.reserve_avax)?;
+
+    let mut failures = 0;
+    for (signer, result) in signers.sweep(args.to, args.token, reserve).await {
+        match result {
+            Ok(Some(receipt)) => info!(?signer, tx_hash = ?receipt.transaction_hash, "swept"),
+            Ok(None) => info!(?signer, "nothing to sweep"),
+            Err(error) if error.chain().any(|e| e.is::<WritesDisabled>()) => info!(?signer, "safe mode: not swept"),
+            Err(error) => {
+                failures += 1;
+                warn!(?signer, "sweep failed: {error:#}");
+            }
+        }
+    }
+    if failures > 0 {
+        bail!("{failures} signer(s) failed to sweep");
+    }
+    Ok(())
+}
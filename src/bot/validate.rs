@@ -0,0 +1,202 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    signers::Signer,
+    types::Address,
+};
+use eyre::{bail, eyre, Result};
+
+use crate::{
+    bot::{signer_pool::parse_wallets, start_bot::Args},
+    common::spread_metrics::parse_spread_pairs,
+    simulator::{FoundrySimulator, AVALANCHE_FUJI_CHAIN_ID, AVALANCHE_MAINNET_CHAIN_ID},
+    utils::{
+        config::{
+            parse_flashloan_premiums, parse_pool_fee_overrides, parse_protocols, parse_referral_rebates,
+            parse_token_probe_amounts,
+        },
+        secret::Secret,
+        token_config::TokenConfig,
+    },
+};
+
+/// Give up on an endpoint after this long, so a black-holed URL fails the check instead of hanging it.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Pass => write!(f, "PASS"),
+            Status::Warn => write!(f, "WARN"),
+            Status::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Report {
+    failures: usize,
+}
+
+impl Report {
+    fn check<T>(&mut self, name: &str, result: Result<T>, describe: impl FnOnce(&T) -> String) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.line(Status::Pass, name, &describe(&value));
+                Some(value)
+            }
+            Err(error) => {
+                self.line(Status::Fail, name, &format!("{error:#}"));
+                None
+            }
+        }
+    }
+
+    fn line(&mut self, status: Status, name: &str, detail: &str) {
+        if matches!(status, Status::Fail) {
+            self.failures += 1;
+        }
+        println!("[{status}] {name:<14} {detail}");
+    }
+}
+
+async fn with_timeout<T>(fut: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+    tokio::time::timeout(CONNECT_TIMEOUT, fut)
+        .await
+        .map_err(|_| eyre!("timed out after {CONNECT_TIMEOUT:?}"))?
+}
+
+/// Preflight for `start-bot`: takes the same flags and env, checks config, signer, RPC, WS and anvil
+/// without indexing pools or spawning workers, and fails if any required check fails.
+pub async fn run(args: Args) -> Result<()> {
+    let mut report = Report::default();
+    let config = &args.worker_config;
+
+    report.check(
+        "pool fees",
+        parse_pool_fee_overrides(&config.pool_fee_overrides),
+        |overrides| format!("{} override(s)", overrides.len()),
+    );
+    report.check(
+        "protocols",
+        parse_protocols(&config.protocols),
+        |protocols| {
+            if protocols.is_empty() {
+                "all supported".to_string()
+            } else {
+                protocols.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")
+            }
+        },
+    );
+    report.check(
+        "flashloan fees",
+        parse_flashloan_premiums(&config.flashloan_premiums),
+        |premiums| format!("{} override(s)", premiums.len()),
+    );
+    report.check(
+        "referral rebates",
+        parse_referral_rebates(&config.referral_rebates),
+        |rebates| match config.referral_address {
+            Some(address) => format!("{} protocol(s) to {address:?}", rebates.len()),
+            None => "off: no referral address".to_string(),
+        },
+    );
+    report.check(
+        "probe amounts",
+        parse_token_probe_amounts(&config.token_probe_amounts),
+        |overrides| format!("{} token override(s)", overrides.len()),
+    );
+    report.check(
+        "spread pairs",
+        parse_spread_pairs(&config.spread_pairs),
+        |pairs| pairs.iter().map(|p| p.label.as_str()).collect::<Vec<_>>().join(", "),
+    );
+    report.check(
+        "wavax",
+        Address::from_str(config.wavax_address.trim()).map_err(|e| eyre!("{:?}: {e}", config.wavax_address)),
+        |address| format!("{address:?}"),
+    );
+    if let Some(gas_token) = &config.gas_token {
+        report.check(
+            "gas token",
+            Address::from_str(gas_token.trim()).map_err(|e| eyre!("{gas_token:?}: {e}")),
+            |address| format!("{address:?}"),
+        );
+    }
+    let profit_token = config.profit_token.clone().unwrap_or_else(|| config.wavax_address.clone());
+    report.check(
+        "profit token",
+        TokenConfig::new()
+            .get_token_by_address(&profit_token)
+            .ok_or_else(|| eyre!("unknown profit token {profit_token}")),
+        |token| format!("{profit_token} ({} decimals)", token.decimals),
+    );
+
+    report.check(
+        "signers",
+        parse_wallets(std::iter::once(&args.private_key).chain(&args.signer_keys).map(Secret::expose)),
+        |wallets| wallets.iter().map(|w| format!("{:?}", w.address())).collect::<Vec<_>>().join(", "),
+    );
+    if args.safe_mode {
+        report.line(Status::Warn, "safe mode", "on: no tx will be signed or sent");
+    }
+    if let Some(webhook_url) = &args.webhook_url {
+        report.check(
+            "webhook",
+            webhook_url.parse::<reqwest::Url>().map_err(|e| eyre!("{webhook_url:?}: {e}")),
+            |url| format!("{url}{}", if args.webhook_secret.is_some() { " (signed)" } else { "" }),
+        );
+    }
+    if let Some(contract_address) = &args.contract_address {
+        report.check(
+            "contract",
+            Address::from_str(contract_address).map_err(|e| eyre!("{contract_address:?}: {e}")),
+            |address| format!("{address:?}"),
+        );
+    }
+
+    let rpc_url = &args.http_config.rpc_url;
+    let rpc = with_timeout(async {
+        let provider = Provider::<Http>::try_from(rpc_url.as_str())?;
+        let chain_id = provider.get_chainid().await?.as_u64();
+        let block_number = provider.get_block_number().await?.as_u64();
+        Ok((chain_id, block_number))
+    })
+    .await;
+    if let Some((chain_id, _)) = report.check("rpc", rpc, |(chain_id, block_number)| {
+        format!("{rpc_url}: chain id {chain_id}, block {block_number}")
+    }) {
+        if chain_id != AVALANCHE_MAINNET_CHAIN_ID && chain_id != AVALANCHE_FUJI_CHAIN_ID {
+            report.line(Status::Warn, "chain id", &format!("{chain_id} is neither Avalanche mainnet nor Fuji"));
+        }
+    }
+
+    let ws_url = &args.http_config.ws_url;
+    let ws = with_timeout(async {
+        let (mut stream, _) = tokio_tungstenite::connect_async(ws_url.as_str()).await?;
+        let _ = stream.close(None).await;
+        Ok(())
+    })
+    .await;
+    report.check("ws", ws, |_| ws_url.clone());
+
+    match FoundrySimulator::check_anvil() {
+        Ok(version) => report.line(Status::Pass, "anvil", &version),
+        // only the fork simulators need anvil, unless they may fall back to RPC calls
+        Err(error) if config.simulate_pending_txs && !config.allow_http_fallback => report.line(Status::Fail, "anvil", &format!("{error:#}")),
+        Err(error) => report.line(Status::Warn, "anvil", &format!("{error:#}")),
+    }
+
+    if report.failures > 0 {
+        bail!("{} check(s) failed", report.failures);
+    }
+    println!("all checks passed");
+    Ok(())
+}
@@ -1,8 +1,11 @@
 pub mod notification;
+pub mod price_oracle;
 pub mod search;
+pub mod spread_metrics;
+pub mod webhook;
 
 use eyre::Result;
-use ethers::{providers::{Http, Provider, Middleware}, types::{BlockId, BlockNumber}};
+use ethers::{providers::{Http, Provider, Middleware}, types::{Address, BlockId, BlockNumber}};
 use std::sync::Arc;
 use crate::bot::simulator::SimEpoch;
 
@@ -19,3 +22,22 @@ pub async fn get_latest_block(rpc_url: &str) -> Result<BlockNumber> {
     let latest_block = provider.get_block_number().await?;
     Ok(latest_block)
 }
+
+pub async fn get_chain_id(rpc_url: &str) -> Result<u64> {
+    let provider = Provider::<Http>::try_from(rpc_url)?;
+    let chain_id = provider.get_chainid().await?;
+    Ok(chain_id.as_u64())
+}
+
+/// The `(label, address)` entries of `contracts` with no code on the connected chain, e.g. mainnet routers
+/// when the RPC points at Fuji.
+pub async fn missing_contracts(rpc_url: &str, contracts: &[(String, Address)]) -> Result<Vec<(String, Address)>> {
+    let provider = Provider::<Http>::try_from(rpc_url)?;
+    let mut missing = vec![];
+    for (label, address) in contracts {
+        if provider.get_code(*address, None).await?.is_empty() {
+            missing.push((label.clone(), *address));
+        }
+    }
+    Ok(missing)
+}
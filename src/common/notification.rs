@@ -1,8 +1,15 @@
 use std::{fmt::Write, time::Duration};
 
 use infra::executor::telegram_message::{escape, Message, MessageBuilder};
-use ethers::types::H256;
-use utils::{coin, link, telegram};
+use ethers::types::{H256, U256};
+use utils::{
+    coin,
+    config::{is_wavax, pegged_usd_value},
+    link, telegram,
+    token_config::TokenConfig,
+};
+
+use super::price_oracle::price_oracle;
 
 use strategy::{arb::ArbResult, BUILD_VERSION};
 
@@ -11,6 +18,28 @@ const GROUP_AVAX_ARB: &str = "";
 const THREAD_LOW_PROFIT: &str = "";
 const THREAD_HIGH_PROFIT: &str = "";
 
+/// USD value of the arb's profit when it is realized in a pegged stablecoin, or in WAVAX while the
+/// AVAX/USD feed is fresh. `None` otherwise.
+pub fn profit_usd(res: &ArbResult) -> Option<f64> {
+    let trade_res = &res.best_trial_result;
+    let decimals = TokenConfig::new().get_token_by_address(&trade_res.token_address)?.decimals;
+    if let Some(usd) = pegged_usd_value(&trade_res.token_address, trade_res.profit, decimals) {
+        return Some(usd);
+    }
+
+    profit_avax(res).and_then(|avax| price_oracle().avax_usd().map(|price| avax * price))
+}
+
+/// Profit in AVAX when it is realized in WAVAX, `None` otherwise.
+pub fn profit_avax(res: &ArbResult) -> Option<f64> {
+    let trade_res = &res.best_trial_result;
+    if !is_wavax(&trade_res.token_address) {
+        return None;
+    }
+
+    Some(coin::wei_to_avax(trade_res.profit))
+}
+
 pub fn new_tg_messages(
     digest: H256,
     arb_digest: H256,
@@ -76,7 +105,7 @@ pub fn new_tg_messages(
 
     println!("msg: {}", msg);
 
-    let thread_id = if trade_res.profit > 1000000000 {
+    let thread_id = if trade_res.profit > U256::from(1_000_000_000u64) {
         "125670"
     } else {
         telegram::CHAT_MONEY_PRINTER_THREAD_TEST
@@ -91,7 +120,7 @@ pub fn new_tg_messages(
         .build();
 
     // AVAX Arbitrage Group
-    let thread_id = if trade_res.profit > 1000000000 {
+    let thread_id = if trade_res.profit > U256::from(1_000_000_000u64) {
         THREAD_HIGH_PROFIT
     } else {
         THREAD_LOW_PROFIT
@@ -0,0 +1,244 @@
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{transaction::eip2718::TypedTransaction, Address, Bytes, TransactionRequest, I256, U256},
+};
+use eyre::{ensure, Result};
+use once_cell::sync::Lazy;
+use tracing::{debug, warn};
+
+use crate::{
+    engine::{async_trait, Collector, CollectorStream},
+    types::{Event, SystemEvent},
+};
+
+/// Chainlink AVAX / USD aggregator on Avalanche C-Chain.
+pub const CHAINLINK_AVAX_USD_FEED: &str = "0x0A77230d17318075983913bC2145DB16C7366156";
+
+/// `latestRoundData()` selector
+const LATEST_ROUND_DATA: [u8; 4] = [0xfe, 0xaf, 0x96, 0x8c];
+const FEED_DECIMALS: i32 = 8;
+
+/// Average C-Chain block time, used to turn a block budget into a wall-clock one.
+pub const AVAX_BLOCK_TIME: Duration = Duration::from_secs(2);
+
+struct PricePoint {
+    avax_usd: f64,
+    // the feed's own `updatedAt`, not when it was read: a feed that stopped updating reads fine but is stale
+    updated_at: SystemTime,
+}
+
+impl PricePoint {
+    fn age(&self) -> Duration {
+        SystemTime::now().duration_since(self.updated_at).unwrap_or_default()
+    }
+}
+
+/// Cached AVAX/USD price. Readers only get a price while it is fresh, so USD thresholds never run on a
+/// price that froze during an RPC outage.
+pub struct PriceOracle {
+    price: RwLock<Option<PricePoint>>,
+    max_age: RwLock<Duration>,
+    // no feed configured: USD thresholds never apply
+    disabled: AtomicBool,
+    // the AVAX-threshold fallback was logged since the last fresh price
+    fallback_logged: AtomicBool,
+}
+
+impl PriceOracle {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            price: RwLock::new(None),
+            max_age: RwLock::new(max_age),
+            disabled: AtomicBool::new(false),
+            fallback_logged: AtomicBool::new(false),
+        }
+    }
+
+    /// Mark the oracle as having no feed, e.g. with `--no-price-feed`.
+    pub fn disable(&self) {
+        self.disabled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled.load(Ordering::Relaxed)
+    }
+
+    /// Say, once per outage, that USD thresholds are being replaced by AVAX ones. Returns whether it logged.
+    pub fn log_avax_fallback_once(&self) -> bool {
+        if self.fallback_logged.swap(true, Ordering::Relaxed) {
+            return false;
+        }
+        if self.is_disabled() {
+            warn!("no AVAX/USD price feed configured, WAVAX profit is gated by AVAX-denominated thresholds");
+        } else {
+            warn!("AVAX/USD price is stale, WAVAX profit is gated by AVAX-denominated thresholds until it recovers");
+        }
+        true
+    }
+
+    pub fn set_max_age(&self, max_age: Duration) {
+        *self.max_age.write().unwrap() = max_age;
+    }
+
+    /// Record the feed's answer `avax_usd` of round time `updated_at`.
+    pub fn update(&self, avax_usd: f64, updated_at: SystemTime) {
+        let point = PricePoint { avax_usd, updated_at };
+        if point.age() <= *self.max_age.read().unwrap() {
+            self.fallback_logged.store(false, Ordering::Relaxed);
+        }
+        *self.price.write().unwrap() = Some(point);
+    }
+
+    /// AVAX price in USD, `None` if never fetched or stale.
+    pub fn avax_usd(&self) -> Option<f64> {
+        let max_age = *self.max_age.read().unwrap();
+        self.price
+            .read()
+            .unwrap()
+            .as_ref()
+            .filter(|p| p.age() <= max_age)
+            .map(|p| p.avax_usd)
+    }
+
+    /// Time since the feed last updated the cached price, `None` if never fetched.
+    pub fn age(&self) -> Option<Duration> {
+        self.price.read().unwrap().as_ref().map(PricePoint::age)
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.avax_usd().is_none()
+    }
+}
+
+static PRICE_ORACLE: Lazy<PriceOracle> = Lazy::new(|| PriceOracle::new(AVAX_BLOCK_TIME * 30));
+
+pub fn price_oracle() -> &'static PriceOracle {
+    &PRICE_ORACLE
+}
+
+/// Read the latest answer of a Chainlink aggregator, scaled to a float, with the time it was updated at.
+pub async fn fetch_chainlink_price(provider: &Provider<Http>, feed: Address) -> Result<(f64, SystemTime)> {
+    let tx: TypedTransaction = TransactionRequest::new()
+        .to(feed)
+        .data(Bytes::from(LATEST_ROUND_DATA.to_vec()))
+        .into();
+    let output = provider.call(&tx, None).await?;
+
+    // (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound)
+    ensure!(output.len() >= 160, "unexpected latestRoundData output length {}", output.len());
+    let answer = I256::from_raw(ethers::types::U256::from_big_endian(&output[32..64]));
+    ensure!(answer > I256::zero(), "non-positive answer {answer}");
+    let updated_at = U256::from_big_endian(&output[96..128]);
+    ensure!(!updated_at.is_zero() && updated_at <= U256::from(u64::MAX), "bad updatedAt {updated_at}");
+
+    Ok((
+        answer.as_i128() as f64 / 10f64.powi(FEED_DECIMALS),
+        UNIX_EPOCH + Duration::from_secs(updated_at.as_u64()),
+    ))
+}
+
+/// Polls the AVAX/USD feed every `interval` and refreshes the global oracle. Emits
+/// `SystemEvent::PriceFeedStale` when the feed's last update is older than `max_age_blocks`, and
+/// `SystemEvent::PriceFeedRecovered` once it is fresh again.
+pub struct PriceFeedCollector {
+    provider: Provider<Http>,
+    feed: Address,
+    interval: Duration,
+    max_age_blocks: u32,
+}
+
+impl PriceFeedCollector {
+    pub fn new(rpc_url: &str, interval: Duration, max_age_blocks: u32) -> Result<Self> {
+        price_oracle().set_max_age(AVAX_BLOCK_TIME * max_age_blocks);
+        Ok(Self {
+            provider: Provider::<Http>::try_from(rpc_url)?,
+            feed: Address::from_str(CHAINLINK_AVAX_USD_FEED)?,
+            interval,
+            max_age_blocks,
+        })
+    }
+}
+
+#[async_trait]
+impl Collector<Event> for PriceFeedCollector {
+    fn name(&self) -> &str {
+        "PriceFeedCollector"
+    }
+
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, Event>> {
+        let oracle = price_oracle();
+        let max_age_blocks = self.max_age_blocks;
+
+        let stream = async_stream::stream! {
+            let mut was_stale = false;
+            loop {
+                match fetch_chainlink_price(&self.provider, self.feed).await {
+                    Ok((price, updated_at)) => {
+                        debug!(price, ?updated_at, "AVAX/USD price updated");
+                        oracle.update(price, updated_at);
+                    }
+                    Err(error) => warn!(?error, "failed to fetch AVAX/USD price"),
+                }
+
+                let stale = oracle.is_stale();
+                if stale && !was_stale {
+                    warn!(
+                        max_age_blocks,
+                        age = ?oracle.age(),
+                        "AVAX/USD price feed is stale, falling back to AVAX-denominated thresholds"
+                    );
+                    yield Event::System(SystemEvent::PriceFeedStale { age: oracle.age() });
+                } else if let Some(avax_usd) = oracle.avax_usd().filter(|_| was_stale) {
+                    warn!(avax_usd, "AVAX/USD price feed recovered");
+                    yield Event::System(SystemEvent::PriceFeedRecovered { avax_usd });
+                }
+                was_stale = stale;
+
+                tokio::time::sleep(self.interval).await;
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_oracle_staleness() {
+        let oracle = PriceOracle::new(Duration::from_secs(60));
+        assert!(oracle.is_stale());
+
+        oracle.update(25.0, SystemTime::now());
+        assert_eq!(oracle.avax_usd(), Some(25.0));
+
+        // a round the feed posted long ago is stale as soon as it is read
+        oracle.update(26.0, SystemTime::now() - Duration::from_secs(120));
+        assert!(oracle.is_stale());
+        assert!(oracle.age().unwrap() >= Duration::from_secs(120));
+    }
+
+    #[test]
+    fn test_avax_fallback_logged_once_per_outage() {
+        let oracle = PriceOracle::new(Duration::from_secs(60));
+        oracle.disable();
+        assert!(oracle.is_disabled());
+        assert!(oracle.log_avax_fallback_once());
+        assert!(!oracle.log_avax_fallback_once());
+
+        // a fresh price ends the outage, the next one is logged again
+        oracle.update(25.0, SystemTime::now());
+        assert!(oracle.log_avax_fallback_once());
+    }
+}
@@ -211,6 +211,75 @@ where
     (max_in, max_f, max_out)
 }
 
+/// Relative precision `bisect_last_positive` stops at, as a divisor of the best input.
+const BISECT_REL_TOLERANCE: u128 = 1_000;
+
+/// Binary search for the largest input in `[min, max]` the `goal` still scores above zero, e.g. the largest
+/// trade a path stays profitable at. Assumes scores only fall past `min`, as a profit curve does beyond its
+/// optimum, so `min` should be the optimum or a size below it. Stops once the bracket is within
+/// `1 / BISECT_REL_TOLERANCE` (0.1%) of the best input, since each step costs a simulation.
+///
+/// Returns the input with its output, or `None` when `min` itself scores zero.
+pub async fn bisect_last_positive<T, INP, OUT>(
+    min: INP,
+    max: INP,
+    goal: impl SearchGoal<T, INP, OUT>,
+    additional_ctx: &T,
+) -> Option<(INP, OUT)>
+where
+    INP: Copy
+        + Clone
+        + std::ops::Add<Output = INP>
+        + std::ops::Div<Output = INP>
+        + std::ops::Sub<Output = INP>
+        + std::ops::Mul<Output = INP>
+        + PartialOrd
+        + PartialEq
+        + Ord
+        + Eq
+        + std::hash::Hash
+        + std::fmt::Debug
+        + TryFrom<u128>,
+    OUT: Clone,
+{
+    assert!(min <= max);
+
+    let (zero, one, two, tolerance) = match (
+        INP::try_from(0),
+        INP::try_from(1),
+        INP::try_from(2),
+        INP::try_from(BISECT_REL_TOLERANCE),
+    ) {
+        (Ok(zero), Ok(one), Ok(two), Ok(tolerance)) => (zero, one, two, tolerance),
+        _ => unreachable!("Can't convert 0, 1, 2 or the tolerance to INP type"),
+    };
+
+    let (f, out) = goal.evaluate(min, additional_ctx).await;
+    if f <= zero {
+        return None;
+    }
+    let mut best = (min, out);
+
+    let (f, out) = goal.evaluate(max, additional_ctx).await;
+    if f > zero {
+        return Some((max, out));
+    }
+
+    // invariant: `best.0` scores above zero, `right` doesn't
+    let mut right = max;
+    while right - best.0 > one && right - best.0 > best.0 / tolerance {
+        let mid = best.0 + (right - best.0) / two;
+        let (f, out) = goal.evaluate(mid, additional_ctx).await;
+        if f > zero {
+            best = (mid, out);
+        } else {
+            right = mid;
+        }
+    }
+
+    Some(best)
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -273,4 +342,39 @@ mod tests {
         assert_eq!(input, 7);
         assert_eq!(output, 4729882751161429615u128);
     }
+
+    #[tokio::test]
+    async fn test_bisect_last_positive() {
+        // profit peaks at 40 and turns negative (clamped to zero) past 73
+        struct TestGoal;
+
+        #[async_trait]
+        impl SearchGoal<(), u128, i128> for TestGoal {
+            async fn evaluate(&self, inp: u128, _: &()) -> (u128, i128) {
+                let profit = 1_089 - (inp as i128 - 40).pow(2);
+                (profit.max(0) as u128, profit)
+            }
+        }
+
+        assert_eq!(bisect_last_positive(40u128, 1_000, TestGoal, &()).await, Some((72, 65)));
+        assert_eq!(bisect_last_positive(10u128, 50, TestGoal, &()).await, Some((50, 989)));
+        assert_eq!(bisect_last_positive(73u128, 1_000, TestGoal, &()).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_bisect_last_positive_stops_at_relative_tolerance() {
+        // profitable below 10^21; pinning the frontier down to a single unit would take ~73 steps
+        struct TestGoal;
+
+        #[async_trait]
+        impl SearchGoal<(), u128, ()> for TestGoal {
+            async fn evaluate(&self, inp: u128, _: &()) -> (u128, ()) {
+                (1_000_000_000_000_000_000_000u128.saturating_sub(inp), ())
+            }
+        }
+
+        let (found, _) = bisect_last_positive(1u128, 10u128.pow(22), TestGoal, &()).await.unwrap();
+        assert!(found < 10u128.pow(21));
+        assert!(found >= 10u128.pow(21) / 1_000 * 999);
+    }
 }
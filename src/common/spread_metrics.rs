@@ -0,0 +1,190 @@
+use std::{net::SocketAddr, time::Duration};
+
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::Address,
+};
+use eyre::{eyre, Result};
+use once_cell::sync::Lazy;
+use prometheus::{register_gauge_vec, Encoder, GaugeVec, TextEncoder};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    dex::{median_mid, pair_spread, Defi, ReserveCache},
+    utils::token_config::TokenConfig,
+};
+
+static DEX_PRICE: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "arb_pair_dex_price",
+        "Mid price of the pair on each DEX, in whole quote tokens per base token",
+        &["pair", "dex"]
+    )
+    .unwrap()
+});
+
+static DEX_DEVIATION_BPS: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "arb_pair_dex_deviation_bps",
+        "Deviation of each DEX's mid price from the median across DEXes, in bps",
+        &["pair", "dex"]
+    )
+    .unwrap()
+});
+
+static SPREAD_BPS: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "arb_pair_spread_bps",
+        "Best bid on one DEX against best ask on another, net of pool fees, in bps",
+        &["pair"]
+    )
+    .unwrap()
+});
+
+/// A watched pair and its metrics label, e.g. "WAVAX/USDC".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpreadPair {
+    pub base: Address,
+    pub quote: Address,
+    pub label: String,
+}
+
+/// Parse `<base>/<quote>` token address entries, e.g. from `--spread-pairs` / `SPREAD_PAIRS`.
+pub fn parse_spread_pairs<S: AsRef<str>>(entries: &[S]) -> Result<Vec<SpreadPair>> {
+    let tokens = TokenConfig::new();
+    let symbol = |token: Address| {
+        tokens
+            .get_token_by_address(&format!("{token:?}"))
+            .map(|t| t.symbol.clone())
+            .ok_or_else(|| eyre!("unknown token {token:?}"))
+    };
+
+    entries
+        .iter()
+        .map(|entry| {
+            let entry = entry.as_ref().trim();
+            let (base, quote) = entry
+                .split_once('/')
+                .ok_or_else(|| eyre!("invalid spread pair {entry:?}, expected <base>/<quote>"))?;
+            let base = base.trim().parse::<Address>().map_err(|e| eyre!("invalid token {base:?}: {e}"))?;
+            let quote = quote.trim().parse::<Address>().map_err(|e| eyre!("invalid token {quote:?}: {e}"))?;
+            eyre::ensure!(base != quote, "spread pair {entry:?} has the same token twice");
+            let label = format!("{}/{}", symbol(base)?, symbol(quote)?);
+            Ok(SpreadPair { base, quote, label })
+        })
+        .collect()
+}
+
+/// Record every pair's per-DEX prices and cross-DEX spread whenever a new block is seen, whether or not an arb
+/// is taken. Polls every `interval`; reserves are fetched once per pool per block.
+pub fn spawn_spread_monitor(rpc_url: &str, defi: Defi, pairs: Vec<SpreadPair>, interval: Duration) -> Result<()> {
+    let provider = Provider::<Http>::try_from(rpc_url)?;
+
+    tokio::spawn(async move {
+        let mut cache = ReserveCache::new();
+        let mut last_block = 0;
+        loop {
+            match provider.get_block_number().await {
+                Ok(block) if block.as_u64() != last_block => {
+                    last_block = block.as_u64();
+                    for pair in &pairs {
+                        if let Err(error) = record_pair(&defi, pair, last_block, &mut cache).await {
+                            debug!(pair = %pair.label, "failed to record spread: {error:#}");
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(error) => warn!(?error, "failed to fetch block number for spread metrics"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    Ok(())
+}
+
+async fn record_pair(defi: &Defi, pair: &SpreadPair, block: u64, cache: &mut ReserveCache) -> Result<()> {
+    let quotes = defi.venue_quotes(pair.base, pair.quote, block, cache).await?;
+    let median = median_mid(&quotes).ok_or_else(|| eyre!("no venue quotes"))?;
+
+    for quote in &quotes {
+        DEX_PRICE.with_label_values(&[&pair.label, &quote.dex]).set(quote.mid);
+        DEX_DEVIATION_BPS
+            .with_label_values(&[&pair.label, &quote.dex])
+            .set((quote.mid - median) / median * 10_000.0);
+    }
+    if let Some(spread) = pair_spread(&quotes) {
+        SPREAD_BPS.with_label_values(&[&pair.label]).set(spread.spread_bps);
+        debug!(
+            pair = %pair.label,
+            block,
+            bid_dex = %spread.bid_dex,
+            ask_dex = %spread.ask_dex,
+            spread_bps = spread.spread_bps,
+            "pair spread"
+        );
+    }
+    Ok(())
+}
+
+/// Serve the default Prometheus registry as plain text on `addr`, for any request path.
+pub async fn serve_metrics(addr: SocketAddr) -> Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("Serving Prometheus metrics on {addr}");
+
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(error) => {
+                    warn!(?error, "failed to accept metrics connection");
+                    continue;
+                }
+            };
+            tokio::spawn(async move {
+                // the request itself doesn't matter, only drain it
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request).await;
+
+                let mut body = vec![];
+                if let Err(error) = TextEncoder::new().encode(&prometheus::gather(), &mut body) {
+                    warn!(?error, "failed to encode metrics");
+                    return;
+                }
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    TextEncoder::new().format_type(),
+                    body.len()
+                );
+                let _ = stream.write_all(header.as_bytes()).await;
+                let _ = stream.write_all(&body).await;
+            });
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spread_pairs() {
+        let pairs = parse_spread_pairs(&[
+            "0xB31f66AA3C1e785363F0875A1B74E27b85FD66c7/0xB97EF9Ef8734C71904D8002F8b6Bc66Dd9c48a6E",
+        ])
+        .unwrap();
+        assert_eq!(pairs[0].label, "WAVAX/USDC");
+
+        assert!(parse_spread_pairs(&["0xB31f66AA3C1e785363F0875A1B74E27b85FD66c7"]).is_err());
+        assert!(parse_spread_pairs(&[
+            "0xB31f66AA3C1e785363F0875A1B74E27b85FD66c7/0xB31f66AA3C1e785363F0875A1B74E27b85FD66c7"
+        ])
+        .is_err());
+    }
+}
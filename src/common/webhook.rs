@@ -0,0 +1,294 @@
+use std::time::Duration;
+
+use ethers::types::{Address, TransactionReceipt, H256};
+use eyre::{bail, Result};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::warn;
+
+use crate::{
+    strategy::{arb::TrialResult, arbitrage_analyzer::ArbitrageOpportunity},
+    types::SystemEvent,
+    utils::{current_time_ms, token_config::TokenConfig},
+};
+
+/// Bumped on any breaking change to `WebhookPayload`. Fields may be added within a version, but are never
+/// renamed, retyped or removed.
+pub const WEBHOOK_SCHEMA_VERSION: u32 = 1;
+
+const MAX_ATTEMPTS: u32 = 4;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Body of every webhook POST. Token amounts are decimal strings of raw units, since they overflow
+/// JSON-safe integers:
+///
+/// ```json
+/// {
+///   "schema_version": 1,
+///   "sent_at_ms": 1760000000000,
+///   "kind": "opportunity",
+///   "token": "0xb31f66aa3c1e785363f0875a1b74e27b85fd66c7",
+///   "token_symbol": "WAVAX",
+///   "amount_in": "1000000000000000000",
+///   "amount_out": "1004000000000000000",
+///   "gas_cost": "1000000000000000",
+///   "net_profit": "3000000000000000",
+///   "hops": [{ "protocol": "TraderJoe", "pool": "0x…", "token_in": "0x…", "token_out": "0x…" }]
+/// }
+/// ```
+///
+/// A fill has `"kind": "fill"` with `tx_hash`, `block_number`, `success`, `gas_used` and
+/// `effective_gas_price`. A system event has `"kind": "system"` with `event` (`price_feed_stale` or
+/// `price_feed_recovered`) and `message`. When a secret is configured the body's HMAC-SHA256 is sent as
+/// `X-Arb-Signature: sha256=<hex>`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    pub schema_version: u32,
+    pub sent_at_ms: u64,
+    #[serde(flatten)]
+    pub event: WebhookEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    Opportunity(OpportunityPayload),
+    Fill(FillPayload),
+    System(SystemPayload),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpportunityPayload {
+    pub token: String,
+    pub token_symbol: String,
+    pub amount_in: String,
+    pub amount_out: String,
+    pub gas_cost: String,
+    pub net_profit: String,
+    pub hops: Vec<HopPayload>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HopPayload {
+    pub protocol: String,
+    pub pool: Address,
+    pub token_in: String,
+    pub token_out: String,
+}
+
+impl From<&ArbitrageOpportunity> for OpportunityPayload {
+    fn from(opportunity: &ArbitrageOpportunity) -> Self {
+        Self {
+            token: opportunity.token_address.clone(),
+            token_symbol: opportunity.token_name.clone(),
+            amount_in: opportunity.amount_in.to_string(),
+            amount_out: opportunity.amount_out.to_string(),
+            gas_cost: opportunity.gas_cost.to_string(),
+            net_profit: opportunity.net_profit.to_string(),
+            hops: opportunity
+                .hops
+                .iter()
+                .map(|hop| HopPayload {
+                    protocol: hop.protocol.to_string(),
+                    pool: hop.pool,
+                    token_in: hop.token_in.clone(),
+                    token_out: hop.token_out.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&TrialResult> for OpportunityPayload {
+    fn from(trial: &TrialResult) -> Self {
+        let token_symbol = TokenConfig::new()
+            .get_token_by_address(&trial.token_address)
+            .map(|token| token.symbol.clone())
+            .unwrap_or_else(|| "Unknown".to_string());
+        Self {
+            token: trial.token_address.clone(),
+            token_symbol,
+            amount_in: trial.amount_in.to_string(),
+            amount_out: trial.amount_out.to_string(),
+            gas_cost: trial.gas_cost.max(0).to_string(),
+            net_profit: trial.net_profit.max(0).to_string(),
+            hops: trial
+                .trade_path
+                .path
+                .iter()
+                .map(|dex| HopPayload {
+                    protocol: dex.protocol().to_string(),
+                    pool: dex.pool_address(),
+                    token_in: dex.coin_in_type(),
+                    token_out: dex.coin_out_type(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FillPayload {
+    pub tx_hash: H256,
+    pub block_number: Option<u64>,
+    pub success: bool,
+    pub gas_used: String,
+    pub effective_gas_price: String,
+}
+
+impl From<&TransactionReceipt> for FillPayload {
+    fn from(receipt: &TransactionReceipt) -> Self {
+        Self {
+            tx_hash: receipt.transaction_hash,
+            block_number: receipt.block_number.map(|b| b.as_u64()),
+            success: receipt.status.is_some_and(|s| s.as_u64() == 1),
+            gas_used: receipt.gas_used.unwrap_or_default().to_string(),
+            effective_gas_price: receipt.effective_gas_price.unwrap_or_default().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemPayload {
+    pub event: String,
+    pub message: String,
+}
+
+impl From<&SystemEvent> for SystemPayload {
+    fn from(event: &SystemEvent) -> Self {
+        let (event, message) = match event {
+            SystemEvent::PriceFeedStale { age: Some(age) } => (
+                "price_feed_stale",
+                format!("AVAX/USD feed last updated {}s ago, USD thresholds suspended", age.as_secs()),
+            ),
+            SystemEvent::PriceFeedStale { age: None } => (
+                "price_feed_stale",
+                "AVAX/USD feed never read, USD thresholds suspended".to_string(),
+            ),
+            SystemEvent::PriceFeedRecovered { avax_usd } => {
+                ("price_feed_recovered", format!("AVAX/USD feed recovered at {avax_usd}"))
+            }
+        };
+        Self {
+            event: event.to_string(),
+            message,
+        }
+    }
+}
+
+/// Hex HMAC-SHA256 of `body` under `secret`.
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(body);
+    mac.finalize().into_bytes().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Delay before retry `attempt` (0-based): doubling from `BASE_BACKOFF`.
+fn backoff(attempt: u32) -> Duration {
+    BASE_BACKOFF * 2u32.pow(attempt.min(6))
+}
+
+/// POSTs opportunities, fills and system events as JSON to a generic HTTP webhook (Discord/Slack bridges,
+/// incident tooling), retrying with backoff on failures and non-2xx responses.
+#[derive(Clone)]
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    secret: Option<String>,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str, secret: Option<String>) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?,
+            url: url.parse()?,
+            secret,
+        })
+    }
+
+    pub async fn notify(&self, event: WebhookEvent) -> Result<()> {
+        let payload = WebhookPayload {
+            schema_version: WEBHOOK_SCHEMA_VERSION,
+            sent_at_ms: current_time_ms(),
+            event,
+        };
+        let body = serde_json::to_vec(&payload)?;
+
+        let mut attempt = 0;
+        loop {
+            let mut request = self
+                .client
+                .post(self.url.clone())
+                .header("Content-Type", "application/json")
+                .header("X-Arb-Schema-Version", WEBHOOK_SCHEMA_VERSION.to_string())
+                .body(body.clone());
+            if let Some(secret) = &self.secret {
+                request = request.header("X-Arb-Signature", format!("sha256={}", sign(secret, &body)));
+            }
+
+            let error = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => eyre::eyre!("webhook responded {}", response.status()),
+                Err(error) => error.into(),
+            };
+            attempt += 1;
+            if attempt >= MAX_ATTEMPTS {
+                bail!("webhook failed after {attempt} attempts: {error:#}");
+            }
+            warn!(attempt, "webhook post failed, retrying: {error:#}");
+            tokio::time::sleep(backoff(attempt - 1)).await;
+        }
+    }
+
+    /// Post in the background, so a slow endpoint never holds up the caller.
+    pub fn spawn_notify(&self, event: WebhookEvent) {
+        let notifier = self.clone();
+        tokio::spawn(async move {
+            if let Err(error) = notifier.notify(event).await {
+                warn!("{error:#}");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_schema() {
+        let payload = WebhookPayload {
+            schema_version: WEBHOOK_SCHEMA_VERSION,
+            sent_at_ms: 1,
+            event: WebhookEvent::Fill(FillPayload {
+                tx_hash: H256::zero(),
+                block_number: Some(100),
+                success: true,
+                gas_used: "21000".to_string(),
+                effective_gas_price: "25000000000".to_string(),
+            }),
+        };
+        let json = serde_json::to_value(&payload).unwrap();
+        // downstream parsers rely on these exact keys
+        assert_eq!(json["schema_version"], 1);
+        assert_eq!(json["kind"], "fill");
+        assert_eq!(json["block_number"], 100);
+        assert_eq!(json["gas_used"], "21000");
+        assert_eq!(serde_json::from_value::<WebhookPayload>(json).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_sign_and_backoff() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        assert_eq!(backoff(0), BASE_BACKOFF);
+        assert_eq!(backoff(2), BASE_BACKOFF * 4);
+        assert_eq!(backoff(50), backoff(6));
+    }
+}
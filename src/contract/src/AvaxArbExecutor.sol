@@ -12,6 +12,10 @@ interface IERC20 {
     function approve(address spender, uint256 amount) external returns (bool);
 }
 
+interface IWrappedNative {
+    function withdraw(uint256 amount) external;
+}
+
 interface IAaveV3Pool {
     function flashLoanSimple(
         address receiver,
@@ -50,6 +54,7 @@ contract AvaxArbExecutor {
     error NotProfitable();
     error InvalidCallback();
     error TransferFailed();
+    error InvalidSwapData();
 
     /* ========== STATE ========== */
 
@@ -206,11 +211,44 @@ contract AvaxArbExecutor {
 
             IERC20(token).approve(spender, amount);
             return offset + 84;
+        } else if (swapType == 4) {
+            // 区块构建者报酬：解包 wrapped native 付给 block.coinbase。
+            // 放在最后一步，之后的利润检查不通过时整笔交易回滚，报酬也不会付出。
+            // 参数是紧凑编码的 [地址 20字节][数量 32字节]，不能用 abi.decode
+            address wrappedNative = _readAddress(data, offset);
+            uint256 amount = _readUint(data, offset + 20);
+
+            IWrappedNative(wrappedNative).withdraw(amount);
+            (bool ok, ) = block.coinbase.call{value: amount}("");
+            if (!ok) revert TransferFailed();
+            return offset + 52;
         }
 
         return offset;
     }
 
+    /// @notice 读取 data[offset:offset+20] 处紧凑编码的地址
+    function _readAddress(
+        bytes memory data,
+        uint256 offset
+    ) internal pure returns (address value) {
+        if (data.length < offset + 20) revert InvalidSwapData();
+        assembly {
+            value := shr(96, mload(add(add(data, 32), offset)))
+        }
+    }
+
+    /// @notice 读取 data[offset:offset+32] 处的 uint256
+    function _readUint(
+        bytes memory data,
+        uint256 offset
+    ) internal pure returns (uint256 value) {
+        if (data.length < offset + 32) revert InvalidSwapData();
+        assembly {
+            value := mload(add(add(data, 32), offset))
+        }
+    }
+
     /// @notice 切片bytes数据
     function _slice(
         bytes memory data,
@@ -0,0 +1,86 @@
+// SPDX-License-Identifier: MIT
+pragma solidity 0.8.30;
+
+import {Test} from "forge-std/Test.sol";
+import {AvaxArbExecutor} from "../src/AvaxArbExecutor.sol";
+
+/// @notice 最小的 WAVAX：记账余额，withdraw 时付出原生币
+contract MockWrappedNative {
+    mapping(address => uint256) public balanceOf;
+
+    function mint(address to, uint256 amount) external {
+        balanceOf[to] += amount;
+    }
+
+    function transfer(address to, uint256 amount) external returns (bool) {
+        balanceOf[msg.sender] -= amount;
+        balanceOf[to] += amount;
+        return true;
+    }
+
+    function withdraw(uint256 amount) external {
+        balanceOf[msg.sender] -= amount;
+        (bool ok, ) = msg.sender.call{value: amount}("");
+        require(ok, "withdraw failed");
+    }
+}
+
+contract AvaxArbExecutorTest is Test {
+    AvaxArbExecutor executor;
+    MockWrappedNative wavax;
+    MockWrappedNative profitToken;
+    address builder = makeAddr("builder");
+
+    function setUp() public {
+        executor = new AvaxArbExecutor();
+        wavax = new MockWrappedNative();
+        profitToken = new MockWrappedNative();
+
+        wavax.mint(address(executor), 1 ether);
+        vm.deal(address(wavax), 1 ether);
+        vm.coinbase(builder);
+    }
+
+    function _params(
+        bytes memory swapData
+    ) internal view returns (AvaxArbExecutor.ArbParams memory) {
+        return
+            AvaxArbExecutor.ArbParams({
+                tokenIn: address(wavax),
+                amountIn: 0,
+                swapData: swapData,
+                profitToken: address(profitToken),
+                minProfit: 0,
+                tag: bytes32(0)
+            });
+    }
+
+    function test_coinbasePayment() public {
+        // 与 SwapDataEncoder::encode_coinbase_payment 相同的编码：[类型 4][地址 20字节][数量 32字节]
+        bytes memory swapData = abi.encodePacked(
+            uint8(1),
+            uint8(4),
+            address(wavax),
+            uint256(0.3 ether)
+        );
+
+        executor.executeArb(_params(swapData));
+
+        assertEq(builder.balance, 0.3 ether);
+        assertEq(wavax.balanceOf(address(executor)), 0.7 ether);
+        assertEq(address(executor).balance, 0);
+    }
+
+    function test_coinbasePaymentTruncated() public {
+        // 数量少了一个字节
+        bytes memory swapData = abi.encodePacked(
+            uint8(1),
+            uint8(4),
+            address(wavax),
+            bytes31(0)
+        );
+
+        vm.expectRevert(AvaxArbExecutor.InvalidSwapData.selector);
+        executor.executeArb(_params(swapData));
+    }
+}
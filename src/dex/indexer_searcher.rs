@@ -3,26 +3,45 @@ use dex_indexer::{
     DexIndexer,
 };
 use eyre::{bail, ensure, OptionExt, Result};
+use ethers::{
+    providers::{Http, Provider},
+    types::{Address, H160, U256},
+};
 use object_pool::ObjectPool;
-use simulator::Simulator;
-use std::sync::Arc;
+use simulator::{SimulateCtx, Simulator};
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashSet,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 use sui_sdk::SUI_COIN_TYPE;
 use sui_types::base_types::ObjectID;
 use tokio::sync::OnceCell;
 use tokio::task::JoinSet;
+use tracing::warn;
 
 use super::{
-    aftermath::Aftermath, cetus::Cetus, deepbook_v2::DeepbookV2, flowx_clmm::FlowxClmm, turbos::Turbos, Dex,
-    DexSearcher, Path,
+    aftermath::Aftermath, cetus::Cetus, deepbook_v2::DeepbookV2, flowx_clmm::FlowxClmm, platypus::PlatypusDex,
+    pool_health::is_delisted, registry::DexRegistry, turbos::Turbos, Dex, DexSearcher, Path,
 };
 use crate::defi::{blue_move::BlueMove, kriya_amm::KriyaAmm, kriya_clmm::KriyaClmm};
 
 static INDEXER: OnceCell<Arc<DexIndexer>> = OnceCell::const_new();
 
+/// Malformed pools already warned about, so each is reported once rather than on every search.
+static REJECTED_POOLS: Lazy<Mutex<HashSet<Address>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Throwaway account used to quote swaps; its `token_in` balance is overridden for the simulation.
+pub(crate) const QUOTE_SENDER: Address = H160([0x11; 20]);
+
 #[derive(Clone)]
 pub struct IndexerDexSearcher {
     simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>,
     indexer: Arc<DexIndexer>,
+    // for adapters that read pool state directly (Platypus asset registry)
+    provider: Arc<Provider<Http>>,
+    registry: DexRegistry,
 }
 
 impl IndexerDexSearcher {
@@ -38,16 +57,53 @@ impl IndexerDexSearcher {
         Ok(Self {
             simulator_pool,
             indexer,
+            provider: Arc::new(Provider::<Http>::try_from(http_url)?),
+            registry: DexRegistry::default(),
         })
     }
+
+    /// Handle for registering adapters of protocols the core crate doesn't ship.
+    pub fn registry(&self) -> DexRegistry {
+        self.registry.clone()
+    }
+}
+
+/// Simulate swapping `amount_in` through a single pool and return the amount of `coin_out` received.
+async fn quote_swap(
+    simulator: Arc<Box<dyn Simulator>>,
+    dex: Box<dyn Dex>,
+    amount_in: U256,
+    mut sim_ctx: SimulateCtx,
+) -> Result<U256> {
+    let token_in = Address::from_str(&dex.coin_in_type())?;
+    let token_out = Address::from_str(&dex.coin_out_type())?;
+    sim_ctx.with_override_balance(QUOTE_SENDER, token_in, amount_in);
+
+    let tx = dex.swap_tx(QUOTE_SENDER, QUOTE_SENDER, amount_in).await?;
+    let resp = simulator.simulate_tx_request(tx, sim_ctx).await?;
+
+    let amount_out = resp
+        .balance_changes
+        .iter()
+        .find(|bc| bc.address == QUOTE_SENDER && bc.token == token_out && bc.amount > 0)
+        .map(|bc| U256::from(bc.amount as u128))
+        .ok_or_eyre("no output balance change")?;
+
+    Ok(amount_out)
 }
 
 async fn new_dexes(
     simulator: Arc<Box<dyn Simulator>>,
+    provider: &Provider<Http>,
+    registry: &DexRegistry,
     pool: &Pool,
     token_in_type: &str,
     token_out_type: Option<String>,
 ) -> Result<Vec<Box<dyn Dex>>> {
+    if let Some(factory) = registry.get(&pool.protocol.to_string()) {
+        return factory.new_dexes(simulator, provider, pool, token_in_type, token_out_type).await;
+    }
+
     let dexes = match pool.protocol {
         Protocol::Turbos => {
             let dex = Turbos::new(simulator, pool, token_in_type).await?;
@@ -89,6 +145,12 @@ async fn new_dexes(
             vec![Box::new(dex) as Box<dyn Dex>]
         }
 
+        Protocol::Platypus => PlatypusDex::new(provider, pool, token_in_type, token_out_type)
+            .await?
+            .into_iter()
+            .map(|dex| Box::new(dex) as Box<dyn Dex>)
+            .collect(),
+
         _ => bail!("unsupported protocol: {:?}", pool.protocol),
     };
 
@@ -111,17 +173,36 @@ impl DexSearcher for IndexerDexSearcher {
         );
 
         let mut join_set = JoinSet::new();
-        for pool in pools.unwrap() {
+        for pool in pools.unwrap().into_iter().filter(|pool| self.registry.is_enabled(&pool.protocol)) {
             let simulator = self.simulator_pool.get();
             let token_in_type = token_in_type.to_string();
             let token_out_type = token_out_type.clone();
-            join_set.spawn(async move { new_dexes(simulator, &pool, &token_in_type, token_out_type).await });
+            let provider = self.provider.clone();
+            let registry = self.registry.clone();
+            join_set.spawn(async move {
+                new_dexes(simulator, &provider, &registry, &pool, &token_in_type, token_out_type).await
+            });
         }
 
         let mut res = Vec::new();
         while let Some(Ok(result)) = join_set.join_next().await {
             match result {
-                Ok(dexes) => res.extend(dexes),
+                Ok(dexes) => res.extend(dexes.into_iter().filter(|dex| {
+                    if is_delisted(dex.pool_address()) {
+                        return false;
+                    }
+                    let valid = dex.is_valid();
+                    if !valid && REJECTED_POOLS.lock().unwrap().insert(dex.pool_address()) {
+                        warn!(
+                            pool = ?dex.pool_address(),
+                            protocol = %dex.protocol(),
+                            token_in = %dex.coin_in_type(),
+                            token_out = %dex.coin_out_type(),
+                            "rejecting malformed pool"
+                        );
+                    }
+                    valid
+                })),
                 Err(_error) => {
                     // trace!(?error, "invalid pool");
                 }
@@ -131,6 +212,37 @@ impl DexSearcher for IndexerDexSearcher {
         Ok(res)
     }
 
+    async fn find_dexes_ranked(
+        &self,
+        token_in_type: &str,
+        token_out_type: Option<String>,
+        amount_in: U256,
+        sim_ctx: &SimulateCtx,
+    ) -> Result<Vec<Box<dyn Dex>>> {
+        let dexes = self.find_dexes(token_in_type, token_out_type).await?;
+
+        let mut join_set = JoinSet::new();
+        for (idx, dex) in dexes.iter().enumerate() {
+            let simulator = self.simulator_pool.get();
+            let dex = dex.clone();
+            let sim_ctx = sim_ctx.clone();
+            join_set.spawn(async move { (idx, quote_swap(simulator, dex, amount_in, sim_ctx).await) });
+        }
+
+        let mut outputs = vec![U256::zero(); dexes.len()];
+        while let Some(Ok((idx, result))) = join_set.join_next().await {
+            // pools that fail to quote rank last
+            if let Ok(amount_out) = result {
+                outputs[idx] = amount_out;
+            }
+        }
+
+        let mut ranked = dexes.into_iter().zip(outputs).collect::<Vec<_>>();
+        ranked.sort_by_key(|(_, amount_out)| std::cmp::Reverse(*amount_out));
+
+        Ok(ranked.into_iter().map(|(dex, _)| dex).collect())
+    }
+
     async fn find_test_path(&self, path: &[ObjectID]) -> Result<Path> {
         let mut dexes = vec![];
         let mut coin_in = SUI_COIN_TYPE.to_string();
@@ -138,7 +250,7 @@ impl DexSearcher for IndexerDexSearcher {
         for pool_id in path {
             let simulator = self.simulator_pool.get();
             let pool = self.indexer.get_pool_by_id(pool_id).ok_or_eyre("pool not found")?;
-            let dex = new_dexes(simulator, &pool, &coin_in, None).await?.pop().unwrap();
+            let dex = new_dexes(simulator, &self.provider, &self.registry, &pool, &coin_in, None).await?.pop().unwrap();
             coin_in = dex.coin_out_type();
             dexes.push(dex);
         }
@@ -1,42 +1,159 @@
 mod indexer_searcher;
 mod pangolin;
+mod platypus;
+mod pool_health;
+mod registry;
+mod reserves_check;
+mod router_call;
+mod spread;
 mod sushi_swap;
+#[cfg(test)]
+mod test_fixtures;
 mod trade;
 mod trader_joe;
+mod uniswap_v2;
 mod utils;
 
 use std::{
     collections::{HashMap, HashSet},
     fmt,
     hash::Hash,
-    sync::Arc,
+    sync::{Arc, Mutex, RwLock},
 };
 
 use ::utils::coin;
 use dex_indexer::types::Protocol;
 use eyre::{bail, ensure, Result};
+use indexer_searcher::QUOTE_SENDER;
 pub use indexer_searcher::IndexerDexSearcher;
+pub use platypus::{PlatypusAsset, PlatypusDex};
+pub use pool_health::{is_delisted, relist_pools};
+pub use registry::{DexFactory, DexRegistry};
+pub use reserves_check::{get_pair_tokens, ReserveDivergence};
+pub use router_call::{DecodedSwap, NativeName, RouterCall};
+pub use spread::{median_mid, pair_spread, spot_price, PairSpread, ReserveCache, VenueQuote};
 use object_pool::ObjectPool;
+use once_cell::sync::Lazy;
+use prometheus::{register_int_counter, IntCounter};
 use simulator::{SimulateCtx, Simulator};
-use ethers::types::{Address, TransactionRequest};
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{
+        transaction::{eip2718::TypedTransaction, eip2930::AccessList},
+        Address, TransactionRequest, U256,
+    },
+};
 use tokio::task::JoinSet;
-use tracing::Instrument;
+use tracing::{debug, warn, Instrument};
 use trade::{FlashResult, TradeResult};
-pub use trade::{Path, TradeCtx, TradeType, Trader};
-
-use crate::{config::pegged_coin_types, types::Source};
+pub use trade::{token_handle, Path, TradeCtx, TradeType, Trader};
+pub use uniswap_v2::{AmountInCache, PoolReserves, UniswapV2Calculator, DEFAULT_FEE_BPS};
+
+use crate::{
+    bot::contract_executor::V2Hop,
+    common::search::{bisect_last_positive, SearchGoal},
+    config::{
+        is_pegged_coin, is_wavax, max_paths_evaluated, min_distinct_protocols, pool_fee_bps, profit_token,
+        remove_route_override, route_override, route_overrides, route_search_budget, trust_nonpositive_gas_cost,
+        wavax_address,
+    },
+    types::Source,
+    utils::{
+        math::{i128_from_delta, u256_to_i128_checked},
+        token_config::TokenConfig,
+    },
+};
 
 const MAX_POOL_COUNT: usize = 10;
 const MIN_LIQUIDITY: u128 = 1000;
+const SIMULATE_SWAP_GAS_LIMIT: u64 = 300_000;
+
+static PATHS_TRUNCATED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "arb_paths_truncated_total",
+        "Candidate paths dropped by the max-paths-evaluated cap instead of being simulated"
+    )
+    .unwrap()
+});
+
+static ROUTE_SEARCHES_TIMED_OUT: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "arb_route_searches_timed_out_total",
+        "Route searches cut short by the route search budget, returning the routes found so far"
+    )
+    .unwrap()
+});
+
+/// Paths of route overrides, resolved once (at the startup check or on first use) instead of per opportunity.
+/// Keyed by lowercased token.
+static OVERRIDE_PATHS: Lazy<RwLock<HashMap<String, Path>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+static GAS_COST_ANOMALIES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "arb_gas_cost_anomalies_total",
+        "Simulated trades that reported a zero or negative gas cost"
+    )
+    .unwrap()
+});
+
+/// `Err` when the simulation of `path` reported a gas cost no real tx has (zero or negative), which would
+/// inflate its profit, unless configured to trust it. Either way the anomaly is counted.
+fn check_gas_cost(path: &Path, trade_res: &TradeResult) -> Result<()> {
+    if trade_res.gas_cost > 0 {
+        return Ok(());
+    }
+    GAS_COST_ANOMALIES.inc();
+    if trust_nonpositive_gas_cost() {
+        return Ok(());
+    }
+    warn!(gas_cost = trade_res.gas_cost, ?path, "discarding trade with a non-positive simulated gas cost");
+    bail!("non-positive simulated gas cost {}", trade_res.gas_cost)
+}
 
-// WAVAX address - commonly used native token
-pub const WAVAX_ADDRESS: &str = "0xB31f66AA3C1e785363F0875A1B74E27b85FD66c7";
+/// Router, factory and other contracts `protocol` relies on; empty for protocols without a fixed deployment.
+pub async fn related_contract_addresses(protocol: &Protocol) -> Vec<String> {
+    match protocol {
+        Protocol::TraderJoe => trader_joe::trader_joe_related_contract_addresses().await,
+        Protocol::Pangolin => pangolin::pangolin_related_contract_addresses().await,
+        Protocol::SushiSwap => sushi_swap::sushi_swap_related_contract_addresses().await,
+        _ => vec![],
+    }
+}
+
+/// `factory()` selector of UniswapV2-style pairs.
+const FACTORY: [u8; 4] = [0xc4, 0x5a, 0x01, 0x55];
+
+/// Protocol whose factory deployed `pool`; an error for pools of factories the bot doesn't know.
+pub async fn pool_protocol(provider: &Provider<Http>, pool: Address) -> Result<Protocol> {
+    let tx: TypedTransaction = TransactionRequest::new().to(pool).data(FACTORY.to_vec()).into();
+    let output = provider.call(&tx, None).await?;
+    ensure!(output.len() >= 32, "short factory output from {pool:?}");
+    let factory = Address::from_slice(&output[12..32]);
+
+    for protocol in [Protocol::TraderJoe, Protocol::Pangolin, Protocol::SushiSwap] {
+        let known = related_contract_addresses(&protocol).await;
+        if known.iter().any(|address| address.parse::<Address>().is_ok_and(|address| address == factory)) {
+            return Ok(protocol);
+        }
+    }
+    bail!("pool {pool:?} comes from unknown factory {factory:?}")
+}
 
 #[async_trait::async_trait]
 pub trait DexSearcher: Send + Sync {
     // token_address: e.g. "0xB31f66AA3C1e785363F0875A1B74E27b85FD66c7"
     async fn find_dexes(&self, token_in_address: &str, token_out_address: Option<String>) -> Result<Vec<Box<dyn Dex>>>;
 
+    /// Like `find_dexes`, but simulates a swap of `amount_in` through every candidate and returns them
+    /// ordered by output, best first.
+    async fn find_dexes_ranked(
+        &self,
+        token_in_address: &str,
+        token_out_address: Option<String>,
+        amount_in: U256,
+        sim_ctx: &SimulateCtx,
+    ) -> Result<Vec<Box<dyn Dex>>>;
+
     async fn find_test_path(&self, path: &[Address]) -> Result<Path>;
 }
 
@@ -46,9 +163,16 @@ pub trait Dex: Send + Sync + CloneBoxedDex {
         false
     }
 
+    /// Premium this pool charges on a flashloan, in bps of the borrowed amount, unless overridden per
+    /// protocol in `--flashloan-premiums`. Only meaningful when `support_flashloan`; `extend_flashloan_tx`
+    /// charges it through `trade::lender_premium`.
+    fn flashloan_premium_bps(&self) -> u32 {
+        0
+    }
+
     /// Extend the trade_tx with a flashloan tx.
-    /// Returns (token_out, receipt).
-    async fn extend_flashloan_tx(&self, _ctx: &mut TradeCtx, _amount: u64) -> Result<FlashResult> {
+    /// Returns (token_out, receipt) and the premium owed on top of `amount`.
+    async fn extend_flashloan_tx(&self, _ctx: &mut TradeCtx, _amount: U256) -> Result<FlashResult> {
         bail!("flashloan not supported")
     }
 
@@ -65,7 +189,7 @@ pub trait Dex: Send + Sync + CloneBoxedDex {
         ctx: &mut TradeCtx,
         sender: Address,
         token_in: ethers::types::Bytes,
-        amount_in: Option<u64>,
+        amount_in: Option<U256>,
     ) -> Result<ethers::types::Bytes>;
 
     fn coin_in_type(&self) -> String;
@@ -74,12 +198,42 @@ pub trait Dex: Send + Sync + CloneBoxedDex {
     fn liquidity(&self) -> u128;
     fn pool_address(&self) -> Address;
 
+    /// Swap fee of this pool in bps, used by the constant-product sizing math. Adapters that know the pool's
+    /// real fee (V2 fee rates, V3 fee tiers) override this; others get the configured override or 0.3%.
+    fn fee_bps(&self) -> u32 {
+        pool_fee_bps(self.pool_address(), DEFAULT_FEE_BPS as u16) as u32
+    }
+
+    /// Output of swapping `amount_in` through this pool, from the pool state the adapter was built with. Sizes the
+    /// input of a hop that starts a new call in the middle of a trade tx.
+    fn get_amount_out(&self, _amount_in: U256) -> Result<U256> {
+        bail!("{} can't quote a swap without simulating it", self.protocol())
+    }
+
+    /// Whether the router calls this adapter builds carry the referral address. Only these hops earn a
+    /// referral rebate.
+    fn takes_referral(&self) -> bool {
+        false
+    }
+
+    /// Whether the pool can be routed through: a non-zero pool address and two distinct, non-zero tokens.
+    /// Malformed or uninitialized indexer entries fail this and would otherwise become DFS self-loops.
+    fn is_valid(&self) -> bool {
+        let is_zero = |token: &str| token.parse::<Address>().map_or(false, |t| t.is_zero());
+        let (token_in, token_out) = (self.coin_in_type(), self.coin_out_type());
+
+        !self.pool_address().is_zero()
+            && !token_in.eq_ignore_ascii_case(&token_out)
+            && !is_zero(&token_in)
+            && !is_zero(&token_out)
+    }
+
     /// flip the coin_in_type and coin_out_type
     fn flip(&mut self);
 
     // for debug
     fn is_a2b(&self) -> bool;
-    async fn swap_tx(&self, sender: Address, recipient: Address, amount_in: u64) -> Result<TransactionRequest>;
+    async fn swap_tx(&self, sender: Address, recipient: Address, amount_in: U256) -> Result<TransactionRequest>;
 }
 
 pub trait CloneBoxedDex {
@@ -131,20 +285,36 @@ impl fmt::Debug for Box<dyn Dex> {
 #[derive(Clone)]
 pub struct Defi {
     dex_searcher: Arc<dyn DexSearcher>,
+    simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>,
     trader: Arc<Trader>,
+    registry: DexRegistry,
+    provider: Arc<Provider<Http>>,
+    // shared across calls; keyed on pool state, so entries for reserves that moved are never served
+    amount_in_cache: Arc<Mutex<AmountInCache>>,
 }
 
 impl Defi {
     pub async fn new(http_url: &str, simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>) -> Result<Self> {
         let dex_searcher = IndexerDexSearcher::new(http_url, simulator_pool.clone()).await?;
-        let trade = Trader::new(simulator_pool).await?;
+        let registry = dex_searcher.registry();
+        let trade = Trader::new(simulator_pool.clone()).await?;
 
         Ok(Self {
             dex_searcher: Arc::new(dex_searcher),
+            simulator_pool,
             trader: Arc::new(trade),
+            registry,
+            provider: Arc::new(Provider::<Http>::try_from(http_url)?),
+            amount_in_cache: Arc::new(Mutex::new(AmountInCache::new())),
         })
     }
 
+    /// Register a `DexFactory` for pools whose protocol displays as `protocol`, so downstream crates can add
+    /// DEXes without patching this one. Takes effect for every later path search.
+    pub fn register_dex_factory(&self, protocol: impl Into<String>, factory: Arc<dyn DexFactory>) {
+        self.registry.register(protocol, factory);
+    }
+
     #[allow(dead_code)]
     pub async fn find_dexes(&self, token_in_address: &str, token_out_address: Option<String>) -> Result<Vec<Box<dyn Dex>>> {
         self.dex_searcher.find_dexes(token_in_address, token_out_address).await
@@ -155,16 +325,55 @@ impl Defi {
     }
 
     pub async fn find_sell_paths_with_hops(&self, token_in_address: &str, max_hops: usize) -> Result<Vec<Path>> {
+        self.find_sell_paths_inner(token_in_address, max_hops, None).await
+    }
+
+    /// Same as `find_sell_paths_with_hops`, but the first hop's pools are pruned by simulated output for
+    /// `amount_in` instead of by liquidity. Later hops trade other tokens, so they still prune by liquidity.
+    pub async fn find_sell_paths_ranked(
+        &self,
+        token_in_address: &str,
+        max_hops: usize,
+        amount_in: U256,
+        sim_ctx: &SimulateCtx,
+    ) -> Result<Vec<Path>> {
+        self.find_sell_paths_inner(token_in_address, max_hops, Some((amount_in, sim_ctx)))
+            .await
+    }
+
+    async fn find_sell_paths_inner(
+        &self,
+        token_in_address: &str,
+        max_hops: usize,
+        ranking: Option<(U256, &SimulateCtx)>,
+    ) -> Result<Vec<Path>> {
         if coin::is_native_coin(token_in_address) {
             return Ok(vec![Path::default()]);
         }
+        if let Some(path) = OVERRIDE_PATHS.read().unwrap().get(&token_in_address.to_lowercase()) {
+            return Ok(vec![path.clone()]);
+        }
+        if let Some(pools) = route_override(token_in_address) {
+            match self.override_path(token_in_address, &pools).await {
+                Ok(path) => {
+                    OVERRIDE_PATHS
+                        .write()
+                        .unwrap()
+                        .insert(token_in_address.to_lowercase(), path.clone());
+                    return Ok(vec![path]);
+                }
+                Err(error) => warn!(token = token_in_address, "route override failed, searching: {error:#}"),
+            }
+        }
 
         let mut all_hops = HashMap::new();
         let mut stack = vec![token_in_address.to_string()];
         let mut visited = HashSet::new();
         let mut visited_dexes = HashSet::new();
 
-        for nth_hop in 0..max_hops {
+        // hops are expanded a layer at a time, so a search cut short still has every short route
+        let deadline = route_search_budget().map(|budget| tokio::time::Instant::now() + budget);
+        'expand: for nth_hop in 0..max_hops {
             let is_last_hop = nth_hop == max_hops - 1;
             let mut new_stack = vec![];
 
@@ -180,15 +389,37 @@ impl Defi {
                     if token_address != token_in_address {
                         Some(token_in_address.to_string())
                     } else {
-                        Some(WAVAX_ADDRESS.to_string())
+                        Some(wavax_address())
                     }
-                } else if pegged_coin_types().contains(token_address.as_str()) {
-                    Some(WAVAX_ADDRESS.to_string())
+                } else if is_pegged_coin(&token_address) {
+                    Some(wavax_address())
                 } else {
                     None
                 };
 
-                let mut dexes = if let Ok(dexes) = self.dex_searcher.find_dexes(&token_address, token_out_address).await {
+                let ranking = ranking.filter(|_| token_address == token_in_address);
+                let search = async {
+                    match ranking {
+                        Some((amount_in, sim_ctx)) => {
+                            self.dex_searcher
+                                .find_dexes_ranked(&token_address, token_out_address, amount_in, sim_ctx)
+                                .await
+                        }
+                        None => self.dex_searcher.find_dexes(&token_address, token_out_address).await,
+                    }
+                };
+                let dexes = match deadline {
+                    Some(deadline) => match tokio::time::timeout_at(deadline, search).await {
+                        Ok(dexes) => dexes,
+                        Err(_) => {
+                            ROUTE_SEARCHES_TIMED_OUT.inc();
+                            debug!(token = token_in_address, nth_hop, "route search budget spent");
+                            break 'expand;
+                        }
+                    },
+                    None => search.await,
+                };
+                let mut dexes = if let Ok(dexes) = dexes {
                     dexes
                 } else {
                     continue;
@@ -198,7 +429,10 @@ impl Defi {
 
                 if dexes.len() > MAX_POOL_COUNT {
                     dexes.retain(|dex| !visited_dexes.contains(&dex.pool_address()));
-                    dexes.sort_by_key(|dex| std::cmp::Reverse(dex.liquidity()));
+                    // ranked dexes are already ordered by simulated output
+                    if ranking.is_none() {
+                        dexes.sort_by_key(|dex| std::cmp::Reverse(dex.liquidity()));
+                    }
                     dexes.truncate(MAX_POOL_COUNT);
                 }
 
@@ -226,9 +460,98 @@ impl Defi {
         let mut routes = vec![];
         dfs_with_target(token_in_address, token_in_address, &mut vec![], &all_hops, &mut routes, max_hops);
 
+        let mut routes = dedup_routes(routes);
+        routes.retain(|route| distinct_protocols(route) >= min_distinct_protocols());
+        sort_routes(&mut routes);
+
+        if let Some(max_paths) = max_paths_evaluated() {
+            let total = routes.len();
+            routes = top_routes(routes, max_paths);
+            if routes.len() < total {
+                PATHS_TRUNCATED.inc_by((total - routes.len()) as u64);
+                debug!(token = token_in_address, total, kept = routes.len(), "capped paths to evaluate");
+            }
+        }
+
         Ok(routes.into_iter().map(Path::new).collect())
     }
 
+    /// The cycle through `pools`, in order, starting and ending in `token_in_address`. Errors if a pool isn't
+    /// indexed, doesn't trade the token the previous hop ends in, or the last hop doesn't return the token.
+    pub async fn override_path(&self, token_in_address: &str, pools: &[Address]) -> Result<Path> {
+        let mut token = token_in_address.to_lowercase();
+        let mut path = vec![];
+        for &pool in pools {
+            let dex = self
+                .dex_searcher
+                .find_dexes(&token, None)
+                .await?
+                .into_iter()
+                .find(|dex| dex.pool_address() == pool)
+                .ok_or_else(|| eyre::eyre!("pool {pool:?} is not indexed or doesn't trade {token}"))?;
+            token = dex.coin_out_type().to_lowercase();
+            path.push(dex);
+        }
+        ensure!(
+            token == token_in_address.to_lowercase(),
+            "route ends in {token} instead of {token_in_address}"
+        );
+        Ok(Path::new(path))
+    }
+
+    /// Check every configured route override against the index and drop the ones that don't form a cycle,
+    /// so their tokens are searched instead of failing on each opportunity. Valid ones are kept resolved for
+    /// the path search.
+    pub async fn validate_route_overrides(&self) {
+        for (token, pools) in route_overrides() {
+            match self.override_path(&token, &pools).await {
+                Ok(path) => {
+                    debug!(%token, ?path, "route override");
+                    OVERRIDE_PATHS.write().unwrap().insert(token.to_lowercase(), path);
+                }
+                Err(error) => {
+                    warn!(%token, "invalid route override, falling back to the path search: {error:#}");
+                    remove_route_override(&token);
+                }
+            }
+        }
+    }
+
+    /// 2-leg cycles selling `token_in_address` for WAVAX on one protocol and buying it back on another,
+    /// whose marginal spread at `block` is at least `min_spread_bps`; widest first. Empty for WAVAX itself.
+    pub async fn find_direct_pair_paths(
+        &self,
+        token_in_address: &str,
+        min_spread_bps: u32,
+        block: u64,
+        cache: &mut ReserveCache,
+    ) -> Result<Vec<Path>> {
+        let wavax = wavax_address();
+        if coin::is_native_coin(token_in_address) || is_wavax(token_in_address) {
+            return Ok(vec![]);
+        }
+
+        let (sells, buys) = tokio::try_join!(
+            self.dex_searcher.find_dexes(token_in_address, Some(wavax.clone())),
+            self.dex_searcher.find_dexes(&wavax, Some(token_in_address.to_string())),
+        )?;
+        let candidates = direct_pair_routes(&sells, &buys).into_iter().map(Path::new).collect::<Vec<_>>();
+
+        self.prefetch_reserves(&candidates, block, cache).await;
+        let mut paths = vec![];
+        for path in candidates {
+            // pools without reserves (e.g. Platypus) can't be checked cheaply; the full search covers them
+            if let Ok(spread_bps) = self.path_spread_bps(&path, block, cache).await {
+                if spread_bps >= min_spread_bps as f64 {
+                    paths.push((spread_bps, path));
+                }
+            }
+        }
+        paths.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        Ok(paths.into_iter().map(|(_, path)| path).collect())
+    }
+
     pub async fn find_buy_paths(&self, token_out_address: &str) -> Result<Vec<Path>> {
         let mut paths = self.find_sell_paths(token_out_address).await?;
         for path in &mut paths {
@@ -245,7 +568,7 @@ impl Defi {
         &self,
         paths: &[Path],
         sender: Address,
-        amount_in: u64,
+        amount_in: U256,
         trade_type: TradeType,
         gas_limit: u64,
         sim_ctx: &SimulateCtx,
@@ -265,7 +588,8 @@ impl Defi {
                 async move {
                     let result = trade
                         .get_trade_result(&path, sender, amount_in, trade_type, gas_limit, sim_ctx)
-                        .await;
+                        .await
+                        .and_then(|trade_res| check_gas_cost(&path, &trade_res).map(|_| trade_res));
 
                     (idx, result)
                 }
@@ -277,7 +601,8 @@ impl Defi {
         while let Some(Ok((idx, trade_res))) = joinset.join_next().await {
             match trade_res {
                 Ok(trade_res) => {
-                    if trade_res > best_trade_res {
+                    // results arrive in completion order; on a tie keep the earlier path so the pick is reproducible
+                    if trade_res > best_trade_res || (trade_res == best_trade_res && idx < best_idx) {
                         best_idx = idx;
                         best_trade_res = trade_res;
                     }
@@ -289,26 +614,315 @@ impl Defi {
             }
         }
 
-        ensure!(best_trade_res.amount_out > 0, "zero amount_out");
+        ensure!(!best_trade_res.amount_out.is_zero(), "zero amount_out");
 
         Ok(PathTradeResult::new(paths[best_idx].clone(), amount_in, best_trade_res))
     }
 
+    /// Quote exactly one pool: swap `amount_in` of `token_in_address` through `pool_address`, bypassing
+    /// path search. Errors if the pool does not trade `token_in_address`.
+    pub async fn simulate_swap(
+        &self,
+        pool_address: Address,
+        token_in_address: &str,
+        amount_in: U256,
+        sim_ctx: &SimulateCtx,
+    ) -> Result<U256> {
+        let dex = self
+            .dex_searcher
+            .find_dexes(token_in_address, None)
+            .await?
+            .into_iter()
+            .find(|dex| dex.pool_address() == pool_address)
+            .ok_or_else(|| eyre::eyre!("pool {pool_address:?} does not contain token {token_in_address}"))?;
+
+        let path = Path::new(vec![dex]);
+        let trade_res = self
+            .trader
+            .get_trade_result(
+                &path,
+                QUOTE_SENDER,
+                amount_in,
+                TradeType::Swap,
+                SIMULATE_SWAP_GAS_LIMIT,
+                sim_ctx.clone(),
+            )
+            .await?;
+
+        Ok(trade_res.amount_out)
+    }
+
+    /// Realized exchange rate of `path` for `amount_in`, e.g. 24.8 for "1 WAVAX = 24.8 USDC". Simulated, so it
+    /// includes every hop's fee and price impact.
+    pub async fn effective_price(&self, path: &Path, amount_in: U256, sim_ctx: &SimulateCtx) -> Result<f64> {
+        ensure!(!path.is_empty(), "empty path");
+        let token_in = path.coin_in_type().parse::<Address>()?;
+        let mut sim_ctx = sim_ctx.clone();
+        sim_ctx.with_override_balance(QUOTE_SENDER, token_in, amount_in);
+
+        let trade_res = self
+            .trader
+            .get_trade_result(path, QUOTE_SENDER, amount_in, TradeType::Swap, SIMULATE_SWAP_GAS_LIMIT, sim_ctx)
+            .await?;
+
+        path.price_from_amounts(amount_in, trade_res.amount_out)
+    }
+
+    /// Net profit of `path` at each of `sizes`, in the order given, for plotting profit against size. Sizes
+    /// that fail to simulate are left out.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn profit_curve(
+        &self,
+        path: &Path,
+        sender: Address,
+        sizes: &[U256],
+        trade_type: TradeType,
+        gas_limit: u64,
+        sim_ctx: &SimulateCtx,
+    ) -> Vec<(U256, i128)> {
+        let mut joinset = JoinSet::new();
+        for (idx, &amount_in) in sizes.iter().enumerate() {
+            let ctx = ProfitCtx {
+                trader: self.trader.clone(),
+                path: path.clone(),
+                sender,
+                trade_type,
+                gas_limit,
+                sim_ctx: sim_ctx.clone(),
+            };
+            joinset.spawn(async move { (idx, ctx.profit(amount_in).await) }.in_current_span());
+        }
+
+        let mut curve = vec![None; sizes.len()];
+        while let Some(Ok((idx, result))) = joinset.join_next().await {
+            curve[idx] = result.ok().map(|profit| (sizes[idx], profit));
+        }
+        curve.into_iter().flatten().collect()
+    }
+
+    /// Largest `amount_in` in `[min, max]` at which `path` still nets a profit, found by bisection: the
+    /// frontier past which slippage eats the edge. `min` should be at or below the optimal size, e.g. the
+    /// result of the golden-section search. `None` when `path` isn't profitable at `min`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn max_profitable_size(
+        &self,
+        path: &Path,
+        sender: Address,
+        min: U256,
+        max: U256,
+        trade_type: TradeType,
+        gas_limit: u64,
+        sim_ctx: &SimulateCtx,
+    ) -> Option<U256> {
+        let ctx = ProfitCtx {
+            trader: self.trader.clone(),
+            path: path.clone(),
+            sender,
+            trade_type,
+            gas_limit,
+            sim_ctx: sim_ctx.clone(),
+        };
+        bisect_last_positive(min, max.max(min), ProfitGoal, &ctx)
+            .await
+            .map(|(amount_in, _)| amount_in)
+    }
+
+    /// Compounded price impact of `amount_in` along `path`, in bps, from each pool's reserves at `block`.
+    /// Errors for paths through pools without `getReserves` (e.g. Platypus).
+    pub async fn price_impact_bps(&self, path: &Path, amount_in: U256, block: u64) -> Result<u64> {
+        let pools = self.cached_path_reserves(path, block, &mut ReserveCache::default()).await?;
+        UniswapV2Calculator::calculate_path_price_impact(amount_in, &pools)
+    }
+
+    /// Debugging aid for profits that simulate but revert live: compare each pool's reserves at the block the
+    /// path was quoted on with the latest block and warn about every pool that moved.
+    pub async fn log_reserve_divergences(&self, path: &Path, quoted_block: u64) -> Vec<ReserveDivergence> {
+        let divergences = reserves_check::find_reserve_divergences(&self.provider, path, quoted_block).await;
+        for divergence in &divergences {
+            warn!(
+                pool = ?divergence.pool,
+                quoted_block,
+                quoted = ?divergence.quoted,
+                live = ?divergence.live,
+                change_bps = divergence.max_change_bps(),
+                "reserves diverged since quote"
+            );
+        }
+        divergences
+    }
+
+    /// Every venue's quote for `base`/`quote` at `block`, from reserves cached per block. Keeps the deepest
+    /// pool of each protocol and skips pools without `getReserves` (e.g. Platypus).
+    pub async fn venue_quotes(
+        &self,
+        base: Address,
+        quote: Address,
+        block: u64,
+        cache: &mut ReserveCache,
+    ) -> Result<Vec<VenueQuote>> {
+        let tokens = TokenConfig::new();
+        let decimals = |token: Address| {
+            tokens
+                .get_token_by_address(&format!("{token:?}"))
+                .map(|t| t.decimals)
+                .ok_or_else(|| eyre::eyre!("unknown token {token:?}"))
+        };
+        let decimals = (decimals(base)?, decimals(quote)?);
+
+        let mut deepest: HashMap<String, Box<dyn Dex>> = HashMap::new();
+        for dex in self.find_dexes(&format!("{base:?}"), Some(format!("{quote:?}"))).await? {
+            let protocol = dex.protocol().to_string();
+            if deepest.get(&protocol).map_or(true, |d| dex.liquidity() > d.liquidity()) {
+                deepest.insert(protocol, dex);
+            }
+        }
+
+        let mut quotes = vec![];
+        for (protocol, dex) in deepest {
+            let pool = dex.pool_address();
+            let (reserve0, reserve1) = match cache.get(&self.provider, pool, block).await {
+                Ok(reserves) => reserves,
+                Err(error) => {
+                    debug!(?pool, %protocol, "skip venue quote: {error:#}");
+                    continue;
+                }
+            };
+            // UniswapV2 pairs sort their tokens by address
+            let reserves = if base < quote { (reserve0, reserve1) } else { (reserve1, reserve0) };
+            quotes.extend(VenueQuote::from_reserves(protocol, pool, reserves, decimals, dex.fee_bps()));
+        }
+        Ok(quotes)
+    }
+
+    /// Load the reserves of every pool on `paths` at `block` into `cache` in one batched read, ahead of
+    /// `path_spread_bps` on each of them.
+    pub async fn prefetch_reserves(&self, paths: &[Path], block: u64, cache: &mut ReserveCache) {
+        let pools = paths.iter().flat_map(|path| path.path.iter().map(|dex| dex.pool_address())).collect::<Vec<_>>();
+        cache.prefetch(&self.provider, &pools, block).await;
+    }
+
+    /// Marginal spread of cyclic `path` at `block`, in bps net of fees, from reserves cached per block. Cheap
+    /// next to a simulation, so it screens out paths that can't clear gas. Errors for paths through pools
+    /// without `getReserves` (e.g. Platypus).
+    pub async fn path_spread_bps(&self, path: &Path, block: u64, cache: &mut ReserveCache) -> Result<f64> {
+        let pools = self.cached_path_reserves(path, block, cache).await?;
+        UniswapV2Calculator::path_spread_bps(&pools)
+    }
+
+    /// Each hop of `path` oriented in the trade direction, from reserves at `block` cached per block.
+    pub async fn cached_path_reserves(
+        &self,
+        path: &Path,
+        block: u64,
+        cache: &mut ReserveCache,
+    ) -> Result<Vec<PoolReserves>> {
+        let mut pools = Vec::with_capacity(path.path.len());
+        for dex in &path.path {
+            let (reserve0, reserve1) = cache.get(&self.provider, dex.pool_address(), block).await?;
+            // pairs order their reserves by token address
+            let token_in = dex.coin_in_type().parse::<Address>()?;
+            let token_out = dex.coin_out_type().parse::<Address>()?;
+            let (reserve_in, reserve_out) = if token_in < token_out {
+                (reserve0, reserve1)
+            } else {
+                (reserve1, reserve0)
+            };
+            pools.push(PoolReserves::for_dex(dex.as_ref(), reserve_in, reserve_out));
+        }
+        Ok(pools)
+    }
+
+    /// `path` as UniswapV2 hops for the arb contract, from reserves at `block`. Fails on pools without
+    /// `getReserves`, which the contract can't swap through.
+    pub async fn v2_hops(&self, path: &Path, block: u64) -> Result<Vec<V2Hop>> {
+        ensure!(
+            path.path.iter().all(|dex| dex.protocol() != Protocol::Platypus),
+            "the arb contract only swaps through V2 pairs"
+        );
+        let pools = self.cached_path_reserves(path, block, &mut ReserveCache::default()).await?;
+        path.path
+            .iter()
+            .zip(pools)
+            .map(|(dex, reserves)| {
+                Ok(V2Hop {
+                    pair: dex.pool_address(),
+                    token_in: dex.coin_in_type().parse()?,
+                    token_out: dex.coin_out_type().parse()?,
+                    reserves,
+                })
+            })
+            .collect()
+    }
+
+    /// Pair each hop of `path` with its `(reserve_in, reserve_out)`, charging the hop's own pool fee rather
+    /// than a protocol default.
+    pub fn path_reserves(&self, path: &Path, reserves: &[(U256, U256)]) -> Result<Vec<PoolReserves>> {
+        ensure!(
+            path.path.len() == reserves.len(),
+            "{} hops but {} reserve pairs",
+            path.path.len(),
+            reserves.len()
+        );
+
+        Ok(path
+            .path
+            .iter()
+            .zip(reserves)
+            .map(|(dex, &(reserve_in, reserve_out))| PoolReserves::for_dex(dex.as_ref(), reserve_in, reserve_out))
+            .collect())
+    }
+
+    /// Inputs required at each hop to receive exactly `amount_out`, e.g. for sizing a flashloan repay.
+    /// Per-hop results are memoized across calls for as long as the pool's reserves don't change.
+    pub fn get_amounts_in(&self, amount_out: U256, path: &[PoolReserves]) -> Result<Vec<U256>> {
+        let mut cache = self.amount_in_cache.lock().unwrap();
+        UniswapV2Calculator::get_amounts_in(amount_out, path, &mut cache)
+    }
+
+    pub fn get_optimal_input_amount(&self, path: &[PoolReserves]) -> Option<U256> {
+        UniswapV2Calculator::get_optimal_input_amount(path)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub async fn build_final_tx_data(
         &self,
         sender: Address,
-        amount_in: u64,
+        amount_in: U256,
         path: &Path,
+        trade_type: TradeType,
         gas_limit: u64,
         gas_price: u64,
         source: Source,
-    ) -> Result<TransactionRequest> {
-        let (tx_data, _) = self
-            .trader
-            .get_flashloan_trade_tx(path, sender, amount_in, gas_limit, gas_price, source)
-            .await?;
+        use_access_list: bool,
+    ) -> Result<(TransactionRequest, Option<AccessList>)> {
+        let tx_data = match trade_type {
+            TradeType::Swap => {
+                self.trader
+                    .get_swap_trade_tx(path, sender, amount_in, gas_limit, gas_price)
+                    .await?
+                    .0
+            }
+            TradeType::Flashloan => {
+                self.trader
+                    .get_flashloan_trade_tx(path, sender, amount_in, gas_limit, gas_price, source)
+                    .await?
+                    .0
+            }
+        };
 
-        Ok(tx_data)
+        if !use_access_list {
+            return Ok((tx_data, None));
+        }
+        // an arb without an access list still works, it just pays for cold storage reads
+        let access_list = match self.simulator_pool.get().create_access_list(&tx_data).await {
+            Ok(access_list) => Some(access_list),
+            Err(error) => {
+                warn!("failed to create access list, sending without one: {error:#}");
+                None
+            }
+        };
+
+        Ok((tx_data, access_list))
     }
 }
 
@@ -351,6 +965,67 @@ fn dfs_with_target(
     }
 }
 
+/// Ordered (pool, token_in) hops of a route; identifies a route independently of search order.
+fn route_key(route: &[Box<dyn Dex>]) -> Vec<(Address, String)> {
+    route.iter().map(|dex| (dex.pool_address(), dex.coin_in_type())).collect()
+}
+
+/// Sort routes by their pool addresses so the output, and tie-breaking between equally profitable paths,
+/// doesn't depend on `HashMap` iteration or on which pool lookup finished first.
+fn sort_routes(routes: &mut [Vec<Box<dyn Dex>>]) {
+    routes.sort_by_cached_key(|route| route_key(route));
+}
+
+/// Keep the `max` routes with the deepest bottleneck pool, a cheap stand-in for how much a route can
+/// carry before its weakest hop eats the edge. Stable, so equally deep routes keep their sorted order.
+fn top_routes(mut routes: Vec<Vec<Box<dyn Dex>>>, max: usize) -> Vec<Vec<Box<dyn Dex>>> {
+    if routes.len() > max {
+        routes.sort_by_cached_key(|route| std::cmp::Reverse(route.iter().map(|dex| dex.liquidity()).min()));
+        routes.truncate(max);
+    }
+    routes
+}
+
+/// Number of different protocols a route trades on.
+fn distinct_protocols(route: &[Box<dyn Dex>]) -> usize {
+    route.iter().map(|dex| dex.protocol().to_string()).collect::<HashSet<_>>().len()
+}
+
+/// Every `sell` leg followed by a `buy` leg on a different protocol, for pools with enough liquidity.
+fn direct_pair_routes(sells: &[Box<dyn Dex>], buys: &[Box<dyn Dex>]) -> Vec<Vec<Box<dyn Dex>>> {
+    let liquid = |dex: &&Box<dyn Dex>| dex.liquidity() >= MIN_LIQUIDITY;
+    sells
+        .iter()
+        .filter(liquid)
+        .flat_map(|sell| {
+            buys.iter()
+                .filter(liquid)
+                .filter(|buy| buy.protocol().to_string() != sell.protocol().to_string())
+                .map(|buy| vec![sell.clone(), buy.clone()])
+        })
+        .collect()
+}
+
+/// Drop routes that would simulate identically: the same ordered pools in the same direction, or a
+/// route that crosses the same pool twice in the same direction. Keeps the first occurrence.
+fn dedup_routes(routes: Vec<Vec<Box<dyn Dex>>>) -> Vec<Vec<Box<dyn Dex>>> {
+    let mut seen = HashSet::new();
+
+    routes
+        .into_iter()
+        .filter(|route| {
+            let key = route_key(route);
+
+            let unique_hops: HashSet<_> = key.iter().collect();
+            if unique_hops.len() != key.len() {
+                return false;
+            }
+
+            seen.insert(key)
+        })
+        .collect()
+}
+
 // Legacy function for compatibility
 fn dfs(
     token_address: &str,
@@ -375,35 +1050,98 @@ fn dfs(
     }
 }
 
+/// One path simulated at varying sizes, for `profit_curve` and `max_profitable_size`.
+struct ProfitCtx {
+    trader: Arc<Trader>,
+    path: Path,
+    sender: Address,
+    trade_type: TradeType,
+    gas_limit: u64,
+    sim_ctx: SimulateCtx,
+}
+
+impl ProfitCtx {
+    async fn profit(&self, amount_in: U256) -> Result<i128> {
+        let trade_res = self
+            .trader
+            .get_trade_result(
+                &self.path,
+                self.sender,
+                amount_in,
+                self.trade_type,
+                self.gas_limit,
+                self.sim_ctx.clone(),
+            )
+            .await?;
+        check_gas_cost(&self.path, &trade_res)?;
+        Ok(PathTradeResult::new(self.path.clone(), amount_in, trade_res).profit())
+    }
+}
+
+struct ProfitGoal;
+
+#[async_trait::async_trait]
+impl SearchGoal<ProfitCtx, U256, i128> for ProfitGoal {
+    /// Scores the profit clamped at zero, so a failed simulation counts as unprofitable.
+    async fn evaluate(&self, amount_in: U256, ctx: &ProfitCtx) -> (U256, i128) {
+        let profit = ctx.profit(amount_in).await.unwrap_or(0);
+        (U256::from(profit.max(0) as u128), profit)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PathTradeResult {
     pub path: Path,
-    pub amount_in: u64,
-    pub amount_out: u64,
+    pub amount_in: U256,
+    pub amount_out: U256,
     pub gas_cost: i64,
     pub cache_misses: u64,
+    /// Owed to the flashloan lender on top of `amount_in`; zero for swaps.
+    pub flashloan_premium: U256,
+    /// Estimated referral rebate, in the token the path starts in; counted towards profit.
+    pub referral_rebate: U256,
 }
 
 impl PathTradeResult {
-    pub fn new(path: Path, amount_in: u64, trade_res: TradeResult) -> Self {
+    pub fn new(path: Path, amount_in: U256, trade_res: TradeResult) -> Self {
         Self {
             path,
             amount_in,
             amount_out: trade_res.amount_out,
             gas_cost: trade_res.gas_cost,
             cache_misses: trade_res.cache_misses,
+            flashloan_premium: trade_res.flashloan_premium,
+            referral_rebate: trade_res.referral_rebate,
+        }
+    }
+
+    /// Gas cost expressed in the token the path starts and ends in.
+    fn gas_cost_in_path_token(&self) -> i128 {
+        let profit_token = profit_token();
+        if profit_token.is_profit_token(&self.path.coin_in_type()) {
+            profit_token.gas_cost_in_token(self.gas_cost)
+        } else {
+            self.gas_cost as i128
         }
     }
 
     pub fn profit(&self) -> i128 {
         // Support circular arbitrage: if input and output tokens are the same, calculate profit
         if self.path.coin_in_type() == self.path.coin_out_type() {
-            return self.amount_out as i128 - self.amount_in as i128 - self.gas_cost as i128;
+            // a gross result beyond i128 can only come from a broken quote, never trade on it
+            let gross = i128_from_delta(
+                self.amount_out.saturating_add(self.referral_rebate),
+                self.amount_in.saturating_add(self.flashloan_premium),
+            )
+            .unwrap_or(i128::MIN);
+            return gross.saturating_sub(self.gas_cost_in_path_token());
         }
         
         // For non-circular paths, we can't easily calculate profit without knowing token values
         // Return negative gas cost to indicate this is not a profitable complete arbitrage
-        -(self.gas_cost as i128)
+        let premium = u256_to_i128_checked(self.flashloan_premium).unwrap_or(i128::MAX);
+        let rebate = u256_to_i128_checked(self.referral_rebate).unwrap_or(0);
+        (-(self.gas_cost as i128)).saturating_sub(premium).saturating_add(rebate)
     }
 }
 
@@ -426,9 +1164,323 @@ mod tests {
     use simulator::HttpSimulator;
     use tracing::info;
 
-    use super::*;
+    use super::{
+        test_fixtures::{USDC, WAVAX},
+        *,
+    };
     use crate::config::tests::TEST_HTTP_URL;
 
+    #[derive(Clone)]
+    struct MockDex {
+        pool: Address,
+        token_in: String,
+        token_out: String,
+        liquidity: u128,
+        protocol: Protocol,
+    }
+
+    impl MockDex {
+        fn boxed(pool: u64, token_in: &str, token_out: &str) -> Box<dyn Dex> {
+            Self::boxed_with_liquidity(pool, token_in, token_out, MIN_LIQUIDITY)
+        }
+
+        fn boxed_with_liquidity(pool: u64, token_in: &str, token_out: &str, liquidity: u128) -> Box<dyn Dex> {
+            Box::new(Self {
+                pool: Address::from_low_u64_be(pool),
+                token_in: token_in.to_string(),
+                token_out: token_out.to_string(),
+                liquidity,
+                protocol: Protocol::TraderJoe,
+            })
+        }
+
+        fn boxed_on(pool: u64, token_in: &str, token_out: &str, protocol: Protocol) -> Box<dyn Dex> {
+            Box::new(Self {
+                pool: Address::from_low_u64_be(pool),
+                token_in: token_in.to_string(),
+                token_out: token_out.to_string(),
+                liquidity: MIN_LIQUIDITY,
+                protocol,
+            })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Dex for MockDex {
+        async fn extend_trade_tx(
+            &self,
+            _ctx: &mut TradeCtx,
+            _sender: Address,
+            _token_in: ethers::types::Bytes,
+            _amount_in: Option<U256>,
+        ) -> Result<ethers::types::Bytes> {
+            bail!("mock pool {:?} has no on-chain trade", self.pool)
+        }
+
+        fn coin_in_type(&self) -> String {
+            self.token_in.clone()
+        }
+
+        fn coin_out_type(&self) -> String {
+            self.token_out.clone()
+        }
+
+        fn protocol(&self) -> Protocol {
+            self.protocol.clone()
+        }
+
+        fn liquidity(&self) -> u128 {
+            self.liquidity
+        }
+
+        fn pool_address(&self) -> Address {
+            self.pool
+        }
+
+        fn flip(&mut self) {
+            std::mem::swap(&mut self.token_in, &mut self.token_out);
+        }
+
+        fn is_a2b(&self) -> bool {
+            self.token_in < self.token_out
+        }
+
+        /// A UniswapV2 router swap, with the mock pool standing in for the router.
+        async fn swap_tx(&self, sender: Address, recipient: Address, amount_in: U256) -> Result<TransactionRequest> {
+            uniswap_v2::router_swap_tx(self, self.pool, sender, recipient, amount_in, None)
+        }
+    }
+
+    #[test]
+    fn test_profit_above_u64_max() {
+        let path = Path::new(vec![MockDex::boxed(1, WAVAX, USDC), MockDex::boxed(2, USDC, WAVAX)]);
+        let wavax = U256::exp10(18);
+        // 20 WAVAX in wei does not fit in a u64
+        let amount_in = wavax * 20;
+        assert!(amount_in > U256::from(u64::MAX));
+
+        let trade_res = TradeResult {
+            amount_out: wavax * 201 / 10,
+            flashloan_premium: wavax / 100,
+            ..Default::default()
+        };
+        let result = PathTradeResult::new(path, amount_in, trade_res);
+        assert_eq!(result.profit(), 90_000_000_000_000_000);
+
+        let losing = PathTradeResult {
+            amount_out: wavax * 19,
+            ..result
+        };
+        assert_eq!(losing.profit(), -1_010_000_000_000_000_000);
+    }
+
+    #[test]
+    fn test_nonpositive_gas_cost_discarded() {
+        let path = Path::new(vec![MockDex::boxed(1, WAVAX, USDC), MockDex::boxed(2, USDC, WAVAX)]);
+        let trade_res = |gas_cost| TradeResult {
+            gas_cost,
+            ..Default::default()
+        };
+
+        assert!(check_gas_cost(&path, &trade_res(21_000)).is_ok());
+        assert!(check_gas_cost(&path, &trade_res(0)).is_err());
+        assert!(check_gas_cost(&path, &trade_res(-1)).is_err());
+    }
+
+    #[test]
+    fn test_is_valid_dex() {
+        let zero = format!("{:?}", Address::zero());
+        assert!(MockDex::boxed(1, WAVAX, USDC).is_valid());
+        // same token on both sides, whatever the case
+        assert!(!MockDex::boxed(1, WAVAX, &WAVAX.to_lowercase()).is_valid());
+        assert!(!MockDex::boxed(1, WAVAX, &zero).is_valid());
+        assert!(!MockDex::boxed(1, &zero, USDC).is_valid());
+        assert!(!MockDex::boxed(0, WAVAX, USDC).is_valid());
+    }
+
+    #[test]
+    fn test_flashloan_premium_in_profit() {
+        let path = Path::new(vec![MockDex::boxed(1, WAVAX, USDC), MockDex::boxed(2, USDC, WAVAX)]);
+        let amount_in = U256::exp10(19);
+        // 5 bps gross edge
+        let trade_res = TradeResult {
+            amount_out: amount_in + amount_in / 2_000,
+            ..Default::default()
+        };
+        assert!(PathTradeResult::new(path.clone(), amount_in, trade_res.clone()).profit() > 0);
+
+        // the mock pool can't lend, so the path borrows from the fallback lender at 9 bps
+        let premium = trade::lender_premium(
+            crate::config::FALLBACK_FLASHLOAN_PROVIDER,
+            crate::config::DEFAULT_FLASHLOAN_PREMIUM_BPS,
+            amount_in,
+        );
+        let flashloan_res = TradeResult {
+            flashloan_premium: premium,
+            ..trade_res
+        };
+        assert_eq!(flashloan_res.flashloan_premium, amount_in * 9 / 10_000);
+        assert!(PathTradeResult::new(path, amount_in, flashloan_res).profit() < 0);
+    }
+
+    #[tokio::test]
+    async fn test_referral_rebate_in_calldata_and_profit() {
+        use test_fixtures::{assert_router_swap, AMOUNT_IN, SENDER};
+
+        let referral = Address::repeat_byte(0x4e);
+        let joe = |pool, token_in: &str, token_out: &str| -> Box<dyn Dex> {
+            let pool = Address::from_low_u64_be(pool);
+            let dex = trader_joe::TraderJoeDex::new(pool, token_in.to_string(), token_out.to_string(), 1_000_000, 30);
+            Box::new(dex.with_reserves(U256::exp10(24), U256::exp10(24)))
+        };
+        let path = Path::new(vec![joe(1, WAVAX, USDC), joe(2, USDC, WAVAX)]);
+
+        // the router call carries the referral address right after the swap arguments
+        let mut ctx = TradeCtx::with_referral(Some(referral));
+        let mut handle = token_handle(WAVAX).unwrap();
+        for (i, dex) in path.path.iter().enumerate() {
+            let amount_in = if i == 0 { Some(AMOUNT_IN) } else { None };
+            handle = dex.extend_trade_tx(&mut ctx, SENDER, handle, amount_in).await.unwrap();
+        }
+        let tx = ctx.calls[0].tx();
+        assert_eq!(tx.data.as_deref(), Some(&[ctx.calls[0].swap.encode(), referral.as_bytes().to_vec()].concat()[..]));
+        let quote = path.path[1].get_amount_out(path.path[0].get_amount_out(AMOUNT_IN).unwrap()).unwrap();
+        assert_router_swap(&tx, trader_joe::TRADER_JOE_ROUTER, &[WAVAX, USDC, WAVAX], quote, SENDER);
+
+        // 20% of the 30 bps fee on both hops
+        let rebates = HashMap::from([(Protocol::TraderJoe.to_string().to_lowercase(), 2_000)]);
+        let rebate = trade::referral_rebate(&path, AMOUNT_IN, &rebates);
+        assert_eq!(rebate, AMOUNT_IN * 12 / 10_000);
+        // the mock pools never put the referral address into their calls, so they earn nothing
+        let mock_path = Path::new(vec![MockDex::boxed(1, WAVAX, USDC), MockDex::boxed(2, USDC, WAVAX)]);
+        assert!(trade::referral_rebate(&mock_path, AMOUNT_IN, &rebates).is_zero());
+
+        // with the rebate, a 10 bps loss turns into a 2 bps profit
+        let trade_res = TradeResult {
+            amount_out: AMOUNT_IN - AMOUNT_IN / 1_000,
+            referral_rebate: rebate,
+            ..Default::default()
+        };
+        assert_eq!(trade_res.net_amount_out(), AMOUNT_IN + AMOUNT_IN * 2 / 10_000);
+        assert_eq!(PathTradeResult::new(path, AMOUNT_IN, trade_res).profit(), 4_000_000_000_000_000);
+    }
+
+    #[test]
+    fn test_top_routes_by_bottleneck_liquidity() {
+        let route = |pools: [(u64, u128); 2]| {
+            vec![
+                MockDex::boxed_with_liquidity(pools[0].0, "A", "B", pools[0].1),
+                MockDex::boxed_with_liquidity(pools[1].0, "B", "A", pools[1].1),
+            ]
+        };
+        let routes = vec![
+            route([(1, 1_000_000), (2, 5_000)]),
+            route([(3, 50_000), (4, 60_000)]),
+            route([(5, 10_000), (6, 1_000_000)]),
+        ];
+
+        let kept = top_routes(routes.clone(), 2);
+        assert_eq!(kept.iter().map(|r| r[0].pool_address()).collect::<Vec<_>>(), [
+            Address::from_low_u64_be(3),
+            Address::from_low_u64_be(5)
+        ]);
+        assert_eq!(top_routes(routes, 5).len(), 3);
+    }
+
+    #[test]
+    fn test_dedup_routes() {
+        // B is reachable from A through pool 1, and pool 1 is reported twice (e.g. by two searches),
+        // so the DFS yields A -1-> B -2-> A twice.
+        let mut hops = HashMap::new();
+        hops.insert(
+            "A".to_string(),
+            vec![MockDex::boxed(1, "A", "B"), MockDex::boxed(1, "A", "B"), MockDex::boxed(3, "A", "B")],
+        );
+        hops.insert("B".to_string(), vec![MockDex::boxed(2, "B", "A")]);
+
+        let mut routes = vec![];
+        dfs_with_target("A", "A", &mut vec![], &hops, &mut routes, 2);
+        assert_eq!(routes.len(), 3);
+
+        let routes = dedup_routes(routes);
+        assert_eq!(routes.len(), 2);
+
+        // same pool crossed twice in the same direction
+        let looping = vec![vec![
+            MockDex::boxed(1, "A", "B"),
+            MockDex::boxed(2, "B", "A"),
+            MockDex::boxed(1, "A", "B"),
+        ]];
+        assert!(dedup_routes(looping).is_empty());
+    }
+
+    #[test]
+    fn test_distinct_protocols() {
+        let same_venue = vec![MockDex::boxed(1, "A", "B"), MockDex::boxed(2, "B", "A")];
+        assert_eq!(distinct_protocols(&same_venue), 1);
+
+        let cross_venue = vec![
+            MockDex::boxed(1, "A", "B"),
+            MockDex::boxed_on(2, "B", "C", Protocol::Pangolin),
+            MockDex::boxed(3, "C", "A"),
+        ];
+        assert_eq!(distinct_protocols(&cross_venue), 2);
+    }
+
+    #[test]
+    fn test_direct_pair_routes() {
+        let sells = vec![
+            MockDex::boxed(1, "T", "W"),
+            MockDex::boxed_on(2, "T", "W", Protocol::Pangolin),
+            MockDex::boxed_with_liquidity(3, "T", "W", 0),
+        ];
+        let buys = vec![MockDex::boxed(4, "W", "T"), MockDex::boxed_on(5, "W", "T", Protocol::Pangolin)];
+
+        let routes = direct_pair_routes(&sells, &buys)
+            .iter()
+            .map(|route| route.iter().map(|dex| dex.pool_address().to_low_u64_be()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        // same-protocol pairs and the illiquid pool are skipped
+        assert_eq!(routes, [vec![1, 5], vec![2, 4]]);
+    }
+
+    #[test]
+    fn test_price_from_amounts() {
+        let wavax = "0xB31f66AA3C1e785363F0875A1B74E27b85FD66c7";
+        let usdc = "0xB97EF9Ef8734C71904D8002F8b6Bc66Dd9c48a6E";
+        let mid = "0x0000000000000000000000000000000000000042";
+        let path = Path::new(vec![MockDex::boxed(1, wavax, mid), MockDex::boxed(2, mid, usdc)]);
+
+        // 1 WAVAX (18 decimals) -> 24.8 USDC (6 decimals)
+        let price = path.price_from_amounts(1_000_000_000_000_000_000, 24_800_000).unwrap();
+        assert!((price - 24.8).abs() < 1e-9);
+
+        let unknown = Path::new(vec![MockDex::boxed(3, wavax, mid)]);
+        assert!(unknown.price_from_amounts(1, 1).is_err());
+    }
+
+    #[test]
+    fn test_sort_routes() {
+        let mut routes = vec![
+            vec![MockDex::boxed(2, "A", "B"), MockDex::boxed(1, "B", "A")],
+            vec![MockDex::boxed(1, "A", "B"), MockDex::boxed(3, "B", "A")],
+            vec![MockDex::boxed(1, "A", "B"), MockDex::boxed(2, "B", "A")],
+        ];
+        let mut reversed = routes.iter().rev().cloned().collect::<Vec<_>>();
+
+        sort_routes(&mut routes);
+        sort_routes(&mut reversed);
+
+        let pools = |routes: &[Vec<Box<dyn Dex>>]| {
+            routes
+                .iter()
+                .map(|route| route.iter().map(|dex| dex.pool_address().to_low_u64_be()).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(pools(&routes), vec![vec![1, 2], vec![1, 3], vec![2, 1]]);
+        assert_eq!(pools(&routes), pools(&reversed));
+    }
+
     #[tokio::test]
     async fn test_find_sell_paths() {
         mev_logger::init_console_logger_with_directives(None, &["arb=debug", "dex_indexer=debug"]);
@@ -1,11 +1,19 @@
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc};
 
 use dex_indexer::types::Protocol;
 use ethers::types::{Address, U256};
 use eyre::Result;
 use simulator::Simulator;
 
-use super::{Dex, FlashResult, TradeCtx};
+use crate::config::{pool_fee_override, referral_address};
+
+use super::{
+    uniswap_v2::{extend_router_trade, quote_from_reserves, router_swap_tx},
+    Dex, FlashResult, TradeCtx,
+};
+
+/// Pangolin V1 router, which `swap_tx` and `extend_trade_tx` go through.
+pub const PANGOLIN_ROUTER: &str = "0xE54Ca86531e17Ef3616d22Ca28b0D458b6C89106";
 
 #[derive(Debug, Clone)]
 pub struct PangolinDex {
@@ -14,6 +22,9 @@ pub struct PangolinDex {
     pub token_out: String,
     pub liquidity: u128,
     pub fee_rate: u64,
+    /// `(reserve_in, reserve_out)` in the trade direction, when the pair was read with them; what
+    /// `get_amount_out` quotes from.
+    pub reserves: Option<(U256, U256)>,
 }
 
 impl PangolinDex {
@@ -29,9 +40,15 @@ impl PangolinDex {
             token_in,
             token_out,
             liquidity,
-            fee_rate,
+            fee_rate: pool_fee_override(pool).map(u64::from).unwrap_or(fee_rate),
+            reserves: None,
         }
     }
+
+    pub fn with_reserves(mut self, reserve_in: U256, reserve_out: U256) -> Self {
+        self.reserves = Some((reserve_in, reserve_out));
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -40,7 +57,7 @@ impl Dex for PangolinDex {
         false
     }
 
-    async fn extend_flashloan_tx(&self, _ctx: &mut TradeCtx, _amount: u64) -> Result<FlashResult> {
+    async fn extend_flashloan_tx(&self, _ctx: &mut TradeCtx, _amount: U256) -> Result<FlashResult> {
         eyre::bail!("flashloan not supported")
     }
 
@@ -53,10 +70,10 @@ impl Dex for PangolinDex {
         ctx: &mut TradeCtx,
         sender: Address,
         coin_in: ethers::types::Bytes,
-        amount_in: Option<u64>,
+        amount_in: Option<U256>,
     ) -> Result<ethers::types::Bytes> {
-        // Pangolin swap implementation would go here
-        todo!("Pangolin swap not implemented yet")
+        let router = Address::from_str(PANGOLIN_ROUTER)?;
+        extend_router_trade(ctx, self, router, sender, &coin_in, amount_in)
     }
 
     fn coin_in_type(&self) -> String {
@@ -79,23 +96,36 @@ impl Dex for PangolinDex {
         self.pool
     }
 
+    fn fee_bps(&self) -> u32 {
+        self.fee_rate as u32
+    }
+
+    fn takes_referral(&self) -> bool {
+        true
+    }
+
+    fn get_amount_out(&self, amount_in: U256) -> Result<U256> {
+        quote_from_reserves(self, self.reserves, amount_in)
+    }
+
     fn flip(&mut self) {
         std::mem::swap(&mut self.token_in, &mut self.token_out);
+        self.reserves = self.reserves.map(|(reserve_in, reserve_out)| (reserve_out, reserve_in));
     }
 
     fn is_a2b(&self) -> bool {
         self.token_in < self.token_out
     }
 
-    async fn swap_tx(&self, sender: Address, recipient: Address, amount_in: u64) -> Result<ethers::types::TransactionRequest> {
-        // Pangolin swap transaction building would go here
-        todo!("Pangolin swap_tx not implemented yet")
+    async fn swap_tx(&self, sender: Address, recipient: Address, amount_in: U256) -> Result<ethers::types::TransactionRequest> {
+        let router = Address::from_str(PANGOLIN_ROUTER)?;
+        router_swap_tx(self, router, sender, recipient, amount_in, referral_address())
     }
 }
 
 pub async fn pangolin_related_contract_addresses() -> Vec<String> {
     vec![
-        "0xE54Ca86531e17Ef3616d22Ca28b0D458b6C89106".to_string(), // Pangolin Router
+        PANGOLIN_ROUTER.to_string(), // Pangolin Router
         "0xefa94DE7a4656D787667C749f7E1223D71E9FD88".to_string(), // Pangolin Factory
     ]
 }
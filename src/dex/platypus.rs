@@ -0,0 +1,414 @@
+use std::str::FromStr;
+
+use dex_indexer::types::{Pool, Protocol};
+use ethers::{
+    abi::{self, Token},
+    providers::{Http, Middleware, Provider},
+    types::{transaction::eip2718::TypedTransaction, Address, Bytes, TransactionRequest, U256},
+    utils::id,
+};
+use eyre::{ensure, eyre, Result};
+
+use super::{DecodedSwap, Dex, RouterCall, TradeCtx};
+use crate::config::{min_amount_out, swap_deadline};
+
+const WAD: u128 = 1_000_000_000_000_000_000;
+
+// Platypus slippage curve parameters (WAD), as deployed on the main pool
+const SLIPPAGE_PARAM_K: u128 = 20_000_000_000_000; // 0.00002
+const SLIPPAGE_PARAM_N: u32 = 7;
+const C1: u128 = 376_927_610_599_998_308;
+const X_THRESHOLD: u128 = 329_811_659_274_998_519;
+
+/// Cash and liability of one Platypus asset, in the underlying token's decimals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlatypusAsset {
+    pub address: Address,
+    pub cash: U256,
+    pub liability: U256,
+    pub decimals: u8,
+}
+
+/// Single-sided stableswap pool (Platypus / Wombat v1 style). Each token has its own asset, and
+/// slippage depends on the coverage ratio (cash / liability) of the two assets involved.
+#[derive(Debug, Clone)]
+pub struct PlatypusDex {
+    pub pool: Address,
+    pub token_in: String,
+    pub token_out: String,
+    pub asset_in: PlatypusAsset,
+    pub asset_out: PlatypusAsset,
+    /// WAD
+    pub haircut_rate: U256,
+}
+
+impl PlatypusDex {
+    /// One dex per (token_in, token_out) pair the pool supports; `token_out` restricts it to a single pair.
+    pub async fn new(
+        provider: &Provider<Http>,
+        pool: &Pool,
+        token_in: &str,
+        token_out: Option<String>,
+    ) -> Result<Vec<Self>> {
+        let pool_address = Address::from_str(&pool.pool.to_string())?;
+        let token_in_address = Address::from_str(token_in)?;
+
+        let tokens = match token_out {
+            Some(token_out) => vec![Address::from_str(&token_out)?],
+            None => get_token_addresses(provider, pool_address).await?,
+        };
+        let haircut_rate = call_u256(provider, pool_address, "getHaircutRate()", vec![]).await?;
+        let asset_in = get_asset(provider, pool_address, token_in_address).await?;
+
+        let mut dexes = vec![];
+        for token_out_address in tokens.into_iter().filter(|t| *t != token_in_address) {
+            let asset_out = get_asset(provider, pool_address, token_out_address).await?;
+            dexes.push(Self {
+                pool: pool_address,
+                token_in: format!("{token_in_address:?}"),
+                token_out: format!("{token_out_address:?}"),
+                asset_in,
+                asset_out,
+                haircut_rate,
+            });
+        }
+
+        Ok(dexes)
+    }
+
+    /// Port of `Pool.quotePotentialSwap`, assuming the assets trade 1:1 (stablecoins).
+    pub fn get_amount_out(&self, amount_in: U256) -> Result<U256> {
+        ensure!(!amount_in.is_zero(), "zero amount_in");
+        ensure!(
+            !self.asset_in.liability.is_zero() && !self.asset_out.liability.is_zero(),
+            "asset without liability"
+        );
+
+        let ideal_out = convert_decimals(amount_in, self.asset_in.decimals, self.asset_out.decimals);
+        ensure!(ideal_out < self.asset_out.cash, "insufficient cash in to asset");
+
+        let slippage_from = slippage(self.asset_in.cash, self.asset_in.liability, amount_in, true);
+        let slippage_to = slippage(self.asset_out.cash, self.asset_out.liability, ideal_out, false);
+        let swapping_slippage = (U256::from(WAD) + slippage_from).saturating_sub(slippage_to);
+
+        let to_amount = wmul(ideal_out, swapping_slippage);
+        let haircut = wmul(to_amount, self.haircut_rate);
+
+        Ok(to_amount - haircut)
+    }
+
+    /// Pool `swap` of `amount_in` paid out to `to`, accepting no less than `quote` less `--swap-slippage-bps`.
+    fn pool_swap(&self, amount_in: U256, quote: U256, to: Address) -> Result<DecodedSwap> {
+        Ok(DecodedSwap::PlatypusSwap {
+            from_token: Address::from_str(&self.token_in)?,
+            to_token: Address::from_str(&self.token_out)?,
+            from_amount: amount_in,
+            minimum_to_amount: min_amount_out(quote),
+            to,
+            deadline: swap_deadline()?,
+        })
+    }
+}
+
+fn wmul(x: U256, y: U256) -> U256 {
+    (x * y + U256::from(WAD / 2)) / U256::from(WAD)
+}
+
+fn wdiv(x: U256, y: U256) -> U256 {
+    (x * U256::from(WAD) + y / 2) / y
+}
+
+fn convert_decimals(amount: U256, from: u8, to: u8) -> U256 {
+    if from >= to {
+        amount / U256::exp10((from - to) as usize)
+    } else {
+        amount * U256::exp10((to - from) as usize)
+    }
+}
+
+/// `_slippageFunc`: linear below the threshold, `k / x^n` above it.
+fn slippage_func(x: U256) -> U256 {
+    if x < U256::from(X_THRESHOLD) {
+        return U256::from(C1) - x;
+    }
+
+    let mut pow = U256::from(WAD);
+    for _ in 0..SLIPPAGE_PARAM_N {
+        pow = wmul(pow, x);
+    }
+    if pow.is_zero() {
+        return U256::from(C1);
+    }
+    wdiv(U256::from(SLIPPAGE_PARAM_K), pow)
+}
+
+/// `_slippage`: average slippage over the coverage ratio change caused by adding/removing `cash_change`.
+fn slippage(cash: U256, liability: U256, cash_change: U256, add_cash: bool) -> U256 {
+    let cov_before = wdiv(cash, liability);
+    let cash_after = if add_cash {
+        cash + cash_change
+    } else {
+        cash.saturating_sub(cash_change)
+    };
+    let cov_after = wdiv(cash_after, liability);
+    if cov_before == cov_after {
+        return U256::zero();
+    }
+
+    let slippage_before = slippage_func(cov_before);
+    let slippage_after = slippage_func(cov_after);
+    if cov_before > cov_after {
+        wdiv(slippage_after.saturating_sub(slippage_before), cov_before - cov_after)
+    } else {
+        wdiv(slippage_before.saturating_sub(slippage_after), cov_after - cov_before)
+    }
+}
+
+async fn call(provider: &Provider<Http>, to: Address, signature: &str, args: Vec<Token>) -> Result<Bytes> {
+    let mut data = id(signature).to_vec();
+    data.extend(abi::encode(&args));
+    let tx: TypedTransaction = TransactionRequest::new().to(to).data(data).into();
+    Ok(provider.call(&tx, None).await?)
+}
+
+async fn call_u256(provider: &Provider<Http>, to: Address, signature: &str, args: Vec<Token>) -> Result<U256> {
+    let output = call(provider, to, signature, args).await?;
+    ensure!(output.len() >= 32, "short output for {signature}");
+    Ok(U256::from_big_endian(&output[..32]))
+}
+
+async fn get_token_addresses(provider: &Provider<Http>, pool: Address) -> Result<Vec<Address>> {
+    let output = call(provider, pool, "getTokenAddresses()", vec![]).await?;
+    let decoded = abi::decode(&[abi::ParamType::Array(Box::new(abi::ParamType::Address))], &output)?;
+    decoded
+        .into_iter()
+        .next()
+        .and_then(|t| t.into_array())
+        .ok_or_else(|| eyre!("invalid getTokenAddresses output"))?
+        .into_iter()
+        .map(|t| t.into_address().ok_or_else(|| eyre!("invalid token address")))
+        .collect()
+}
+
+/// Resolve `token`'s asset through the pool's asset registry and read its state.
+async fn get_asset(provider: &Provider<Http>, pool: Address, token: Address) -> Result<PlatypusAsset> {
+    let output = call(provider, pool, "assetOf(address)", vec![Token::Address(token)]).await?;
+    ensure!(output.len() >= 32, "short output for assetOf");
+    let asset = Address::from_slice(&output[12..32]);
+    ensure!(!asset.is_zero(), "token {token:?} not supported by pool {pool:?}");
+
+    let cash = call_u256(provider, asset, "cash()", vec![]).await?;
+    let liability = call_u256(provider, asset, "liability()", vec![]).await?;
+    let decimals = call_u256(provider, asset, "decimals()", vec![]).await?;
+
+    Ok(PlatypusAsset {
+        address: asset,
+        cash,
+        liability,
+        decimals: decimals.as_u32() as u8,
+    })
+}
+
+#[async_trait::async_trait]
+impl Dex for PlatypusDex {
+    /// Always a call of its own on the pool: there is no router path to join. A chained hop spends the
+    /// previous hop's quoted output.
+    async fn extend_trade_tx(
+        &self,
+        ctx: &mut TradeCtx,
+        sender: Address,
+        coin_in: Bytes,
+        amount_in: Option<U256>,
+    ) -> Result<Bytes> {
+        let token_in = Address::from_str(&self.token_in)?;
+        ensure!(
+            coin_in.as_ref() == token_in.as_bytes(),
+            "hop trades {token_in:?} but the previous hop delivers {coin_in}"
+        );
+        let amount_in = match amount_in {
+            Some(amount_in) => amount_in,
+            None => ctx
+                .quoted_amount
+                .ok_or_else(|| eyre!("chained hop through Platypus pool {:?} follows no quoted hop", self.pool))?,
+        };
+        let quote = self.get_amount_out(amount_in)?;
+
+        ctx.calls.push(RouterCall {
+            router: self.pool,
+            sender,
+            swap: self.pool_swap(amount_in, quote, sender)?,
+            referral: None,
+        });
+        ctx.quoted_amount = Some(quote);
+
+        Ok(Bytes::from(Address::from_str(&self.token_out)?.as_bytes().to_vec()))
+    }
+
+    fn get_amount_out(&self, amount_in: U256) -> Result<U256> {
+        PlatypusDex::get_amount_out(self, amount_in)
+    }
+
+    fn coin_in_type(&self) -> String {
+        self.token_in.clone()
+    }
+
+    fn coin_out_type(&self) -> String {
+        self.token_out.clone()
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::Platypus
+    }
+
+    fn liquidity(&self) -> u128 {
+        // the output side bounds the trade size
+        self.asset_out.cash.min(U256::from(u128::MAX)).as_u128()
+    }
+
+    fn pool_address(&self) -> Address {
+        self.pool
+    }
+
+    fn flip(&mut self) {
+        std::mem::swap(&mut self.token_in, &mut self.token_out);
+        std::mem::swap(&mut self.asset_in, &mut self.asset_out);
+    }
+
+    fn is_a2b(&self) -> bool {
+        self.token_in < self.token_out
+    }
+
+    /// Unlike the router swaps, the pool's `swap` is sent as is, so it carries its own minimum out (the quote
+    /// less `--swap-slippage-bps`) and deadline.
+    async fn swap_tx(&self, sender: Address, recipient: Address, amount_in: U256) -> Result<TransactionRequest> {
+        let quote = self.get_amount_out(amount_in)?;
+        Ok(RouterCall {
+            router: self.pool,
+            sender,
+            swap: self.pool_swap(amount_in, quote, recipient)?,
+            referral: None,
+        }
+        .tx())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usdc_usdt(cash_in: u64, cash_out: u64) -> PlatypusDex {
+        let asset = |cash: u64| PlatypusAsset {
+            address: Address::zero(),
+            cash: U256::from(cash) * U256::exp10(6),
+            liability: U256::from(1_000_000u64) * U256::exp10(6),
+            decimals: 6,
+        };
+        PlatypusDex {
+            pool: Address::zero(),
+            token_in: "usdc".to_string(),
+            token_out: "usdt".to_string(),
+            asset_in: asset(cash_in),
+            asset_out: asset(cash_out),
+            // 0.01%
+            haircut_rate: U256::exp10(14),
+        }
+    }
+
+    fn usdc_usdt_pool() -> PlatypusDex {
+        let mut dex = usdc_usdt(1_000_000, 1_000_000);
+        dex.pool = Address::repeat_byte(0x01);
+        dex.token_in = crate::dex::test_fixtures::USDC.to_string();
+        dex.token_out = "0x9702230A8Ea53601f5cD2dc00fDBc13d4dF4A8c7".to_string();
+        dex
+    }
+
+    /// Check `tx` is a pool `swap` of `amount_in` paid out to `to`, with the min-out and deadline it should set.
+    fn assert_pool_swap(dex: &PlatypusDex, tx: &TransactionRequest, amount_in: U256, to: Address) {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        use crate::{
+            config::{DEFAULT_SWAP_SLIPPAGE_BPS, SWAP_DEADLINE},
+            dex::test_fixtures::decode_call,
+        };
+
+        let args = decode_call(
+            tx,
+            dex.pool,
+            "swap(address,address,uint256,uint256,address,uint256)",
+            &[
+                abi::ParamType::Address,
+                abi::ParamType::Address,
+                abi::ParamType::Uint(256),
+                abi::ParamType::Uint(256),
+                abi::ParamType::Address,
+                abi::ParamType::Uint(256),
+            ],
+        );
+        assert_eq!(args[0], Token::Address(dex.token_in.parse().unwrap()), "fromToken");
+        assert_eq!(args[1], Token::Address(dex.token_out.parse().unwrap()), "toToken");
+        assert_eq!(args[2], Token::Uint(amount_in), "fromAmount");
+        let quote = dex.get_amount_out(amount_in).unwrap();
+        let minimum_out = quote * U256::from(10_000 - DEFAULT_SWAP_SLIPPAGE_BPS) / U256::from(10_000);
+        assert_eq!(args[3], Token::Uint(minimum_out), "minimumToAmount");
+        assert_eq!(args[4], Token::Address(to), "to");
+        let deadline = args[5].clone().into_uint().unwrap().as_u64();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        assert!(deadline > now && deadline <= now + SWAP_DEADLINE.as_secs(), "deadline");
+    }
+
+    #[tokio::test]
+    async fn test_swap_tx_encoding() {
+        use crate::dex::test_fixtures::{RECIPIENT, SENDER};
+
+        // AMOUNT_IN is more than the pool holds
+        let dex = usdc_usdt_pool();
+        let amount_in = U256::from(1_000) * U256::exp10(6);
+        let tx = dex.swap_tx(SENDER, RECIPIENT, amount_in).await.unwrap();
+        assert_pool_swap(&dex, &tx, amount_in, RECIPIENT);
+    }
+
+    #[tokio::test]
+    async fn test_extend_trade_tx_encoding() {
+        use crate::dex::{test_fixtures::SENDER, token_handle};
+
+        let dex = usdc_usdt_pool();
+        let amount_in = U256::from(1_000) * U256::exp10(6);
+        let usdc = token_handle(&dex.token_in).unwrap();
+
+        // a chained hop needs the previous hop's quote
+        let mut ctx = TradeCtx::new();
+        assert!(dex.extend_trade_tx(&mut ctx, SENDER, usdc.clone(), None).await.is_err());
+
+        // and spends it in a call of its own on the pool
+        ctx.quoted_amount = Some(amount_in);
+        let usdt = dex.extend_trade_tx(&mut ctx, SENDER, usdc, None).await.unwrap();
+        assert_eq!(usdt, token_handle(&dex.token_out).unwrap());
+        assert_eq!(ctx.calls.len(), 1);
+        assert_pool_swap(&dex, &ctx.calls[0].tx(), amount_in, SENDER);
+        assert_eq!(ctx.quoted_amount, Some(dex.get_amount_out(amount_in).unwrap()));
+
+        assert!(dex.extend_trade_tx(&mut ctx, SENDER, usdt, None).await.is_err(), "hop doesn't trade USDT");
+    }
+
+    #[test]
+    fn test_balanced_pool_quotes_near_par() {
+        let dex = usdc_usdt(1_000_000, 1_000_000);
+        let amount_in = U256::from(1_000) * U256::exp10(6);
+        let out = dex.get_amount_out(amount_in).unwrap();
+
+        assert!(out < amount_in);
+        assert!(out > amount_in * 999 / 1000);
+    }
+
+    #[test]
+    fn test_coverage_drives_slippage() {
+        let amount_in = U256::from(100_000) * U256::exp10(6);
+        let balanced = usdc_usdt(1_000_000, 1_000_000).get_amount_out(amount_in).unwrap();
+        // to-asset already drained: worse price
+        let drained = usdc_usdt(1_000_000, 400_000).get_amount_out(amount_in).unwrap();
+        assert!(drained < balanced);
+
+        // can't take more than the to-asset's cash
+        assert!(usdc_usdt(1_000_000, 1_000).get_amount_out(amount_in).is_err());
+    }
+}
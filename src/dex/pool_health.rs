@@ -0,0 +1,112 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use ethers::types::Address;
+use once_cell::sync::Lazy;
+use prometheus::{register_int_counter, IntCounter};
+use tracing::{info, warn};
+
+use crate::config::delist_after_failures;
+
+static POOLS_DELISTED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "arb_pools_delisted_total",
+        "Pools taken out of routing after consecutive getReserves calls found no code"
+    )
+    .unwrap()
+});
+
+static POOL_HEALTH: Lazy<Mutex<PoolHealth>> = Lazy::new(|| Mutex::new(PoolHealth::default()));
+
+/// Consecutive failed `getReserves` reads per pool, and the pools delisted for reaching the limit. Only empty
+/// outputs (no code at the pool) count: reverts happen on every call to pools without `getReserves`, and an
+/// RPC outage says nothing about the pool.
+#[derive(Debug, Default)]
+struct PoolHealth {
+    failures: HashMap<Address, usize>,
+    delisted: HashSet<Address>,
+}
+
+impl PoolHealth {
+    /// Count a failed read of `pool`; true when it just reached `limit` and got delisted.
+    fn record_failure(&mut self, pool: Address, limit: usize) -> bool {
+        let failures = self.failures.entry(pool).or_default();
+        *failures += 1;
+        limit > 0 && *failures >= limit && self.delisted.insert(pool)
+    }
+
+    fn record_success(&mut self, pool: Address) {
+        self.failures.remove(&pool);
+    }
+
+    fn relist(&mut self, pool: Address) -> bool {
+        self.failures.remove(&pool);
+        self.delisted.remove(&pool)
+    }
+}
+
+pub(super) fn record_reserve_failure(pool: Address) {
+    let limit = delist_after_failures();
+    if POOL_HEALTH.lock().unwrap().record_failure(pool, limit) {
+        POOLS_DELISTED.inc();
+        warn!(?pool, failures = limit, "delisting pool: getReserves keeps finding no code");
+    }
+}
+
+pub(super) fn record_reserve_success(pool: Address) {
+    POOL_HEALTH.lock().unwrap().record_success(pool);
+}
+
+/// Whether `pool` was taken out of routing for having no code at too many `getReserves` reads in a row.
+pub fn is_delisted(pool: Address) -> bool {
+    POOL_HEALTH.lock().unwrap().delisted.contains(&pool)
+}
+
+#[cfg(test)]
+pub(super) fn failures(pool: Address) -> usize {
+    POOL_HEALTH.lock().unwrap().failures.get(&pool).copied().unwrap_or_default()
+}
+
+/// Put delisted pools back into routing with a clean failure count, e.g. from `--relist-pools` on reload.
+pub fn relist_pools(pools: &[Address]) {
+    let mut health = POOL_HEALTH.lock().unwrap();
+    for &pool in pools {
+        if health.relist(pool) {
+            info!(?pool, "relisted pool");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delist_after_consecutive_failures() {
+        let pool = Address::repeat_byte(0x01);
+        let mut health = PoolHealth::default();
+
+        assert!(!health.record_failure(pool, 3));
+        assert!(!health.record_failure(pool, 3));
+        // a good read resets the streak
+        health.record_success(pool);
+        assert!(!health.record_failure(pool, 3));
+        assert!(!health.record_failure(pool, 3));
+        assert!(health.record_failure(pool, 3));
+        assert!(health.delisted.contains(&pool));
+        // reported once
+        assert!(!health.record_failure(pool, 3));
+
+        assert!(health.relist(pool));
+        assert!(!health.delisted.contains(&pool));
+        assert!(!health.record_failure(pool, 3));
+
+        // a zero limit never delists
+        let other = Address::repeat_byte(0x02);
+        for _ in 0..10 {
+            assert!(!health.record_failure(other, 0));
+        }
+    }
+}
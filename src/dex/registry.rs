@@ -0,0 +1,89 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use dex_indexer::types::{Pool, Protocol};
+use ethers::providers::{Http, Provider};
+use eyre::Result;
+use simulator::Simulator;
+
+use super::Dex;
+use crate::config::is_protocol_enabled;
+
+/// Builds the `Dex` adapters for one pool of a protocol the core crate doesn't know about.
+///
+/// A pool holding more than two coins (Curve, Balancer) returns one `Dex` per tradable pair entering on
+/// `token_in_type`: every other coin when `token_out_type` is `None`, only that coin otherwise. The router then
+/// sees each leg as its own hop, as it does for Platypus.
+#[async_trait::async_trait]
+pub trait DexFactory: Send + Sync {
+    async fn new_dexes(
+        &self,
+        simulator: Arc<Box<dyn Simulator>>,
+        provider: &Provider<Http>,
+        pool: &Pool,
+        token_in_type: &str,
+        token_out_type: Option<String>,
+    ) -> Result<Vec<Box<dyn Dex>>>;
+}
+
+/// Runtime-registered `DexFactory`s, keyed by the protocol's display name (the same name the indexer
+/// reports for the pool). A registered factory takes precedence over the built-in adapter.
+#[derive(Clone, Default)]
+pub struct DexRegistry {
+    factories: Arc<RwLock<HashMap<String, Arc<dyn DexFactory>>>>,
+}
+
+impl DexRegistry {
+    /// Register `factory` for `protocol`, replacing any factory registered before.
+    pub fn register(&self, protocol: impl Into<String>, factory: Arc<dyn DexFactory>) {
+        self.factories.write().unwrap().insert(protocol.into(), factory);
+    }
+
+    pub fn get(&self, protocol: &str) -> Option<Arc<dyn DexFactory>> {
+        self.factories.read().unwrap().get(protocol).cloned()
+    }
+
+    /// Whether pools of `protocol` are searched. Registering a factory is how a downstream crate opts its
+    /// protocol in, so those always are; built-in protocols follow `--protocols`.
+    pub fn is_enabled(&self, protocol: &Protocol) -> bool {
+        self.get(&protocol.to_string()).is_some() || is_protocol_enabled(protocol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoopFactory;
+
+    #[async_trait::async_trait]
+    impl DexFactory for NoopFactory {
+        async fn new_dexes(
+            &self,
+            _simulator: Arc<Box<dyn Simulator>>,
+            _provider: &Provider<Http>,
+            _pool: &Pool,
+            _token_in_type: &str,
+            _token_out_type: Option<String>,
+        ) -> Result<Vec<Box<dyn Dex>>> {
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn test_register_replaces_and_is_shared() {
+        let registry = DexRegistry::default();
+        let handle = registry.clone();
+        assert!(registry.get("TinySwap").is_none());
+
+        handle.register("TinySwap", Arc::new(NoopFactory));
+        let first = registry.get("TinySwap").unwrap();
+        assert!(registry.get("Cetus").is_none());
+
+        handle.register("TinySwap", Arc::new(NoopFactory));
+        let second = registry.get("TinySwap").unwrap();
+        assert!(!Arc::ptr_eq(&first, &second));
+    }
+}
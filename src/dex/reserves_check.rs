@@ -0,0 +1,216 @@
+use ethers::{
+    abi::{self, ParamType, Token},
+    providers::{Http, Middleware, Provider},
+    types::{transaction::eip2718::TypedTransaction, Address, BlockId, TransactionRequest, U256},
+};
+use eyre::{ensure, eyre, Result};
+use tracing::debug;
+
+use super::{
+    pool_health::{record_reserve_failure, record_reserve_success},
+    Path,
+};
+use crate::config::multicall_address;
+
+/// `getReserves()` selector of UniswapV2-style pairs.
+const GET_RESERVES: [u8; 4] = [0x09, 0x02, 0xf1, 0xac];
+/// `aggregate3((address,bool,bytes)[])` selector of Multicall3.
+const AGGREGATE3: [u8; 4] = [0x82, 0xad, 0x56, 0xcb];
+/// `token0()` / `token1()` selectors of UniswapV2-style pairs.
+const TOKEN0: [u8; 4] = [0x0d, 0xfe, 0x16, 0x81];
+const TOKEN1: [u8; 4] = [0xd2, 0x12, 0x20, 0xa7];
+
+/// A pool whose live reserves differ from the ones at the block its path was quoted on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReserveDivergence {
+    pub pool: Address,
+    /// (reserve0, reserve1) at the quote block
+    pub quoted: (U256, U256),
+    /// (reserve0, reserve1) at the latest block
+    pub live: (U256, U256),
+}
+
+impl ReserveDivergence {
+    /// Largest relative move of either reserve, in bps of the quoted value.
+    pub fn max_change_bps(&self) -> u64 {
+        let change = |quoted: U256, live: U256| {
+            if quoted.is_zero() {
+                return if live.is_zero() { 0 } else { u64::MAX };
+            }
+            let delta = if live > quoted { live - quoted } else { quoted - live };
+            (delta.saturating_mul(U256::from(10_000u64)) / quoted).min(U256::from(u64::MAX)).as_u64()
+        };
+        change(self.quoted.0, self.live.0).max(change(self.quoted.1, self.live.1))
+    }
+}
+
+pub(super) async fn get_reserves(provider: &Provider<Http>, pool: Address, block: Option<u64>) -> Result<(U256, U256)> {
+    let tx: TypedTransaction = TransactionRequest::new().to(pool).data(GET_RESERVES.to_vec()).into();
+    let output = provider.call(&tx, block.map(|b| BlockId::Number(b.into()))).await?;
+    record_reserves_output(pool, &output).ok_or_else(|| eyre!("short getReserves output from {pool:?}"))
+}
+
+/// Reserves out of a successful `getReserves()` call, recording the pool's health. Only an empty output counts
+/// as a failure: it is what an address without code (a self-destructed pool) returns. Reverts don't, since
+/// pools without `getReserves` (e.g. Platypus) revert on every call.
+fn record_reserves_output(pool: Address, output: &[u8]) -> Option<(U256, U256)> {
+    if output.is_empty() {
+        record_reserve_failure(pool);
+        return None;
+    }
+    if output.len() < 64 {
+        return None;
+    }
+    record_reserve_success(pool);
+    Some((U256::from_big_endian(&output[..32]), U256::from_big_endian(&output[32..64])))
+}
+
+/// `getReserves()` of every pool in one call through the configured Multicall3, or as concurrent individual
+/// calls when none is configured or the multicall itself fails. One entry per pool, in order; `None` for pools
+/// whose call reverted (e.g. Platypus, which has no `getReserves`).
+pub(super) async fn get_reserves_batch(
+    provider: &Provider<Http>,
+    pools: &[Address],
+    block: u64,
+) -> Vec<Option<(U256, U256)>> {
+    if let Some(multicall) = multicall_address() {
+        let tx: TypedTransaction =
+            TransactionRequest::new().to(multicall).data(encode_aggregate3(pools)).into();
+        match provider.call(&tx, Some(BlockId::Number(block.into()))).await {
+            Ok(output) => match decode_aggregate3(&output, pools.len()) {
+                Ok(results) => {
+                    return pools
+                        .iter()
+                        .zip(results)
+                        .map(|(&pool, (success, data))| success.then(|| record_reserves_output(pool, &data)).flatten())
+                        .collect();
+                }
+                Err(error) => debug!(?multicall, "bad multicall output, reading reserves one by one: {error:#}"),
+            },
+            Err(error) => debug!(?multicall, "multicall failed, reading reserves one by one: {error:#}"),
+        }
+    }
+
+    futures::future::join_all(pools.iter().map(|&pool| get_reserves(provider, pool, Some(block))))
+        .await
+        .into_iter()
+        .map(Result::ok)
+        .collect()
+}
+
+/// Calldata for `aggregate3` with one failure-tolerant `getReserves()` per pool.
+fn encode_aggregate3(pools: &[Address]) -> Vec<u8> {
+    let calls = pools
+        .iter()
+        .map(|&pool| Token::Tuple(vec![Token::Address(pool), Token::Bool(true), Token::Bytes(GET_RESERVES.to_vec())]))
+        .collect();
+    let mut data = AGGREGATE3.to_vec();
+    data.extend(abi::encode(&[Token::Array(calls)]));
+    data
+}
+
+/// `(success, returnData)` per call out of `aggregate3`'s `(bool success, bytes returnData)[]`.
+fn decode_aggregate3(output: &[u8], calls: usize) -> Result<Vec<(bool, Vec<u8>)>> {
+    let kind = ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Bool, ParamType::Bytes])));
+    let results = abi::decode(&[kind], output)?
+        .pop()
+        .and_then(Token::into_array)
+        .ok_or_else(|| eyre!("not a result array"))?;
+    ensure!(results.len() == calls, "{} results for {} calls", results.len(), calls);
+
+    results
+        .into_iter()
+        .map(|result| {
+            let mut fields = result.into_tuple().ok_or_else(|| eyre!("result is not a tuple"))?.into_iter();
+            let success = fields.next().and_then(Token::into_bool).ok_or_else(|| eyre!("missing success"))?;
+            let data = fields.next().and_then(Token::into_bytes).ok_or_else(|| eyre!("missing returnData"))?;
+            Ok((success, data))
+        })
+        .collect()
+}
+
+/// `(token0, token1)` of a UniswapV2-style pair, the order its reserves are reported in.
+pub async fn get_pair_tokens(provider: &Provider<Http>, pool: Address) -> Result<(Address, Address)> {
+    let mut tokens = [Address::zero(); 2];
+    for (token, selector) in tokens.iter_mut().zip([TOKEN0, TOKEN1]) {
+        let tx: TypedTransaction = TransactionRequest::new().to(pool).data(selector.to_vec()).into();
+        let output = provider.call(&tx, None).await?;
+        ensure!(output.len() >= 32, "short token output from {pool:?}");
+        *token = Address::from_slice(&output[12..32]);
+    }
+    Ok((tokens[0], tokens[1]))
+}
+
+/// Compare every pool's reserves at `quoted_block` against the latest block. Pools without `getReserves`
+/// (e.g. Platypus) are skipped.
+pub async fn find_reserve_divergences(
+    provider: &Provider<Http>,
+    path: &Path,
+    quoted_block: u64,
+) -> Vec<ReserveDivergence> {
+    let mut divergences = vec![];
+    for dex in &path.path {
+        let pool = dex.pool_address();
+        let (quoted, live) = match (
+            get_reserves(provider, pool, Some(quoted_block)).await,
+            get_reserves(provider, pool, None).await,
+        ) {
+            (Ok(quoted), Ok(live)) => (quoted, live),
+            (Err(error), _) | (_, Err(error)) => {
+                debug!(?pool, protocol = %dex.protocol(), "skip reserves check: {error:#}");
+                continue;
+            }
+        };
+        if quoted != live {
+            divergences.push(ReserveDivergence { pool, quoted, live });
+        }
+    }
+    divergences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_change_bps() {
+        let divergence = |quoted: (u64, u64), live: (u64, u64)| ReserveDivergence {
+            pool: Address::zero(),
+            quoted: (U256::from(quoted.0), U256::from(quoted.1)),
+            live: (U256::from(live.0), U256::from(live.1)),
+        };
+
+        assert_eq!(divergence((1_000, 1_000), (1_000, 1_000)).max_change_bps(), 0);
+        // reserve1 dropped 5%, reserve0 grew 1%
+        assert_eq!(divergence((1_000, 2_000), (1_010, 1_900)).max_change_bps(), 500);
+        assert_eq!(divergence((0, 1_000), (1, 1_000)).max_change_bps(), u64::MAX);
+    }
+
+    #[test]
+    fn test_aggregate3_round_trip() {
+        let pools = [Address::repeat_byte(0x01), Address::repeat_byte(0x02)];
+        let calldata = encode_aggregate3(&pools);
+        assert_eq!(calldata[..4], AGGREGATE3);
+
+        // the second pool reverted, as a pool without getReserves would
+        let reserves = abi::encode(&[Token::Uint(100.into()), Token::Uint(200.into()), Token::Uint(0.into())]);
+        let output = abi::encode(&[Token::Array(vec![
+            Token::Tuple(vec![Token::Bool(true), Token::Bytes(reserves)]),
+            Token::Tuple(vec![Token::Bool(false), Token::Bytes(vec![])]),
+        ])]);
+        let results = decode_aggregate3(&output, pools.len()).unwrap();
+        assert_eq!(results, vec![(true, reserves), (false, vec![])]);
+        assert!(decode_aggregate3(&output, 3).is_err());
+    }
+
+    #[test]
+    fn test_only_empty_output_counts_as_failure() {
+        let pool = Address::repeat_byte(0x46);
+        let reserves = abi::encode(&[Token::Uint(100.into()), Token::Uint(200.into()), Token::Uint(0.into())]);
+        assert_eq!(record_reserves_output(pool, &reserves), Some((U256::from(100), U256::from(200))));
+        assert_eq!(record_reserves_output(pool, &[0; 32]), None);
+        assert_eq!(super::super::pool_health::failures(pool), 0, "short output is not a missing pool");
+        assert_eq!(record_reserves_output(pool, &[]), None);
+        assert_eq!(super::super::pool_health::failures(pool), 1);
+    }
+}
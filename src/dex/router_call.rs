@@ -0,0 +1,638 @@
+//! Typed router calldata: the one place that reads and writes swap calls, shared by the mempool decoder
+//! and the tx builders. A new router version is a new variant here.
+
+use std::collections::HashMap;
+
+use ethers::{
+    abi::{self, ParamType, Token},
+    types::{Address, TransactionRequest, U256},
+    utils::id,
+};
+use eyre::{eyre, OptionExt, Result};
+use once_cell::sync::Lazy;
+
+/// How a V2 router spells the native coin in its function names: Uniswap/Sushi forks say `ETH`,
+/// TraderJoe and Pangolin say `AVAX`. The arguments are the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NativeName {
+    Eth,
+    Avax,
+}
+
+impl NativeName {
+    fn as_str(self) -> &'static str {
+        match self {
+            NativeName::Eth => "ETH",
+            NativeName::Avax => "AVAX",
+        }
+    }
+}
+
+/// A decoded swap call on a UniswapV2-style router, a UniswapV3 `SwapRouter` or a Platypus pool. Native-in
+/// variants carry their input in the tx value, not the calldata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodedSwap {
+    /// `swapExactTokensForTokens`, or its `SupportingFeeOnTransferTokens` twin.
+    ExactTokensForTokens {
+        amount_in: U256,
+        amount_out_min: U256,
+        path: Vec<Address>,
+        to: Address,
+        deadline: U256,
+        supporting_fee: bool,
+    },
+    /// `swapTokensForExactTokens`.
+    TokensForExactTokens {
+        amount_out: U256,
+        amount_in_max: U256,
+        path: Vec<Address>,
+        to: Address,
+        deadline: U256,
+    },
+    /// `swapExactAVAXForTokens` / `swapExactETHForTokens`, optionally fee-supporting.
+    ExactAvaxForTokens {
+        native: NativeName,
+        amount_out_min: U256,
+        path: Vec<Address>,
+        to: Address,
+        deadline: U256,
+        supporting_fee: bool,
+    },
+    /// `swapAVAXForExactTokens` / `swapETHForExactTokens`.
+    AvaxForExactTokens {
+        native: NativeName,
+        amount_out: U256,
+        path: Vec<Address>,
+        to: Address,
+        deadline: U256,
+    },
+    /// `swapExactTokensForAVAX` / `swapExactTokensForETH`, optionally fee-supporting.
+    ExactTokensForAvax {
+        native: NativeName,
+        amount_in: U256,
+        amount_out_min: U256,
+        path: Vec<Address>,
+        to: Address,
+        deadline: U256,
+        supporting_fee: bool,
+    },
+    /// `swapTokensForExactAVAX` / `swapTokensForExactETH`.
+    TokensForExactAvax {
+        native: NativeName,
+        amount_out: U256,
+        amount_in_max: U256,
+        path: Vec<Address>,
+        to: Address,
+        deadline: U256,
+    },
+    /// V3 `exactInputSingle((tokenIn, tokenOut, fee, recipient, deadline, amountIn, amountOutMinimum,
+    /// sqrtPriceLimitX96))`.
+    V3ExactInputSingle {
+        token_in: Address,
+        token_out: Address,
+        fee: u32,
+        recipient: Address,
+        deadline: U256,
+        amount_in: U256,
+        amount_out_min: U256,
+        sqrt_price_limit_x96: U256,
+    },
+    /// V3 `exactInput((path, recipient, deadline, amountIn, amountOutMinimum))`, where `path` is packed
+    /// `token (20 bytes) | fee (3 bytes) | token | ...`.
+    V3ExactInput {
+        path: Vec<u8>,
+        recipient: Address,
+        deadline: U256,
+        amount_in: U256,
+        amount_out_min: U256,
+    },
+    /// Platypus pool `swap(fromToken, toToken, fromAmount, minimumToAmount, to, deadline)`; the pool is its
+    /// own router.
+    PlatypusSwap {
+        from_token: Address,
+        to_token: Address,
+        from_amount: U256,
+        minimum_to_amount: U256,
+        to: Address,
+        deadline: U256,
+    },
+}
+
+/// One shape of every variant, keyed by selector. Decoding looks the shape up and fills in its fields.
+static SHAPES: Lazy<HashMap<[u8; 4], DecodedSwap>> = Lazy::new(|| {
+    let (zero, path) = (U256::zero(), Vec::new());
+    let mut shapes = vec![
+        DecodedSwap::TokensForExactTokens {
+            amount_out: zero,
+            amount_in_max: zero,
+            path: path.clone(),
+            to: Address::zero(),
+            deadline: zero,
+        },
+        DecodedSwap::V3ExactInputSingle {
+            token_in: Address::zero(),
+            token_out: Address::zero(),
+            fee: 0,
+            recipient: Address::zero(),
+            deadline: zero,
+            amount_in: zero,
+            amount_out_min: zero,
+            sqrt_price_limit_x96: zero,
+        },
+        DecodedSwap::V3ExactInput {
+            path: vec![],
+            recipient: Address::zero(),
+            deadline: zero,
+            amount_in: zero,
+            amount_out_min: zero,
+        },
+        DecodedSwap::PlatypusSwap {
+            from_token: Address::zero(),
+            to_token: Address::zero(),
+            from_amount: zero,
+            minimum_to_amount: zero,
+            to: Address::zero(),
+            deadline: zero,
+        },
+    ];
+    for supporting_fee in [false, true] {
+        shapes.push(DecodedSwap::ExactTokensForTokens {
+            amount_in: zero,
+            amount_out_min: zero,
+            path: path.clone(),
+            to: Address::zero(),
+            deadline: zero,
+            supporting_fee,
+        });
+    }
+    for native in [NativeName::Eth, NativeName::Avax] {
+        for supporting_fee in [false, true] {
+            shapes.push(DecodedSwap::ExactAvaxForTokens {
+                native,
+                amount_out_min: zero,
+                path: path.clone(),
+                to: Address::zero(),
+                deadline: zero,
+                supporting_fee,
+            });
+            shapes.push(DecodedSwap::ExactTokensForAvax {
+                native,
+                amount_in: zero,
+                amount_out_min: zero,
+                path: path.clone(),
+                to: Address::zero(),
+                deadline: zero,
+                supporting_fee,
+            });
+        }
+        shapes.push(DecodedSwap::AvaxForExactTokens {
+            native,
+            amount_out: zero,
+            path: path.clone(),
+            to: Address::zero(),
+            deadline: zero,
+        });
+        shapes.push(DecodedSwap::TokensForExactAvax {
+            native,
+            amount_out: zero,
+            amount_in_max: zero,
+            path: path.clone(),
+            to: Address::zero(),
+            deadline: zero,
+        });
+    }
+    shapes.into_iter().map(|shape| (shape.selector(), shape)).collect()
+});
+
+const V2_ARGS: &str = "(uint256,uint256,address[],address,uint256)";
+const V2_NATIVE_IN_ARGS: &str = "(uint256,address[],address,uint256)";
+
+fn fee_suffix(supporting_fee: bool) -> &'static str {
+    if supporting_fee {
+        "SupportingFeeOnTransferTokens"
+    } else {
+        ""
+    }
+}
+
+impl DecodedSwap {
+    /// Decode router calldata; `None` for anything that isn't a known swap call.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let selector: [u8; 4] = data.get(..4)?.try_into().ok()?;
+        let shape = SHAPES.get(&selector)?;
+        let tokens = abi::decode(&shape.params(), &data[4..]).ok()?;
+        shape.with_args(tokens).ok()
+    }
+
+    /// Full calldata: selector followed by the ABI-encoded arguments.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = self.selector().to_vec();
+        data.extend(abi::encode(&self.args()));
+        data
+    }
+
+    pub fn signature(&self) -> String {
+        match self {
+            DecodedSwap::ExactTokensForTokens { supporting_fee, .. } => {
+                format!("swapExactTokensForTokens{}{V2_ARGS}", fee_suffix(*supporting_fee))
+            }
+            DecodedSwap::TokensForExactTokens { .. } => format!("swapTokensForExactTokens{V2_ARGS}"),
+            DecodedSwap::ExactAvaxForTokens { native, supporting_fee, .. } => {
+                format!("swapExact{}ForTokens{}{V2_NATIVE_IN_ARGS}", native.as_str(), fee_suffix(*supporting_fee))
+            }
+            DecodedSwap::AvaxForExactTokens { native, .. } => {
+                format!("swap{}ForExactTokens{V2_NATIVE_IN_ARGS}", native.as_str())
+            }
+            DecodedSwap::ExactTokensForAvax { native, supporting_fee, .. } => {
+                format!("swapExactTokensFor{}{}{V2_ARGS}", native.as_str(), fee_suffix(*supporting_fee))
+            }
+            DecodedSwap::TokensForExactAvax { native, .. } => {
+                format!("swapTokensForExact{}{V2_ARGS}", native.as_str())
+            }
+            DecodedSwap::V3ExactInputSingle { .. } => {
+                "exactInputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160))".to_string()
+            }
+            DecodedSwap::V3ExactInput { .. } => "exactInput((bytes,address,uint256,uint256,uint256))".to_string(),
+            DecodedSwap::PlatypusSwap { .. } => "swap(address,address,uint256,uint256,address,uint256)".to_string(),
+        }
+    }
+
+    pub fn selector(&self) -> [u8; 4] {
+        id(self.signature())
+    }
+
+    /// Tokens along the route, input first. A V3 packed path is unpacked; a truncated one yields the
+    /// tokens read before the cut.
+    pub fn path(&self) -> Vec<Address> {
+        match self {
+            DecodedSwap::ExactTokensForTokens { path, .. }
+            | DecodedSwap::TokensForExactTokens { path, .. }
+            | DecodedSwap::ExactAvaxForTokens { path, .. }
+            | DecodedSwap::AvaxForExactTokens { path, .. }
+            | DecodedSwap::ExactTokensForAvax { path, .. }
+            | DecodedSwap::TokensForExactAvax { path, .. } => path.clone(),
+            DecodedSwap::V3ExactInputSingle { token_in, token_out, .. } => vec![*token_in, *token_out],
+            DecodedSwap::PlatypusSwap { from_token, to_token, .. } => vec![*from_token, *to_token],
+            DecodedSwap::V3ExactInput { path, .. } => path
+                .chunks(23)
+                .filter(|hop| hop.len() >= 20)
+                .map(|hop| Address::from_slice(&hop[..20]))
+                .collect(),
+        }
+    }
+
+    pub fn token_in(&self) -> Option<Address> {
+        self.path().first().copied()
+    }
+
+    pub fn token_out(&self) -> Option<Address> {
+        self.path().last().copied()
+    }
+
+    /// The most `token_in` the swap can spend: the exact input, the exact-out maximum, or `value` for
+    /// native-in calls.
+    pub fn max_amount_in(&self, value: U256) -> U256 {
+        match self {
+            DecodedSwap::ExactTokensForTokens { amount_in, .. }
+            | DecodedSwap::ExactTokensForAvax { amount_in, .. }
+            | DecodedSwap::V3ExactInputSingle { amount_in, .. }
+            | DecodedSwap::V3ExactInput { amount_in, .. }
+            | DecodedSwap::PlatypusSwap { from_amount: amount_in, .. } => *amount_in,
+            DecodedSwap::TokensForExactTokens { amount_in_max, .. }
+            | DecodedSwap::TokensForExactAvax { amount_in_max, .. } => *amount_in_max,
+            DecodedSwap::ExactAvaxForTokens { .. } | DecodedSwap::AvaxForExactTokens { .. } => value,
+        }
+    }
+
+    fn params(&self) -> Vec<ParamType> {
+        let path = ParamType::Array(Box::new(ParamType::Address));
+        match self {
+            DecodedSwap::ExactAvaxForTokens { .. } | DecodedSwap::AvaxForExactTokens { .. } => {
+                vec![ParamType::Uint(256), path, ParamType::Address, ParamType::Uint(256)]
+            }
+            DecodedSwap::V3ExactInputSingle { .. } => vec![ParamType::Tuple(vec![
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Uint(24),
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(160),
+            ])],
+            DecodedSwap::V3ExactInput { .. } => vec![ParamType::Tuple(vec![
+                ParamType::Bytes,
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+            ])],
+            DecodedSwap::PlatypusSwap { .. } => vec![
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Address,
+                ParamType::Uint(256),
+            ],
+            _ => vec![
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                path,
+                ParamType::Address,
+                ParamType::Uint(256),
+            ],
+        }
+    }
+
+    fn args(&self) -> Vec<Token> {
+        let path_arg = |path: &[Address]| Token::Array(path.iter().copied().map(Token::Address).collect());
+        match self {
+            DecodedSwap::ExactTokensForTokens { amount_in: a, amount_out_min: b, path, to, deadline, .. }
+            | DecodedSwap::ExactTokensForAvax { amount_in: a, amount_out_min: b, path, to, deadline, .. }
+            | DecodedSwap::TokensForExactTokens { amount_out: a, amount_in_max: b, path, to, deadline }
+            | DecodedSwap::TokensForExactAvax { amount_out: a, amount_in_max: b, path, to, deadline, .. } => vec![
+                Token::Uint(*a),
+                Token::Uint(*b),
+                path_arg(path),
+                Token::Address(*to),
+                Token::Uint(*deadline),
+            ],
+            DecodedSwap::ExactAvaxForTokens { amount_out_min: a, path, to, deadline, .. }
+            | DecodedSwap::AvaxForExactTokens { amount_out: a, path, to, deadline, .. } => vec![
+                Token::Uint(*a),
+                path_arg(path),
+                Token::Address(*to),
+                Token::Uint(*deadline),
+            ],
+            DecodedSwap::V3ExactInputSingle {
+                token_in,
+                token_out,
+                fee,
+                recipient,
+                deadline,
+                amount_in,
+                amount_out_min,
+                sqrt_price_limit_x96,
+            } => vec![Token::Tuple(vec![
+                Token::Address(*token_in),
+                Token::Address(*token_out),
+                Token::Uint((*fee).into()),
+                Token::Address(*recipient),
+                Token::Uint(*deadline),
+                Token::Uint(*amount_in),
+                Token::Uint(*amount_out_min),
+                Token::Uint(*sqrt_price_limit_x96),
+            ])],
+            DecodedSwap::V3ExactInput { path, recipient, deadline, amount_in, amount_out_min } => {
+                vec![Token::Tuple(vec![
+                    Token::Bytes(path.clone()),
+                    Token::Address(*recipient),
+                    Token::Uint(*deadline),
+                    Token::Uint(*amount_in),
+                    Token::Uint(*amount_out_min),
+                ])]
+            }
+            DecodedSwap::PlatypusSwap { from_token, to_token, from_amount, minimum_to_amount, to, deadline } => vec![
+                Token::Address(*from_token),
+                Token::Address(*to_token),
+                Token::Uint(*from_amount),
+                Token::Uint(*minimum_to_amount),
+                Token::Address(*to),
+                Token::Uint(*deadline),
+            ],
+        }
+    }
+
+    /// The same variant as `self` with its fields taken from decoded `args`, in `params()` order.
+    fn with_args(&self, args: Vec<Token>) -> Result<Self> {
+        let mut args = Args(args.into_iter());
+        Ok(match *self {
+            DecodedSwap::ExactTokensForTokens { supporting_fee, .. } => DecodedSwap::ExactTokensForTokens {
+                amount_in: args.uint()?,
+                amount_out_min: args.uint()?,
+                path: args.path()?,
+                to: args.address()?,
+                deadline: args.uint()?,
+                supporting_fee,
+            },
+            DecodedSwap::TokensForExactTokens { .. } => DecodedSwap::TokensForExactTokens {
+                amount_out: args.uint()?,
+                amount_in_max: args.uint()?,
+                path: args.path()?,
+                to: args.address()?,
+                deadline: args.uint()?,
+            },
+            DecodedSwap::ExactAvaxForTokens { native, supporting_fee, .. } => DecodedSwap::ExactAvaxForTokens {
+                native,
+                amount_out_min: args.uint()?,
+                path: args.path()?,
+                to: args.address()?,
+                deadline: args.uint()?,
+                supporting_fee,
+            },
+            DecodedSwap::AvaxForExactTokens { native, .. } => DecodedSwap::AvaxForExactTokens {
+                native,
+                amount_out: args.uint()?,
+                path: args.path()?,
+                to: args.address()?,
+                deadline: args.uint()?,
+            },
+            DecodedSwap::ExactTokensForAvax { native, supporting_fee, .. } => DecodedSwap::ExactTokensForAvax {
+                native,
+                amount_in: args.uint()?,
+                amount_out_min: args.uint()?,
+                path: args.path()?,
+                to: args.address()?,
+                deadline: args.uint()?,
+                supporting_fee,
+            },
+            DecodedSwap::TokensForExactAvax { native, .. } => DecodedSwap::TokensForExactAvax {
+                native,
+                amount_out: args.uint()?,
+                amount_in_max: args.uint()?,
+                path: args.path()?,
+                to: args.address()?,
+                deadline: args.uint()?,
+            },
+            DecodedSwap::V3ExactInputSingle { .. } => {
+                let mut params = args.tuple()?;
+                DecodedSwap::V3ExactInputSingle {
+                    token_in: params.address()?,
+                    token_out: params.address()?,
+                    fee: params.uint24()?,
+                    recipient: params.address()?,
+                    deadline: params.uint()?,
+                    amount_in: params.uint()?,
+                    amount_out_min: params.uint()?,
+                    sqrt_price_limit_x96: params.uint()?,
+                }
+            }
+            DecodedSwap::V3ExactInput { .. } => {
+                let mut params = args.tuple()?;
+                DecodedSwap::V3ExactInput {
+                    path: params.next()?.into_bytes().ok_or_eyre("path is not bytes")?,
+                    recipient: params.address()?,
+                    deadline: params.uint()?,
+                    amount_in: params.uint()?,
+                    amount_out_min: params.uint()?,
+                }
+            }
+            DecodedSwap::PlatypusSwap { .. } => DecodedSwap::PlatypusSwap {
+                from_token: args.address()?,
+                to_token: args.address()?,
+                from_amount: args.uint()?,
+                minimum_to_amount: args.uint()?,
+                to: args.address()?,
+                deadline: args.uint()?,
+            },
+        })
+    }
+}
+
+/// A swap on `router` sent by `sender`: one call of a trade an adapter builds up in a `TradeCtx`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouterCall {
+    pub router: Address,
+    pub sender: Address,
+    pub swap: DecodedSwap,
+    /// Referral address appended after the ABI-encoded arguments, where router referral programs read it.
+    /// The swap itself ignores the trailing bytes.
+    pub referral: Option<Address>,
+}
+
+impl RouterCall {
+    pub fn tx(&self) -> TransactionRequest {
+        let mut data = self.swap.encode();
+        if let Some(referral) = self.referral {
+            data.extend_from_slice(referral.as_bytes());
+        }
+        TransactionRequest::new().from(self.sender).to(self.router).data(data)
+    }
+}
+
+/// Decoded arguments consumed in order.
+struct Args(std::vec::IntoIter<Token>);
+
+impl Args {
+    fn next(&mut self) -> Result<Token> {
+        self.0.next().ok_or_else(|| eyre!("missing argument"))
+    }
+
+    fn uint(&mut self) -> Result<U256> {
+        self.next()?.into_uint().ok_or_eyre("expected uint")
+    }
+
+    /// ethabi doesn't range check `uint24` when decoding, so calldata can carry any 256-bit value.
+    fn uint24(&mut self) -> Result<u32> {
+        let value = self.uint()?;
+        u32::try_from(value)
+            .ok()
+            .filter(|value| *value <= 0xff_ffff)
+            .ok_or_else(|| eyre!("uint24 out of range: {value}"))
+    }
+
+    fn address(&mut self) -> Result<Address> {
+        self.next()?.into_address().ok_or_eyre("expected address")
+    }
+
+    fn path(&mut self) -> Result<Vec<Address>> {
+        self.next()?
+            .into_array()
+            .ok_or_eyre("expected address[]")?
+            .into_iter()
+            .map(|token| token.into_address().ok_or_eyre("expected address"))
+            .collect()
+    }
+
+    fn tuple(&mut self) -> Result<Args> {
+        let fields = self.next()?.into_tuple().ok_or_eyre("expected tuple")?;
+        Ok(Args(fields.into_iter()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_every_shape() {
+        for shape in SHAPES.values() {
+            assert_eq!(DecodedSwap::decode(&shape.encode()).as_ref(), Some(shape), "{}", shape.signature());
+        }
+    }
+
+    #[test]
+    fn test_decode_known_selectors() {
+        // selectors as seen on chain
+        let swap = DecodedSwap::ExactTokensForTokens {
+            amount_in: 1_000.into(),
+            amount_out_min: 990.into(),
+            path: vec![Address::repeat_byte(1), Address::repeat_byte(2)],
+            to: Address::repeat_byte(3),
+            deadline: U256::MAX,
+            supporting_fee: false,
+        };
+        assert_eq!(swap.selector(), [0x38, 0xed, 0x17, 0x39]);
+        let native_in = DecodedSwap::ExactAvaxForTokens {
+            native: NativeName::Eth,
+            amount_out_min: 0.into(),
+            path: vec![],
+            to: Address::zero(),
+            deadline: 0.into(),
+            supporting_fee: false,
+        };
+        assert_eq!(native_in.selector(), [0x7f, 0xf3, 0x6a, 0xb5]);
+
+        let decoded = DecodedSwap::decode(&swap.encode()).unwrap();
+        assert_eq!(decoded.token_in(), Some(Address::repeat_byte(1)));
+        assert_eq!(decoded.token_out(), Some(Address::repeat_byte(2)));
+        assert_eq!(decoded.max_amount_in(U256::zero()), 1_000.into());
+
+        assert_eq!(DecodedSwap::decode(&[0xa9, 0x05, 0x9c, 0xbb]), None, "transfer is not a swap");
+        assert_eq!(DecodedSwap::decode(&swap.encode()[..40]), None, "truncated calldata");
+    }
+
+    #[test]
+    fn test_v3_fee_out_of_range() {
+        let swap = DecodedSwap::V3ExactInputSingle {
+            token_in: Address::repeat_byte(0xa),
+            token_out: Address::repeat_byte(0xb),
+            fee: 3_000,
+            recipient: Address::repeat_byte(0xd),
+            deadline: U256::MAX,
+            amount_in: 5.into(),
+            amount_out_min: 1.into(),
+            sqrt_price_limit_x96: 0.into(),
+        };
+        let mut calldata = swap.encode();
+        assert_eq!(DecodedSwap::decode(&calldata).as_ref(), Some(&swap));
+
+        // the fee is the third word of the params, past the selector
+        let fee_word = 4 + 2 * 32;
+        calldata[fee_word + 28] = 0x01;
+        assert_eq!(DecodedSwap::decode(&calldata), None, "fee above uint24");
+        calldata[fee_word] = 0xff;
+        assert_eq!(DecodedSwap::decode(&calldata), None, "fee above u32");
+    }
+
+    #[test]
+    fn test_v3_packed_path() {
+        let (a, b, c) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb), Address::repeat_byte(0xc));
+        let mut path = a.as_bytes().to_vec();
+        path.extend([0x00, 0x0b, 0xb8]);
+        path.extend(b.as_bytes());
+        path.extend([0x00, 0x01, 0xf4]);
+        path.extend(c.as_bytes());
+
+        let swap = DecodedSwap::V3ExactInput {
+            path,
+            recipient: Address::repeat_byte(0xd),
+            deadline: U256::MAX,
+            amount_in: 5.into(),
+            amount_out_min: 1.into(),
+        };
+        let decoded = DecodedSwap::decode(&swap.encode()).unwrap();
+        assert_eq!(decoded.path(), [a, b, c]);
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use ethers::{
+    providers::{Http, Provider},
+    types::{Address, U256},
+};
+use eyre::{eyre, Result};
+
+use super::reserves_check::{get_pair_tokens, get_reserves, get_reserves_batch};
+use crate::utils::token_config::TokenConfig;
+
+const BPS: f64 = 10_000.0;
+
+/// One venue's price for a pair, in whole quote tokens per whole base token, from its reserves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VenueQuote {
+    pub dex: String,
+    pub pool: Address,
+    /// Reserve ratio, before fees.
+    pub mid: f64,
+    pub fee_bps: u32,
+}
+
+impl VenueQuote {
+    /// `None` for an empty pool.
+    pub fn from_reserves(
+        dex: String,
+        pool: Address,
+        (reserve_base, reserve_quote): (U256, U256),
+        (base_decimals, quote_decimals): (u8, u8),
+        fee_bps: u32,
+    ) -> Option<Self> {
+        if reserve_base.is_zero() || reserve_quote.is_zero() {
+            return None;
+        }
+        let whole = |amount: U256, decimals: u8| {
+            amount.to_string().parse::<f64>().unwrap_or_default() / 10f64.powi(decimals as i32)
+        };
+        let mid = whole(reserve_quote, quote_decimals) / whole(reserve_base, base_decimals);
+
+        Some(Self { dex, pool, mid, fee_bps })
+    }
+
+    /// Marginal price we can sell the base token at, net of the pool fee.
+    pub fn bid(&self) -> f64 {
+        self.mid * (1.0 - self.fee_bps as f64 / BPS)
+    }
+
+    /// Marginal price we can buy the base token at, net of the pool fee.
+    pub fn ask(&self) -> f64 {
+        self.mid / (1.0 - self.fee_bps as f64 / BPS)
+    }
+}
+
+/// Best cross-venue spread of a pair: buy on `ask_dex`, sell on `bid_dex`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairSpread {
+    pub bid_dex: String,
+    pub ask_dex: String,
+    /// `(bid - ask) / ask`. Positive means the marginal arb is profitable before gas.
+    pub spread_bps: f64,
+}
+
+/// Widest spread between the bid of one venue and the ask of another. `None` with fewer than two venues.
+pub fn pair_spread(quotes: &[VenueQuote]) -> Option<PairSpread> {
+    let mut best: Option<PairSpread> = None;
+    for (i, bid) in quotes.iter().enumerate() {
+        for (j, ask) in quotes.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            let spread_bps = (bid.bid() - ask.ask()) / ask.ask() * BPS;
+            if best.as_ref().map_or(true, |best| spread_bps > best.spread_bps) {
+                best = Some(PairSpread {
+                    bid_dex: bid.dex.clone(),
+                    ask_dex: ask.dex.clone(),
+                    spread_bps,
+                });
+            }
+        }
+    }
+    best
+}
+
+/// Median mid price across venues, the reference a single venue's deviation is measured against.
+pub fn median_mid(quotes: &[VenueQuote]) -> Option<f64> {
+    let mut mids: Vec<f64> = quotes.iter().map(|q| q.mid).collect();
+    if mids.is_empty() {
+        return None;
+    }
+    mids.sort_by(|a, b| a.total_cmp(b));
+    let mid = mids.len() / 2;
+    Some(if mids.len() % 2 == 0 { (mids[mid - 1] + mids[mid]) / 2.0 } else { mids[mid] })
+}
+
+/// Spot price of `pool` at `block` in whole `token1` per whole `token0`, the pair's own token order, before
+/// fees: e.g. 25.0 for a WAVAX/USDC pair whose token0 is WAVAX. Reads reserves through `cache`, so a later
+/// block re-reads them. Errors for pools without `getReserves` (e.g. Platypus) and tokens missing from the
+/// token config.
+pub async fn spot_price(provider: &Provider<Http>, pool: Address, block: u64, cache: &mut ReserveCache) -> Result<f64> {
+    let (token0, token1) = cache.tokens(provider, pool).await?;
+    let tokens = TokenConfig::new();
+    let decimals = |token: Address| {
+        tokens
+            .get_token_by_address(&format!("{token:?}"))
+            .map(|t| t.decimals)
+            .ok_or_else(|| eyre!("unknown token {token:?}"))
+    };
+    let decimals = (decimals(token0)?, decimals(token1)?);
+
+    let reserves = cache.get(provider, pool, block).await?;
+    VenueQuote::from_reserves(String::new(), pool, reserves, decimals, 0)
+        .map(|quote| quote.mid)
+        .ok_or_else(|| eyre!("empty pool {pool:?}"))
+}
+
+/// `(reserve0, reserve1)` of UniswapV2-style pools, fetched at most once per pool per block, and their
+/// `(token0, token1)`, which never change.
+#[derive(Default)]
+pub struct ReserveCache {
+    block: u64,
+    reserves: HashMap<Address, (U256, U256)>,
+    tokens: HashMap<Address, (Address, Address)>,
+}
+
+impl ReserveCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn get(&mut self, provider: &Provider<Http>, pool: Address, block: u64) -> Result<(U256, U256)> {
+        if block != self.block {
+            self.block = block;
+            self.reserves.clear();
+        }
+        if let Some(reserves) = self.reserves.get(&pool) {
+            return Ok(*reserves);
+        }
+
+        let reserves = get_reserves(provider, pool, Some(block)).await?;
+        self.reserves.insert(pool, reserves);
+        Ok(reserves)
+    }
+
+    /// Fetch the reserves of every pool in `pools` not cached yet for `block` in one batch. Pools that fail are
+    /// left out, so a later `get` reports their error.
+    pub async fn prefetch(&mut self, provider: &Provider<Http>, pools: &[Address], block: u64) {
+        if block != self.block {
+            self.block = block;
+            self.reserves.clear();
+        }
+        let mut missing = pools.iter().copied().filter(|pool| !self.reserves.contains_key(pool)).collect::<Vec<_>>();
+        missing.sort();
+        missing.dedup();
+        if missing.is_empty() {
+            return;
+        }
+
+        let reserves = get_reserves_batch(provider, &missing, block).await;
+        for (pool, reserves) in missing.into_iter().zip(reserves) {
+            if let Some(reserves) = reserves {
+                self.reserves.insert(pool, reserves);
+            }
+        }
+    }
+
+    pub async fn tokens(&mut self, provider: &Provider<Http>, pool: Address) -> Result<(Address, Address)> {
+        if let Some(tokens) = self.tokens.get(&pool) {
+            return Ok(*tokens);
+        }
+
+        let tokens = get_pair_tokens(provider, pool).await?;
+        self.tokens.insert(pool, tokens);
+        Ok(tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(dex: &str, mid: f64, fee_bps: u32) -> VenueQuote {
+        VenueQuote {
+            dex: dex.to_string(),
+            pool: Address::zero(),
+            mid,
+            fee_bps,
+        }
+    }
+
+    #[test]
+    fn test_venue_quote_normalizes_decimals() {
+        // 1_000 WAVAX (18 decimals) against 25_000 USDC (6 decimals)
+        let quote = VenueQuote::from_reserves(
+            "TraderJoe".to_string(),
+            Address::zero(),
+            (U256::exp10(18) * 1_000, U256::exp10(6) * 25_000),
+            (18, 6),
+            30,
+        )
+        .unwrap();
+        assert!((quote.mid - 25.0).abs() < 1e-9);
+        assert!(quote.bid() < quote.mid && quote.mid < quote.ask());
+
+        let empty = VenueQuote::from_reserves(String::new(), Address::zero(), (U256::zero(), U256::one()), (18, 6), 30);
+        assert!(empty.is_none());
+    }
+
+    #[test]
+    fn test_pair_spread_crosses_venues() {
+        assert!(pair_spread(&[quote("TraderJoe", 25.0, 30)]).is_none());
+
+        // Pangolin is 1% rich: buy on TraderJoe, sell on Pangolin, ~40 bps after both fees
+        let quotes = [quote("TraderJoe", 25.0, 30), quote("Pangolin", 25.25, 30), quote("SushiSwap", 25.0, 30)];
+        let spread = pair_spread(&quotes).unwrap();
+        assert_eq!(spread.bid_dex, "Pangolin");
+        assert_ne!(spread.ask_dex, "Pangolin");
+        assert!((spread.spread_bps - 40.0).abs() < 1.0, "{}", spread.spread_bps);
+
+        // aligned venues: the spread is just both fees, negative
+        let spread = pair_spread(&[quote("TraderJoe", 25.0, 30), quote("Pangolin", 25.0, 30)]).unwrap();
+        assert!(spread.spread_bps < -59.0 && spread.spread_bps > -61.0, "{}", spread.spread_bps);
+
+        assert_eq!(median_mid(&quotes), Some(25.0));
+        assert_eq!(median_mid(&quotes[..2]), Some(25.125));
+    }
+}
@@ -0,0 +1,59 @@
+//! Shared fixtures for the adapters' calldata tests.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ethers::{
+    abi::{self, ParamType, Token},
+    types::{Address, TransactionRequest, U256},
+    utils::id,
+};
+
+use crate::config::{DEFAULT_SWAP_SLIPPAGE_BPS, SWAP_DEADLINE};
+
+pub const WAVAX: &str = "0xB31f66AA3C1e785363F0875A1B74E27b85FD66c7";
+pub const USDC: &str = "0xB97EF9Ef8734C71904D8002F8b6Bc66Dd9c48a6E";
+pub const SENDER: Address = Address::repeat_byte(0x51);
+pub const RECIPIENT: Address = Address::repeat_byte(0x52);
+/// 20 WAVAX, more wei than a `u64` holds, so every adapter is checked to encode the full amount.
+pub const AMOUNT_IN: U256 = U256([1_553_255_926_290_448_384, 1, 0, 0]);
+
+/// Assert `tx` calls `signature` on `to` from `SENDER`, and decode its arguments as `params`.
+pub fn decode_call(tx: &TransactionRequest, to: Address, signature: &str, params: &[ParamType]) -> Vec<Token> {
+    assert_eq!(tx.from, Some(SENDER));
+    assert_eq!(tx.to, Some(to.into()));
+
+    let data = tx.data.as_ref().expect("no calldata");
+    assert_eq!(data[..4], id(signature), "selector of {signature}");
+    abi::decode(params, &data[4..]).expect("calldata does not match the signature")
+}
+
+/// Check a `swapExactTokensForTokens` of `AMOUNT_IN` along `path` through `router`, paying out to `to`: it
+/// must accept no less than `quote` less the default slippage, and expire within `SWAP_DEADLINE`.
+pub fn assert_router_swap(tx: &TransactionRequest, router: &str, path: &[&str], quote: U256, to: Address) {
+    let args = decode_call(
+        tx,
+        router.parse().unwrap(),
+        "swapExactTokensForTokens(uint256,uint256,address[],address,uint256)",
+        &[
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Array(Box::new(ParamType::Address)),
+            ParamType::Address,
+            ParamType::Uint(256),
+        ],
+    );
+
+    assert_eq!(args[0], Token::Uint(AMOUNT_IN), "amountIn");
+    let min_out = quote * U256::from(10_000 - DEFAULT_SWAP_SLIPPAGE_BPS) / U256::from(10_000);
+    assert!(!min_out.is_zero());
+    assert_eq!(args[1], Token::Uint(min_out), "amountOutMin");
+    assert_eq!(
+        args[2],
+        Token::Array(path.iter().map(|token| Token::Address(token.parse().unwrap())).collect()),
+        "path"
+    );
+    assert_eq!(args[3], Token::Address(to), "to");
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let deadline = args[4].clone().into_uint().expect("deadline").as_u64();
+    assert!(now < deadline && deadline <= now + SWAP_DEADLINE.as_secs(), "deadline");
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fmt,
     ops::{Deref, DerefMut},
     str::FromStr,
@@ -7,6 +7,7 @@ use std::{
 };
 
 use ::utils::coin;
+use ethers::types::{Address, Bytes, U256};
 use eyre::{ensure, eyre, Result};
 use object_pool::ObjectPool;
 use simulator::{SimulateCtx, Simulator};
@@ -21,12 +22,21 @@ use sui_types::{
 };
 use tracing::instrument;
 
-use super::{navi::Navi, shio::Shio, Dex};
-use crate::{config::*, types::Source};
+use super::{navi::Navi, shio::Shio, Dex, RouterCall};
+use crate::{
+    config::*,
+    types::Source,
+    utils::{
+        math::{u256_to_f64, u256_to_i128_checked},
+        token_config::TokenConfig,
+    },
+};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum TradeType {
+    /// Funded from the sender's own balance.
     Swap,
+    #[default]
     Flashloan,
 }
 
@@ -35,6 +45,8 @@ pub struct FlashResult {
     pub coin_out: Argument,
     pub receipt: Argument,
     pub pool: Option<Argument>,
+    /// Owed to the lender on top of the borrowed amount, see `lender_premium`.
+    pub premium: U256,
 }
 
 #[derive(Clone)]
@@ -48,13 +60,65 @@ pub struct Trader {
 pub struct TradeCtx {
     pub ptb: ProgrammableTransactionBuilder,
     pub command_count: u16,
+    /// Router calls of the trade, in execution order, as the EVM adapters' `extend_trade_tx` adds them.
+    pub calls: Vec<RouterCall>,
+    /// Quoted output of the last hop added: the input of a chained hop that can't join the previous call.
+    pub quoted_amount: Option<U256>,
+    /// Referral address from `--referral-address`, put into the router calls of the adapters that
+    /// `takes_referral`.
+    pub referral: Option<Address>,
 }
 
 #[derive(Default, Debug, Clone)]
 pub struct TradeResult {
-    pub amount_out: u64,
+    pub amount_out: U256,
     pub gas_cost: i64,
     pub cache_misses: u64,
+    /// Owed to the flashloan lender on top of `amount_in`; zero for swaps.
+    pub flashloan_premium: U256,
+    /// Estimated referral rebate, in the token the path starts in, for the hops whose router call carries the
+    /// referral address.
+    pub referral_rebate: U256,
+}
+
+impl TradeResult {
+    /// What is left of `amount_out` plus the referral rebate once the flashloan premium is paid, what paths are
+    /// ranked by.
+    pub fn net_amount_out(&self) -> U256 {
+        self.amount_out.saturating_add(self.referral_rebate).saturating_sub(self.flashloan_premium)
+    }
+}
+
+/// Premium `provider` charges on borrowing `amount` at `premium_bps`, unless `--flashloan-premiums` overrides
+/// it: what a lender's `extend_flashloan_tx` puts in its `FlashResult`.
+pub fn lender_premium(provider: &str, premium_bps: u32, amount: U256) -> U256 {
+    flashloan_premium(amount, flashloan_premium_override(provider).unwrap_or(premium_bps))
+}
+
+/// Premium owed on borrowing `amount`, rounded up as lenders do.
+pub fn flashloan_premium(amount: U256, premium_bps: u32) -> U256 {
+    let (premium, rem) = (amount * U256::from(premium_bps)).div_mod(U256::from(10_000));
+    if rem.is_zero() {
+        premium
+    } else {
+        premium + 1
+    }
+}
+
+/// Handle `extend_trade_tx` passes between hops for `token`: the address of the token a hop delivers.
+pub fn token_handle(token: &str) -> Result<Bytes> {
+    Ok(Bytes::from(Address::from_str(token)?.as_bytes().to_vec()))
+}
+
+/// Referral rebates trading `amount_in` along `path` would earn at `rebates` (share of the fee in bps, keyed by
+/// lowercase protocol). Only hops that `takes_referral` count, the others never tell their router the referral
+/// address. Every hop of an arb cycle trades about the value of `amount_in`, so each hop's rebate is taken on
+/// `amount_in` at that pool's fee.
+pub fn referral_rebate(path: &Path, amount_in: U256, rebates: &HashMap<String, u32>) -> U256 {
+    path.path.iter().filter(|dex| dex.takes_referral()).fold(U256::zero(), |rebate, dex| {
+        let share_bps = rebates.get(&dex.protocol().to_string().to_lowercase()).copied().unwrap_or_default();
+        rebate.saturating_add(amount_in * U256::from(dex.fee_bps()) * U256::from(share_bps) / U256::exp10(8))
+    })
 }
 
 impl Trader {
@@ -82,7 +146,7 @@ impl Trader {
         &self,
         path: &Path,
         sender: SuiAddress,
-        amount_in: u64,
+        amount_in: U256,
         trade_type: TradeType,
         gas_coins: Vec<ObjectRef>,
         mut sim_ctx: SimulateCtx,
@@ -90,10 +154,12 @@ impl Trader {
         ensure!(!path.is_empty(), "empty path");
         let gas_price = sim_ctx.epoch.gas_price;
 
-        let (tx_data, mocked_coin_in) = match trade_type {
+        let (tx_data, mocked_coin_in, flashloan_premium) = match trade_type {
             TradeType::Swap => {
-                self.get_swap_trade_tx(path, sender, amount_in, gas_coins, gas_price)
-                    .await?
+                let (tx_data, mocked_coin_in) = self
+                    .get_swap_trade_tx(path, sender, amount_in, gas_coins, gas_price)
+                    .await?;
+                (tx_data, mocked_coin_in, U256::zero())
             }
             TradeType::Flashloan => {
                 self.get_flashloan_trade_tx(path, sender, amount_in, gas_coins, gas_price, Source::Public)
@@ -130,7 +196,7 @@ impl Trader {
             if bc.owner == Owner::AddressOwner(sender) && bc.coin_type == coin_out {
                 amount_out = bc.amount;
                 if coin_in == coin_out && out_is_native {
-                    amount_out = amount_out + amount_in as i128 + gas_cost as i128;
+                    amount_out = amount_out + u256_to_i128_checked(amount_in)? + gas_cost as i128;
                 }
 
                 ensure!(amount_out >= 0, "negative amount_out {}", amount_out);
@@ -140,9 +206,11 @@ impl Trader {
         ensure!(amount_out != i128::MIN, "no balance change for owner: {:?}", sender);
 
         Ok(TradeResult {
-            amount_out: amount_out as u64,
+            amount_out: U256::from(amount_out as u128),
             gas_cost,
             cache_misses: resp.cache_misses,
+            flashloan_premium,
+            referral_rebate: referral_rebate(path, amount_in, &referral_rebates()),
         })
     }
 
@@ -150,12 +218,12 @@ impl Trader {
         &self,
         path: &Path,
         sender: SuiAddress,
-        amount_in: u64,
+        amount_in: U256,
         gas_coins: Vec<ObjectRef>,
         gas_price: u64,
     ) -> Result<(TransactionData, Option<Object>)> {
         ensure!(!path.is_empty(), "empty path");
-        let mut ctx = TradeCtx::default();
+        let mut ctx = TradeCtx::with_referral(referral_address());
 
         // 1. prepare coin_in
         let mocked_sui = coin::mocked_sui(sender, amount_in);
@@ -177,19 +245,20 @@ impl Trader {
         Ok((tx_data, Some(mocked_sui)))
     }
 
+    /// Also returns the premium the lender charges, from its `FlashResult`.
     pub async fn get_flashloan_trade_tx(
         &self,
         path: &Path,
         sender: SuiAddress,
-        amount_in: u64,
+        amount_in: U256,
         gas_coins: Vec<ObjectRef>,
         gas_price: u64,
         source: Source,
-    ) -> Result<(TransactionData, Option<Object>)> {
+    ) -> Result<(TransactionData, Option<Object>, U256)> {
         ensure!(!path.is_empty(), "empty path");
         let first_dex = &path.path[0];
 
-        let mut ctx = TradeCtx::default();
+        let mut ctx = TradeCtx::with_referral(referral_address());
 
         // 1. flashloan
         let flash_res = if first_dex.support_flashloan() {
@@ -198,6 +267,8 @@ impl Trader {
             self.navi.extend_flashloan_tx(&mut ctx, amount_in)?
         };
 
+        let premium = flash_res.premium;
+
         // 2. swap
         let mut coin_in_arg = flash_res.coin_out;
         let dex_iter: Box<dyn Iterator<Item = &Box<dyn Dex>> + Send> = if first_dex.support_flashloan() {
@@ -243,7 +314,7 @@ impl Trader {
             }
         };
 
-        Ok((tx_data, None))
+        Ok((tx_data, None, premium))
     }
 }
 
@@ -252,6 +323,13 @@ impl TradeCtx {
         Self::default()
     }
 
+    pub fn with_referral(referral: Option<Address>) -> Self {
+        Self {
+            referral,
+            ..Self::default()
+        }
+    }
+
     pub fn command(&mut self, cmd: Command) {
         self.ptb.command(cmd);
         self.command_count += 1;
@@ -361,13 +439,13 @@ impl DerefMut for TradeCtx {
 
 impl PartialEq for TradeResult {
     fn eq(&self, other: &Self) -> bool {
-        self.amount_out == other.amount_out
+        self.net_amount_out() == other.net_amount_out()
     }
 }
 
 impl PartialOrd for TradeResult {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.amount_out.partial_cmp(&other.amount_out)
+        self.net_amount_out().partial_cmp(&other.net_amount_out())
     }
 }
 
@@ -399,6 +477,27 @@ impl Path {
         self.path.last().unwrap().coin_out_type()
     }
 
+    /// Units of `coin_out_type` received per unit of `coin_in_type`, both normalized by their decimals, for
+    /// a trade of `amount_in` yielding `amount_out` (raw units). Errors if either token's decimals are unknown.
+    pub fn price_from_amounts(&self, amount_in: U256, amount_out: U256) -> Result<f64> {
+        ensure!(!self.is_empty(), "empty path");
+        ensure!(!amount_in.is_zero(), "zero amount_in");
+
+        let tokens = TokenConfig::new();
+        let decimals = |token: String| {
+            tokens
+                .get_token_by_address(&token)
+                .map(|info| info.decimals)
+                .ok_or_else(|| eyre!("unknown decimals for {token}"))
+        };
+        let decimals_in = decimals(self.coin_in_type())?;
+        let decimals_out = decimals(self.coin_out_type())?;
+
+        let amount_in = u256_to_f64(amount_in) / 10f64.powi(decimals_in as i32);
+        let amount_out = u256_to_f64(amount_out) / 10f64.powi(decimals_out as i32);
+        Ok(amount_out / amount_in)
+    }
+
     pub fn contains_pool(&self, pool_id: Option<ObjectID>) -> bool {
         if let Some(pool_id) = pool_id {
             self.path.iter().any(|dex| dex.object_id() == pool_id)
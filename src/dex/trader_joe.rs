@@ -1,11 +1,19 @@
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc};
 
 use dex_indexer::types::Protocol;
 use ethers::types::{Address, U256};
 use eyre::Result;
 use simulator::Simulator;
 
-use super::{Dex, FlashResult, TradeCtx};
+use crate::config::{pool_fee_override, referral_address};
+
+use super::{
+    uniswap_v2::{extend_router_trade, quote_from_reserves, router_swap_tx},
+    Dex, FlashResult, TradeCtx,
+};
+
+/// TraderJoe V1 router, which `swap_tx` and `extend_trade_tx` go through.
+pub const TRADER_JOE_ROUTER: &str = "0x60aE616a2155Ee3d9A68541Ba4544862310933d4";
 
 #[derive(Debug, Clone)]
 pub struct TraderJoeDex {
@@ -14,6 +22,9 @@ pub struct TraderJoeDex {
     pub token_out: String, 
     pub liquidity: u128,
     pub fee_rate: u64,
+    /// `(reserve_in, reserve_out)` in the trade direction, when the pair was read with them; what
+    /// `get_amount_out` quotes from.
+    pub reserves: Option<(U256, U256)>,
 }
 
 impl TraderJoeDex {
@@ -29,25 +40,29 @@ impl TraderJoeDex {
             token_in,
             token_out,
             liquidity,
-            fee_rate,
+            fee_rate: pool_fee_override(pool).map(u64::from).unwrap_or(fee_rate),
+            reserves: None,
         }
     }
+
+    pub fn with_reserves(mut self, reserve_in: U256, reserve_out: U256) -> Self {
+        self.reserves = Some((reserve_in, reserve_out));
+        self
+    }
 }
 
 #[async_trait::async_trait]
 impl Dex for TraderJoeDex {
     fn support_flashloan(&self) -> bool {
-        true
+        false
     }
 
-    async fn extend_flashloan_tx(&self, _ctx: &mut TradeCtx, _amount: u64) -> Result<FlashResult> {
-        // TraderJoe flashloan implementation would go here
-        todo!("TraderJoe flashloan not implemented yet")
+    async fn extend_flashloan_tx(&self, _ctx: &mut TradeCtx, _amount: U256) -> Result<FlashResult> {
+        eyre::bail!("flashloan not supported")
     }
 
     async fn extend_repay_tx(&self, _ctx: &mut TradeCtx, _coin: ethers::types::Bytes, _flash_res: FlashResult) -> Result<ethers::types::Bytes> {
-        // TraderJoe repay implementation would go here
-        todo!("TraderJoe repay not implemented yet")  
+        eyre::bail!("flashloan not supported")
     }
 
     async fn extend_trade_tx(
@@ -55,10 +70,10 @@ impl Dex for TraderJoeDex {
         ctx: &mut TradeCtx,
         sender: Address,
         coin_in: ethers::types::Bytes,
-        amount_in: Option<u64>,
+        amount_in: Option<U256>,
     ) -> Result<ethers::types::Bytes> {
-        // TraderJoe swap implementation would go here
-        todo!("TraderJoe swap not implemented yet")
+        let router = Address::from_str(TRADER_JOE_ROUTER)?;
+        extend_router_trade(ctx, self, router, sender, &coin_in, amount_in)
     }
 
     fn coin_in_type(&self) -> String {
@@ -81,8 +96,21 @@ impl Dex for TraderJoeDex {
         self.pool
     }
 
+    fn fee_bps(&self) -> u32 {
+        self.fee_rate as u32
+    }
+
+    fn takes_referral(&self) -> bool {
+        true
+    }
+
+    fn get_amount_out(&self, amount_in: U256) -> Result<U256> {
+        quote_from_reserves(self, self.reserves, amount_in)
+    }
+
     fn flip(&mut self) {
         std::mem::swap(&mut self.token_in, &mut self.token_out);
+        self.reserves = self.reserves.map(|(reserve_in, reserve_out)| (reserve_out, reserve_in));
     }
 
     fn is_a2b(&self) -> bool {
@@ -90,15 +118,15 @@ impl Dex for TraderJoeDex {
         self.token_in < self.token_out
     }
 
-    async fn swap_tx(&self, sender: Address, recipient: Address, amount_in: u64) -> Result<ethers::types::TransactionRequest> {
-        // TraderJoe swap transaction building would go here
-        todo!("TraderJoe swap_tx not implemented yet")
+    async fn swap_tx(&self, sender: Address, recipient: Address, amount_in: U256) -> Result<ethers::types::TransactionRequest> {
+        let router = Address::from_str(TRADER_JOE_ROUTER)?;
+        router_swap_tx(self, router, sender, recipient, amount_in, referral_address())
     }
 }
 
 pub async fn trader_joe_related_contract_addresses() -> Vec<String> {
     vec![
-        "0x60aE616a2155Ee3d9A68541Ba4544862310933d4".to_string(), // TraderJoe Router
+        TRADER_JOE_ROUTER.to_string(), // TraderJoe Router
         "0x9Ad6C38BE94206cA50bb0d90783181662f0Cfa10".to_string(), // TraderJoe Factory  
     ]
 }
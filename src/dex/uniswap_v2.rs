@@ -0,0 +1,545 @@
+use std::{collections::HashMap, str::FromStr};
+
+use ethers::types::{Address, Bytes, TransactionRequest, U256};
+use eyre::{ensure, eyre, Result};
+
+use crate::{
+    config::{min_amount_out, pool_fee_bps, swap_deadline},
+    utils::math::u256_to_f64,
+};
+
+use super::{DecodedSwap, Dex, RouterCall, TradeCtx};
+
+const BPS: u64 = 10_000;
+
+/// Default UniswapV2-style swap fee (0.3%).
+pub const DEFAULT_FEE_BPS: u32 = 30;
+
+/// Reserves of a pool oriented in the trade direction, plus its swap fee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PoolReserves {
+    pub reserve_in: U256,
+    pub reserve_out: U256,
+    pub fee_bps: u32,
+}
+
+impl PoolReserves {
+    pub fn new(reserve_in: U256, reserve_out: U256, fee_bps: u32) -> Self {
+        Self {
+            reserve_in,
+            reserve_out,
+            fee_bps,
+        }
+    }
+
+    /// Reserves of `pool`, with its fee taken from `pool_fee_overrides` when configured.
+    pub fn for_pool(pool: Address, reserve_in: U256, reserve_out: U256) -> Self {
+        Self::new(reserve_in, reserve_out, pool_fee_bps(pool, DEFAULT_FEE_BPS as u16) as u32)
+    }
+
+    /// Reserves of the pool behind `dex`, charged at that pool's own fee.
+    pub fn for_dex(dex: &dyn Dex, reserve_in: U256, reserve_out: U256) -> Self {
+        Self::new(reserve_in, reserve_out, dex.fee_bps())
+    }
+}
+
+/// Constant-product AMM math for TraderJoe / Pangolin / SushiSwap pairs.
+pub struct UniswapV2Calculator;
+
+impl UniswapV2Calculator {
+    /// Output amount for swapping `amount_in` into the pool.
+    pub fn get_amount_out(amount_in: U256, pool: &PoolReserves) -> Result<U256> {
+        ensure!(!amount_in.is_zero(), "zero amount_in");
+        ensure!(
+            !pool.reserve_in.is_zero() && !pool.reserve_out.is_zero(),
+            "empty reserves"
+        );
+
+        let amount_in_with_fee = amount_in * (BPS - pool.fee_bps as u64);
+        let numerator = amount_in_with_fee * pool.reserve_out;
+        let denominator = pool.reserve_in * BPS + amount_in_with_fee;
+
+        Ok(numerator / denominator)
+    }
+
+    /// Input amount required to receive exactly `amount_out` from the pool.
+    pub fn get_amount_in(amount_out: U256, pool: &PoolReserves) -> Result<U256> {
+        ensure!(!amount_out.is_zero(), "zero amount_out");
+        ensure!(amount_out < pool.reserve_out, "insufficient liquidity for amount_out");
+
+        let numerator = pool.reserve_in * amount_out * BPS;
+        let denominator = (pool.reserve_out - amount_out) * (BPS - pool.fee_bps as u64);
+
+        Ok(numerator / denominator + 1)
+    }
+
+    /// Price impact of swapping `amount_in` into the pool, in bps: how far the spot price moves against the
+    /// trade. Computed from the ratio of the post-swap to the pre-swap spot price, which are in the same units,
+    /// so the result doesn't depend on the two tokens' decimals.
+    pub fn calculate_price_impact(amount_in: U256, pool: &PoolReserves) -> Result<u64> {
+        let amount_out = Self::get_amount_out(amount_in, pool)?;
+
+        // post / pre = ((reserve_out - out) / (reserve_in + in)) / (reserve_out / reserve_in)
+        let post_over_pre_bps = (pool.reserve_out - amount_out) * pool.reserve_in * BPS
+            / (pool.reserve_out * (pool.reserve_in + amount_in));
+
+        Ok(BPS.saturating_sub(post_over_pre_bps.as_u64()))
+    }
+
+    /// Price impact of swapping `amount_in` through every pool of `path` in turn, in bps: each hop moves the
+    /// price by its own impact on the amount the previous hop delivered, and the moves compound.
+    pub fn calculate_path_price_impact(amount_in: U256, path: &[PoolReserves]) -> Result<u64> {
+        ensure!(!path.is_empty(), "empty path");
+
+        let mut amount = amount_in;
+        let mut remaining_bps = BPS;
+        for pool in path {
+            let impact = Self::calculate_price_impact(amount, pool)?;
+            remaining_bps = remaining_bps * (BPS - impact.min(BPS)) / BPS;
+            amount = Self::get_amount_out(amount, pool)?;
+        }
+
+        Ok(BPS - remaining_bps)
+    }
+
+    /// Marginal return of one lap round cyclic `path`, net of every hop's fee, in bps: positive when an
+    /// infinitesimal trade is profitable before gas. Decimals cancel out over a cycle.
+    pub fn path_spread_bps(path: &[PoolReserves]) -> Result<f64> {
+        ensure!(!path.is_empty(), "empty path");
+
+        let mut rate = 1.0;
+        for pool in path {
+            ensure!(
+                !pool.reserve_in.is_zero() && !pool.reserve_out.is_zero(),
+                "empty reserves"
+            );
+            rate *= u256_to_f64(pool.reserve_out) / u256_to_f64(pool.reserve_in) * (BPS - pool.fee_bps as u64) as f64
+                / BPS as f64;
+        }
+
+        Ok((rate - 1.0) * BPS as f64)
+    }
+
+    /// Inputs required at each hop to receive exactly `amount_out` at the end of `path`.
+    /// `amounts[0]` is the amount to send into the first pool.
+    pub fn get_amounts_in(amount_out: U256, path: &[PoolReserves], cache: &mut AmountInCache) -> Result<Vec<U256>> {
+        ensure!(!path.is_empty(), "empty path");
+
+        let mut amounts = vec![U256::zero(); path.len() + 1];
+        amounts[path.len()] = amount_out;
+        for (i, pool) in path.iter().enumerate().rev() {
+            amounts[i] = cache.get_amount_in(amounts[i + 1], pool)?;
+        }
+
+        Ok(amounts)
+    }
+
+    /// Closed-form optimal input for a cyclic path, by folding the pools into one virtual pool.
+    /// Returns `None` when the path has no edge.
+    pub fn get_optimal_input_amount(path: &[PoolReserves]) -> Option<U256> {
+        let first = path.first()?;
+        let (mut ea, mut eb) = (first.reserve_in, first.reserve_out);
+
+        for pool in &path[1..] {
+            let r = U256::from(BPS - pool.fee_bps as u64);
+            let denominator = pool.reserve_in * BPS + r * eb;
+            if denominator.is_zero() {
+                return None;
+            }
+            let next_ea = ea * pool.reserve_in * BPS / denominator;
+            let next_eb = r * eb * pool.reserve_out / denominator;
+            ea = next_ea;
+            eb = next_eb;
+        }
+
+        let r = U256::from(BPS - first.fee_bps as u64);
+        let root = (ea * eb * r / BPS).integer_sqrt();
+        if root <= ea {
+            return None;
+        }
+
+        Some((root - ea) * BPS / r)
+    }
+
+    /// `(amount_in, profit)` of the optimally sized trade round cyclic `path`, from reserves alone.
+    /// `None` when the path has no edge.
+    pub fn get_optimal_profit(path: &[PoolReserves]) -> Option<(U256, U256)> {
+        let amount_in = Self::get_optimal_input_amount(path)?;
+        let mut amount = amount_in;
+        for pool in path {
+            amount = Self::get_amount_out(amount, pool).ok()?;
+        }
+        Some((amount_in, amount.checked_sub(amount_in)?))
+    }
+}
+
+/// `swapExactTokensForTokens` of `amount_in` along `path`, accepting no less than `quote` less
+/// `--swap-slippage-bps` and valid for `SWAP_DEADLINE`.
+fn exact_in_swap(amount_in: U256, quote: U256, path: Vec<Address>, to: Address) -> Result<DecodedSwap> {
+    Ok(DecodedSwap::ExactTokensForTokens {
+        amount_in,
+        amount_out_min: min_amount_out(quote),
+        path,
+        to,
+        deadline: swap_deadline()?,
+        supporting_fee: false,
+    })
+}
+
+/// Single-hop `swapExactTokensForTokens` of `dex`'s pair through UniswapV2-style `router`, tagged with
+/// `referral` if any.
+pub fn router_swap_tx(
+    dex: &dyn Dex,
+    router: Address,
+    sender: Address,
+    recipient: Address,
+    amount_in: U256,
+    referral: Option<Address>,
+) -> Result<TransactionRequest> {
+    let path = vec![Address::from_str(&dex.coin_in_type())?, Address::from_str(&dex.coin_out_type())?];
+    Ok(RouterCall {
+        router,
+        sender,
+        swap: exact_in_swap(amount_in, dex.get_amount_out(amount_in)?, path, recipient)?,
+        referral,
+    }
+    .tx())
+}
+
+/// Quote of swapping `amount_in` through V2 `dex` at its `(reserve_in, reserve_out)`: the V2 adapters'
+/// `get_amount_out`.
+pub fn quote_from_reserves(dex: &dyn Dex, reserves: Option<(U256, U256)>, amount_in: U256) -> Result<U256> {
+    let (reserve_in, reserve_out) = reserves.ok_or_else(|| eyre!("no reserves read for pool {:?}", dex.pool_address()))?;
+    UniswapV2Calculator::get_amount_out(amount_in, &PoolReserves::for_dex(dex, reserve_in, reserve_out))
+}
+
+/// Add `dex`'s hop through UniswapV2-style `router` to `ctx`, paid out to `sender`, and return the handle of
+/// the token it delivers. `handle` is what the previous hop delivered. A hop with a known `amount_in` is a new
+/// call, tagged with `ctx.referral`. A chained one extends the previous call's path when that call is on the
+/// same router; after a router change it becomes its own call, spending the previous hop's quoted output.
+/// Either way the call's minimum out follows the quote of its last hop.
+pub fn extend_router_trade(
+    ctx: &mut TradeCtx,
+    dex: &dyn Dex,
+    router: Address,
+    sender: Address,
+    handle: &Bytes,
+    amount_in: Option<U256>,
+) -> Result<Bytes> {
+    let token_in = Address::from_str(&dex.coin_in_type())?;
+    let token_out = Address::from_str(&dex.coin_out_type())?;
+    ensure!(
+        handle.as_ref() == token_in.as_bytes(),
+        "hop trades {token_in:?} but the previous hop delivers {handle}"
+    );
+    let hop_in = match amount_in {
+        Some(amount_in) => amount_in,
+        None => ctx
+            .quoted_amount
+            .ok_or_else(|| eyre!("chained hop through {router:?} follows no quoted hop"))?,
+    };
+    let quote = dex.get_amount_out(hop_in)?;
+
+    let joined = match ctx.calls.last_mut() {
+        Some(RouterCall {
+            router: previous,
+            swap: DecodedSwap::ExactTokensForTokens { path, amount_out_min, .. },
+            ..
+        }) if amount_in.is_none() && *previous == router => {
+            path.push(token_out);
+            *amount_out_min = min_amount_out(quote);
+            true
+        }
+        _ => false,
+    };
+    if !joined {
+        ctx.calls.push(RouterCall {
+            router,
+            sender,
+            swap: exact_in_swap(hop_in, quote, vec![token_in, token_out], sender)?,
+            referral: ctx.referral,
+        });
+    }
+    ctx.quoted_amount = Some(quote);
+
+    Ok(Bytes::from(token_out.as_bytes().to_vec()))
+}
+
+/// Entries an `AmountInCache` holds before it starts over; results for past pool states are never hit again.
+const AMOUNT_IN_CACHE_CAPACITY: usize = 100_000;
+
+/// Memoizes per-hop exact-out results, within a routing call and across calls.
+/// The key includes the pool's reserves, so a cache never serves a result for a different pool state.
+#[derive(Default)]
+pub struct AmountInCache {
+    cache: HashMap<(PoolReserves, U256), U256>,
+}
+
+impl AmountInCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_amount_in(&mut self, amount_out: U256, pool: &PoolReserves) -> Result<U256> {
+        if let Some(amount_in) = self.cache.get(&(*pool, amount_out)) {
+            return Ok(*amount_in);
+        }
+
+        let amount_in = UniswapV2Calculator::get_amount_in(amount_out, pool)?;
+        if self.cache.len() >= AMOUNT_IN_CACHE_CAPACITY {
+            self.cache.clear();
+        }
+        self.cache.insert((*pool, amount_out), amount_in);
+        Ok(amount_in)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(reserve_in: u64, reserve_out: u64) -> PoolReserves {
+        PoolReserves::new(U256::from(reserve_in), U256::from(reserve_out), DEFAULT_FEE_BPS)
+    }
+
+    #[test]
+    fn test_amount_in_round_trip() {
+        let pool = pool(1_000_000, 2_000_000);
+        let amount_out = U256::from(10_000);
+
+        let amount_in = UniswapV2Calculator::get_amount_in(amount_out, &pool).unwrap();
+        let out = UniswapV2Calculator::get_amount_out(amount_in, &pool).unwrap();
+        assert!(out >= amount_out);
+    }
+
+    #[test]
+    fn test_amounts_in_memoized() {
+        let path = [pool(1_000_000, 2_000_000), pool(2_000_000, 1_100_000)];
+        let mut cache = AmountInCache::new();
+
+        let first = UniswapV2Calculator::get_amounts_in(U256::from(1_000), &path, &mut cache).unwrap();
+        assert_eq!(cache.len(), 2);
+
+        let second = UniswapV2Calculator::get_amounts_in(U256::from(1_000), &path, &mut cache).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_optimal_input_amount() {
+        // 10% price discrepancy between the two pools
+        let path = [pool(1_000_000_000, 1_000_000_000), pool(1_000_000_000, 1_100_000_000)];
+        let optimal = UniswapV2Calculator::get_optimal_input_amount(&path).unwrap();
+
+        let profit = |amount_in: U256| {
+            let mid = UniswapV2Calculator::get_amount_out(amount_in, &path[0]).unwrap();
+            let out = UniswapV2Calculator::get_amount_out(mid, &path[1]).unwrap();
+            out.as_u128() as i128 - amount_in.as_u128() as i128
+        };
+        assert!(profit(optimal) > 0);
+        assert!(profit(optimal) >= profit(optimal * 2));
+        assert!(profit(optimal) >= profit(optimal / 2));
+
+        let (amount_in, best) = UniswapV2Calculator::get_optimal_profit(&path).unwrap();
+        assert_eq!(amount_in, optimal);
+        assert_eq!(best.as_u128() as i128, profit(optimal));
+
+        // no edge
+        let flat = [pool(1_000_000, 1_000_000), pool(1_000_000, 1_000_000)];
+        assert!(UniswapV2Calculator::get_optimal_input_amount(&flat).is_none());
+        assert!(UniswapV2Calculator::get_optimal_profit(&flat).is_none());
+    }
+
+    #[test]
+    fn test_price_impact_is_decimal_invariant() {
+        // 1M WAVAX (18 decimals) against 20M USDC (6 decimals), and the same pool in whole units
+        let wavax = U256::exp10(18);
+        let usdc = U256::exp10(6);
+        let raw = PoolReserves::new(wavax * 1_000_000, usdc * 20_000_000, DEFAULT_FEE_BPS);
+        let whole = pool(1_000_000, 20_000_000);
+
+        let impact = UniswapV2Calculator::calculate_price_impact(wavax * 10_000, &raw).unwrap();
+        assert_eq!(impact, UniswapV2Calculator::calculate_price_impact(U256::from(10_000), &whole).unwrap());
+        // 1% of the reserve in moves the price by ~2%
+        assert!((190..=200).contains(&impact), "{impact}");
+
+        // same trade the other way round
+        let reversed = PoolReserves::new(raw.reserve_out, raw.reserve_in, DEFAULT_FEE_BPS);
+        let impact = UniswapV2Calculator::calculate_price_impact(usdc * 200_000, &reversed).unwrap();
+        assert!((190..=200).contains(&impact), "{impact}");
+    }
+
+    #[test]
+    fn test_path_price_impact_compounds() {
+        let single = UniswapV2Calculator::calculate_price_impact(U256::from(10_000), &pool(1_000_000, 1_000_000)).unwrap();
+        let path = [pool(1_000_000, 1_000_000), pool(100_000_000, 100_000_000)];
+        // the deep second pool barely adds to the first hop's impact
+        let impact = UniswapV2Calculator::calculate_path_price_impact(U256::from(10_000), &path).unwrap();
+        assert!(impact >= single && impact <= single + 3, "{impact} vs {single}");
+
+        let twice = [pool(1_000_000, 1_000_000), pool(1_000_000, 1_000_000)];
+        let impact = UniswapV2Calculator::calculate_path_price_impact(U256::from(10_000), &twice).unwrap();
+        assert!(impact > single * 19 / 10, "{impact} vs {single}");
+    }
+
+    #[test]
+    fn test_path_spread_bps() {
+        // mirrored pools: the lap just pays both fees
+        let mirror = [pool(1_000_000, 2_000_000), pool(2_000_000, 1_000_000)];
+        let spread = UniswapV2Calculator::path_spread_bps(&mirror).unwrap();
+        assert!((spread + 59.91).abs() < 0.01, "{spread}");
+
+        // a 1% rich second pool leaves ~40 bps after fees
+        let rich = [pool(1_000_000, 2_000_000), pool(2_000_000, 1_010_000)];
+        let spread = UniswapV2Calculator::path_spread_bps(&rich).unwrap();
+        assert!((spread - 39.49).abs() < 0.01, "{spread}");
+
+        assert!(UniswapV2Calculator::path_spread_bps(&[]).is_err());
+        assert!(UniswapV2Calculator::path_spread_bps(&[pool(0, 1_000)]).is_err());
+    }
+
+    #[test]
+    fn test_pool_fee_override_precedence() {
+        let overridden = Address::from_low_u64_be(0xfee1);
+        let untouched = Address::from_low_u64_be(0xfee2);
+        crate::config::update_tunables(|tunables| tunables.set_pool_fee_override(overridden, 25));
+
+        let reserves = (U256::from(1_000_000), U256::from(1_000_000));
+        assert_eq!(PoolReserves::for_pool(overridden, reserves.0, reserves.1).fee_bps, 25);
+        assert_eq!(PoolReserves::for_pool(untouched, reserves.0, reserves.1).fee_bps, DEFAULT_FEE_BPS);
+
+        // a lower fee gives more out
+        let out_overridden =
+            UniswapV2Calculator::get_amount_out(U256::from(1_000), &PoolReserves::for_pool(overridden, reserves.0, reserves.1));
+        let out_default =
+            UniswapV2Calculator::get_amount_out(U256::from(1_000), &PoolReserves::for_pool(untouched, reserves.0, reserves.1));
+        assert!(out_overridden.unwrap() >= out_default.unwrap());
+    }
+
+    #[test]
+    fn test_dex_fee_used_for_sizing() {
+        let stable = super::super::trader_joe::TraderJoeDex::new(
+            Address::from_low_u64_be(0xfee3),
+            "A".to_string(),
+            "B".to_string(),
+            1_000_000,
+            4,
+        );
+        let reserves = (U256::from(1_000_000), U256::from(1_000_000));
+
+        let sized = PoolReserves::for_dex(&stable, reserves.0, reserves.1);
+        assert_eq!(sized.fee_bps, 4);
+        assert!(
+            UniswapV2Calculator::get_amount_out(U256::from(1_000), &sized).unwrap()
+                > UniswapV2Calculator::get_amount_out(U256::from(1_000), &pool(1_000_000, 1_000_000)).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_router_adapters_calldata() {
+        use crate::dex::{
+            pangolin::{PangolinDex, PANGOLIN_ROUTER},
+            sushi_swap::{SushiSwapDex, SUSHI_SWAP_ROUTER},
+            test_fixtures::*,
+            token_handle,
+            trader_joe::{TraderJoeDex, TRADER_JOE_ROUTER},
+        };
+
+        type NewDex = fn(Address, &str, &str) -> Box<dyn Dex>;
+        let adapters: [(NewDex, &str); 3] = [
+            (
+                |pool, token_in, token_out| {
+                    let dex = TraderJoeDex::new(pool, token_in.to_string(), token_out.to_string(), 1_000_000, 30);
+                    Box::new(dex.with_reserves(U256::exp10(24), U256::exp10(24)))
+                },
+                TRADER_JOE_ROUTER,
+            ),
+            (
+                |pool, token_in, token_out| {
+                    let dex = PangolinDex::new(pool, token_in.to_string(), token_out.to_string(), 1_000_000, 30);
+                    Box::new(dex.with_reserves(U256::exp10(24), U256::exp10(24)))
+                },
+                PANGOLIN_ROUTER,
+            ),
+            (
+                |pool, token_in, token_out| {
+                    let dex = SushiSwapDex::new(pool, token_in.to_string(), token_out.to_string(), 1_000_000, 30);
+                    Box::new(dex.with_reserves(U256::exp10(24), U256::exp10(24)))
+                },
+                SUSHI_SWAP_ROUTER,
+            ),
+        ];
+
+        for (new_dex, router) in adapters {
+            let mut dex = new_dex(Address::repeat_byte(0x01), WAVAX, USDC);
+            let tx = dex.swap_tx(SENDER, RECIPIENT, AMOUNT_IN).await.unwrap();
+            assert_router_swap(&tx, router, &[WAVAX, USDC], dex.get_amount_out(AMOUNT_IN).unwrap(), RECIPIENT);
+
+            dex.flip();
+            let tx = dex.swap_tx(SENDER, RECIPIENT, AMOUNT_IN).await.unwrap();
+            assert_router_swap(&tx, router, &[USDC, WAVAX], dex.get_amount_out(AMOUNT_IN).unwrap(), RECIPIENT);
+
+            let to_usdc = new_dex(Address::repeat_byte(0x01), WAVAX, USDC);
+            let to_wavax = new_dex(Address::repeat_byte(0x02), USDC, WAVAX);
+            let mut ctx = TradeCtx::new();
+            let usdc = to_usdc
+                .extend_trade_tx(&mut ctx, SENDER, token_handle(WAVAX).unwrap(), Some(AMOUNT_IN))
+                .await
+                .unwrap();
+            assert_eq!(usdc, token_handle(USDC).unwrap());
+            let quote = to_usdc.get_amount_out(AMOUNT_IN).unwrap();
+            assert_router_swap(&ctx.calls[0].tx(), router, &[WAVAX, USDC], quote, SENDER);
+
+            let wavax = to_wavax.extend_trade_tx(&mut ctx, SENDER, usdc, None).await.unwrap();
+            assert_eq!(wavax, token_handle(WAVAX).unwrap());
+            // the next hop through the same router joins the call's path, and its quote bounds the output
+            assert_eq!(ctx.calls.len(), 1);
+            let quote = to_wavax.get_amount_out(quote).unwrap();
+            assert_router_swap(&ctx.calls[0].tx(), router, &[WAVAX, USDC, WAVAX], quote, SENDER);
+        }
+    }
+
+    #[test]
+    fn test_extend_router_trade_chaining() {
+        use crate::dex::{pangolin::PangolinDex, test_fixtures::*, token_handle, trader_joe::TraderJoeDex};
+
+        let (joe, pangolin) = (Address::repeat_byte(0x0a), Address::repeat_byte(0x0b));
+        let reserves = U256::exp10(24);
+        let to_usdc = TraderJoeDex::new(Address::repeat_byte(0x01), WAVAX.to_string(), USDC.to_string(), 1_000_000, 30)
+            .with_reserves(reserves, reserves);
+        let to_wavax = PangolinDex::new(Address::repeat_byte(0x02), USDC.to_string(), WAVAX.to_string(), 1_000_000, 30)
+            .with_reserves(reserves, reserves);
+        let tokens = |tokens: &[&str]| tokens.iter().map(|t| Address::from_str(t).unwrap()).collect::<Vec<_>>();
+        let wavax = token_handle(WAVAX).unwrap();
+
+        let mut ctx = TradeCtx::new();
+        // a hop spends what the previous one delivered
+        assert!(extend_router_trade(&mut ctx, &to_wavax, pangolin, SENDER, &wavax, Some(AMOUNT_IN)).is_err());
+        // a chained hop needs a quoted hop before it
+        assert!(extend_router_trade(&mut ctx, &to_usdc, joe, SENDER, &wavax, None).is_err());
+
+        // on the same router, the next hop joins the call's path
+        let usdc = extend_router_trade(&mut ctx, &to_usdc, joe, SENDER, &wavax, Some(AMOUNT_IN)).unwrap();
+        extend_router_trade(&mut ctx, &to_wavax, joe, SENDER, &usdc, None).unwrap();
+        assert_eq!(ctx.calls.len(), 1);
+        assert_eq!(ctx.calls[0].swap.path(), tokens(&[WAVAX, USDC, WAVAX]));
+
+        // TraderJoe -> Pangolin: the second call spends the first hop's quoted output
+        let mut ctx = TradeCtx::new();
+        let usdc = extend_router_trade(&mut ctx, &to_usdc, joe, SENDER, &wavax, Some(AMOUNT_IN)).unwrap();
+        let quoted_usdc = to_usdc.get_amount_out(AMOUNT_IN).unwrap();
+        assert_eq!(extend_router_trade(&mut ctx, &to_wavax, pangolin, SENDER, &usdc, None).unwrap(), wavax);
+        assert_eq!(ctx.calls.iter().map(|call| call.router).collect::<Vec<_>>(), [joe, pangolin]);
+        assert_eq!(ctx.calls[1].swap.path(), tokens(&[USDC, WAVAX]));
+        assert_eq!(ctx.calls[1].swap.max_amount_in(U256::zero()), quoted_usdc);
+        assert_eq!(ctx.quoted_amount, Some(to_wavax.get_amount_out(quoted_usdc).unwrap()));
+    }
+}
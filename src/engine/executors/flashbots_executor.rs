@@ -7,7 +7,7 @@ use ethers::{
 };
 use ethers_flashbots::{BundleRequest, FlashbotsMiddleware};
 use reqwest::Url;
-use tracing::error;
+use tracing::{error, warn};
 
 use crate::types::Executor;
 
@@ -42,6 +42,11 @@ where
 {
     /// Send a bundle to transactions to the Flashbots relay.
     async fn execute(&self, action: FlashbotsBundle) -> Result<()> {
+        if crate::utils::config::safe_mode() {
+            warn!(txs = action.len(), "safe mode: not signing flashbots bundle");
+            return Ok(());
+        }
+
         // Add txs to bundle.
         let mut bundle = BundleRequest::new();
 
@@ -1,19 +1,38 @@
 use std::{
     ops::{Div, Mul},
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
-use crate::types::Executor;
+use crate::{simulator::effective_gas_price, types::Executor, utils::math::nearest_rank};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use tracing::warn;
 use ethers::{
     providers::Middleware,
-    types::{transaction::eip2718::TypedTransaction, U256},
+    types::{transaction::eip2718::TypedTransaction, U256, U64},
 };
 
 /// An executor that sends transactions to the mempool.
 pub struct MempoolExecutor<M> {
     client: Arc<M>,
+    gas_price_strategy: GasPriceStrategy,
+    /// Percentile gas price and the block it was computed at.
+    cached_gas_price: Mutex<Option<(U64, U256)>>,
+}
+
+/// How to price a transaction that has no gas bid attached.
+#[derive(Debug, Clone, Default)]
+pub enum GasPriceStrategy {
+    /// The node's `eth_gasPrice`.
+    #[default]
+    Node,
+    /// The `percentile`-th percentile of the effective gas prices paid by the txs in the last `blocks`
+    /// blocks, plus `tip`. Recomputed once per block.
+    Percentile {
+        percentile: u8,
+        blocks: u64,
+        tip: U256,
+    },
 }
 
 /// Information about the gas bid for a transaction.
@@ -34,7 +53,77 @@ pub struct SubmitTxToMempool {
 
 impl<M: Middleware> MempoolExecutor<M> {
     pub fn new(client: Arc<M>) -> Self {
-        Self { client }
+        Self::with_gas_price_strategy(client, GasPriceStrategy::Node)
+    }
+
+    pub fn with_gas_price_strategy(client: Arc<M>, gas_price_strategy: GasPriceStrategy) -> Self {
+        Self {
+            client,
+            gas_price_strategy,
+            cached_gas_price: Mutex::new(None),
+        }
+    }
+}
+
+impl<M> MempoolExecutor<M>
+where
+    M: Middleware,
+    M::Error: 'static,
+{
+    /// Gas price for a tx without a gas bid, according to the configured strategy.
+    pub async fn gas_price(&self) -> Result<U256> {
+        let (percentile, blocks, tip) = match &self.gas_price_strategy {
+            GasPriceStrategy::Node => {
+                return self
+                    .client
+                    .get_gas_price()
+                    .await
+                    .context("Error getting gas price: {}");
+            }
+            GasPriceStrategy::Percentile {
+                percentile,
+                blocks,
+                tip,
+            } => (*percentile, *blocks, *tip),
+        };
+
+        let latest = self
+            .client
+            .get_block_number()
+            .await
+            .context("Error getting block number")?;
+        if let Some((block, gas_price)) = *self.cached_gas_price.lock().unwrap() {
+            if block == latest {
+                return Ok(gas_price);
+            }
+        }
+
+        let mut prices = vec![];
+        for number in (0..blocks.max(1)).filter_map(|i| latest.as_u64().checked_sub(i)) {
+            let Some(block) = self
+                .client
+                .get_block_with_txs(number)
+                .await
+                .context("Error getting block")?
+            else {
+                continue;
+            };
+            let base_fee = block.base_fee_per_gas.unwrap_or_default();
+            prices.extend(block.transactions.iter().filter_map(|tx| effective_gas_price(tx, base_fee)));
+        }
+
+        prices.sort_unstable();
+        let gas_price = match nearest_rank(&prices, percentile as usize) {
+            Some(price) => price + tip,
+            // empty blocks: nothing to compete with, fall back to the node
+            None => self
+                .client
+                .get_gas_price()
+                .await
+                .context("Error getting gas price: {}")?,
+        };
+        *self.cached_gas_price.lock().unwrap() = Some((latest, gas_price));
+        Ok(gas_price)
     }
 }
 
@@ -46,6 +135,11 @@ where
 {
     /// Send a transaction to the mempool.
     async fn execute(&self, mut action: SubmitTxToMempool) -> Result<()> {
+        if crate::utils::config::safe_mode() {
+            warn!(to = ?action.tx.to(), "safe mode: not sending tx to the mempool");
+            return Ok(());
+        }
+
         let gas_usage = self
             .client
             .estimate_gas(&action.tx, None)
@@ -61,11 +155,7 @@ where
                 .mul(gas_bid_info.bid_percentage)
                 .div(100);
         } else {
-            bid_gas_price = self
-                .client
-                .get_gas_price()
-                .await
-                .context("Error getting gas price: {}")?;
+            bid_gas_price = self.gas_price().await?;
         }
         action.tx.set_gas_price(bid_gas_price);
         self.client.send_transaction(action.tx, None).await?;
@@ -30,14 +30,32 @@ pub struct HttpConfig {
     #[arg(long, env = "AVAX_WS_URL", default_value = "wss://api.avax.network/ext/bc/C/ws")]
     pub ws_url: String,
 
-    #[arg(long, help = "deprecated")]
+    /// Not supported: every provider and the simulators speak HTTP or WS. Kept so setting it fails loudly
+    /// instead of being silently ignored.
+    #[arg(long, hide = true, value_parser = reject_ipc_path)]
     pub ipc_path: Option<String>,
 }
 
+fn reject_ipc_path(_: &str) -> Result<String, String> {
+    Err("IPC transport is not supported; point --rpc-url and --ws-url at the local node's HTTP and WS endpoints".to_string())
+}
+
 #[derive(clap::Subcommand)]
 pub enum Command {
     StartBot(bot::start_bot::Args),
+    /// Check config, signer, RPC, WS and anvil with the `start-bot` flags, then exit
+    Validate(bot::start_bot::Args),
     Run(strategy::arb::Args),
+    /// Send every signer's AVAX (or an ERC20) balance to a cold wallet
+    SweepProfits(bot::sweep::Args),
+    /// Measure path enumeration and simulation throughput for a token set at a pinned block
+    Bench(tools::bench::Args),
+    /// Compare two exported pool-cache snapshots
+    DiffSnapshots(tools::diff_snapshots::Args),
+    /// Dump the indexed pools with their tokens, reserves and fees to CSV or JSON
+    ExportPools(tools::export_pools::Args),
+    /// Log cross-DEX spreads found from reserves alone, without simulating or building txs
+    Scan(tools::scan::Args),
     // ContractArb功能与StartBot重复，已删除
     // ContractArb(strategy::contract_arb::ContractArbArgs),
     // PoolIds工具命令，用不到，已删除
@@ -50,6 +68,12 @@ async fn main() -> Result<()> {
 
     match args.command {
         Command::StartBot(args) => bot::start_bot::run(args).await,
+        Command::Validate(args) => bot::validate::run(args).await,
         Command::Run(args) => strategy::arb::run(args).await,
+        Command::SweepProfits(args) => bot::sweep::run(args).await,
+        Command::Bench(args) => tools::bench::run(args).await,
+        Command::DiffSnapshots(args) => tools::diff_snapshots::run(args).await,
+        Command::ExportPools(args) => tools::export_pools::run(args).await,
+        Command::Scan(args) => tools::scan::run(args).await,
     }
 }
@@ -1,20 +1,102 @@
 use async_trait::async_trait;
-use eyre::Result;
+use eyre::{Result, WrapErr};
 use ethers::{
-    providers::{Http, Provider, Middleware},
-    types::{Address, Block, Transaction, TransactionReceipt, U256, H256, BlockId, Bytes},
+    providers::{Http, Provider, Middleware, ProviderError},
+    types::{
+        transaction::eip2930::AccessList, Address, Block, Transaction, TransactionReceipt, TransactionRequest, U256, H256,
+        BlockId, Bytes,
+    },
     utils::Anvil,
 };
+use once_cell::sync::Lazy;
+use prometheus::{register_int_counter, IntCounter};
 use std::{
     collections::HashMap,
     process::{Child, Command, Stdio},
-    sync::Arc,
-    time::Duration,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{Mutex, OwnedSemaphorePermit, Semaphore},
+    time::sleep,
 };
-use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 
-use super::{BalanceChange, SimulateCtx, SimulateResult, Simulator};
+use crate::{
+    bindings::avaxarbexecutor::{ArbParams, AvaxArbExecutor, BYTECODE},
+    config::{anvil_min_free_mb, max_anvils},
+    utils::math::{i128_from_delta, u256_to_i128_checked},
+};
+
+use super::{
+    checked_amount, effective_gas_price, revert::simulation_error, warn_on_unexpected_chain_id, BalanceChange, SimulateCtx, SimulateResult, Simulator, AVALANCHE_FUJI_CHAIN_ID,
+    AVALANCHE_MAINNET_CHAIN_ID,
+};
+
+/// header not found 时回退的区块数
+const HEADER_NOT_FOUND_RETRY_BLOCKS: u64 = 2;
+
+/// 闪电贷套利模拟时 owner 至少持有的原生余额，用于支付部署和执行的 gas
+const FLASH_ARB_GAS_FUNDING: U256 = U256([10_000_000_000_000_000_000, 0, 0, 0]);
+
+/// 重启后等待 anvil 响应 RPC 的最长时间（fork 大状态时启动较慢）
+const ANVIL_READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 等待空闲 anvil 名额的最长时间，超时后报错而不是无限排队
+const ANVIL_SLOT_TIMEOUT: Duration = Duration::from_secs(120);
+
+static ANVIL_RESTARTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!("arb_anvil_restarts_total", "Anvil processes respawned after dying mid-run").unwrap()
+});
+
+/// 同时运行的 anvil 名额（`--max-anvils`），首个 fork 模拟器启动时按当时的配置创建；未配置时不限
+static ANVIL_SLOTS: Lazy<Option<Arc<Semaphore>>> = Lazy::new(|| max_anvils().map(|max| Arc::new(Semaphore::new(max))));
+
+/// 占用一个 anvil 名额，名额满时排队等待；未配置上限时返回 `None`
+async fn acquire_anvil_slot() -> Result<Option<OwnedSemaphorePermit>> {
+    let Some(slots) = ANVIL_SLOTS.as_ref() else {
+        return Ok(None);
+    };
+    if slots.available_permits() == 0 {
+        info!("anvil 名额已满（--max-anvils {}），排队等待", max_anvils().unwrap_or_default());
+    }
+    let permit = tokio::time::timeout(ANVIL_SLOT_TIMEOUT, slots.clone().acquire_owned())
+        .await
+        .map_err(|_| {
+            eyre::eyre!(
+                "no free anvil slot after {:?}: all {} are in use, raise --max-anvils or use fewer simulators",
+                ANVIL_SLOT_TIMEOUT,
+                max_anvils().unwrap_or_default()
+            )
+        })??;
+    Ok(Some(permit))
+}
+
+/// `/proc/meminfo` 中的 MemAvailable，单位 MB；非 Linux 或无法读取时为 `None`
+fn mem_available_mb() -> Option<usize> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemAvailable:"))?;
+    let kb: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb / 1024)
+}
+
+/// 可用内存低于 `--anvil-min-free-mb` 时拒绝再启动 anvil，避免整机 OOM
+fn ensure_memory_for_anvil() -> Result<()> {
+    let min_free_mb = anvil_min_free_mb();
+    if min_free_mb == 0 {
+        return Ok(());
+    }
+    if let Some(available_mb) = mem_available_mb() {
+        eyre::ensure!(
+            available_mb >= min_free_mb,
+            "refusing to spawn anvil: {available_mb} MB available, --anvil-min-free-mb requires {min_free_mb} MB"
+        );
+    }
+    Ok(())
+}
 
 #[derive(Clone)]
 pub struct FoundrySimulator {
@@ -22,7 +104,48 @@ pub struct FoundrySimulator {
     pub fork_url: String,
     pub anvil_port: u16,
     pub chain_id: u64,
-    anvil_process: Option<Arc<Child>>,
+    /// 启动时的 fork 区块，anvil 重启时沿用
+    fork_block: Option<u64>,
+    /// 所有克隆共享，anvil 退出后由最先发现的克隆重启
+    anvil_process: Option<Arc<Mutex<Child>>>,
+    /// 本实例占用的 anvil 名额，最后一个克隆释放时归还
+    _anvil_slot: Option<Arc<OwnedSemaphorePermit>>,
+    /// 已预热的 fork 区块，所有克隆共享同一个 anvil 因此也共享它
+    warm_fork: Arc<Mutex<Option<WarmFork>>>,
+    /// 模拟在回滚快照前被取消（如超时）时置位，fork 上残留着它的状态，下次模拟前先恢复
+    fork_dirty: Arc<AtomicBool>,
+}
+
+/// anvil 当前 fork 所在的区块及其干净状态的快照。一个 anvil 同一时刻只能 fork 一个区块，
+/// 预热新区块时旧的自动丢弃；池中的每个 anvil 各自保留最近预热的区块
+#[derive(Debug, Clone, Copy)]
+struct WarmFork {
+    block: u64,
+    snapshot: U256,
+}
+
+/// 在打快照到回滚之间持有；未 `disarm` 就被丢弃（future 被取消或回滚失败）时把 fork 标记为脏
+struct DirtyOnDrop<'a> {
+    dirty: &'a AtomicBool,
+    armed: bool,
+}
+
+impl<'a> DirtyOnDrop<'a> {
+    fn new(dirty: &'a AtomicBool) -> Self {
+        Self { dirty, armed: true }
+    }
+
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for DirtyOnDrop<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            self.dirty.store(true, Ordering::SeqCst);
+        }
+    }
 }
 
 impl FoundrySimulator {
@@ -32,10 +155,20 @@ impl FoundrySimulator {
         fork_block: Option<u64>,
     ) -> Result<Self> {
         let port = anvil_port.unwrap_or(8545);
+
+        let anvil_version = Self::check_anvil()?;
+        debug!("使用 {}", anvil_version);
+
+        let anvil_slot = acquire_anvil_slot().await?;
+        ensure_memory_for_anvil()?;
+
+        // 从 fork RPC 获取真实的 chain id，使任意 Avalanche 子网都可以 fork
+        let fork_provider = Provider::<Http>::try_from(fork_url.as_str())?;
+        let fork_chain_id = fork_provider.get_chainid().await?.as_u64();
         
-        info!("启动 Foundry Anvil 进程，端口: {}", port);
+        info!("启动 Foundry Anvil 进程，端口: {}, chain id: {}", port, fork_chain_id);
         
-        let anvil_process = Self::start_anvil(&fork_url, port, fork_block).await?;
+        let anvil_process = Self::start_anvil(&fork_url, port, fork_block, fork_chain_id).await?;
         let anvil_url = format!("http://localhost:{}", port);
         
         // 等待 Anvil 启动
@@ -45,13 +178,20 @@ impl FoundrySimulator {
         let provider = Arc::new(provider);
         
         let chain_id = provider.get_chainid().await?.as_u64();
+        if chain_id != fork_chain_id {
+            warn!("Anvil chain id {} 与 fork RPC chain id {} 不一致", chain_id, fork_chain_id);
+        }
         
         Ok(Self {
             provider,
             fork_url,
             anvil_port: port,
             chain_id,
-            anvil_process: Some(Arc::new(anvil_process)),
+            fork_block,
+            anvil_process: Some(Arc::new(Mutex::new(anvil_process))),
+            _anvil_slot: anvil_slot.map(Arc::new),
+            warm_fork: Arc::new(Mutex::new(None)),
+            fork_dirty: Arc::new(AtomicBool::new(false)),
         })
     }
 
@@ -60,8 +200,8 @@ impl FoundrySimulator {
         anvil_port: Option<u16>,
         fork_block: Option<u64>,
     ) -> Result<Self> {
-        let mut simulator = Self::new(fork_url, anvil_port, fork_block).await?;
-        simulator.chain_id = 43114; // Force Avalanche mainnet chain ID
+        let simulator = Self::new(fork_url, anvil_port, fork_block).await?;
+        warn_on_unexpected_chain_id(simulator.chain_id, AVALANCHE_MAINNET_CHAIN_ID);
         Ok(simulator)
     }
 
@@ -70,12 +210,35 @@ impl FoundrySimulator {
         anvil_port: Option<u16>,
         fork_block: Option<u64>,
     ) -> Result<Self> {
-        let mut simulator = Self::new(fork_url, anvil_port, fork_block).await?;
-        simulator.chain_id = 43113; // Force Avalanche fuji chain ID
+        let simulator = Self::new(fork_url, anvil_port, fork_block).await?;
+        warn_on_unexpected_chain_id(simulator.chain_id, AVALANCHE_FUJI_CHAIN_ID);
         Ok(simulator)
     }
 
-    async fn start_anvil(fork_url: &str, port: u16, fork_block: Option<u64>) -> Result<Child> {
+    /// 检查 anvil 是否已安装且可以运行，返回其版本信息
+    pub fn check_anvil() -> Result<String> {
+        let output = match Command::new("anvil").arg("--version").output() {
+            Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                eyre::bail!(
+                    "anvil not found in PATH. Install foundry (https://book.getfoundry.sh/getting-started/installation) \
+                     or run with the HTTP simulator"
+                );
+            }
+            Err(e) => eyre::bail!("failed to run `anvil --version`: {}", e),
+        };
+
+        eyre::ensure!(
+            output.status.success(),
+            "`anvil --version` exited with {}, the installed foundry may be incompatible: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    async fn start_anvil(fork_url: &str, port: u16, fork_block: Option<u64>, chain_id: u64) -> Result<Child> {
         let mut cmd = Command::new("anvil");
         cmd.arg("--host").arg("127.0.0.1")
            .arg("--port").arg(port.to_string())
@@ -84,7 +247,7 @@ impl FoundrySimulator {
            .arg("--gas-price").arg("25000000000") // 25 gwei default for Avalanche
            .arg("--accounts").arg("10")
            .arg("--balance").arg("10000")
-           .arg("--chain-id").arg("43114"); // Default to Avalanche mainnet
+           .arg("--chain-id").arg(chain_id.to_string());
         
         if let Some(block) = fork_block {
             cmd.arg("--fork-block-number").arg(block.to_string());
@@ -99,7 +262,95 @@ impl FoundrySimulator {
         Ok(child)
     }
 
+    /// anvil 退出（OOM、panic）或不再响应后，按原 fork 配置在同一端口重新启动并等待其就绪。
+    /// 多个克隆同时发现时只有第一个会重启
+    async fn respawn_anvil(&self) -> Result<()> {
+        let Some(anvil_process) = &self.anvil_process else {
+            eyre::bail!("anvil 不由本模拟器启动，无法重启");
+        };
+        let mut child = anvil_process.lock().await;
+        if child.try_wait()?.is_none() && self.provider.get_block_number().await.is_ok() {
+            // 其他克隆已重启，或只是瞬时的连接错误
+            return Ok(());
+        }
+
+        warn!(port = self.anvil_port, "anvil 已退出或无响应，正在重启");
+        // 进程可能仍在但已卡死；已退出时 kill 会报错，忽略即可
+        let _ = child.kill();
+        let _ = child.wait();
+        *child = Self::start_anvil(&self.fork_url, self.anvil_port, self.fork_block, self.chain_id).await?;
+        self.wait_ready().await?;
+        *self.warm_fork.lock().await = None;
+
+        ANVIL_RESTARTS.inc();
+        info!(port = self.anvil_port, "anvil 已重启");
+        Ok(())
+    }
+
+    /// 等待 anvil 开始响应 RPC，最多 `ANVIL_READY_TIMEOUT`
+    async fn wait_ready(&self) -> Result<()> {
+        let start = Instant::now();
+        while self.provider.get_block_number().await.is_err() {
+            eyre::ensure!(start.elapsed() < ANVIL_READY_TIMEOUT, "anvil 在 {:?} 内未就绪", ANVIL_READY_TIMEOUT);
+            sleep(Duration::from_millis(250)).await;
+        }
+        Ok(())
+    }
+
     pub async fn reset_fork(&self, block_number: Option<u64>) -> Result<()> {
+        let mut warm_fork = self.warm_fork.lock().await;
+        *warm_fork = None;
+        self.anvil_reset(block_number).await
+    }
+
+    /// 切换到 `block` 的干净状态：已预热时回滚到它的快照，否则重新 fork 并预热
+    async fn fork_at(&self, block: u64) -> Result<()> {
+        let mut warm_fork = self.warm_fork.lock().await;
+        // 先取出再回滚：回滚或重新快照中途失败、超时取消时不会留下已失效的快照 id
+        if let Some(warm) = warm_fork.take().filter(|warm| warm.block == block) {
+            // evm_revert 会消耗快照，回滚后重新打一个
+            let reverted = async {
+                self.evm_revert(warm.snapshot).await?;
+                self.evm_snapshot().await
+            }
+            .await;
+            match reverted {
+                Ok(snapshot) => {
+                    *warm_fork = Some(WarmFork { block, snapshot });
+                    return Ok(());
+                }
+                Err(error) => warn!("回滚到预热快照失败，重新 fork 区块 {}: {error:#}", block),
+            }
+        }
+
+        self.anvil_reset(Some(block)).await?;
+        *warm_fork = Some(WarmFork {
+            block,
+            snapshot: self.evm_snapshot().await?,
+        });
+        debug!("已预热 fork 区块 {}", block);
+        Ok(())
+    }
+
+    /// 上次模拟在回滚快照前被取消时恢复干净状态：回滚到预热快照，或重新 fork
+    async fn recover_dirty_fork(&self) -> Result<()> {
+        if !self.fork_dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let block = self.warm_fork.lock().await.map(|warm| warm.block).or(self.fork_block);
+        warn!("上次模拟在回滚快照前被取消，恢复 fork 区块 {:?}", block);
+        let recovered = match block {
+            Some(block) => self.fork_at(block).await,
+            None => self.reset_fork(None).await,
+        };
+        if recovered.is_err() {
+            self.fork_dirty.store(true, Ordering::SeqCst);
+        }
+        recovered
+    }
+
+    async fn anvil_reset(&self, block_number: Option<u64>) -> Result<()> {
         let method = "anvil_reset";
         let mut params = vec![serde_json::json!({
             "forking": {
@@ -136,6 +387,22 @@ impl FoundrySimulator {
         Ok(())
     }
 
+    pub async fn set_storage_at(&self, address: Address, slot: H256, value: H256) -> Result<()> {
+        let method = "anvil_setStorageAt";
+        let params = vec![
+            serde_json::json!(format!("{:#x}", address)),
+            serde_json::json!(format!("{:#x}", slot)),
+            serde_json::json!(format!("{:#x}", value)),
+        ];
+
+        let _: serde_json::Value = self.provider
+            .request(method, params)
+            .await?;
+
+        debug!("设置合约 {} 的存储槽 {:#x} 为 {:#x}", address, slot, value);
+        Ok(())
+    }
+
     pub async fn impersonate_account(&self, address: Address) -> Result<()> {
         let method = "anvil_impersonateAccount";
         let params = vec![serde_json::json!(format!("{:#x}", address))];
@@ -176,7 +443,7 @@ impl FoundrySimulator {
             balance_changes.push(BalanceChange {
                 address: tx.from,
                 token: Address::zero(), // Native AVAX
-                amount: -(gas_cost.as_u128() as i128),
+                amount: checked_amount(i128_from_delta(U256::zero(), gas_cost))?,
             });
         }
 
@@ -186,14 +453,14 @@ impl FoundrySimulator {
                 balance_changes.push(BalanceChange {
                     address: tx.from,
                     token: Address::zero(),
-                    amount: -(value.as_u128() as i128),
+                    amount: checked_amount(i128_from_delta(U256::zero(), value))?,
                 });
 
                 if let Some(to) = tx.to {
                     balance_changes.push(BalanceChange {
                         address: to,
                         token: Address::zero(),
-                        amount: value.as_u128() as i128,
+                        amount: checked_amount(u256_to_i128_checked(value))?,
                     });
                 }
             }
@@ -204,7 +471,7 @@ impl FoundrySimulator {
             balance_changes.push(BalanceChange {
                 address: tx.from,
                 token: *token,
-                amount: -(amount.as_u128() as i128),
+                amount: checked_amount(i128_from_delta(U256::zero(), *amount))?,
             });
         }
 
@@ -218,43 +485,98 @@ impl FoundrySimulator {
         Ok(balance_changes)
     }
 
-    fn parse_transfer_log(&self, log: &ethers::types::Log) -> Option<BalanceChange> {
-        // ERC20 Transfer 事件的签名
-        const TRANSFER_SIGNATURE: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
-        
-        if log.topics.is_empty() || format!("{:#x}", log.topics[0]) != TRANSFER_SIGNATURE {
-            return None;
+    async fn evm_snapshot(&self) -> Result<U256> {
+        let id: U256 = self.provider.request("evm_snapshot", ()).await?;
+        Ok(id)
+    }
+
+    async fn evm_revert(&self, id: U256) -> Result<()> {
+        let reverted: bool = self.provider.request("evm_revert", [id]).await?;
+        eyre::ensure!(reverted, "evm_revert 失败: snapshot {}", id);
+        Ok(())
+    }
+
+    /// 按原交易的发送者、接收者、金额、数据和 gas 上限构造重放请求
+    fn replay_request(tx: &Transaction) -> TransactionRequest {
+        let mut request = TransactionRequest::new()
+            .from(tx.from)
+            .value(tx.value)
+            .data(tx.input.clone());
+        if !tx.gas.is_zero() {
+            request = request.gas(tx.gas);
         }
-        
-        if log.topics.len() != 3 {
-            return None;
+        if let Some(to) = tx.to {
+            request = request.to(to);
         }
-        
-        // 解析转账事件: Transfer(address indexed from, address indexed to, uint256 value)
-        let _from = Address::from(log.topics[1]);
-        let to = Address::from(log.topics[2]);
-        
-        if log.data.len() >= 32 {
-            let amount = U256::from_big_endian(&log.data[..32]);
-            return Some(BalanceChange {
-                address: to,
-                token: log.address,
-                amount: amount.as_u128() as i128,
-            });
+        request
+    }
+
+    /// 以原发送者身份在 fork 上真实执行一笔交易（anvil 自动出块）
+    async fn apply_pre_tx(&self, pre_tx: &Transaction) -> Result<()> {
+        let request = Self::replay_request(pre_tx);
+
+        self.impersonate_account(pre_tx.from).await?;
+        let receipt = match self.provider.send_transaction(request, None).await {
+            Ok(pending) => pending.await,
+            Err(e) => Err(e),
+        };
+        self.stop_impersonating(pre_tx.from).await?;
+
+        let receipt = receipt?.ok_or_else(|| eyre::eyre!("前置交易 {:?} 无收据", pre_tx.hash))?;
+        eyre::ensure!(receipt.status == Some(1u64.into()), "前置交易 {:?} 执行回滚", pre_tx.hash);
+        Ok(())
+    }
+
+    /// 先执行 `ctx.pre_txs`，再模拟 `tx`；结束后回滚到快照，保证 fork 状态不被污染
+    async fn simulate_with_pre_txs(&self, tx: Transaction, mut ctx: SimulateCtx) -> Result<SimulateResult> {
+        if let Some(fork_block) = ctx.fork_block.take() {
+            self.fork_at(fork_block).await?;
         }
-        
-        None
+
+        let snapshot = self.evm_snapshot().await?;
+        let guard = DirtyOnDrop::new(&self.fork_dirty);
+        let result = async {
+            for pre_tx in std::mem::take(&mut ctx.pre_txs) {
+                self.apply_pre_tx(&pre_tx).await?;
+            }
+            self.simulate_once(tx, ctx).await
+        }
+        .await;
+        self.evm_revert(snapshot).await?;
+        guard.disarm();
+
+        result
     }
-}
 
-#[async_trait]
-impl Simulator for FoundrySimulator {
-    async fn simulate(&self, tx: Transaction, ctx: SimulateCtx) -> Result<SimulateResult> {
+    /// 在快照内真实执行 `tx` 得到实际 gas_used（已扣除退款，`eth_estimateGas` 会高估），执行后回滚。
+    /// 调用方需已模拟 `tx.from`
+    async fn executed_gas_used(&self, tx: &Transaction) -> Result<U256> {
+        let snapshot = self.evm_snapshot().await?;
+        let guard = DirtyOnDrop::new(&self.fork_dirty);
+        let receipt = async {
+            let pending = self.provider.send_transaction(Self::replay_request(tx), None).await?;
+            pending.await?.ok_or_else(|| eyre::eyre!("模拟交易 {:?} 无收据", tx.hash))
+        }
+        .await;
+        self.evm_revert(snapshot).await?;
+        guard.disarm();
+
+        let receipt = receipt?;
+        eyre::ensure!(receipt.status == Some(1u64.into()), "模拟交易 {:?} 执行回滚", tx.hash);
+        receipt.gas_used.ok_or_else(|| eyre::eyre!("模拟交易 {:?} 收据缺少 gas_used", tx.hash))
+    }
+
+    async fn simulate_once(&self, tx: Transaction, ctx: SimulateCtx) -> Result<SimulateResult> {
+        self.recover_dirty_fork().await?;
+        if !ctx.pre_txs.is_empty() {
+            return Box::pin(self.simulate_with_pre_txs(tx, ctx)).await;
+        }
+
         let simulation_start = std::time::Instant::now();
         
-        // 如果需要重置 fork 到特定区块
+        // 切换到指定区块的 fork，已预热时复用
         if let Some(fork_block) = ctx.fork_block {
-            self.reset_fork(Some(fork_block)).await?;
+            self.fork_at(fork_block).await?;
         }
 
         // 应用余额覆盖
@@ -284,20 +606,27 @@ impl Simulator for FoundrySimulator {
             Ok(result) => result,
             Err(e) => {
                 self.stop_impersonating(tx.from).await?;
-                return Err(eyre::eyre!("交易模拟失败: {}", e));
+                return Err(simulation_error(self.name(), e).wrap_err("交易模拟失败"));
             }
         };
 
-        // 估算 gas
-        let gas_estimate = self.provider
-            .estimate_gas(&tx.clone().into(), None)
-            .await
-            .unwrap_or(U256::from(21000));
+        // 实际执行得到的 gas_used；执行失败时退回 estimate_gas
+        let gas_estimate = match self.executed_gas_used(&tx).await {
+            Ok(gas_used) => gas_used,
+            Err(error) => {
+                debug!("实际执行取 gas 失败，改用 estimate_gas: {error:#}");
+                self.provider
+                    .estimate_gas(&tx.clone().into(), None)
+                    .await
+                    .unwrap_or(U256::from(21000))
+            }
+        };
 
-        // 获取 gas 价格
-        let gas_price = tx.gas_price.unwrap_or_else(|| {
-            ctx.epoch.base_fee.max(U256::from(25_000_000_000)) // 25 gwei minimum
-        });
+        // 按区块 base fee 计算交易上链时实际支付的 gas 价格
+        let gas_price = match effective_gas_price(&tx, ctx.epoch.base_fee) {
+            Some(gas_price) => gas_price,
+            None => self.provider.get_gas_price().await?,
+        };
 
         // 创建模拟的交易收据
         let receipt = TransactionReceipt {
@@ -335,6 +664,176 @@ impl Simulator for FoundrySimulator {
         })
     }
 
+    /// 以 `owner` 身份部署套利合约（构造函数把部署者设为 owner），返回合约地址。调用方需已模拟 `owner`
+    async fn deploy_arb_contract(&self, owner: Address) -> Result<Address> {
+        let request = TransactionRequest::new().from(owner).data(Bytes::from(BYTECODE.to_vec()));
+        let receipt = self
+            .provider
+            .send_transaction(request, None)
+            .await?
+            .await?
+            .ok_or_else(|| eyre::eyre!("套利合约部署无收据"))?;
+        eyre::ensure!(receipt.status == Some(1u64.into()), "套利合约部署回滚");
+        receipt.contract_address.ok_or_else(|| eyre::eyre!("套利合约部署收据缺少合约地址"))
+    }
+
+    /// 在当前 fork 状态上真实执行一笔 `executeArbWithFlash`，调用方负责快照/回滚和模拟 `owner`
+    async fn execute_flash_arb(
+        &self,
+        owner: Address,
+        contract: Option<Address>,
+        params: ArbParams,
+        ctx: &SimulateCtx,
+    ) -> Result<SimulateResult> {
+        let simulation_start = Instant::now();
+
+        for pre_tx in &ctx.pre_txs {
+            self.apply_pre_tx(pre_tx).await?;
+        }
+
+        let owner_balance = self.get_balance(owner, Address::zero()).await.unwrap_or_default();
+        if owner_balance < FLASH_ARB_GAS_FUNDING {
+            self.set_balance(owner, FLASH_ARB_GAS_FUNDING).await?;
+        }
+        let contract = match contract {
+            Some(contract) => contract,
+            None => self.deploy_arb_contract(owner).await?,
+        };
+
+        let profit_token = params.profit_token;
+        let balance_before = self
+            .get_balance(owner, profit_token)
+            .await
+            .ok_or_else(|| eyre::eyre!("无法读取 owner 的利润代币余额"))?;
+
+        let mut request = AvaxArbExecutor::new(contract, self.provider.clone())
+            .execute_arb_with_flash(params)
+            .tx;
+        request.set_from(owner);
+
+        // 先 eth_call 取得回滚原因（NotProfitable、还款不足等），回滚时不再发送
+        if let Err(e) = self.provider.call(&request, None).await {
+            return Err(simulation_error(self.name(), e).wrap_err("闪电贷套利模拟失败"));
+        }
+        let receipt = self
+            .provider
+            .send_transaction(request, None)
+            .await?
+            .await?
+            .ok_or_else(|| eyre::eyre!("闪电贷套利交易无收据"))?;
+        eyre::ensure!(receipt.status == Some(1u64.into()), "闪电贷套利交易回滚");
+
+        let balance_after = self
+            .get_balance(owner, profit_token)
+            .await
+            .ok_or_else(|| eyre::eyre!("无法读取 owner 的利润代币余额"))?;
+        let profit = checked_amount(i128_from_delta(balance_after, balance_before))?;
+
+        let gas_used = receipt.gas_used.unwrap_or_default();
+        let gas_price = receipt.effective_gas_price.unwrap_or(ctx.epoch.base_fee);
+        debug!("闪电贷套利模拟耗时: {:?}", simulation_start.elapsed());
+
+        Ok(SimulateResult {
+            transaction_hash: receipt.transaction_hash,
+            gas_used,
+            gas_price,
+            balance_changes: vec![BalanceChange {
+                address: owner,
+                token: profit_token,
+                amount: profit,
+            }],
+            logs: receipt.logs.clone(),
+            receipt,
+            cache_misses: 0,
+        })
+    }
+
+    fn parse_transfer_log(&self, log: &ethers::types::Log) -> Option<BalanceChange> {
+        // ERC20 Transfer 事件的签名
+        const TRANSFER_SIGNATURE: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+        
+        if log.topics.is_empty() || format!("{:#x}", log.topics[0]) != TRANSFER_SIGNATURE {
+            return None;
+        }
+        
+        if log.topics.len() != 3 {
+            return None;
+        }
+        
+        // 解析转账事件: Transfer(address indexed from, address indexed to, uint256 value)
+        let _from = Address::from(log.topics[1]);
+        let to = Address::from(log.topics[2]);
+        
+        if log.data.len() >= 32 {
+            let amount = U256::from_big_endian(&log.data[..32]);
+            let amount = match u256_to_i128_checked(amount) {
+                Ok(amount) => amount,
+                Err(e) => {
+                    error!(token = ?log.address, "转账金额超出 i128 范围, 忽略: {}", e);
+                    return None;
+                }
+            };
+            return Some(BalanceChange {
+                address: to,
+                token: log.address,
+                amount,
+            });
+        }
+        
+        None
+    }
+}
+
+/// fork RPC 尚未索引最新区块时 anvil 返回的错误
+fn is_header_not_found(error: &eyre::Report) -> bool {
+    format!("{:#}", error).to_lowercase().contains("header not found")
+}
+
+/// anvil 进程已退出或不再接受连接：按错误类型判断，而不是匹配错误信息
+fn is_anvil_down(error: &eyre::Report) -> bool {
+    error.chain().any(|e| {
+        if let Some(e) = e.downcast_ref::<std::io::Error>() {
+            matches!(e.kind(), std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::ConnectionReset)
+        } else if let Some(ProviderError::HTTPError(e)) = e.downcast_ref::<ProviderError>() {
+            e.is_connect()
+        } else if let Some(e) = e.downcast_ref::<reqwest::Error>() {
+            e.is_connect()
+        } else {
+            false
+        }
+    })
+}
+
+#[async_trait]
+impl Simulator for FoundrySimulator {
+    async fn simulate(&self, tx: Transaction, ctx: SimulateCtx) -> Result<SimulateResult> {
+        match self.simulate_once(tx.clone(), ctx.clone()).await {
+            Err(e) if is_header_not_found(&e) => {
+                // 链头区块可能还未被 fork RPC 索引，回退几个区块后重试一次
+                let block = ctx.fork_block.unwrap_or(ctx.epoch.block_number);
+                let retry_block = block.saturating_sub(HEADER_NOT_FOUND_RETRY_BLOCKS);
+                warn!("fork 区块 {} header not found，回退到区块 {} 重试", block, retry_block);
+
+                self.reset_fork(Some(retry_block)).await?;
+                let mut ctx = ctx;
+                ctx.fork_block = None;
+                let result = self.simulate_once(tx, ctx).await;
+
+                // 回退只针对这一次模拟；恢复到目标区块，免得后续不指定 fork 区块的模拟跑在旧状态上
+                if let Err(error) = self.reset_fork(Some(block)).await {
+                    warn!("fork 恢复到区块 {} 失败: {:#}", block, error);
+                }
+                result
+            }
+            Err(e) if is_anvil_down(&e) => {
+                warn!("anvil 连接失败，重启后重试: {:#}", e);
+                self.respawn_anvil().await.wrap_err("anvil 重启失败")?;
+                self.simulate_once(tx, ctx).await
+            }
+            result => result,
+        }
+    }
+
     fn name(&self) -> &str {
         "FoundrySimulator"
     }
@@ -344,9 +843,12 @@ impl Simulator for FoundrySimulator {
             // 原生 AVAX 余额
             self.provider.get_balance(account, None).await.ok()
         } else {
-            // ERC20 代币余额 - 需要调用 balanceOf 方法
-            // TODO: 实现 ERC20 余额查询
-            None
+            // ERC20 代币余额：balanceOf(account)
+            let mut data = ethers::utils::id("balanceOf(address)").to_vec();
+            data.extend(ethers::abi::encode(&[ethers::abi::Token::Address(account)]));
+            let tx = TransactionRequest::new().to(token).data(data);
+            let output = self.provider.call(&tx.into(), None).await.ok()?;
+            (output.len() >= 32).then(|| U256::from_big_endian(&output[..32]))
         }
     }
 
@@ -364,6 +866,39 @@ impl Simulator for FoundrySimulator {
             .await
             .map_err(Into::into)
     }
+
+    async fn create_access_list(&self, tx: &TransactionRequest) -> Result<AccessList> {
+        let result = self.provider.create_access_list(&tx.clone().into(), None).await?;
+        Ok(result.access_list)
+    }
+
+    async fn warm_block(&self, block: u64) -> Result<()> {
+        self.fork_at(block).await
+    }
+
+    async fn simulate_flash_arb(
+        &self,
+        owner: Address,
+        contract: Option<Address>,
+        params: ArbParams,
+        mut ctx: SimulateCtx,
+    ) -> Result<SimulateResult> {
+        self.recover_dirty_fork().await?;
+        if let Some(fork_block) = ctx.fork_block.take() {
+            self.fork_at(fork_block).await?;
+        }
+
+        // 部署、闪电贷和还款都真实执行，结束后回滚，保证 fork 状态不被污染
+        let snapshot = self.evm_snapshot().await?;
+        let guard = DirtyOnDrop::new(&self.fork_dirty);
+        self.impersonate_account(owner).await?;
+        let result = self.execute_flash_arb(owner, contract, params, &ctx).await;
+        self.stop_impersonating(owner).await?;
+        self.evm_revert(snapshot).await?;
+        guard.disarm();
+
+        result
+    }
 }
 
 impl Drop for FoundrySimulator {
@@ -442,4 +977,22 @@ impl Simulator for ReplaySimulator {
     async fn estimate_gas(&self, tx: &Transaction) -> Result<U256> {
         self.foundry_sim.estimate_gas(tx).await
     }
+
+    async fn create_access_list(&self, tx: &TransactionRequest) -> Result<AccessList> {
+        self.foundry_sim.create_access_list(tx).await
+    }
+
+    async fn warm_block(&self, block: u64) -> Result<()> {
+        self.foundry_sim.warm_block(block).await
+    }
+
+    async fn simulate_flash_arb(
+        &self,
+        owner: Address,
+        contract: Option<Address>,
+        params: ArbParams,
+        ctx: SimulateCtx,
+    ) -> Result<SimulateResult> {
+        self.foundry_sim.simulate_flash_arb(owner, contract, params, ctx).await
+    }
 }
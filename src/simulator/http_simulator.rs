@@ -2,13 +2,21 @@ use async_trait::async_trait;
 use eyre::Result;
 use ethers::{
     providers::{Http, Provider, Middleware},
-    types::{Address, Block, Transaction, TransactionReceipt, U256, H256, BlockId},
+    types::{
+        transaction::eip2930::AccessList, Address, Block, Transaction, TransactionReceipt, TransactionRequest, U256, H256,
+        BlockId,
+    },
     utils::parse_ether,
 };
 use std::sync::Arc;
 use tracing::warn;
 
-use super::{BalanceChange, SimulateCtx, SimulateResult, Simulator};
+use crate::utils::math::{i128_from_delta, u256_to_i128_checked};
+
+use super::{
+    checked_amount, effective_gas_price, revert::simulation_error, warn_on_unexpected_chain_id, BalanceChange, SimulateCtx, SimulateResult, Simulator, AVALANCHE_FUJI_CHAIN_ID,
+    AVALANCHE_MAINNET_CHAIN_ID,
+};
 
 #[derive(Clone)]
 pub struct HttpSimulator {
@@ -33,11 +41,15 @@ impl HttpSimulator {
     }
 
     pub async fn new_avalanche_mainnet(rpc_url: impl AsRef<str>) -> Result<Self> {
-        Self::new(rpc_url, Some(43114)).await // Avalanche C-Chain mainnet
+        let simulator = Self::new(rpc_url, None).await?;
+        warn_on_unexpected_chain_id(simulator.chain_id, AVALANCHE_MAINNET_CHAIN_ID);
+        Ok(simulator)
     }
 
     pub async fn new_avalanche_fuji(rpc_url: impl AsRef<str>) -> Result<Self> {
-        Self::new(rpc_url, Some(43113)).await // Avalanche C-Chain testnet (Fuji)
+        let simulator = Self::new(rpc_url, None).await?;
+        warn_on_unexpected_chain_id(simulator.chain_id, AVALANCHE_FUJI_CHAIN_ID);
+        Ok(simulator)
     }
 
     pub async fn max_budget(&self) -> U256 {
@@ -70,7 +82,7 @@ impl HttpSimulator {
             balance_changes.push(BalanceChange {
                 address: tx.from,
                 token: Address::zero(), // Native AVAX
-                amount: -(gas_cost.as_u128() as i128),
+                amount: checked_amount(i128_from_delta(U256::zero(), gas_cost))?,
             });
         }
 
@@ -81,7 +93,7 @@ impl HttpSimulator {
                 balance_changes.push(BalanceChange {
                     address: tx.from,
                     token: Address::zero(),
-                    amount: -(value.as_u128() as i128),
+                    amount: checked_amount(i128_from_delta(U256::zero(), value))?,
                 });
 
                 // Recipient gains value (if not a contract creation)
@@ -89,7 +101,7 @@ impl HttpSimulator {
                     balance_changes.push(BalanceChange {
                         address: to,
                         token: Address::zero(),
-                        amount: value.as_u128() as i128,
+                        amount: checked_amount(u256_to_i128_checked(value))?,
                     });
                 }
             }
@@ -100,7 +112,7 @@ impl HttpSimulator {
             balance_changes.push(BalanceChange {
                 address: tx.from,
                 token: *token,
-                amount: -(amount.as_u128() as i128),
+                amount: checked_amount(i128_from_delta(U256::zero(), *amount))?,
             });
         }
 
@@ -116,6 +128,7 @@ impl Simulator for HttpSimulator {
     async fn simulate(&self, tx: Transaction, ctx: SimulateCtx) -> Result<SimulateResult> {
         // Note: This is a simplified simulation using call/estimateGas
         // For more accurate simulation, consider using anvil fork mode
+        eyre::ensure!(ctx.pre_txs.is_empty(), "pre_txs require a fork simulator (FoundrySimulator)");
         
         let block_id = if let Some(fork_block) = ctx.fork_block {
             BlockId::Number(fork_block.into())
@@ -126,13 +139,14 @@ impl Simulator for HttpSimulator {
         // Estimate gas
         let gas_estimate = self.provider
             .estimate_gas(&tx.into(), Some(block_id))
-            .await?;
+            .await
+            .map_err(|e| simulation_error(self.name(), e))?;
 
-        // Get current gas price or use provided one
-        let gas_price = if tx.gas_price.is_some() {
-            tx.gas_price.unwrap()
-        } else {
-            self.get_gas_price().await.unwrap_or(ctx.epoch.base_fee)
+        // what the tx pays at inclusion on top of the block's base fee; the node's suggestion only when
+        // neither is known
+        let gas_price = match effective_gas_price(&tx, ctx.epoch.base_fee) {
+            Some(gas_price) => gas_price,
+            None => self.get_gas_price().await?,
         };
 
         // Create a mock receipt (since we can't actually execute without sending)
@@ -196,4 +210,10 @@ impl Simulator for HttpSimulator {
             .await
             .map_err(Into::into)
     }
+
+    async fn create_access_list(&self, tx: &TransactionRequest) -> Result<AccessList> {
+        let result = self.provider.create_access_list(&tx.clone().into(), None).await?;
+        Ok(result.access_list)
+    }
 }
+
@@ -0,0 +1,218 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use ethers::types::{transaction::eip2930::AccessList, Address, Block, Transaction, TransactionRequest, H256, U256};
+use eyre::Result;
+use once_cell::sync::Lazy;
+use prometheus::{register_counter, register_int_counter, register_int_gauge, Counter, IntCounter, IntGauge};
+use tracing::warn;
+
+use super::{SimulateCtx, SimulateResult, Simulator};
+use crate::bindings::avaxarbexecutor::ArbParams;
+
+static SIMULATORS_AVAILABLE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("arb_simulators_available", "Pooled simulators not serving any call right now").unwrap()
+});
+
+static SIMULATOR_POOL_EXHAUSTIONS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "arb_simulator_pool_exhaustions_total",
+        "Times every pooled simulator became busy, so further calls queue behind running ones"
+    )
+    .unwrap()
+});
+
+static SIMULATOR_POOL_EXHAUSTED_SECONDS: Lazy<Counter> = Lazy::new(|| {
+    register_counter!(
+        "arb_simulator_pool_exhausted_seconds_total",
+        "Time spent with every pooled simulator busy"
+    )
+    .unwrap()
+});
+
+/// 模拟器池的占用情况：正在处理调用的模拟器数，以及全部占满的起始时间
+#[derive(Debug)]
+pub struct PoolUsage {
+    size: usize,
+    warn_after: Duration,
+    state: Mutex<UsageState>,
+}
+
+#[derive(Debug, Default)]
+struct UsageState {
+    busy: usize,
+    exhausted_since: Option<Instant>,
+    warned: bool,
+}
+
+impl PoolUsage {
+    /// `size` 个模拟器的池；全部占满超过 `warn_after` 时告警
+    pub fn new(size: usize, warn_after: Duration) -> Arc<Self> {
+        SIMULATORS_AVAILABLE.set(size as i64);
+        Arc::new(Self {
+            size,
+            warn_after,
+            state: Mutex::new(UsageState::default()),
+        })
+    }
+
+    fn acquire(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        state.busy += 1;
+        SIMULATORS_AVAILABLE.set(self.size.saturating_sub(state.busy) as i64);
+        if state.busy == self.size {
+            SIMULATOR_POOL_EXHAUSTIONS.inc();
+            state.exhausted_since = Some(now);
+            state.warned = false;
+        }
+    }
+
+    fn release(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(since) = state.exhausted_since.take() {
+            let exhausted = now.saturating_duration_since(since);
+            SIMULATOR_POOL_EXHAUSTED_SECONDS.inc_by(exhausted.as_secs_f64());
+            if exhausted >= self.warn_after && !state.warned {
+                warn!(size = self.size, ?exhausted, "simulator pool was exhausted, consider a larger --num-simulators");
+            }
+        }
+        state.busy = state.busy.saturating_sub(1);
+        SIMULATORS_AVAILABLE.set(self.size.saturating_sub(state.busy) as i64);
+    }
+
+    /// 调用开始时检查：已占满超过 `warn_after` 时每次占满只告警一次，不必等到释放
+    fn check(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        let Some(since) = state.exhausted_since else {
+            return;
+        };
+        let exhausted = now.saturating_duration_since(since);
+        if exhausted >= self.warn_after && !state.warned {
+            state.warned = true;
+            warn!(size = self.size, ?exhausted, "simulator pool exhausted, calls are queueing");
+        }
+    }
+}
+
+/// 统计池中模拟器的占用：有调用在执行时视为占用，并发的多次调用只算一次
+pub struct MeteredSimulator {
+    inner: Box<dyn Simulator>,
+    usage: Arc<PoolUsage>,
+    in_flight: AtomicUsize,
+}
+
+/// 调用结束（包括被取消）时释放占用
+struct InFlight<'a>(&'a MeteredSimulator);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if self.0.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.0.usage.release(Instant::now());
+        }
+    }
+}
+
+impl MeteredSimulator {
+    pub fn new(inner: Box<dyn Simulator>, usage: Arc<PoolUsage>) -> Self {
+        Self {
+            inner,
+            usage,
+            in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    fn track(&self) -> InFlight<'_> {
+        let now = Instant::now();
+        if self.in_flight.fetch_add(1, Ordering::AcqRel) == 0 {
+            self.usage.acquire(now);
+        } else {
+            self.usage.check(now);
+        }
+        InFlight(self)
+    }
+}
+
+#[async_trait]
+impl Simulator for MeteredSimulator {
+    async fn simulate(&self, tx: Transaction, ctx: SimulateCtx) -> Result<SimulateResult> {
+        let _in_flight = self.track();
+        self.inner.simulate(tx, ctx).await
+    }
+
+    async fn get_balance(&self, account: Address, token: Address) -> Option<U256> {
+        let _in_flight = self.track();
+        self.inner.get_balance(account, token).await
+    }
+
+    async fn get_block(&self, block_number: Option<u64>) -> Option<Block<H256>> {
+        let _in_flight = self.track();
+        self.inner.get_block(block_number).await
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn max_gas_limit(&self) -> U256 {
+        self.inner.max_gas_limit()
+    }
+
+    async fn estimate_gas(&self, tx: &Transaction) -> Result<U256> {
+        let _in_flight = self.track();
+        self.inner.estimate_gas(tx).await
+    }
+
+    async fn create_access_list(&self, tx: &TransactionRequest) -> Result<AccessList> {
+        let _in_flight = self.track();
+        self.inner.create_access_list(tx).await
+    }
+
+    async fn warm_block(&self, block: u64) -> Result<()> {
+        let _in_flight = self.track();
+        self.inner.warm_block(block).await
+    }
+
+    async fn simulate_flash_arb(
+        &self,
+        owner: Address,
+        contract: Option<Address>,
+        params: ArbParams,
+        ctx: SimulateCtx,
+    ) -> Result<SimulateResult> {
+        let _in_flight = self.track();
+        self.inner.simulate_flash_arb(owner, contract, params, ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_exhaustion() {
+        let usage = PoolUsage::new(2, Duration::from_secs(1));
+        let start = Instant::now();
+
+        usage.acquire(start);
+        assert!(usage.state.lock().unwrap().exhausted_since.is_none());
+
+        usage.acquire(start);
+        assert_eq!(usage.state.lock().unwrap().exhausted_since, Some(start));
+
+        usage.check(start + Duration::from_millis(500));
+        assert!(!usage.state.lock().unwrap().warned);
+        usage.check(start + Duration::from_secs(2));
+        assert!(usage.state.lock().unwrap().warned);
+
+        usage.release(start + Duration::from_secs(3));
+        let state = usage.state.lock().unwrap();
+        assert_eq!(state.busy, 1);
+        assert!(state.exhausted_since.is_none());
+    }
+}
@@ -1,13 +1,68 @@
 mod foundry_simulator;
 mod http_simulator;
+mod metered_simulator;
+mod revert;
+mod timeout_simulator;
 
 use async_trait::async_trait;
 use eyre::Result;
-use ethers::types::{Address, Block, Transaction, TransactionReceipt, U256, H256};
+use ethers::types::{transaction::eip2930::AccessList, Address, Block, Transaction, TransactionReceipt, TransactionRequest, U256, H256};
+
+use crate::bindings::avaxarbexecutor::ArbParams;
 use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
 
 pub use foundry_simulator::FoundrySimulator;
 pub use http_simulator::HttpSimulator;
+pub use metered_simulator::{MeteredSimulator, PoolUsage};
+pub use revert::{RevertReason, SimulationReverted};
+pub use timeout_simulator::{SimulationTimeout, TimeoutSimulator};
+
+pub const AVALANCHE_MAINNET_CHAIN_ID: u64 = 43114;
+pub const AVALANCHE_FUJI_CHAIN_ID: u64 = 43113;
+
+/// The chain id always comes from the RPC; this only flags a constructor used against the wrong network.
+pub(crate) fn warn_on_unexpected_chain_id(actual: u64, expected: u64) {
+    if actual != expected {
+        warn!(actual, expected, "RPC chain id differs from the expected network, using the RPC's chain id");
+    }
+}
+
+/// Build a fork simulator backed by anvil. If anvil is unavailable and `allow_http_fallback` is set,
+/// degrade to an `HttpSimulator` against `fork_url` with a warning instead of failing.
+pub async fn new_fork_simulator(
+    fork_url: String,
+    anvil_port: Option<u16>,
+    fork_block: Option<u64>,
+    allow_http_fallback: bool,
+) -> Result<Box<dyn Simulator>> {
+    match FoundrySimulator::check_anvil() {
+        Ok(_) => Ok(Box::new(FoundrySimulator::new(fork_url, anvil_port, fork_block).await?)),
+        Err(e) if allow_http_fallback => {
+            warn!("{e:#}; falling back to HttpSimulator");
+            Ok(Box::new(HttpSimulator::new(&fork_url, None).await?))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Reject balance changes that don't fit in i128 with a logged error instead of wrapping them.
+pub(crate) fn checked_amount(amount: Result<i128>) -> Result<i128> {
+    amount.map_err(|e| {
+        error!("balance change out of i128 range: {}", e);
+        e
+    })
+}
+
+/// Gas price `tx` pays when included on top of a block with `base_fee`: its legacy price as is, or the base
+/// fee plus its tip capped at its max fee. A tx without a price pays just the base fee; `None` when that is
+/// unknown too.
+pub fn effective_gas_price(tx: &Transaction, base_fee: U256) -> Option<U256> {
+    if let Some(max_fee) = tx.max_fee_per_gas {
+        return Some(max_fee.min(base_fee + tx.max_priority_fee_per_gas.unwrap_or_default()));
+    }
+    tx.gas_price.or(Some(base_fee).filter(|fee| !fee.is_zero()))
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimulateResult {
@@ -61,6 +116,8 @@ pub struct SimulateCtx {
     pub override_balances: Vec<(Address, Address, U256)>, // (account, token, balance)
     pub flashloan_amount: Option<(Address, U256)>, // (token, amount)
     pub fork_block: Option<u64>,
+    /// 在被模拟交易之前先执行的交易（例如待回跑的 mempool 交易），只有 fork 模拟器支持
+    pub pre_txs: Vec<Transaction>,
 }
 
 impl SimulateCtx {
@@ -70,6 +127,7 @@ impl SimulateCtx {
             override_balances: Vec::new(),
             flashloan_amount: None,
             fork_block: None,
+            pre_txs: Vec::new(),
         }
     }
 
@@ -88,6 +146,11 @@ impl SimulateCtx {
         self
     }
 
+    pub fn with_pre_tx(&mut self, tx: Transaction) -> &mut Self {
+        self.pre_txs.push(tx);
+        self
+    }
+
     pub fn with_base_fee(&mut self, base_fee: U256) -> &mut Self {
         self.epoch.base_fee = base_fee;
         self
@@ -111,4 +174,59 @@ pub trait Simulator: Sync + Send {
 
     /// Estimate gas for a transaction
     async fn estimate_gas(&self, tx: &Transaction) -> Result<U256>;
+
+    /// EIP-2930 access list of the accounts and storage slots `tx` touches, via `eth_createAccessList`
+    /// against this simulator's state.
+    async fn create_access_list(&self, _tx: &TransactionRequest) -> Result<AccessList> {
+        eyre::bail!("{} does not support access lists", self.name())
+    }
+
+    /// Fork at `block` ahead of time, so simulations with `fork_block == block` start from its state instead
+    /// of re-forking each time. A fork simulator keeps the last block warmed and drops it for the next one;
+    /// simulators reading state over RPC have nothing to prepare.
+    async fn warm_block(&self, _block: u64) -> Result<()> {
+        Ok(())
+    }
+
+    /// Run the arb contract's `executeArbWithFlash(params)` end to end as `owner`: the Aave flashloan, the
+    /// swaps in its callback and the repay, in one tx, so reverts and profit match what the contract does
+    /// on chain. Deploys the contract first when `contract` is `None`. The owner's `profit_token` gain is
+    /// reported as its balance change. Needs a fork simulator.
+    async fn simulate_flash_arb(
+        &self,
+        _owner: Address,
+        _contract: Option<Address>,
+        _params: ArbParams,
+        _ctx: SimulateCtx,
+    ) -> Result<SimulateResult> {
+        eyre::bail!("{} does not support flashloan arb simulation", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_gas_price() {
+        let base_fee = U256::from(30);
+        let legacy = Transaction {
+            gas_price: Some(U256::from(50)),
+            ..Default::default()
+        };
+        assert_eq!(effective_gas_price(&legacy, base_fee), Some(U256::from(50)));
+
+        let eip1559 = Transaction {
+            max_fee_per_gas: Some(U256::from(40)),
+            max_priority_fee_per_gas: Some(U256::from(2)),
+            ..Default::default()
+        };
+        assert_eq!(effective_gas_price(&eip1559, base_fee), Some(U256::from(32)));
+        // the max fee caps base fee + tip
+        assert_eq!(effective_gas_price(&eip1559, U256::from(39)), Some(U256::from(40)));
+
+        let unpriced = Transaction::default();
+        assert_eq!(effective_gas_price(&unpriced, base_fee), Some(base_fee));
+        assert_eq!(effective_gas_price(&unpriced, U256::zero()), None);
+    }
 }
@@ -0,0 +1,130 @@
+use std::fmt;
+
+use ethers::{
+    abi::{self, ParamType},
+    providers::{ProviderError, RpcError},
+    types::{Bytes, U256},
+};
+
+/// `Error(string)` selector, what `require`/`revert("...")` encode.
+const ERROR_STRING: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// `Panic(uint256)` selector, for asserts, overflows and division by zero.
+const PANIC: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Why a simulated call reverted, decoded from its revert data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertReason {
+    /// `revert("...")`, e.g. `UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT`.
+    Message(String),
+    Panic(U256),
+    /// A custom error: its selector and ABI-encoded arguments.
+    CustomError([u8; 4], Bytes),
+    /// No revert data, e.g. a bare `revert()` or out of gas.
+    Unknown,
+}
+
+impl RevertReason {
+    pub fn decode(data: &[u8]) -> Self {
+        let Some((selector, args)) = data.split_first_chunk::<4>() else {
+            return Self::Unknown;
+        };
+        let decoded = |kind: ParamType| abi::decode(&[kind], args).ok().and_then(|mut tokens| tokens.pop());
+
+        match *selector {
+            ERROR_STRING => match decoded(ParamType::String).and_then(|t| t.into_string()) {
+                Some(message) => Self::Message(message),
+                None => Self::CustomError(*selector, Bytes::from(args.to_vec())),
+            },
+            PANIC => match decoded(ParamType::Uint(256)).and_then(|t| t.into_uint()) {
+                Some(code) => Self::Panic(code),
+                None => Self::CustomError(*selector, Bytes::from(args.to_vec())),
+            },
+            _ => Self::CustomError(*selector, Bytes::from(args.to_vec())),
+        }
+    }
+
+    /// The revert reason of a failed `eth_call` / `eth_estimateGas`, `None` if it didn't revert (e.g. the
+    /// RPC itself failed). Nodes that drop the revert data still put the message in the error text.
+    pub fn from_provider_error(error: &ProviderError) -> Option<Self> {
+        let response = error.as_error_response()?;
+        if let Some(data) = response.as_revert_data() {
+            return Some(Self::decode(&data));
+        }
+        let message = response.message.strip_prefix("execution reverted")?;
+        let message = message.trim_start_matches(':').trim();
+        Some(if message.is_empty() { Self::Unknown } else { Self::Message(message.to_string()) })
+    }
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Message(message) => write!(f, "{message}"),
+            Self::Panic(code) => write!(f, "panic {code:#x}"),
+            Self::CustomError(selector, args) => write!(f, "custom error 0x{} ({} bytes of args)", hex(selector), args.len()),
+            Self::Unknown => write!(f, "no reason"),
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// A simulated tx reverted. Carried in the error chain so callers can classify the reason.
+#[derive(Debug, Clone)]
+pub struct SimulationReverted {
+    pub simulator: String,
+    pub reason: RevertReason,
+}
+
+impl fmt::Display for SimulationReverted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: execution reverted: {}", self.simulator, self.reason)
+    }
+}
+
+impl std::error::Error for SimulationReverted {}
+
+/// Turn a failed simulation call into `SimulationReverted` when it is a revert, a plain error otherwise.
+pub(crate) fn simulation_error(simulator: &str, error: ProviderError) -> eyre::Report {
+    match RevertReason::from_provider_error(&error) {
+        Some(reason) => SimulationReverted {
+            simulator: simulator.to_string(),
+            reason,
+        }
+        .into(),
+        None => error.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::abi::Token;
+
+    use super::*;
+
+    fn encoded(selector: [u8; 4], token: Token) -> Vec<u8> {
+        let mut data = selector.to_vec();
+        data.extend(abi::encode(&[token]));
+        data
+    }
+
+    #[test]
+    fn test_decode_revert_data() {
+        let data = encoded(ERROR_STRING, Token::String("UniswapV2Router: EXPIRED".to_string()));
+        assert_eq!(RevertReason::decode(&data), RevertReason::Message("UniswapV2Router: EXPIRED".to_string()));
+
+        // arithmetic overflow
+        let data = encoded(PANIC, Token::Uint(U256::from(0x11)));
+        assert_eq!(RevertReason::decode(&data), RevertReason::Panic(U256::from(0x11)));
+        assert_eq!(RevertReason::decode(&data).to_string(), "panic 0x11");
+
+        let data = [0xde, 0xad, 0xbe, 0xef, 0x01];
+        let reason = RevertReason::decode(&data);
+        assert_eq!(reason, RevertReason::CustomError([0xde, 0xad, 0xbe, 0xef], Bytes::from(vec![0x01])));
+        assert!(reason.to_string().contains("0xdeadbeef"));
+
+        assert_eq!(RevertReason::decode(&[]), RevertReason::Unknown);
+    }
+}
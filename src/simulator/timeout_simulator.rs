@@ -0,0 +1,108 @@
+use std::{fmt, future::Future, time::Duration};
+
+use async_trait::async_trait;
+use ethers::types::{transaction::eip2930::AccessList, Address, Block, Transaction, TransactionRequest, H256, U256};
+use eyre::Result;
+use once_cell::sync::Lazy;
+use prometheus::{register_int_counter, IntCounter};
+use tracing::warn;
+
+use super::{SimulateCtx, SimulateResult, Simulator};
+use crate::bindings::avaxarbexecutor::ArbParams;
+
+static SIMULATION_TIMEOUTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!("arb_simulation_timeouts_total", "Simulator calls cut off by the simulate timeout").unwrap()
+});
+
+/// 模拟调用超过截止时间时返回的错误，视为暂时性错误
+#[derive(Debug, Clone)]
+pub struct SimulationTimeout {
+    pub simulator: String,
+    pub method: &'static str,
+    pub timeout: Duration,
+}
+
+impl fmt::Display for SimulationTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}::{} timed out after {:?}", self.simulator, self.method, self.timeout)
+    }
+}
+
+impl std::error::Error for SimulationTimeout {}
+
+/// 为内部模拟器的每次调用加上硬性超时，避免卡住的 RPC / anvil 调用阻塞 worker
+pub struct TimeoutSimulator {
+    inner: Box<dyn Simulator>,
+    timeout: Duration,
+}
+
+impl TimeoutSimulator {
+    pub fn new(inner: Box<dyn Simulator>, timeout: Duration) -> Self {
+        Self { inner, timeout }
+    }
+
+    async fn with_timeout<T>(&self, method: &'static str, fut: impl Future<Output = T>) -> Result<T, SimulationTimeout> {
+        tokio::time::timeout(self.timeout, fut).await.map_err(|_| {
+            SIMULATION_TIMEOUTS.inc();
+            let error = SimulationTimeout {
+                simulator: self.inner.name().to_string(),
+                method,
+                timeout: self.timeout,
+            };
+            warn!(simulator = self.inner.name(), method, timeouts = SIMULATION_TIMEOUTS.get(), "{}", error);
+            error
+        })
+    }
+}
+
+#[async_trait]
+impl Simulator for TimeoutSimulator {
+    async fn simulate(&self, tx: Transaction, ctx: SimulateCtx) -> Result<SimulateResult> {
+        self.with_timeout("simulate", self.inner.simulate(tx, ctx)).await?
+    }
+
+    async fn get_balance(&self, account: Address, token: Address) -> Option<U256> {
+        self.with_timeout("get_balance", self.inner.get_balance(account, token))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    async fn get_block(&self, block_number: Option<u64>) -> Option<Block<H256>> {
+        self.with_timeout("get_block", self.inner.get_block(block_number))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn max_gas_limit(&self) -> U256 {
+        self.inner.max_gas_limit()
+    }
+
+    async fn estimate_gas(&self, tx: &Transaction) -> Result<U256> {
+        self.with_timeout("estimate_gas", self.inner.estimate_gas(tx)).await?
+    }
+
+    async fn create_access_list(&self, tx: &TransactionRequest) -> Result<AccessList> {
+        self.with_timeout("create_access_list", self.inner.create_access_list(tx)).await?
+    }
+
+    async fn warm_block(&self, block: u64) -> Result<()> {
+        self.with_timeout("warm_block", self.inner.warm_block(block)).await?
+    }
+
+    async fn simulate_flash_arb(
+        &self,
+        owner: Address,
+        contract: Option<Address>,
+        params: ArbParams,
+        ctx: SimulateCtx,
+    ) -> Result<SimulateResult> {
+        self.with_timeout("simulate_flash_arb", self.inner.simulate_flash_arb(owner, contract, params, ctx))
+            .await?
+    }
+}
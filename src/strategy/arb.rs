@@ -15,19 +15,29 @@ use eyre::{ensure, ContextCompat, Result};
 use itertools::Itertools;
 use object_pool::ObjectPool;
 use simulator::{HttpSimulator, SimulateCtx, Simulator};
-use ethers::types::{Address, TransactionRequest, H256, U64};
+use ethers::types::{transaction::eip2930::AccessList, Address, TransactionRequest, H256, U256, U64};
 use tokio::task::JoinSet;
 use tracing::{debug, info, instrument, Instrument};
 use utils::coin;
 
 use crate::{
+    bindings::avaxarbexecutor::ArbParams,
+    bot::contract_executor::ArbParamsBuilder,
     common::get_latest_block,
     common::search::{golden_section_search_maximize, SearchGoal},
+    config::{
+        builder_payment, check_reserves, direct_pair_spread_bps, gas_token, flashloan_preference_bps, log_decisions, min_spread_bps, own_funds,
+        probe_amounts, rank_pools_by_output, use_access_list,
+    },
+    dex::{PathTradeResult, ReserveCache},
     tools::{Defi, Path, TradeType},
     types::Source,
+    utils::math::u256_to_i128_checked,
     HttpConfig,
 };
 
+use super::decision::{Decision, OpportunityRecord};
+
 #[derive(Clone, Debug, Parser)]
 pub struct Args {
     #[arg(long)]
@@ -46,6 +56,11 @@ pub struct Args {
     #[arg(long, env = "MAX_HOPS", default_value = "2")]
     pub max_hops: usize,
 
+    /// Also print the best path's net profit at every probe size and the largest size it stays profitable
+    /// at, to see how much capital the edge absorbs.
+    #[arg(long)]
+    pub profit_curve: bool,
+
     #[command(flatten)]
     pub http_config: HttpConfig,
 }
@@ -76,7 +91,7 @@ pub async fn run(args: Args) -> Result<()> {
             &args.token_address,
             pool_address,
             gas_limit,
-            sim_ctx,
+            sim_ctx.clone(),
             true,
             Source::Public,
             args.max_hops,
@@ -84,6 +99,26 @@ pub async fn run(args: Args) -> Result<()> {
         .await?;
 
     info!("{result:#?}");
+
+    if args.profit_curve {
+        let trial = &result.best_trial_result;
+        let sizes = probe_amounts(&args.token_address);
+        let curve = arb
+            .defi
+            .profit_curve(&trial.trade_path, sender, &sizes, trial.trade_type, gas_limit, &sim_ctx)
+            .await;
+        for (amount_in, profit) in curve {
+            info!(%amount_in, profit, "profit curve");
+        }
+
+        // the optimum is profitable, so the frontier lies between it and the largest probe
+        let max = sizes.last().copied().unwrap_or(trial.amount_in);
+        let frontier = arb
+            .defi
+            .max_profitable_size(&trial.trade_path, sender, trial.amount_in, max, trial.trade_type, gas_limit, &sim_ctx)
+            .await;
+        info!(optimum = %trial.amount_in, ?frontier, "max profitable size");
+    }
     Ok(())
 }
 
@@ -96,6 +131,11 @@ pub struct ArbResult {
     pub cache_misses: u64,
     pub source: Source,
     pub tx_data: TransactionRequest,
+    pub access_list: Option<AccessList>,
+    /// Owed to the relay's block builder out of the profit, in the profit's units; zero when none is configured.
+    pub builder_payment: U256,
+    /// Breakdown of the chosen trade, logged with the final decision once the tx is dry-run.
+    pub record: OpportunityRecord,
 }
 
 pub struct Arb {
@@ -121,64 +161,66 @@ impl Arb {
         max_hops: usize,
     ) -> Result<ArbResult> {
         let gas_price = 25_000_000_000u64; // 25 gwei default for AVAX
+        let quoted_block = sim_ctx.epoch.block_number;
 
         let (ctx, create_trial_ctx_duration) = {
             let timer = Instant::now();
-            let ctx = Arc::new(
-                TrialCtx::new(
-                    self.defi.clone(),
-                    sender,
-                    token_address,
-                    pool_address,
-                    gas_limit,
-                    sim_ctx,
-                    max_hops,
-                )
-                .await?,
-            );
+            let ctx = TrialCtx::new(
+                self.defi.clone(),
+                sender,
+                token_address,
+                pool_address,
+                gas_limit,
+                sim_ctx,
+                max_hops,
+            )
+            .await
+            .inspect_err(|error| OpportunityRecord::new(token_address).log(Decision::Skip, &format!("{error:#}")))?;
 
-            (ctx, timer.elapsed())
+            (Arc::new(ctx), timer.elapsed())
         };
 
-        // Grid search
-        let starting_grid = 1_000_000_000_000_000u64; // 0.001 AVAX (18 decimals)
+        // Grid search over the token's probe sizes
+        let probe_amounts = probe_amounts(token_address);
+        ensure!(!probe_amounts.is_empty(), "no probe amounts configured for {}", token_address);
         let mut cache_misses = 0;
         let (mut max_trial_res, grid_search_duration) = {
             let timer = Instant::now();
             let mut joinset = JoinSet::new();
-            for inc in 1..11 {
+            for &amount_in in &probe_amounts {
                 let ctx = ctx.clone();
-                let grid = starting_grid.checked_mul(10u64.pow(inc)).context("Grid overflow")?;
-
-                joinset.spawn(async move { ctx.trial(grid).await }.in_current_span());
+                joinset.spawn(async move { ctx.trial(amount_in).await }.in_current_span());
             }
 
-            let mut max_trial_res = TrialResult::default();
+            // unprofitable trials still compete, so a skipped opportunity reports its closest miss
+            let mut max_trial_res: Option<TrialResult> = None;
             while let Some(Ok(trial_res)) = joinset.join_next().await {
                 // debug!(?trial_res, "Grid searching");
                 if let Ok(trial_res) = trial_res {
                     if trial_res.cache_misses > cache_misses {
                         cache_misses = trial_res.cache_misses;
                     }
-                    if trial_res > max_trial_res {
-                        max_trial_res = trial_res;
+                    if max_trial_res.as_ref().map_or(true, |max| trial_res > *max) {
+                        max_trial_res = Some(trial_res);
                     }
                 }
             }
-            (max_trial_res, timer.elapsed())
+            (max_trial_res.unwrap_or_default(), timer.elapsed())
         };
 
+        if max_trial_res.profit.is_zero() {
+            self.record(&max_trial_res, quoted_block).await.log(Decision::Skip, "no profitable grid");
+        }
         ensure!(
-            max_trial_res.profit > 0,
+            !max_trial_res.profit.is_zero(),
             "cache_misses: {}. No profitable grid found",
             cache_misses
         );
 
         let gss_duration = if use_gss {
-            // GSS
+            // GSS between the probes around the best one
             let timer = Instant::now();
-            let upper_bound = max_trial_res.amount_in.saturating_mul(10);
-            let lower_bound = max_trial_res.amount_in.saturating_div(10);
+            let (lower_bound, upper_bound) = gss_bounds(&probe_amounts, max_trial_res.amount_in);
 
             let goal = TrialGoal;
             let (_, _, trial_res) = golden_section_search_maximize(lower_bound, upper_bound, goal, &ctx).await;
@@ -194,8 +236,11 @@ impl Arb {
             None
         };
 
+        if max_trial_res.profit.is_zero() {
+            self.record(&max_trial_res, quoted_block).await.log(Decision::Skip, "no profitable trade path");
+        }
         ensure!(
-            max_trial_res.profit > 0,
+            !max_trial_res.profit.is_zero(),
             "cache_misses: {}. No profitable trade path found",
             cache_misses
         );
@@ -203,21 +248,51 @@ impl Arb {
         let TrialResult {
             amount_in,
             trade_path,
+            trade_type,
             profit,
             ..
         } = &max_trial_res;
 
+        // the gate is on what is left once the builder is paid
+        let builder_payment = builder_payment(*profit);
+        if builder_payment >= *profit {
+            self.record(&max_trial_res, quoted_block)
+                .await
+                .log(Decision::Skip, &format!("profit doesn't cover the builder payment of {builder_payment}"));
+        }
+        ensure!(
+            builder_payment < *profit,
+            "profit {} doesn't cover the builder payment {}",
+            profit,
+            builder_payment
+        );
+
         let mut source = source;
         if source.deadline().is_some() {
             source = source.with_arb_found_time(utils::current_time_ms());
         }
-        // TODO make bid_amount configurable
-        source = source.with_bid_amount(*profit / 10 * 9);
+        let bid_amount = if builder_payment.is_zero() { *profit / 10 * 9 } else { builder_payment };
+        source = source.with_bid_amount(bid_amount);
+
+        if check_reserves() {
+            self.defi.log_reserve_divergences(trade_path, quoted_block).await;
+        }
 
-        let tx_data = self
+        let record = self.record(&max_trial_res, quoted_block).await;
+        let (tx_data, access_list) = self
             .defi
-            .build_final_tx_data(sender, *amount_in, trade_path, gas_limit, gas_price, source)
-            .await?;
+            .build_final_tx_data(
+                sender,
+                *amount_in,
+                trade_path,
+                *trade_type,
+                gas_limit,
+                gas_price,
+                source,
+                use_access_list(),
+            )
+            .await
+            .inspect_err(|error| record.log(Decision::Skip, &format!("build tx failed: {error:#}")))?;
 
         Ok(ArbResult {
             create_trial_ctx_duration,
@@ -227,8 +302,50 @@ impl Arb {
             cache_misses,
             source,
             tx_data,
+            access_list,
+            builder_payment,
+            record,
         })
     }
+
+    /// `executeArbWithFlash` params for `trial`: flashloan its `amount_in` of its token, swap it through its
+    /// path with reserves at `block`, pay the block builder `builder_payment_wei` and keep the profit in the
+    /// same token.
+    pub async fn flash_arb_params(&self, trial: &TrialResult, block: u64, builder_payment_wei: U256) -> Result<ArbParams> {
+        let token = trial.token_address.parse::<Address>()?;
+        let hops = self.defi.v2_hops(&trial.trade_path, block).await?;
+        Ok(ArbParamsBuilder::new(token, trial.amount_in, token)
+            .add_v2_path(&hops)?
+            .pay_coinbase(gas_token().parse()?, builder_payment_wei)
+            .build())
+    }
+
+    /// The decision record of `trial`. The price impact costs a reserves read per hop, so it is only
+    /// computed while decision logging is on.
+    async fn record(&self, trial: &TrialResult, block: u64) -> OpportunityRecord {
+        let price_impact_bps = if log_decisions() && !trial.trade_path.is_empty() {
+            self.defi.price_impact_bps(&trial.trade_path, trial.amount_in, block).await.ok()
+        } else {
+            None
+        };
+        OpportunityRecord::from_trial(trial, price_impact_bps)
+    }
+}
+
+/// Search bounds around the best probe: its neighbours in the grid, or a decade beyond the grid's ends.
+fn gss_bounds(probe_amounts: &[U256], best: U256) -> (U256, U256) {
+    let lower = probe_amounts
+        .iter()
+        .rev()
+        .find(|&&amount| amount < best)
+        .copied()
+        .unwrap_or(best / 10);
+    let upper = probe_amounts
+        .iter()
+        .find(|&&amount| amount > best)
+        .copied()
+        .unwrap_or(best.saturating_mul(U256::from(10)));
+    (lower, upper)
 }
 
 pub struct TrialCtx {
@@ -252,9 +369,58 @@ impl TrialCtx {
         sim_ctx: SimulateCtx,
         max_hops: usize,
     ) -> Result<Self> {
-        // For circular arbitrage, we just need paths that start and end with the same token
-        let arbitrage_paths = defi.find_sell_paths_with_hops(token_address, max_hops).await?;
-        ensure!(!arbitrage_paths.is_empty(), "no arbitrage paths found for {}", token_address);
+        let block = sim_ctx.epoch.block_number;
+        let mut cache = ReserveCache::new();
+
+        // The obvious 2-leg arb across two venues' WAVAX pools skips the full search when it clears the bar
+        let direct_paths = match direct_pair_spread_bps() {
+            Some(min_bps) => defi
+                .find_direct_pair_paths(token_address, min_bps, block, &mut cache)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|path| pool_address.is_none() || path.contains_pool(pool_address))
+                .collect(),
+            None => vec![],
+        };
+
+        let arbitrage_paths = if !direct_paths.is_empty() {
+            debug!(token = token_address, paths = direct_paths.len(), "direct pair arb, skipping full routing");
+            direct_paths
+        } else {
+            // For circular arbitrage, we just need paths that start and end with the same token
+            let mut arbitrage_paths = if rank_pools_by_output() {
+                // rank the first hop at the middle of the probe grid, the sizes the search starts from
+                let amounts = probe_amounts(token_address);
+                let amount_in = amounts.get(amounts.len() / 2).copied().unwrap_or_else(|| U256::exp10(18));
+                defi.find_sell_paths_ranked(token_address, max_hops, amount_in, &sim_ctx).await?
+            } else {
+                defi.find_sell_paths_with_hops(token_address, max_hops).await?
+            };
+            ensure!(!arbitrage_paths.is_empty(), "no arbitrage paths found for {}", token_address);
+
+            if let Some(min_spread_bps) = min_spread_bps() {
+                defi.prefetch_reserves(&arbitrage_paths, block, &mut cache).await;
+                let mut kept = Vec::with_capacity(arbitrage_paths.len());
+                for path in arbitrage_paths {
+                    match defi.path_spread_bps(&path, block, &mut cache).await {
+                        Ok(spread_bps) if spread_bps < min_spread_bps as f64 => {
+                            debug!(?path, spread_bps, min_spread_bps, "path below spread floor");
+                        }
+                        // paths whose spread can't be read from reserves are left to the simulation
+                        _ => kept.push(path),
+                    }
+                }
+                arbitrage_paths = kept;
+                ensure!(
+                    !arbitrage_paths.is_empty(),
+                    "no paths above the {} bps spread floor for {}",
+                    min_spread_bps,
+                    token_address
+                );
+            }
+            arbitrage_paths
+        };
 
         // Use the same paths for both buy and sell since we're doing circular arbitrage
         let buy_paths = arbitrage_paths.clone();
@@ -286,12 +452,12 @@ impl TrialCtx {
         name = "trial",
         skip_all,
         fields(
-            in = %format!("{:<15}", (amount_in as f64 / 1_000_000_000_000_000_000.0)),
+            in = %format!("{:<15}", coin::wei_to_avax(amount_in)),
             len = %format!("{:<2}", self.buy_paths.len()),
             action="init"
         )
     )]
-    pub async fn trial(&self, amount_in: u64) -> Result<TrialResult> {
+    pub async fn trial(&self, amount_in: U256) -> Result<TrialResult> {
         tracing::Span::current().record("action", "buy");
 
         let timer = Instant::now();
@@ -335,67 +501,89 @@ impl TrialCtx {
         );
 
         tracing::Span::current().record("action", "sell");
-        let best_trade_res = self
-            .defi
-            .find_best_path_exact_in(
-                &trade_paths,
-                self.sender,
-                amount_in,
-                TradeType::Flashloan,
-                self.gas_limit,
-                &self.sim_ctx,
-            )
-            .await?;
+        let best_trade = |trade_type| {
+            self.defi
+                .find_best_path_exact_in(&trade_paths, self.sender, amount_in, trade_type, self.gas_limit, &self.sim_ctx)
+        };
+        let (trade_type, best_trade_res) = if own_funds() {
+            match tokio::join!(best_trade(TradeType::Flashloan), best_trade(TradeType::Swap)) {
+                (Ok(flashloan), Ok(own)) => {
+                    if prefer_own_funds(flashloan.profit(), own.profit(), amount_in, flashloan_preference_bps()) {
+                        (TradeType::Swap, own)
+                    } else {
+                        (TradeType::Flashloan, flashloan)
+                    }
+                }
+                (Ok(flashloan), Err(_)) => (TradeType::Flashloan, flashloan),
+                (Err(_), Ok(own)) => (TradeType::Swap, own),
+                (Err(error), Err(_)) => return Err(error),
+            }
+        } else {
+            (TradeType::Flashloan, best_trade(TradeType::Flashloan).await?)
+        };
 
         let sell_elapsed = timer.elapsed();
-        debug!(token_address = ?self.token_address, result = %best_trade_res, ?buy_elapsed, ?sell_elapsed, "trial result");
-
-        let profit = best_trade_res.profit();
-        if profit <= 0 {
-            return Ok(TrialResult::default());
-        }
+        debug!(token_address = ?self.token_address, result = %best_trade_res, ?trade_type, ?buy_elapsed, ?sell_elapsed, "trial result");
 
-        let result = TrialResult::new(
-            &self.token_address,
-            amount_in,
-            profit as u64,
-            best_trade_res.path,
-            best_trade_res.cache_misses,
-        );
-
-        Ok(result)
+        Ok(TrialResult::new(&self.token_address, trade_type, best_trade_res))
     }
 }
 
+/// Whether an own-funds route netting `own_funds_profit` should be traded over a flashloan netting
+/// `flashloan_profit`: only when it nets more by over `preference_bps` of `amount_in`, since the flashloan
+/// keeps no principal at risk.
+fn prefer_own_funds(flashloan_profit: i128, own_funds_profit: i128, amount_in: U256, preference_bps: u32) -> bool {
+    let margin = u256_to_i128_checked(amount_in)
+        .unwrap_or(i128::MAX)
+        .saturating_mul(preference_bps as i128)
+        / 10_000;
+    own_funds_profit > flashloan_profit.saturating_add(margin)
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct TrialResult {
     pub token_address: String,
-    pub amount_in: u64,
-    pub profit: u64,
+    pub amount_in: U256,
+    /// Net profit, clamped to zero for losing trials.
+    pub profit: U256,
     pub trade_path: Path,
+    /// Flashloan, or `Swap` for the sender's own funds.
+    pub trade_type: TradeType,
     pub cache_misses: u64,
+    pub amount_out: U256,
+    pub gas_cost: i64,
+    pub flashloan_premium: U256,
+    /// Net profit, negative for losing trials.
+    pub net_profit: i128,
 }
 
+/// Ordered by profit; among losing trials the smallest loss is the best.
 impl PartialOrd for TrialResult {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.profit.partial_cmp(&other.profit)
+        (self.profit, self.net_profit).partial_cmp(&(other.profit, other.net_profit))
     }
 }
 
 impl PartialEq for TrialResult {
     fn eq(&self, other: &Self) -> bool {
-        self.profit == other.profit
+        (self.profit, self.net_profit) == (other.profit, other.net_profit)
     }
 }
 
 impl TrialResult {
-    pub fn new(token_address: &str, amount_in: u64, profit: u64, trade_path: Path, cache_misses: u64) -> Self {
+    pub fn new(token_address: &str, trade_type: TradeType, trade_res: PathTradeResult) -> Self {
+        let net_profit = trade_res.profit();
         Self {
             token_address: token_address.to_string(),
-            amount_in,
-            profit,
-            trade_path,
-            cache_misses,
+            amount_in: trade_res.amount_in,
+            profit: U256::from(net_profit.max(0) as u128),
+            trade_path: trade_res.path,
+            trade_type,
+            cache_misses: trade_res.cache_misses,
+            amount_out: trade_res.amount_out,
+            gas_cost: trade_res.gas_cost,
+            flashloan_premium: trade_res.flashloan_premium,
+            net_profit,
         }
     }
 }
@@ -413,8 +601,8 @@ impl fmt::Display for TrialResult {
 pub struct TrialGoal;
 
 #[async_trait]
-impl SearchGoal<TrialCtx, u64, TrialResult> for TrialGoal {
-    async fn evaluate(&self, amount_in: u64, ctx: &TrialCtx) -> (u64, TrialResult) {
+impl SearchGoal<TrialCtx, U256, TrialResult> for TrialGoal {
+    async fn evaluate(&self, amount_in: U256, ctx: &TrialCtx) -> (U256, TrialResult) {
         let trial_res = ctx.trial(amount_in).await.unwrap_or_default();
         (trial_res.profit, trial_res)
     }
@@ -430,6 +618,37 @@ mod tests {
     use super::*;
     use crate::config::tests::{TEST_ATTACKER, TEST_HTTP_URL};
 
+    #[test]
+    fn test_gss_bounds() {
+        let grid = [100u64, 500, 1_000, 5_000].map(U256::from);
+        let bounds = |best: u64| {
+            let (lower, upper) = gss_bounds(&grid, U256::from(best));
+            (lower.as_u64(), upper.as_u64())
+        };
+        assert_eq!(bounds(500), (100, 1_000));
+        assert_eq!(bounds(100), (10, 500));
+        assert_eq!(bounds(5_000), (1_000, 50_000));
+
+        // a 5 WAVAX top probe searches up to 50 WAVAX, past u64::MAX wei
+        let wavax = U256::exp10(18);
+        let grid = [wavax, wavax * 5];
+        assert_eq!(gss_bounds(&grid, wavax * 5), (wavax, wavax * 50));
+    }
+
+    #[test]
+    fn test_prefer_own_funds() {
+        // 10 bps of 20 WAVAX is 0.02 WAVAX
+        let amount_in = U256::exp10(18) * 20;
+        let flashloan = 100_000_000_000_000_000;
+        assert!(!prefer_own_funds(flashloan, flashloan + 20_000_000_000_000_000, amount_in, 10));
+        assert!(prefer_own_funds(flashloan, flashloan + 20_000_000_000_000_001, amount_in, 10));
+        // without a preference the better net wins
+        assert!(prefer_own_funds(flashloan, flashloan + 1, amount_in, 0));
+        assert!(!prefer_own_funds(flashloan, flashloan, amount_in, 0));
+        // a profitable own-funds route still beats a losing flashloan
+        assert!(prefer_own_funds(-1_000, 30_000_000_000_000_000, amount_in, 10));
+    }
+
     #[tokio::test]
     async fn test_find_best_trade_path() {
         mev_logger::init_console_logger_with_directives(None, &["arb=debug"]);
@@ -9,15 +9,46 @@ use ethers::types::{Address, H256};
 
 use crate::types::Source;
 
+#[derive(Clone)]
 pub struct ArbItem {
     pub token: String,
     pub pool_address: Option<Address>,
     pub tx_hash: H256,
     pub sim_ctx: SimulateCtx,
     pub source: Source,
+    /// Attempts already made after transient failures.
+    pub attempts: u32,
+    /// When the opportunity expires from the cache and its insertion order there, so an item taken back off
+    /// the worker channel is cached again as it was.
+    pub expires_at: Instant,
+    pub generation: u64,
+}
+
+/// What makes two opportunities the same: the same token on the same pool at the same block, whichever
+/// source reported it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArbKey {
+    pub token: String,
+    pub pool_address: Option<Address>,
+    pub block: u64,
+}
+
+impl ArbKey {
+    /// Whether this opportunity is on `token`'s `pool_address`, at any block.
+    pub fn is_pool(&self, token: &str, pool_address: Option<Address>) -> bool {
+        self.token == token && self.pool_address == pool_address
+    }
 }
 
 impl ArbItem {
+    pub fn key(&self) -> ArbKey {
+        ArbKey {
+            token: self.token.clone(),
+            pool_address: self.pool_address,
+            block: self.sim_ctx.epoch.block_number,
+        }
+    }
+
     pub fn new(token: String, pool_address: Option<Address>, entry: ArbEntry) -> Self {
         Self {
             token: token.to_string(),
@@ -25,11 +56,14 @@ impl ArbItem {
             tx_hash: entry.hash,
             sim_ctx: entry.sim_ctx,
             source: entry.source,
+            attempts: 0,
+            expires_at: entry.expires_at,
+            generation: entry.generation,
         }
     }
 }
 
-/// The value stored in the HashMap for each token.
+/// The value stored in the HashMap for each token and pool.
 pub struct ArbEntry {
     hash: H256,
     sim_ctx: SimulateCtx,
@@ -63,9 +97,21 @@ impl PartialOrd for HeapItem {
     }
 }
 
-/// A structure to manage ArbItems with uniqueness, reordering, and timed expiration.
+/// Read-only view of a cached entry, for debugging dumps.
+#[derive(Debug, Clone)]
+pub struct ArbEntrySnapshot {
+    pub token: String,
+    pub pool_address: Option<Address>,
+    pub tx_hash: H256,
+    pub source: Source,
+    pub age: Duration,
+    pub expires_in: Duration,
+}
+
+/// A structure to manage ArbItems with uniqueness, reordering, and timed expiration. Entries are unique per
+/// token and pool, so a token's other pools keep their own opportunities.
 pub struct ArbCache {
-    map: HashMap<String, ArbEntry>,
+    map: HashMap<(String, Option<Address>), ArbEntry>,
     heap: BinaryHeap<HeapItem>,
     generation_counter: u64,
     expiration_duration: Duration,
@@ -82,7 +128,7 @@ impl ArbCache {
     }
 
     /// Insert or update an ArbItem.
-    /// If the token already exists, this updates it with a new generation and expiration time.
+    /// If the token and pool already exist, this updates them with a new generation and expiration time.
     pub fn insert(
         &mut self,
         token: String,
@@ -98,7 +144,7 @@ impl ArbCache {
 
         // Insert into the map
         self.map.insert(
-            token.clone(),
+            (token.clone(), pool_address),
             ArbEntry {
                 hash,
                 sim_ctx,
@@ -117,20 +163,53 @@ impl ArbCache {
         });
     }
 
-    /// Attempt to get an ArbItem by token.
+    /// Put an item taken off the worker channel back with its original expiry and insertion order. Dropped when
+    /// it has expired meanwhile or a newer opportunity on the same token and pool was cached since.
+    pub fn reinsert(&mut self, item: ArbItem) -> bool {
+        if item.expires_at <= Instant::now() {
+            return false;
+        }
+        let key = (item.token.clone(), item.pool_address);
+        if self.map.get(&key).is_some_and(|entry| entry.generation > item.generation) {
+            return false;
+        }
+
+        self.map.insert(
+            key,
+            ArbEntry {
+                hash: item.tx_hash,
+                sim_ctx: item.sim_ctx,
+                generation: item.generation,
+                expires_at: item.expires_at,
+                source: item.source,
+            },
+        );
+        self.heap.push(HeapItem {
+            expires_at: item.expires_at,
+            generation: item.generation,
+            token: item.token,
+            pool_address: item.pool_address,
+        });
+        true
+    }
+
+    /// Attempt to get an ArbItem by token and pool.
     #[allow(dead_code)]
-    pub fn get(&self, token: &str) -> Option<(H256, SimulateCtx)> {
-        self.map.get(token).map(|entry| (entry.hash, entry.sim_ctx.clone()))
+    pub fn get(&self, token: &str, pool_address: Option<Address>) -> Option<(H256, SimulateCtx)> {
+        self.map
+            .get(&(token.to_string(), pool_address))
+            .map(|entry| (entry.hash, entry.sim_ctx.clone()))
     }
 
     /// Periodically call this to remove expired entries.
     /// This will pop from the heap until it finds an entry that is not stale and not expired.
-    pub fn remove_expired(&mut self) -> Vec<String> {
-        let mut expired_tokens = Vec::new();
+    pub fn remove_expired(&mut self) -> Vec<(String, Option<Address>)> {
+        let mut expired = Vec::new();
         let now = Instant::now();
         while let Some(top) = self.heap.peek() {
+            let key = (top.token.clone(), top.pool_address);
             // If top is outdated (stale) or expired, pop it and remove from map if needed
-            if let Some(entry) = self.map.get(&top.token) {
+            if let Some(entry) = self.map.get(&key) {
                 if entry.generation != top.generation {
                     // Stale entry, just discard from heap
                     self.heap.pop();
@@ -139,8 +218,8 @@ impl ArbCache {
                 // Matching generation
                 if entry.expires_at <= now {
                     // It's actually expired
-                    expired_tokens.push(top.token.clone());
-                    self.map.remove(&top.token);
+                    self.map.remove(&key);
+                    expired.push(key);
                     self.heap.pop();
                 } else {
                     // The top is not expired and not stale. We can break now.
@@ -151,27 +230,72 @@ impl ArbCache {
                 self.heap.pop();
             }
         }
-        expired_tokens
+        expired
+    }
+
+    /// Current live entries, oldest first.
+    pub fn snapshot(&self) -> Vec<ArbEntrySnapshot> {
+        let now = Instant::now();
+        let mut entries: Vec<_> = self
+            .map
+            .iter()
+            .map(|((token, pool_address), entry)| {
+                let inserted_at = entry.expires_at - self.expiration_duration;
+                ArbEntrySnapshot {
+                    token: token.clone(),
+                    pool_address: *pool_address,
+                    tx_hash: entry.hash,
+                    source: entry.source,
+                    age: now.saturating_duration_since(inserted_at),
+                    expires_in: entry.expires_at.saturating_duration_since(now),
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| b.age.cmp(&a.age));
+        entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Remove and return the most recently inserted live entry, skipping the expiration order `pop_one`
+    /// follows. Used when the worker channel is full and only the freshest work should get through.
+    pub fn pop_newest(&mut self) -> Option<ArbItem> {
+        let now = Instant::now();
+        self.map.retain(|_, entry| entry.expires_at > now);
+
+        let key = self
+            .map
+            .iter()
+            .max_by_key(|(_, entry)| entry.generation)
+            .map(|(key, _)| key.clone())?;
+        // its heap item is now stale and gets discarded lazily
+        let entry = self.map.remove(&key).unwrap();
+
+        let (token, pool_address) = key;
+        Some(ArbItem::new(token, pool_address, entry))
     }
 
     pub fn pop_one(&mut self) -> Option<ArbItem> {
         let now = Instant::now();
         // Keep popping until we find a valid, current entry that's not expired.
         while let Some(top) = self.heap.pop() {
-            if let Some(entry) = self.map.get(&top.token) {
+            let key = (top.token.clone(), top.pool_address);
+            if let Some(entry) = self.map.get(&key) {
                 if entry.generation == top.generation {
-                    // It's the current entry for this token
+                    // It's the current entry for this token and pool
                     if entry.expires_at > now {
                         // It's valid and not expired. We can remove it and return.
-                        let entry = self.map.remove(&top.token).unwrap();
+                        let entry = self.map.remove(&key).unwrap();
                         return Some(ArbItem::new(top.token, top.pool_address, entry));
                     } else {
                         // It's current but expired, remove it from map and continue.
-                        self.map.remove(&top.token);
+                        self.map.remove(&key);
                     }
                 } else {
                     // Stale entry, just continue without touching the map.
-                    // Because a newer entry for this token exists.
+                    // Because a newer entry for this token and pool exists.
                 }
             } else {
                 // The map no longer has this token, meaning it's stale.
@@ -182,3 +306,65 @@ impl ArbCache {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(cache: &mut ArbCache, token: &str) {
+        cache.insert(token.to_string(), None, H256::zero(), SimulateCtx::default(), Source::Public);
+    }
+
+    #[test]
+    fn test_pop_newest_and_oldest() {
+        let mut cache = ArbCache::new(Duration::from_secs(60));
+        insert(&mut cache, "A");
+        insert(&mut cache, "B");
+        insert(&mut cache, "C");
+        // re-inserting A makes it the newest
+        insert(&mut cache, "A");
+
+        assert_eq!(cache.pop_newest().unwrap().token, "A");
+        assert_eq!(cache.pop_one().unwrap().token, "B");
+        assert_eq!(cache.pop_newest().unwrap().token, "C");
+        assert!(cache.pop_newest().is_none());
+        assert!(cache.pop_one().is_none());
+    }
+
+    #[test]
+    fn test_reinsert_keeps_expiry_and_order() {
+        let mut cache = ArbCache::new(Duration::from_secs(60));
+        insert(&mut cache, "A");
+        insert(&mut cache, "B");
+
+        let a = cache.pop_one().unwrap();
+        let expires_at = a.expires_at;
+        assert!(cache.reinsert(a));
+        // still older than B
+        assert_eq!(cache.pop_newest().unwrap().token, "B");
+        let a = cache.pop_newest().unwrap();
+        assert_eq!((a.token.as_str(), a.expires_at), ("A", expires_at));
+
+        // a newer opportunity on the same token wins
+        insert(&mut cache, "A");
+        assert!(!cache.reinsert(a));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_pools_of_a_token_are_kept_apart() {
+        let mut cache = ArbCache::new(Duration::from_secs(60));
+        let (pool_a, pool_b) = (Some(Address::repeat_byte(0xa)), Some(Address::repeat_byte(0xb)));
+        cache.insert("A".to_string(), pool_a, H256::zero(), SimulateCtx::default(), Source::Public);
+        cache.insert("A".to_string(), pool_b, H256::zero(), SimulateCtx::default(), Source::Public);
+        // a second sighting of pool A replaces the first
+        cache.insert("A".to_string(), pool_a, H256::zero(), SimulateCtx::default(), Source::Mempool);
+        assert_eq!(cache.len(), 2);
+
+        assert_eq!(cache.pop_one().unwrap().pool_address, pool_b);
+        let item = cache.pop_one().unwrap();
+        assert_eq!(item.pool_address, pool_a);
+        assert!(item.key().is_pool("A", pool_a));
+        assert!(cache.pop_one().is_none());
+    }
+}
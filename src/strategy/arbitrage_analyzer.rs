@@ -1,32 +1,55 @@
 //! 套利机会分析器 - 负责分析和寻找套利机会
 
 use std::sync::Arc;
-use ethers::types::Address;
+use dex_indexer::types::Protocol;
+use ethers::{
+    providers::{Http, Provider},
+    types::{Address, U256},
+};
 use eyre::Result;
 use object_pool::ObjectPool;
 use tracing::{info, warn};
 
 use crate::{
     strategy::{ArbStrategy, arb::Arb},
-    common::get_latest_block,
-    simulator::{SimulateCtx, SimEpoch, HttpSimulator, Simulator},
+    common::get_latest_epoch,
+    simulator::{SimulateCtx, HttpSimulator, Simulator},
     types::Source,
-    dex::{Defi, TradeType},
-    utils::token_config::TokenConfig,
+    dex::{Defi, Path, PathTradeResult, TradeType},
+    utils::{coin::wei_to_avax, math::u256_to_f64, token_config::TokenConfig},
 };
 
+/// 路径中的单跳（供程序使用，无需解析描述字符串）
+#[derive(Debug, Clone)]
+pub struct OpportunityHop {
+    pub protocol: Protocol,
+    pub pool: Address,
+    pub token_in: String,
+    pub token_out: String,
+}
+
 /// 套利机会结构
+/// `path_description` / `involved_dexes` 仅用于展示；执行器和分析应使用 `path`、`hops` 及原始数量
 #[derive(Debug, Clone)]
 pub struct ArbitrageOpportunity {
     pub token_address: String,
     pub token_name: String,
     pub path_description: String,
     pub involved_dexes: Vec<String>,
-    pub amount_in: u64,
-    pub estimated_profit: u64,
-    pub gas_cost: u64,
-    pub net_profit: u64,
+    pub amount_in: U256,
+    /// 已扣除模拟 gas 成本的利润
+    pub estimated_profit: U256,
+    /// 模拟得到的 gas 费用（wei）：实际 gas_used × 区块 base fee 下的有效 gas 价格
+    pub gas_cost: U256,
+    pub net_profit: U256,
     pub profit_percentage: f64,
+
+    pub path: Path,
+    pub hops: Vec<OpportunityHop>,
+    /// 模拟得到的原始输出数量
+    pub amount_out: U256,
+    /// 模拟得到的原始 gas 费用（wei）
+    pub simulated_gas_cost: i64,
 }
 
 impl ArbitrageOpportunity {
@@ -34,13 +57,15 @@ impl ArbitrageOpportunity {
         token_address: String,
         path_description: String,
         involved_dexes: Vec<String>,
-        amount_in: u64,
-        estimated_profit: u64,
+        trade: &PathTradeResult,
+        estimated_profit: U256,
     ) -> Self {
-        let gas_cost = 1_000_000_000_000_000u64; // 0.001 AVAX估算gas费用
-        let net_profit = estimated_profit.saturating_sub(gas_cost);
-        let profit_percentage = if amount_in > 0 {
-            (net_profit as f64 / amount_in as f64) * 100.0
+        let amount_in = trade.amount_in;
+        // `estimated_profit` 来自 `PathTradeResult::profit()`，已按模拟的 gas 成本扣除，不再重复扣减
+        let gas_cost = U256::from(trade.gas_cost.max(0));
+        let net_profit = estimated_profit;
+        let profit_percentage = if !amount_in.is_zero() {
+            (u256_to_f64(net_profit) / u256_to_f64(amount_in)) * 100.0
         } else {
             0.0
         };
@@ -63,6 +88,20 @@ impl ArbitrageOpportunity {
             gas_cost,
             net_profit,
             profit_percentage,
+            path: trade.path.clone(),
+            hops: trade
+                .path
+                .path
+                .iter()
+                .map(|dex| OpportunityHop {
+                    protocol: dex.protocol(),
+                    pool: dex.pool_address(),
+                    token_in: dex.coin_in_type(),
+                    token_out: dex.coin_out_type(),
+                })
+                .collect(),
+            amount_out: trade.amount_out,
+            simulated_gas_cost: trade.gas_cost,
         }
     }
 
@@ -71,10 +110,10 @@ impl ArbitrageOpportunity {
         println!("💰 代币: {} ({})", self.token_name, self.token_address);
         println!("🔄 路径: {}", self.path_description);
         println!("🏪 涉及DEX: {}", self.involved_dexes.join(", "));
-        println!("💵 交易金额: {:.4} AVAX", self.amount_in as f64 / 1e18);
-        println!("📈 预估利润: {:.4} AVAX", self.estimated_profit as f64 / 1e18);
-        println!("⛽ Gas费用: {:.4} AVAX", self.gas_cost as f64 / 1e18);
-        println!("✨ 净利润: {:.4} AVAX ({:.2}%)", self.net_profit as f64 / 1e18, self.profit_percentage);
+        println!("💵 交易金额: {:.4} AVAX", wei_to_avax(self.amount_in));
+        println!("📈 预估利润: {:.4} AVAX", wei_to_avax(self.estimated_profit));
+        println!("⛽ Gas费用: {:.4} AVAX", wei_to_avax(self.gas_cost));
+        println!("✨ 净利润: {:.4} AVAX ({:.2}%)", wei_to_avax(self.net_profit), self.profit_percentage);
         println!("===============================\n");
     }
 }
@@ -130,21 +169,15 @@ impl ArbitrageAnalyzer {
             }
         };
         
-        // 获取当前区块信息
-        let block_number = match get_latest_block(rpc_url).await {
-            Ok(block) => block,
+        // 获取当前区块信息；gas 成本按该区块真实的 base fee 计算
+        let provider = Arc::new(Provider::<Http>::try_from(rpc_url)?);
+        let epoch = match get_latest_epoch(&provider).await {
+            Ok(epoch) => epoch,
             Err(e) => {
                 warn!("Failed to get latest block: {}", e);
                 return Ok(None);
             }
         };
-        
-        let epoch = SimEpoch {
-            block_number: block_number.as_u64(),
-            block_timestamp: 0,
-            base_fee: ethers::types::U256::from(25_000_000_000u64), // 25 gwei
-            gas_limit: ethers::types::U256::from(30_000_000u64),
-        };
         let sim_ctx = SimulateCtx::new(epoch);
         
         info!("Analyzing arbitrage opportunity for token: {}", token_address);
@@ -165,13 +198,8 @@ impl ArbitrageAnalyzer {
             return Ok(None);
         }
         
-        // 尝试不同的交易金额
-        let test_amounts = [
-            100_000_000_000_000_000u64,   // 0.1 AVAX
-            500_000_000_000_000_000u64,   // 0.5 AVAX  
-            1_000_000_000_000_000_000u64, // 1.0 AVAX
-            5_000_000_000_000_000_000u64, // 5.0 AVAX
-        ];
+        // 尝试该代币配置的交易金额
+        let test_amounts = crate::utils::config::probe_amounts(token_address);
         
         let mut best_opportunity: Option<ArbitrageOpportunity> = None;
         let gas_limit = 300_000u64;
@@ -196,12 +224,12 @@ impl ArbitrageAnalyzer {
                         token_address.to_string(),
                         path_description,
                         involved_dexes,
-                        amount_in,
-                        profit as u64,
+                        &best_result,
+                        U256::from(profit as u128),
                     );
                     
                     // 保留最佳机会
-                    if best_opportunity.is_none() || profit > best_opportunity.as_ref().unwrap().estimated_profit as i128 {
+                    if best_opportunity.as_ref().map_or(true, |best| opportunity.estimated_profit > best.estimated_profit) {
                         best_opportunity = Some(opportunity);
                     }
                     
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+/// Items held back until the block they were included in has enough confirmations. A block has one
+/// confirmation while it is the head, two once the next block is mined, and so on.
+pub struct ConfirmationQueue<T> {
+    confirmations: u64,
+    by_block: BTreeMap<u64, Vec<T>>,
+}
+
+impl<T> ConfirmationQueue<T> {
+    pub fn new(confirmations: u64) -> Self {
+        Self {
+            confirmations,
+            by_block: BTreeMap::new(),
+        }
+    }
+
+    /// Whether items need to wait at all: one confirmation is the head itself.
+    pub fn is_immediate(&self) -> bool {
+        self.confirmations <= 1
+    }
+
+    pub fn push(&mut self, block: u64, item: T) {
+        self.by_block.entry(block).or_default().push(item);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_block.is_empty()
+    }
+
+    /// Remove and return, oldest block first, every item whose block is confirmed at head `latest`.
+    pub fn pop_confirmed(&mut self, latest: u64) -> Vec<T> {
+        let Some(newest_confirmed) = (latest + 1).checked_sub(self.confirmations) else {
+            return vec![];
+        };
+        let pending = self.by_block.split_off(&(newest_confirmed + 1));
+        std::mem::replace(&mut self.by_block, pending).into_values().flatten().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pop_confirmed() {
+        let mut queue = ConfirmationQueue::new(3);
+        assert!(!queue.is_immediate());
+        queue.push(101, "b");
+        queue.push(100, "a");
+        queue.push(101, "c");
+
+        // block 100 has 2 confirmations at head 101
+        assert!(queue.pop_confirmed(101).is_empty());
+        assert_eq!(queue.pop_confirmed(102), ["a"]);
+        assert_eq!(queue.pop_confirmed(110), ["b", "c"]);
+        assert!(queue.is_empty());
+
+        assert!(ConfirmationQueue::<()>::new(1).is_immediate());
+        assert!(ConfirmationQueue::<()>::new(5).pop_confirmed(2).is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use prometheus::{register_int_counter, IntCounter};
+use tracing::debug;
+
+use crate::config::arb_cooldown;
+
+static ARBS_COOLED_DOWN: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "arb_cooldown_skips_total",
+        "Opportunities dropped because their token was on cooldown after a recent arb"
+    )
+    .unwrap()
+});
+
+static COOLDOWNS: Lazy<Mutex<TokenCooldowns>> = Lazy::new(|| Mutex::new(TokenCooldowns::default()));
+
+/// Per token, when its cooldown ends. Unlike the recent arbs window, which only dedups one opportunity,
+/// this holds back every opportunity of the token, whichever tx or pool reports it.
+#[derive(Debug, Default)]
+struct TokenCooldowns {
+    until: HashMap<String, Instant>,
+}
+
+impl TokenCooldowns {
+    /// Cool `token` down for `duration` from `now`. The latest outcome wins, so a failure right after a fill
+    /// shortens the fill's cooldown.
+    fn start(&mut self, token: &str, duration: Duration, now: Instant) {
+        self.until.insert(token.to_string(), now + duration);
+    }
+
+    fn remaining(&mut self, token: &str, now: Instant) -> Option<Duration> {
+        let until = *self.until.get(token)?;
+        if until <= now {
+            self.until.remove(token);
+            return None;
+        }
+        Some(until - now)
+    }
+}
+
+/// Put `token` on the cooldown configured for an arb that was submitted (`success`) or failed to be,
+/// counted from `submitted_at` rather than from when the executors returned.
+pub fn record_arb_outcome(token: &str, success: bool, submitted_at: Instant) {
+    let mut cooldowns = COOLDOWNS.lock().unwrap();
+    match arb_cooldown(success) {
+        Some(duration) => {
+            debug!(token, success, ?duration, "token on cooldown");
+            cooldowns.start(token, duration, submitted_at);
+        }
+        None => {
+            cooldowns.until.remove(token);
+        }
+    }
+}
+
+/// How long `token` stays on cooldown, `None` when its opportunities can go to the workers.
+pub(super) fn remaining(token: &str) -> Option<Duration> {
+    let remaining = COOLDOWNS.lock().unwrap().remaining(token, Instant::now());
+    if remaining.is_some() {
+        ARBS_COOLED_DOWN.inc();
+    }
+    remaining
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cooldown_expires_and_latest_outcome_wins() {
+        let mut cooldowns = TokenCooldowns::default();
+        let start = Instant::now();
+
+        cooldowns.start("a", Duration::from_secs(10), start);
+        assert_eq!(cooldowns.remaining("a", start + Duration::from_secs(4)), Some(Duration::from_secs(6)));
+        assert_eq!(cooldowns.remaining("b", start), None);
+
+        // a failure after the fill replaces its cooldown
+        cooldowns.start("a", Duration::from_secs(2), start + Duration::from_secs(5));
+        assert_eq!(cooldowns.remaining("a", start + Duration::from_secs(6)), Some(Duration::from_secs(1)));
+
+        assert_eq!(cooldowns.remaining("a", start + Duration::from_secs(7)), None);
+        assert!(cooldowns.until.is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+use std::fmt;
+
+use ethers::types::U256;
+use tracing::debug;
+
+use super::arb::TrialResult;
+use crate::{config::log_decisions, dex::TradeType, utils::math::u256_to_i128_checked};
+
+/// What became of an evaluated opportunity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Trade,
+    Skip,
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decision::Trade => write!(f, "trade"),
+            Decision::Skip => write!(f, "skip"),
+        }
+    }
+}
+
+/// Everything that went into trading an opportunity or not: the best path found, its size and the full
+/// profit breakdown. Logged once per opportunity, with the decision, when decision logging is on.
+#[derive(Debug, Clone, Default)]
+pub struct OpportunityRecord {
+    pub token: String,
+    pub path: String,
+    pub trade_type: TradeType,
+    pub amount_in: U256,
+    pub gross_out: U256,
+    pub gas_cost: i64,
+    pub flashloan_premium: U256,
+    pub net_profit: i128,
+    pub price_impact_bps: Option<u64>,
+}
+
+impl OpportunityRecord {
+    /// A record for an opportunity that failed before any path was evaluated.
+    pub fn new(token: &str) -> Self {
+        Self {
+            token: token.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn from_trial(trial: &TrialResult, price_impact_bps: Option<u64>) -> Self {
+        Self {
+            token: trial.token_address.clone(),
+            path: format!("{:?}", trial.trade_path),
+            trade_type: trial.trade_type,
+            amount_in: trial.amount_in,
+            gross_out: trial.amount_out,
+            gas_cost: trial.gas_cost,
+            flashloan_premium: trial.flashloan_premium,
+            net_profit: trial.net_profit,
+            price_impact_bps,
+        }
+    }
+
+    /// Net profit per `amount_in`, in bps.
+    pub fn profit_bps(&self) -> i64 {
+        match u256_to_i128_checked(self.amount_in) {
+            Ok(amount_in) if amount_in > 0 => (self.net_profit.saturating_mul(10_000) / amount_in) as i64,
+            _ => 0,
+        }
+    }
+
+    pub fn log(&self, decision: Decision, reason: &str) {
+        if !log_decisions() {
+            return;
+        }
+        debug!(
+            target: "arb::decision",
+            token = %self.token,
+            path = %self.path,
+            trade_type = ?self.trade_type,
+            amount_in = %self.amount_in,
+            gross_out = %self.gross_out,
+            gas_cost = self.gas_cost,
+            flashloan_premium = %self.flashloan_premium,
+            net_profit = %self.net_profit,
+            profit_bps = self.profit_bps(),
+            price_impact_bps = ?self.price_impact_bps,
+            %decision,
+            reason,
+            "opportunity decision"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profit_bps() {
+        let record = OpportunityRecord {
+            amount_in: U256::from(1_000_000),
+            gross_out: U256::from(1_004_000),
+            gas_cost: 1_000,
+            flashloan_premium: U256::from(900),
+            net_profit: 2_100,
+            ..OpportunityRecord::new("0xtoken")
+        };
+        assert_eq!(record.profit_bps(), 21);
+
+        let losing = OpportunityRecord {
+            net_profit: -5_000,
+            ..record
+        };
+        assert_eq!(losing.profit_bps(), -50);
+        assert_eq!(OpportunityRecord::new("0xtoken").profit_bps(), 0);
+
+        // 20 WAVAX in, beyond u64::MAX wei
+        let large = OpportunityRecord {
+            amount_in: U256::exp10(18) * 20,
+            net_profit: 60_000_000_000_000_000,
+            ..OpportunityRecord::new("0xtoken")
+        };
+        assert_eq!(large.profit_bps(), 30);
+    }
+}
@@ -3,45 +3,88 @@ pub mod contract_arb;
 pub mod transaction_analyzer;
 pub mod arbitrage_analyzer;
 mod arb_cache;
+mod confirmations;
+mod cooldown;
+mod decision;
+mod retry;
 mod worker;
 
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::HashSet,
     str::FromStr,
     sync::Arc,
     time::Duration,
 };
 
-use arb_cache::{ArbCache, ArbItem};
-use async_channel::Sender;
+use arb_cache::{ArbCache, ArbItem, ArbKey};
+use confirmations::ConfirmationQueue;
+use async_channel::{Receiver, Sender};
 use burberry::ActionSubmitter;
 use dex_indexer::types::Protocol;
 use eyre::{ensure, eyre, Result};
 use object_pool::ObjectPool;
+use once_cell::sync::Lazy;
 use rayon::prelude::*;
 use simulator::{ReplaySimulator, SimulateCtx, Simulator};
-use ethers::types::{Address, BlockNumber, Log, TransactionReceipt, H256, U64};
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{Address, BlockNumber, Log, TransactionReceipt, H256, I256, U256, U64},
+};
 use tokio::{
     runtime::{Builder, Handle, RuntimeFlavor},
     task::JoinSet,
 };
-use tracing::{debug, error, info, instrument, warn};
+use tracing::{debug, error, info, info_span, warn, Instrument, Span};
+use retry::RetryQueue;
+use crate::utils::{sampling::LogSampler, seen_cache::SeenCache};
 use worker::Worker;
 
 use crate::{
-    common::get_latest_block,
+    bindings::avaxarbexecutor::AvaxArbExecutor,
+    common::{
+        get_latest_block,
+        webhook::{WebhookEvent, WebhookNotifier},
+    },
+    config::is_wavax,
+    dex::DecodedSwap,
     types::{Action, Event, Source},
 };
 
 use arb::Arb;
+pub use cooldown::record_arb_outcome;
+
+/// Pending tx hashes are remembered this long, so a tx delivered again (reconnect, redundant WS
+/// endpoints) is skipped.
+const PENDING_TX_DEDUP_TTL: Duration = Duration::from_secs(60);
+const PENDING_TX_DEDUP_CAPACITY: usize = 100_000;
+
+/// Opportunities sent to the workers are remembered this long, so the same one reported again (by the
+/// other source, or by another tx in the block) isn't processed twice.
+const RECENT_ARBS_TTL: Duration = Duration::from_secs(10);
+
+/// Max arb items queued for the workers at once.
+const ARB_CHANNEL_CAPACITY: usize = 10;
+
+/// What to do with new opportunities while the worker channel is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ChannelFullPolicy {
+    /// Keep queued items; new ones wait in the cache, oldest first, and may expire there.
+    #[default]
+    Wait,
+    /// Drop the oldest queued items in favour of the newest cached ones.
+    ReplaceOldest,
+}
 
 pub struct ArbStrategy {
     sender: Address,
     arb_item_sender: Option<Sender<ArbItem>>,
+    // only used to evict stale items under `ChannelFullPolicy::ReplaceOldest`
+    arb_item_receiver: Option<Receiver<ArbItem>>,
+    channel_full_policy: ChannelFullPolicy,
     arb_cache: ArbCache,
 
-    recent_arbs: VecDeque<String>,
-    max_recent_arbs: usize,
+    // at most `max_recent_arbs` keys, each for up to `RECENT_ARBS_TTL`
+    recent_arbs: SeenCache<ArbKey>,
 
     simulator_pool: Arc<ObjectPool<Box<dyn Simulator>>>,
     own_simulator: Arc<dyn Simulator>, // only for execution of pending txs
@@ -49,6 +92,21 @@ pub struct ArbStrategy {
     workers: usize,
     current_block: Option<BlockNumber>,
     dedicated_simulator: Option<Arc<ReplaySimulator>>,
+    min_notify_profit_usd: f64,
+    min_notify_profit_avax: f64,
+    // simulate pending txs and size backruns against their post-state (needs fork simulators)
+    simulate_pending_txs: bool,
+    // per-tx logs are sampled; txs that produce an arb item are always logged
+    log_sampler: LogSampler,
+    seen_pending_txs: SeenCache<H256>,
+    // txs sent from these never trigger a backrun, so the bot doesn't chase its own swaps
+    own_addresses: HashSet<Address>,
+    // public tx receipts wait here until their block is deep enough; pending txs never do
+    unconfirmed_receipts: ConfirmationQueue<(TransactionReceipt, Vec<Log>)>,
+    // workers post every arb that passed its dry run here
+    webhook: Option<WebhookNotifier>,
+    // flashloan trades are simulated through this arb contract before they're submitted
+    arb_contract: Option<Address>,
 }
 
 impl ArbStrategy {
@@ -60,26 +118,100 @@ impl ArbStrategy {
         rpc_url: &str,
         workers: usize,
         dedicated_simulator: Option<Arc<ReplaySimulator>>,
+        min_notify_profit_usd: f64,
+        min_notify_profit_avax: f64,
+        simulate_pending_txs: bool,
+        log_sample_rate: u64,
+        channel_full_policy: ChannelFullPolicy,
+        own_addresses: HashSet<Address>,
+        receipt_confirmations: u64,
     ) -> Self {
         let current_block = get_latest_block(&rpc_url).await.unwrap();
 
         Self {
             sender: attacker,
             arb_item_sender: None,
+            arb_item_receiver: None,
+            channel_full_policy,
             arb_cache: ArbCache::new(Duration::from_secs(5)),
-            recent_arbs: VecDeque::with_capacity(recent_arbs),
-            max_recent_arbs: recent_arbs,
+            recent_arbs: SeenCache::new(RECENT_ARBS_TTL, recent_arbs),
             simulator_pool,
             own_simulator,
             rpc_url: rpc_url.to_string(),
             workers,
             current_block: Some(current_block),
             dedicated_simulator,
+            min_notify_profit_usd,
+            min_notify_profit_avax,
+            simulate_pending_txs,
+            log_sampler: LogSampler::new(log_sample_rate),
+            seen_pending_txs: SeenCache::new(PENDING_TX_DEDUP_TTL, PENDING_TX_DEDUP_CAPACITY),
+            own_addresses,
+            unconfirmed_receipts: ConfirmationQueue::new(receipt_confirmations),
+            webhook: None,
+            arb_contract: None,
+        }
+    }
+
+    /// Post every opportunity the workers trade to `webhook`.
+    pub fn with_webhook(mut self, webhook: Option<WebhookNotifier>) -> Self {
+        self.webhook = webhook;
+        self
+    }
+
+    /// Simulate flashloan trades through the deployed arb contract at `arb_contract` before submitting them.
+    pub fn with_arb_contract(mut self, arb_contract: Option<Address>) -> Self {
+        self.arb_contract = arb_contract;
+        self
+    }
+
+    /// Log what the strategy currently believes is actionable: cached opportunities,
+    /// the recent arbs window and the worker channel depth.
+    fn dump_in_flight(&self) {
+        let channel_len = self.arb_item_sender.as_ref().map(|s| s.len()).unwrap_or_default();
+        info!(
+            arb_cache = self.arb_cache.len(),
+            recent_arbs = self.recent_arbs.len(),
+            channel_len,
+            "in-flight opportunities"
+        );
+        for entry in self.arb_cache.snapshot() {
+            info!(
+                token = %entry.token,
+                pool = ?entry.pool_address,
+                tx = ?entry.tx_hash,
+                source = ?entry.source,
+                age_ms = entry.age.as_millis() as u64,
+                expires_in_ms = entry.expires_in.as_millis() as u64,
+                "arb_cache entry"
+            );
+        }
+        info!(recent_arbs = ?self.recent_arbs.iter().collect::<Vec<_>>(), "recent arbs window");
+    }
+
+    /// Take up to `n` of the oldest items still queued for the workers back into the cache, with their original
+    /// expiry, so newer opportunities go first and theirs can be sent again. Returns how many were evicted.
+    fn evict_queued(&mut self, n: usize) -> usize {
+        let receiver = self.arb_item_receiver.as_ref().unwrap();
+        let mut evicted = 0;
+        while evicted < n {
+            let Ok(item) = receiver.try_recv() else {
+                break;
+            };
+            let key = item.key();
+            self.recent_arbs.retain(|recent| recent != &key);
+            self.arb_cache.reinsert(item);
+            evicted += 1;
         }
+        evicted
     }
 
-    #[instrument(name = "on-new-tx-receipt", skip_all, fields(tx = %tx_receipt.transaction_hash))]
     async fn on_new_tx_receipt(&mut self, tx_receipt: TransactionReceipt, logs: Vec<Log>) -> Result<()> {
+        if self.own_addresses.contains(&tx_receipt.from) {
+            debug!(tx = %tx_receipt.transaction_hash, "skip own tx receipt");
+            return Ok(());
+        }
+
         let token_pools = self.parse_involved_token_pools(logs).await;
         if token_pools.is_empty() {
             return Ok(());
@@ -90,6 +222,7 @@ impl ArbStrategy {
         let sim_ctx = SimulateCtx::new(block_number, vec![]);
 
         for (token, pool_address) in token_pools {
+            info!(tx = %tx_hash, %token, "Added arbitrage opportunity from tx receipt to cache");
             self.arb_cache
                 .insert(token, pool_address, tx_hash, sim_ctx.clone(), Source::Public);
         }
@@ -97,35 +230,101 @@ impl ArbStrategy {
         Ok(())
     }
 
-    #[instrument(name = "on-new-pending-tx", skip_all, fields(tx = %tx.hash))]
-    async fn on_new_pending_tx(&mut self, tx: ethers::types::Transaction) -> Result<()> {
-        // 分析pending交易，寻找DEX交易
-        info!("Processing pending tx: {}", tx.hash);
+    /// Act on `tx_receipt` once its block has the configured confirmations, along with every earlier receipt
+    /// that got there in the meantime. Receipts whose block was reorged out are dropped.
+    async fn on_public_tx(&mut self, tx_receipt: TransactionReceipt, logs: Vec<Log>) -> Result<()> {
+        if self.unconfirmed_receipts.is_immediate() {
+            return self.on_new_tx_receipt(tx_receipt, logs).await;
+        }
+
+        let block = tx_receipt.block_number.ok_or_else(|| eyre!("receipt without block number"))?;
+        self.unconfirmed_receipts.push(block.as_u64(), (tx_receipt, logs));
+
+        let latest = get_latest_block(&self.rpc_url).await?.as_u64();
+        let confirmed = self.unconfirmed_receipts.pop_confirmed(latest);
+        if confirmed.is_empty() {
+            return Ok(());
+        }
+
+        let provider = Provider::<Http>::try_from(self.rpc_url.as_str())?;
+        for (tx_receipt, logs) in confirmed {
+            let tx_hash = tx_receipt.transaction_hash;
+            // one failing receipt must not drop the rest of the batch, they are already off the queue
+            let canonical = match provider.get_transaction_receipt(tx_hash).await {
+                Ok(canonical) => canonical,
+                Err(error) => {
+                    warn!(tx = %tx_hash, "failed to re-check confirmed receipt: {error:#}");
+                    continue;
+                }
+            };
+            match canonical {
+                Some(canonical) if canonical.block_hash == tx_receipt.block_hash => {
+                    if let Err(error) = self.on_new_tx_receipt(tx_receipt, logs).await {
+                        warn!(tx = %tx_hash, "failed to handle confirmed receipt: {error:#}");
+                    }
+                }
+                _ => debug!(tx = %tx_hash, "skip receipt reorged out before confirmation"),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn on_new_pending_tx(&mut self, tx: ethers::types::Transaction, sampled: bool) -> Result<()> {
+        if !self.seen_pending_txs.insert(tx.hash) {
+            debug!(tx = %tx.hash, "skip duplicate pending tx");
+            return Ok(());
+        }
+        if self.own_addresses.contains(&tx.from) {
+            debug!(tx = %tx.hash, "skip own pending tx");
+            return Ok(());
+        }
+
+        // 分析pending交易，寻找DEX交易；未被采样的交易只在产生套利机会时输出 info 日志
+        if sampled {
+            info!(seen = self.log_sampler.seen(), "Processing pending tx: {}", tx.hash);
+        }
         
         // 检查交易是否与已知的DEX合约交互
         if let Some(to_address) = tx.to {
             // 这里应该检查to_address是否是已知的DEX路由器地址
             // 对于AVAX链，主要检查TraderJoe、Pangolin、Sushiswap等
             if self.is_dex_router_address(to_address) {
-                info!("Found DEX transaction to: {}", to_address);
+                if sampled {
+                    info!("Found DEX transaction to: {}", to_address);
+                }
                 
                 // 解析交易数据，提取涉及的代币信息
                 if let Ok(swap_info) = self.parse_dex_transaction_data(&tx).await {
-                    info!("Extracted swap info: token={}, amount={}", swap_info.token, swap_info.amount);
+                    if sampled {
+                        info!("Extracted swap info: token={}, amount={}", swap_info.token, swap_info.amount);
+                    }
                     
                     let block_number = self.get_latest_block().await?;
-                    let sim_ctx = SimulateCtx::new(block_number, vec![]);
+                    let mut sim_ctx = SimulateCtx::new(block_number, vec![]);
+
+                    // 先模拟受害交易本身：回滚的交易没有回跑价值；成功的交易作为前置交易放入 sim_ctx，
+                    // 这样 worker 在受害交易执行后的真实储备上计算最优回跑，而不是按 calldata 中的名义数量估算
+                    if self.simulate_pending_txs {
+                        let victim = self.own_simulator.simulate(tx.clone(), sim_ctx.clone()).await?;
+                        ensure!(
+                            victim.receipt.status == Some(1u64.into()),
+                            "pending tx {:?} reverts in simulation",
+                            tx.hash
+                        );
+                        sim_ctx.with_pre_tx(tx.clone());
+                    }
                     
                     // 将套利机会添加到缓存
                     self.arb_cache.insert(
                         swap_info.token,
-                        Some(swap_info.pool_address),
+                        swap_info.pool_address,
                         tx.hash,
                         sim_ctx,
                         Source::Mempool,
                     );
                     
-                    info!("Added arbitrage opportunity from pending tx to cache");
+                    info!(tx = %tx.hash, "Added arbitrage opportunity from pending tx to cache");
                 }
             }
         }
@@ -147,34 +346,44 @@ impl ArbStrategy {
     }
 
     async fn parse_dex_transaction_data(&self, tx: &ethers::types::Transaction) -> Result<SwapInfo> {
-        // 简化版本：从交易数据中提取代币信息
-        // 实际实现需要解析具体的函数调用数据
-        
-        // 这里返回一个示例，实际应该解析tx.input数据
-        let token = if let Some(to) = tx.to {
-            format!("0x{:x}", to) // 简化处理
-        } else {
-            "0xA7D7079b0FEaD91F3e65f86E8915Cb59c1a4C664".to_string() // USDC.e as fallback
-        };
-        
+        let swap = DecodedSwap::decode(&tx.input).ok_or_else(|| eyre!("not a router swap call"))?;
+        let (token_in, token_out) = (
+            swap.token_in().ok_or_else(|| eyre!("empty swap path"))?,
+            swap.token_out().ok_or_else(|| eyre!("empty swap path"))?,
+        );
+
+        // 与 SwapEvent::involved_token_one_side 一致：取非 WAVAX 的一侧
+        let token_in = format!("{:?}", token_in);
+        let token = if !is_wavax(&token_in) { token_in } else { format!("{:?}", token_out) };
+
         Ok(SwapInfo {
             token,
-            amount: tx.value.as_u64(),
-            pool_address: tx.to.unwrap_or_default(),
+            amount: swap.max_amount_in(tx.value),
+            // 路由器调用不指明池子，由 worker 在该代币的所有池子中搜索
+            pool_address: None,
         })
     }
 
     async fn parse_involved_token_pools(&self, logs: Vec<Log>) -> HashSet<(String, Option<Address>)> {
         let mut join_set = JoinSet::new();
+        let provider = match Provider::<Http>::try_from(self.rpc_url.as_str()) {
+            Ok(provider) => Arc::new(provider),
+            Err(error) => {
+                warn!("failed to build provider for swap logs: {error:#}");
+                return HashSet::new();
+            }
+        };
 
         for log in logs {
-            let own_simulator = self.own_simulator.clone();
+            let provider = provider.clone();
             join_set.spawn(async move {
-                // Parse swap events from logs based on different DEX protocols
-                if let Ok(swap_event) = parse_swap_event_from_log(&log, own_simulator).await {
-                    return Some((swap_event.involved_token_one_side(), swap_event.pool_address()));
+                match parse_swap_event_from_log(&log, &provider).await {
+                    Ok(swap_event) => Some((swap_event.involved_token_one_side(), swap_event.pool_address())),
+                    Err(error) => {
+                        debug!(pool = ?log.address, "skip log: {error:#}");
+                        None
+                    }
                 }
-                None
             });
         }
 
@@ -205,16 +414,32 @@ impl ArbStrategy {
     }
 }
 
-async fn parse_swap_event_from_log(log: &Log, simulator: Arc<dyn Simulator>) -> Result<SwapEvent> {
-    // This function should parse different DEX swap events based on the log
-    // For now, we'll return a placeholder
-    // In a real implementation, you'd check the log's address and topics to determine the DEX
-    
-    // TraderJoe Swap event signature: Swap(address,uint256,uint256,uint256,uint256,address)
-    // Pangolin Swap event signature: similar
-    // etc.
-    
-    todo!("Implement swap event parsing from Ethereum logs")
+/// `Swap(address,uint256,uint256,uint256,uint256,address)` of UniswapV2-style pairs (TraderJoe, Pangolin,
+/// SushiSwap).
+static V2_SWAP_TOPIC: Lazy<H256> =
+    Lazy::new(|| H256(ethers::utils::keccak256("Swap(address,uint256,uint256,uint256,uint256,address)")));
+
+/// The swap `log` records, resolved into trade direction from the pair's token0/token1. Errors for logs that
+/// aren't swaps of a pair from a supported protocol.
+async fn parse_swap_event_from_log(log: &Log, provider: &Provider<Http>) -> Result<SwapEvent> {
+    ensure!(log.topics.first() == Some(&*V2_SWAP_TOPIC), "not a swap log");
+    // amount0In, amount1In, amount0Out, amount1Out; sender and to are indexed
+    ensure!(log.data.len() >= 128, "short swap log data: {} bytes", log.data.len());
+    let amount = |i: usize| U256::from_big_endian(&log.data[i * 32..(i + 1) * 32]);
+
+    let pool = log.address;
+    let protocol = crate::dex::pool_protocol(provider, pool).await?;
+    let (token0, token1) = crate::dex::get_pair_tokens(provider, pool).await?;
+    let direction = SwapDirection::from_v2_amounts(
+        &format!("{token0:?}"),
+        &format!("{token1:?}"),
+        amount(0),
+        amount(1),
+        amount(2),
+        amount(3),
+    )?;
+
+    Ok(SwapEvent::from_direction(protocol, Some(pool), direction))
 }
 
 #[derive(Debug, Clone)]
@@ -223,24 +448,98 @@ pub struct SwapEvent {
     pub pool: Option<Address>,
     pub tokens_in: Vec<String>,
     pub tokens_out: Vec<String>,
-    pub amounts_in: Vec<u64>,
-    pub amounts_out: Vec<u64>,
+    pub amounts_in: Vec<U256>,
+    pub amounts_out: Vec<U256>,
 }
 
 #[derive(Debug, Clone)]
 pub struct SwapInfo {
     pub token: String,
-    pub amount: u64,
-    pub pool_address: Address,
+    pub amount: U256,
+    pub pool_address: Option<Address>,
+}
+
+/// A swap resolved into trade direction, independent of the pool's token0/token1 order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapDirection {
+    pub token_in: String,
+    pub token_out: String,
+    pub amount_in: U256,
+    pub amount_out: U256,
+}
+
+impl SwapDirection {
+    /// UniswapV2-style `Swap(sender, amount0In, amount1In, amount0Out, amount1Out, to)`.
+    /// The side with a non-zero `amountXIn` is the input; the other side is the output.
+    pub fn from_v2_amounts(
+        token0: &str,
+        token1: &str,
+        amount0_in: U256,
+        amount1_in: U256,
+        amount0_out: U256,
+        amount1_out: U256,
+    ) -> Result<Self> {
+        let zero_for_one = match (amount0_in.is_zero(), amount1_in.is_zero()) {
+            (false, true) => true,
+            (true, false) => false,
+            // both sides paid in (e.g. a flash swap repaid in both tokens): net out what was received
+            (false, false) => amount0_in.saturating_sub(amount0_out) > amount1_in.saturating_sub(amount1_out),
+            (true, true) => return Err(eyre!("swap without input")),
+        };
+
+        Ok(if zero_for_one {
+            Self::new(token0, token1, amount0_in, amount1_out)
+        } else {
+            Self::new(token1, token0, amount1_in, amount0_out)
+        })
+    }
+
+    /// UniswapV3-style `Swap(sender, recipient, int256 amount0, int256 amount1, ...)`, where amounts are
+    /// pool deltas: positive is paid into the pool (input), negative is sent out (output).
+    // none of the supported protocols emits V3 swaps yet
+    #[allow(dead_code)]
+    pub fn from_v3_amounts(token0: &str, token1: &str, amount0: I256, amount1: I256) -> Result<Self> {
+        ensure!(
+            amount0.is_positive() != amount1.is_positive() && !amount0.is_zero() && !amount1.is_zero(),
+            "swap amounts must have opposite signs: {amount0}, {amount1}"
+        );
+
+        Ok(if amount0.is_positive() {
+            Self::new(token0, token1, amount0.unsigned_abs(), amount1.unsigned_abs())
+        } else {
+            Self::new(token1, token0, amount1.unsigned_abs(), amount0.unsigned_abs())
+        })
+    }
+
+    fn new(token_in: &str, token_out: &str, amount_in: U256, amount_out: U256) -> Self {
+        Self {
+            token_in: token_in.to_string(),
+            token_out: token_out.to_string(),
+            amount_in,
+            amount_out,
+        }
+    }
 }
 
 impl SwapEvent {
+    pub fn from_direction(protocol: Protocol, pool: Option<Address>, direction: SwapDirection) -> Self {
+        Self {
+            protocol,
+            pool,
+            tokens_in: vec![direction.token_in],
+            tokens_out: vec![direction.token_out],
+            amounts_in: vec![direction.amount_in],
+            amounts_out: vec![direction.amount_out],
+        }
+    }
+
     pub fn pool_address(&self) -> Option<Address> {
         self.pool
     }
 
+    /// The non-WAVAX side of the swap, falling back to the input token for WAVAX/WAVAX-less pairs.
     pub fn involved_token_one_side(&self) -> String {
-        if self.tokens_in[0] != crate::tools::dex::WAVAX_ADDRESS {
+        if !crate::utils::config::is_wavax(&self.tokens_in[0]) {
             self.tokens_in[0].to_string()
         } else {
             self.tokens_out[0].to_string()
@@ -288,6 +587,7 @@ impl burberry::Strategy<Event, Action> for ArbStrategy {
 
         let (arb_item_sender, arb_item_receiver) = async_channel::unbounded();
         self.arb_item_sender = Some(arb_item_sender);
+        self.arb_item_receiver = Some(arb_item_receiver.clone());
 
         let sender = self.sender;
         let rpc_url = self.rpc_url.clone();
@@ -309,6 +609,10 @@ impl burberry::Strategy<Event, Action> for ArbStrategy {
             let simulator_pool_worker = self.simulator_pool.clone();
             let simulator_name = simulator_pool_arb.get().name().to_string();
             let dedicated_simulator = self.dedicated_simulator.clone();
+            let min_notify_profit_usd = self.min_notify_profit_usd;
+            let min_notify_profit_avax = self.min_notify_profit_avax;
+            let webhook = self.webhook.clone();
+            let arb_contract = self.arb_contract;
 
             let _ = std::thread::Builder::new()
                 .stack_size(128 * 1024 * 1024) // 128 MB
@@ -328,6 +632,14 @@ impl burberry::Strategy<Event, Action> for ArbStrategy {
                         submitter,
                         arb,
                         dedicated_simulator,
+                        min_notify_profit_usd,
+                        min_notify_profit_avax,
+                        webhook,
+                        arb_contract: arb_contract.map(|address| {
+                            let provider = Provider::<Http>::try_from(rpc_url.as_str()).unwrap();
+                            AvaxArbExecutor::new(address, Arc::new(provider))
+                        }),
+                        retry_queue: RetryQueue::new(),
                     };
                     worker.run().unwrap_or_else(|e| panic!("worker {id} panicked: {e:?}"));
                 });
@@ -339,33 +651,70 @@ impl burberry::Strategy<Event, Action> for ArbStrategy {
         }
 
         info!("workers all spawned!");
+
         Ok(())
     }
 
     async fn process_event(&mut self, event: Event, _submitter: Arc<dyn ActionSubmitter<Action>>) {
+        // per-tx spans are sampled 1-in-N so busy blocks don't flood the logs
+        let span = |name: &'static str, tx: H256, sampled: bool| {
+            if sampled {
+                info_span!("tx", handler = name, %tx)
+            } else {
+                Span::none()
+            }
+        };
         let result = match event {
-            Event::PublicTx(tx_receipt, logs) => self.on_new_tx_receipt(tx_receipt, logs).await,
-            Event::PendingTx(tx) => self.on_new_pending_tx(tx).await,
+            Event::PublicTx(tx_receipt, logs) => {
+                let span = span("on-new-tx-receipt", tx_receipt.transaction_hash, self.log_sampler.sample());
+                self.on_public_tx(tx_receipt, logs).instrument(span).await
+            }
+            Event::PendingTx(tx) => {
+                let sampled = self.log_sampler.sample();
+                let span = span("on-new-pending-tx", tx.hash, sampled);
+                self.on_new_pending_tx(tx, sampled).instrument(span).await
+            }
+            Event::DumpRequested => {
+                self.dump_in_flight();
+                return;
+            }
+            Event::System(event) => {
+                warn!(?event, "system event");
+                if let Some(webhook) = &self.webhook {
+                    webhook.spawn_notify(WebhookEvent::System((&event).into()));
+                }
+                return;
+            }
         };
         if let Err(error) = result {
             error!(?error, "failed to process event");
             return;
         }
 
-        // send arb_item to workers if channel is < 10
-        let channel_len = self.arb_item_sender.as_ref().unwrap().len();
-        if channel_len < 10 {
-            let num_to_send = 10 - channel_len;
+        // send arb_item to workers if channel is not full
+        let mut channel_len = self.arb_item_sender.as_ref().unwrap().len();
+        if channel_len >= ARB_CHANNEL_CAPACITY && self.channel_full_policy == ChannelFullPolicy::ReplaceOldest {
+            let evicted = self.evict_queued(channel_len.min(self.arb_cache.len()));
+            if evicted > 0 {
+                debug!(evicted, "evicted oldest queued arb_items for newer ones");
+            }
+            channel_len -= evicted;
+        }
+
+        if channel_len < ARB_CHANNEL_CAPACITY {
+            let num_to_send = ARB_CHANNEL_CAPACITY - channel_len;
             for _ in 0..num_to_send {
-                if let Some(item) = self.arb_cache.pop_one() {
-                    if !self.recent_arbs.contains(&item.token) {
-                        let token = item.token.clone();
+                let item = match self.channel_full_policy {
+                    ChannelFullPolicy::Wait => self.arb_cache.pop_one(),
+                    ChannelFullPolicy::ReplaceOldest => self.arb_cache.pop_newest(),
+                };
+                if let Some(item) = item {
+                    if let Some(remaining) = cooldown::remaining(&item.token) {
+                        debug!(token = %item.token, ?remaining, "skip arb of token on cooldown");
+                    } else if self.recent_arbs.insert(item.key()) {
                         self.arb_item_sender.as_ref().unwrap().send(item).await.unwrap();
-
-                        self.recent_arbs.push_back(token);
-                        if self.recent_arbs.len() > self.max_recent_arbs {
-                            self.recent_arbs.pop_front();
-                        }
+                    } else {
+                        debug!(token = %item.token, pool = ?item.pool_address, source = ?item.source, "skip recent arb");
                     }
                 } else {
                     // no more arb_item to send
@@ -376,11 +725,45 @@ impl burberry::Strategy<Event, Action> for ArbStrategy {
             warn!("arb_item channel stash {}", channel_len);
         }
 
-        let expired_tokens = self.arb_cache.remove_expired();
-        for token in expired_tokens {
-            if let Some(pos) = self.recent_arbs.iter().position(|x| x == &token) {
-                self.recent_arbs.remove(pos);
-            }
+        for (token, pool_address) in self.arb_cache.remove_expired() {
+            self.recent_arbs.retain(|recent| !recent.is_pool(&token, pool_address));
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAVAX: &str = "0xb31f66aa3c1e785363f0875a1b74e27b85fd66c7";
+    const USDC: &str = "0xb97ef9ef8734c71904d8002f8b6bc66dd9c48a6e";
+
+    #[test]
+    fn test_v2_direction_follows_amounts_not_token_order() {
+        // token0 = WAVAX, token1 = USDC, user sells USDC for WAVAX
+        let direction =
+            SwapDirection::from_v2_amounts(WAVAX, USDC, U256::zero(), U256::from(25), U256::from(1), U256::zero())
+                .unwrap();
+        assert_eq!(direction, SwapDirection::new(USDC, WAVAX, U256::from(25), U256::from(1)));
+
+        let event = SwapEvent::from_direction(Protocol::TraderJoe, None, direction);
+        assert_eq!(event.involved_token_one_side(), USDC);
+
+        assert!(SwapDirection::from_v2_amounts(WAVAX, USDC, U256::zero(), U256::zero(), U256::one(), U256::zero())
+            .is_err());
+    }
+
+    #[test]
+    fn test_v3_direction_by_sign() {
+        // pool received 25 token1 (USDC) and sent 1 token0 (WAVAX)
+        let direction =
+            SwapDirection::from_v3_amounts(WAVAX, USDC, I256::from(-1), I256::from(25)).unwrap();
+        assert_eq!(direction, SwapDirection::new(USDC, WAVAX, U256::from(25), U256::from(1)));
+
+        let direction =
+            SwapDirection::from_v3_amounts(WAVAX, USDC, I256::from(1), I256::from(-25)).unwrap();
+        assert_eq!(direction, SwapDirection::new(WAVAX, USDC, U256::from(1), U256::from(25)));
+
+        assert!(SwapDirection::from_v3_amounts(WAVAX, USDC, I256::from(1), I256::from(25)).is_err());
+    }
+}
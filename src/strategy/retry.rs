@@ -0,0 +1,236 @@
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use ethers::providers::{ProviderError, RpcError};
+use reqwest::StatusCode;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+use simulator::{RevertReason, SimulationReverted, SimulationTimeout};
+
+use super::arb_cache::ArbItem;
+
+/// Delay before a transiently failed item is tried again.
+pub const RETRY_DELAY: Duration = Duration::from_millis(300);
+/// Total attempts per item, including the first one.
+pub const MAX_ATTEMPTS: u32 = 3;
+const MAX_QUEUED: usize = 64;
+
+/// Total number of retries scheduled, across all workers.
+pub static RETRIES: AtomicU64 = AtomicU64::new(0);
+
+/// Why an opportunity failed: transient failures (RPC timeouts, rate limits) are worth another try,
+/// everything else (no route, no profit, reverts) is not. Simulated reverts are split by their reason.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArbError {
+    Transient,
+    Logical,
+    /// Router/pair output below the minimum: the size is too large for the price, or slippage too tight.
+    Slippage,
+    /// Router deadline passed.
+    Expired,
+    /// A pool on the path can't cover the amount out, or the swap breaks its invariant.
+    InsufficientLiquidity,
+    /// Any other revert, most often a bad adapter encoding.
+    Reverted,
+}
+
+impl ArbError {
+    pub fn classify(error: &eyre::Report) -> Self {
+        if error.chain().any(|e| e.is::<SimulationTimeout>()) {
+            return Self::Transient;
+        }
+        if let Some(reverted) = error.chain().find_map(|e| e.downcast_ref::<SimulationReverted>()) {
+            return Self::from_revert(&reverted.reason);
+        }
+
+        if error.chain().any(is_transient) {
+            Self::Transient
+        } else {
+            Self::Logical
+        }
+    }
+
+    /// Map a revert reason, e.g. `UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT` or `Joe: K`, to its cause.
+    pub fn from_revert(reason: &RevertReason) -> Self {
+        let RevertReason::Message(message) = reason else {
+            return Self::Reverted;
+        };
+        // drop the contract prefix ("UniswapV2Router: ", "Joe: ", "Pangolin: ")
+        let code = message.rsplit(": ").next().unwrap_or(message).trim().to_uppercase();
+        match code.as_str() {
+            "INSUFFICIENT_OUTPUT_AMOUNT" | "EXCESSIVE_INPUT_AMOUNT" | "TOO_LITTLE_RECEIVED" => Self::Slippage,
+            "EXPIRED" | "TRANSACTION TOO OLD" => Self::Expired,
+            "INSUFFICIENT_LIQUIDITY" | "K" | "INSUFFICIENT_INPUT_AMOUNT" => Self::InsufficientLiquidity,
+            _ => Self::Reverted,
+        }
+    }
+}
+
+/// JSON-RPC error codes nodes and RPC providers answer with when rate limiting: HTTP's 429 passed through,
+/// and EIP-1474's "limit exceeded".
+const RATE_LIMITED_RPC_CODES: [i64; 2] = [429, -32005];
+
+/// Whether one error of a chain is worth retrying: a timeout, a dropped or refused connection, a rate limit
+/// or a server-side HTTP failure. Matched on the error types, so a revert message that happens to contain
+/// "429" stays logical.
+fn is_transient(error: &(dyn std::error::Error + 'static)) -> bool {
+    if error.is::<SimulationTimeout>() || error.is::<tokio::time::error::Elapsed>() {
+        return true;
+    }
+    if let Some(error) = error.downcast_ref::<std::io::Error>() {
+        return matches!(
+            error.kind(),
+            std::io::ErrorKind::TimedOut
+                | std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionRefused
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::BrokenPipe
+        );
+    }
+    if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+        return is_transient_http(error);
+    }
+    if let Some(error) = error.downcast_ref::<ProviderError>() {
+        if let ProviderError::HTTPError(error) = error {
+            return is_transient_http(error);
+        }
+        return error
+            .as_error_response()
+            .is_some_and(|response| RATE_LIMITED_RPC_CODES.contains(&response.code));
+    }
+    false
+}
+
+fn is_transient_http(error: &reqwest::Error) -> bool {
+    error.is_timeout()
+        || error.is_connect()
+        || error
+            .status()
+            .is_some_and(|status| status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error())
+}
+
+/// Bounded FIFO of items waiting to be retried. All items share the same delay, so the front is
+/// always the next one due.
+#[derive(Default)]
+pub struct RetryQueue {
+    items: VecDeque<(Instant, ArbItem)>,
+}
+
+impl RetryQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedule `item` again if it has attempts left and the queue has room. Returns whether it was queued.
+    pub fn push(&mut self, mut item: ArbItem) -> bool {
+        item.attempts += 1;
+        if item.attempts >= MAX_ATTEMPTS {
+            debug!(token = %item.token, attempts = item.attempts, "giving up after transient failures");
+            return false;
+        }
+        if self.items.len() >= MAX_QUEUED {
+            warn!(token = %item.token, "retry queue full, dropping");
+            return false;
+        }
+
+        let retries = RETRIES.fetch_add(1, Ordering::Relaxed) + 1;
+        debug!(token = %item.token, attempts = item.attempts, retries, "scheduling retry");
+        self.items.push_back((Instant::now() + RETRY_DELAY, item));
+        true
+    }
+
+    pub fn next_due(&self) -> Option<Instant> {
+        self.items.front().map(|(due, _)| *due)
+    }
+
+    pub fn pop(&mut self) -> Option<ArbItem> {
+        self.items.pop_front().map(|(_, item)| item)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        providers::{HttpClientError, JsonRpcError},
+        types::H256,
+    };
+    use simulator::SimulateCtx;
+
+    use super::*;
+    use crate::types::Source;
+
+    #[test]
+    fn test_classify_revert_reasons() {
+        let reverted = |message: &str| {
+            eyre::Report::new(SimulationReverted {
+                simulator: "FoundrySimulator".to_string(),
+                reason: RevertReason::Message(message.to_string()),
+            })
+            .wrap_err("交易模拟失败")
+        };
+        assert_eq!(ArbError::classify(&reverted("UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT")), ArbError::Slippage);
+        assert_eq!(ArbError::classify(&reverted("Joe: EXPIRED")), ArbError::Expired);
+        assert_eq!(ArbError::classify(&reverted("Pangolin: K")), ArbError::InsufficientLiquidity);
+        assert_eq!(ArbError::classify(&reverted("Ownable: caller is not the owner")), ArbError::Reverted);
+        assert_eq!(ArbError::from_revert(&RevertReason::Unknown), ArbError::Reverted);
+    }
+
+    #[test]
+    fn test_classify() {
+        let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        assert_eq!(ArbError::classify(&eyre::Report::new(refused).wrap_err("get_reserves")), ArbError::Transient);
+        let rate_limited = ProviderError::JsonRpcClientError(Box::new(HttpClientError::JsonRpcError(JsonRpcError {
+            code: -32005,
+            message: "limit exceeded".to_string(),
+            data: None,
+        })));
+        assert_eq!(ArbError::classify(&eyre::Report::new(rate_limited)), ArbError::Transient);
+        // only the error type counts, not what the message says
+        assert_eq!(ArbError::classify(&eyre::eyre!("HTTP error 429 Too Many Requests")), ArbError::Logical);
+        assert_eq!(ArbError::classify(&eyre::eyre!("zero amount_out")), ArbError::Logical);
+
+        let timeout = SimulationTimeout {
+            simulator: "HttpSimulator".to_string(),
+            method: "simulate",
+            timeout: Duration::from_secs(1),
+        };
+        assert_eq!(ArbError::classify(&eyre::Report::new(timeout).wrap_err("trial")), ArbError::Transient);
+    }
+
+    #[test]
+    fn test_retry_queue_max_attempts() {
+        let item = ArbItem {
+            token: "0x1".to_string(),
+            pool_address: None,
+            tx_hash: H256::zero(),
+            sim_ctx: SimulateCtx::default(),
+            source: Source::Public,
+            attempts: 0,
+            expires_at: std::time::Instant::now(),
+            generation: 0,
+        };
+
+        let mut queue = RetryQueue::new();
+        assert!(queue.push(item));
+        let item = queue.pop().unwrap();
+        assert_eq!(item.attempts, 1);
+
+        assert!(queue.push(item));
+        let item = queue.pop().unwrap();
+        // third attempt was the last one
+        assert!(!queue.push(item));
+        assert!(queue.is_empty());
+    }
+}
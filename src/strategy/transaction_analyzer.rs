@@ -4,7 +4,7 @@ use ethers::types::{Transaction, Address};
 use tracing::{info, warn};
 use eyre::Result;
 
-use crate::utils::token_config::TokenConfig;
+use crate::{dex::DecodedSwap, utils::token_config::TokenConfig};
 
 /// 交易分析器
 pub struct TransactionAnalyzer {
@@ -40,6 +40,13 @@ impl TransactionAnalyzer {
                 input_data[0], input_data[1], input_data[2], input_data[3]
             ];
             
+            // 路由器 swap 调用：从解码后的路径读取输入代币
+            if let Some(swap) = DecodedSwap::decode(input_data) {
+                let token_in = format!("{:?}", swap.token_in()?);
+                info!("Detected DEX {} transaction to: {}, token_in: {}", swap.signature(), to_str, token_in);
+                return Some(token_in);
+            }
+
             // 检查ERC20函数
             if let Some(function_name) = self.token_config.is_erc20_function(&function_selector) {
                 info!("Detected ERC20 {} to: {}", function_name, to_str);
@@ -74,11 +81,6 @@ impl TransactionAnalyzer {
     /// 从DEX调用中提取代币信息
     fn extract_token_from_dex_call(&self, selector: &[u8; 4], input_data: &[u8]) -> Option<String> {
         match selector {
-            [0x38, 0xed, 0x17, 0x39] | // swapExactTokensForTokens
-            [0x8a, 0x03, 0xb2, 0xd4] | // swapExactTokensForETH  
-            [0x7f, 0xf3, 0x6a, 0xb5] => { // swapExactETHForTokens
-                self.extract_token_from_swap_path(input_data)
-            },
             [0xe8, 0xe3, 0x37, 0x00] => { // addLiquidity
                 self.extract_tokens_from_add_liquidity(input_data)
             },
@@ -86,50 +88,6 @@ impl TransactionAnalyzer {
         }
     }
 
-    /// 从swap函数的路径参数中提取代币地址
-    fn extract_token_from_swap_path(&self, input_data: &[u8]) -> Option<String> {
-        // swapExactTokensForTokens的参数结构：
-        // - amountIn (32 bytes)
-        // - amountOutMin (32 bytes)  
-        // - path offset (32 bytes)
-        // - to (32 bytes)
-        // - deadline (32 bytes)
-        // - path length (32 bytes)
-        // - path data...
-        
-        if input_data.len() < 4 + 32 * 6 {
-            return None;
-        }
-        
-        // 跳过函数选择器和前5个参数，读取路径长度
-        let path_length_offset = 4 + 32 * 5;
-        if input_data.len() < path_length_offset + 32 {
-            return None;
-        }
-        
-        // 读取路径长度（大端序）
-        let path_length = u32::from_be_bytes([
-            input_data[path_length_offset + 28],
-            input_data[path_length_offset + 29], 
-            input_data[path_length_offset + 30],
-            input_data[path_length_offset + 31],
-        ]);
-        
-        if path_length > 0 && input_data.len() >= path_length_offset + 32 + 20 {
-            // 读取第一个代币地址（20字节）
-            let token_start = path_length_offset + 32 + 12; // 跳过长度字段的padding
-            if input_data.len() >= token_start + 20 {
-                let token_bytes = &input_data[token_start..token_start + 20];
-                let token_address = format!("0x{}", 
-                    token_bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>());
-                info!("Extracted token from swap path: {}", token_address);
-                return Some(token_address);
-            }
-        }
-        
-        None
-    }
-
     /// 从addLiquidity函数中提取代币地址
     fn extract_tokens_from_add_liquidity(&self, input_data: &[u8]) -> Option<String> {
         // addLiquidity参数：tokenA, tokenB, ...
@@ -7,16 +7,30 @@ use burberry::ActionSubmitter;
 use eyre::{bail, ensure, Context, OptionExt, Result};
 use object_pool::ObjectPool;
 use simulator::{ReplaySimulator, SimulateCtx, Simulator};
-use ethers::types::{Address, TransactionRequest, H256, U256};
-use tracing::{error, info, instrument};
+use ethers::{
+    providers::{Http, Provider},
+    types::{Address, TransactionRequest, H256, U256},
+};
+use tracing::{error, info, instrument, warn};
 
 use crate::{
     arb::{Arb, ArbResult},
-    common::notification::new_tg_messages,
-    types::{Action, Source},
+    bindings::avaxarbexecutor::AvaxArbExecutor,
+    common::{
+        notification::{new_tg_messages, profit_avax, profit_usd},
+        price_oracle::price_oracle,
+        webhook::{WebhookEvent, WebhookNotifier},
+    },
+    config::profit_token,
+    tools::TradeType,
+    types::{Action, QuotedTx, Source},
 };
 
-use super::arb_cache::ArbItem;
+use super::{
+    arb_cache::ArbItem,
+    decision::Decision,
+    retry::{ArbError, RetryQueue},
+};
 
 pub struct Worker {
     pub _id: usize,
@@ -31,34 +45,56 @@ pub struct Worker {
 
     pub submitter: Arc<dyn ActionSubmitter<Action>>,
     pub arb: Arc<Arb>,
+
+    pub min_notify_profit_usd: f64,
+    pub min_notify_profit_avax: f64,
+
+    pub webhook: Option<WebhookNotifier>,
+    pub arb_contract: Option<AvaxArbExecutor<Provider<Http>>>,
+
+    pub retry_queue: RetryQueue,
 }
 
 impl Worker {
     #[tokio::main]
     pub async fn run(mut self) -> Result<()> {
         loop {
+            let next_retry = self.retry_queue.next_due();
             tokio::select! {
                 arb_item = self.arb_item_receiver.recv() => {
                     if let Err(error) = self.handle_arb_item(arb_item.context("arb_item channel error")?).await {
                         error!(?error, "Handle arb_item failed");
                     }
                 }
+                _ = tokio::time::sleep_until(next_retry.unwrap_or_else(tokio::time::Instant::now)), if next_retry.is_some() => {
+                    let arb_item = self.retry_queue.pop().expect("retry due without item");
+                    if let Err(error) = self.handle_arb_item(arb_item).await {
+                        error!(?error, "Handle retried arb_item failed");
+                    }
+                }
                 else => bail!("strategy channels undefined behavior"),
             }
         }
     }
 
-    #[instrument(skip_all, fields(token = %arb_item.token.split("x").last().unwrap_or(&arb_item.token), tx = %arb_item.tx_hash))]
+    #[instrument(skip_all, fields(
+        token = %arb_item.token.split("x").last().unwrap_or(&arb_item.token),
+        tx = %arb_item.tx_hash,
+        path_len = tracing::field::Empty,
+        profit = tracing::field::Empty,
+    ))]
     pub async fn handle_arb_item(&mut self, arb_item: ArbItem) -> Result<()> {
+        let retry_item = arb_item.clone();
         let ArbItem {
             token,
             pool_address,
             tx_hash,
             sim_ctx,
             source,
+            ..
         } = arb_item;
 
-        if let Some((arb_result, elapsed)) = arbitrage_one_token(
+        let found = match arbitrage_one_token(
             self.arb.clone(),
             self.sender,
             &token,
@@ -69,25 +105,88 @@ impl Worker {
         )
         .await
         {
-            let tx_request = match self.dry_run_tx_request(arb_result.tx_data.clone(), sim_ctx.clone()).await {
+            Ok(found) => found,
+            Err(error) => {
+                // only transient failures reach here; logical ones are `Ok(None)`
+                warn!(?error, attempts = retry_item.attempts, "transient failure, retrying");
+                self.retry_queue.push(retry_item);
+                return Ok(());
+            }
+        };
+
+        if let Some((arb_result, elapsed)) = found {
+            let span = tracing::Span::current();
+            span.record("path_len", arb_result.best_trial_result.trade_path.path.len());
+            span.record("profit", tracing::field::display(arb_result.best_trial_result.profit));
+            // flashloan trades go through the arb contract when one is deployed, which also pays the builder
+            let tx_data = match self.contract_flash_tx(&arb_result, sim_ctx.clone()).await {
+                Ok(Some(tx_data)) => tx_data,
+                Ok(None) if !arb_result.builder_payment.is_zero() => {
+                    arb_result
+                        .record
+                        .log(Decision::Skip, "builder payment needs a flashloan trade through the arb contract");
+                    return Ok(());
+                }
+                Ok(None) => arb_result.tx_data.clone(),
+                Err(error) => {
+                    error!(?arb_result, ?error, "Dry run through the arb contract failed");
+                    arb_result.record.log(Decision::Skip, &format!("contract dry run failed: {error:#}"));
+                    if ArbError::classify(&error) == ArbError::Transient {
+                        self.retry_queue.push(retry_item);
+                    }
+                    return Ok(());
+                }
+            };
+            let access_list = (tx_data == arb_result.tx_data).then(|| arb_result.access_list.clone()).flatten();
+
+            let tx_request = match self.dry_run_tx_request(tx_data, sim_ctx.clone()).await {
                 Ok(tx_request) => tx_request,
                 Err(error) => {
                     error!(?arb_result, ?error, "Dry run final tx_request failed");
+                    arb_result.record.log(Decision::Skip, &format!("dry run failed: {error:#}"));
+                    if ArbError::classify(&error) == ArbError::Transient {
+                        self.retry_queue.push(retry_item);
+                    }
                     return Ok(());
                 }
             };
 
             let arb_tx_hash = H256::zero(); // Placeholder - actual hash would be computed after sending
+            // stamp the tx with the block it was quoted at so the executor can drop it once stale
+            let mut quoted = QuotedTx::new(tx_request, sim_ctx.epoch.block_number)
+                .with_access_list(access_list)
+                .with_token(&token);
+            let best = &arb_result.best_trial_result;
+            if best.trade_type == TradeType::Swap {
+                let token_in = best.token_address.parse::<Address>().context("invalid token address")?;
+                quoted = quoted.with_own_funds(token_in, best.amount_in);
+            }
             let action = match arb_result.source {
-                Source::MevRelay { bid_amount, .. } => Action::MevRelaySubmitBid((tx_request, bid_amount, tx_hash)),
-                _ => Action::ExecutePublicTx(tx_request),
+                Source::MevRelay { bid_amount, .. } => Action::MevRelaySubmitBid((quoted, bid_amount, tx_hash)),
+                _ => Action::ExecutePublicTx(quoted),
             };
 
+            arb_result.record.log(Decision::Trade, "dry run passed");
             self.submitter.submit(action);
+            if let Some(webhook) = &self.webhook {
+                webhook.spawn_notify(WebhookEvent::Opportunity((&arb_result.best_trial_result).into()));
+            }
 
-            let tg_msgs = new_tg_messages(tx_hash, arb_tx_hash, &arb_result, elapsed, &self.simulator_name);
-            for tg_msg in tg_msgs {
-                self.submitter.submit(tg_msg.into());
+            // pegged coins are valued at ~$1 and WAVAX via the price feed; if the feed is stale or not
+            // configured WAVAX profit falls back to the AVAX threshold. Other coins always notify.
+            let notify = match (profit_usd(&arb_result), profit_avax(&arb_result)) {
+                (Some(usd), _) => usd >= self.min_notify_profit_usd,
+                (None, Some(avax)) => {
+                    price_oracle().log_avax_fallback_once();
+                    avax >= self.min_notify_profit_avax
+                }
+                (None, None) => true,
+            };
+            if notify {
+                let tg_msgs = new_tg_messages(tx_hash, arb_tx_hash, &arb_result, elapsed, &self.simulator_name);
+                for tg_msg in tg_msgs {
+                    self.submitter.submit(tg_msg.into());
+                }
             }
 
             // notify dedicated simulator to update more frequently
@@ -115,6 +214,38 @@ impl Worker {
         Ok(tx_request)
     }
 
+    // the arb contract's `executeArbWithFlash` call for a flashloan trade, with the builder payment paid inside
+    // it. Runs it end to end on a fork first, so routes the contract would revert (flashloan fee, repay,
+    // `NotProfitable`) are dropped before submission. `None` without a contract or for own-funds trades.
+    async fn contract_flash_tx(&self, arb_result: &ArbResult, sim_ctx: SimulateCtx) -> Result<Option<TransactionRequest>> {
+        let trial = &arb_result.best_trial_result;
+        let Some(contract) = self.arb_contract.as_ref().filter(|_| trial.trade_type == TradeType::Flashloan) else {
+            return Ok(None);
+        };
+        let builder_payment = profit_token().token_in_gas_wei(arb_result.builder_payment);
+        let params = self
+            .arb
+            .flash_arb_params(trial, sim_ctx.epoch.block_number, builder_payment)
+            .await?;
+
+        let resp = if let Some(dedicated_sim) = &self.dedicated_simulator {
+            dedicated_sim
+                .simulate_flash_arb(self.sender, Some(contract.address()), params.clone(), sim_ctx)
+                .await?
+        } else {
+            self.simulator_pool
+                .get()
+                .simulate_flash_arb(self.sender, Some(contract.address()), params.clone(), sim_ctx)
+                .await?
+        };
+        let profit: i128 = resp.balance_changes.iter().map(|change| change.amount).sum();
+        ensure!(profit > 0, "No profit from the arb contract: {profit}");
+
+        let call = contract.execute_arb_with_flash(params).tx;
+        let data = call.data().cloned().ok_or_eyre("executeArbWithFlash call without calldata")?;
+        Ok(Some(TransactionRequest::new().from(self.sender).to(contract.address()).data(data)))
+    }
+
     // Update gas price and gas limit estimates
     async fn update_gas_estimates(&self, mut tx_request: TransactionRequest) -> Result<TransactionRequest> {
         // For AVAX, use reasonable default gas settings
@@ -139,7 +270,7 @@ async fn arbitrage_one_token(
     sim_ctx: SimulateCtx,
     use_gss: bool,
     source: Source,
-) -> Option<(ArbResult, Duration)> {
+) -> Result<Option<(ArbResult, Duration)>> {
     let start = Instant::now();
     let gas_limit = 300000u64;
     let arb_result = match arb
@@ -147,6 +278,7 @@ async fn arbitrage_one_token(
         .await
     {
         Ok(r) => r,
+        Err(error) if ArbError::classify(&error) == ArbError::Transient => return Err(error),
         Err(error) => {
             let elapsed = start.elapsed();
             if elapsed > Duration::from_secs(1) {
@@ -154,7 +286,7 @@ async fn arbitrage_one_token(
             } else {
                 info!(elapsed = ?elapsed, %token_address, "🥱 No opportunity: {error:#}");
             }
-            return None;
+            return Ok(None);
         }
     };
 
@@ -169,5 +301,5 @@ async fn arbitrage_one_token(
         &arb_result.best_trial_result
     );
 
-    Some((arb_result, start.elapsed()))
+    Ok(Some((arb_result, start.elapsed())))
 }
@@ -0,0 +1,163 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{Address, BlockId},
+};
+use eyre::{ensure, OptionExt, Result, WrapErr};
+use object_pool::ObjectPool;
+
+use crate::{
+    common::{get_chain_id, price_oracle::AVAX_BLOCK_TIME},
+    config::probe_amounts,
+    dex::{Defi, TradeType},
+    simulator::{HttpSimulator, SimEpoch, SimulateCtx, Simulator},
+    utils::math::nearest_rank,
+    HttpConfig,
+};
+
+const GAS_LIMIT: u64 = 300_000;
+
+#[derive(Clone, Debug, Parser)]
+pub struct Args {
+    /// Tokens to route, comma separated
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub tokens: Vec<String>,
+
+    /// Block every simulation runs at, so runs before and after a change see the same state. Defaults to the
+    /// latest block at start.
+    #[arg(long)]
+    pub block: Option<u64>,
+
+    /// Passes over the token set
+    #[arg(long, default_value_t = 3)]
+    pub iterations: usize,
+
+    #[arg(long, default_value_t = 2)]
+    pub max_hops: usize,
+
+    #[arg(long, default_value_t = 4)]
+    pub num_simulators: usize,
+
+    #[command(flatten)]
+    pub http_config: HttpConfig,
+}
+
+/// Latencies of one benchmarked stage, plus how many units (paths, simulations) it got through.
+#[derive(Debug, Default)]
+struct Stage {
+    latencies: Vec<Duration>,
+    units: usize,
+}
+
+impl Stage {
+    fn record(&mut self, elapsed: Duration, units: usize) {
+        self.latencies.push(elapsed);
+        self.units += units;
+    }
+
+    fn total(&self) -> Duration {
+        self.latencies.iter().sum()
+    }
+
+    fn per_second(&self) -> f64 {
+        self.units as f64 / self.total().as_secs_f64().max(f64::EPSILON)
+    }
+
+    fn report(&mut self, name: &str, unit: &str) {
+        self.latencies.sort();
+        // a stage that never ran has no latencies to rank
+        let percentile = |pct| nearest_rank(&self.latencies, pct).map_or("-".to_string(), |d| format!("{d:?}"));
+        println!(
+            "{name:<10} {:>10.1} {unit}/s   p50 {:>10}   p95 {:>10}   ({} {unit} in {} calls)",
+            self.per_second(),
+            percentile(50),
+            percentile(95),
+            self.units,
+            self.latencies.len()
+        );
+    }
+}
+
+/// Measure path enumeration and simulation throughput for a fixed token set at a pinned block, to see how
+/// many tokens per block the current config keeps up with and to compare runs across changes.
+pub async fn run(args: Args) -> Result<()> {
+    ensure!(args.iterations > 0, "iterations must be positive");
+    let rpc_url = args.http_config.rpc_url.clone();
+    let chain_id = get_chain_id(&rpc_url).await?;
+
+    let provider = Provider::<Http>::try_from(rpc_url.as_str())?;
+    let block_id = args.block.map_or(BlockId::latest(), |b| BlockId::Number(b.into()));
+    let block = provider.get_block(block_id).await?.ok_or_eyre("block not found")?;
+    let sim_ctx = SimulateCtx::new(SimEpoch::from_block(&block));
+
+    let simulator_pool: ObjectPool<Box<dyn Simulator>> = {
+        let rpc_url = rpc_url.clone();
+        ObjectPool::try_new(args.num_simulators, move || {
+            let rpc_url = rpc_url.clone();
+            tokio::runtime::Runtime::new()?.block_on(async {
+                Ok(Box::new(HttpSimulator::new(&rpc_url, Some(chain_id)).await?) as Box<dyn Simulator>)
+            })
+        })?
+    };
+    let defi = Defi::new(&rpc_url, Arc::new(simulator_pool)).await?;
+    println!(
+        "benchmarking {} token(s) x {} iteration(s) at block {}",
+        args.tokens.len(),
+        args.iterations,
+        sim_ctx.epoch.block_number
+    );
+
+    let mut paths_stage = Stage::default();
+    let mut sims_stage = Stage::default();
+    let mut tokens_stage = Stage::default();
+    let mut failures = 0;
+    let mut last_failure = None;
+    for _ in 0..args.iterations {
+        for token in &args.tokens {
+            let token_start = Instant::now();
+
+            let start = Instant::now();
+            let paths = defi
+                .find_sell_paths_with_hops(token, args.max_hops)
+                .await
+                .wrap_err_with(|| format!("find paths for {token}"))?;
+            paths_stage.record(start.elapsed(), paths.len());
+
+            if !paths.is_empty() {
+                for amount_in in probe_amounts(token) {
+                    let start = Instant::now();
+                    let result = defi
+                        .find_best_path_exact_in(&paths, Address::zero(), amount_in, TradeType::Flashloan, GAS_LIMIT, &sim_ctx)
+                        .await;
+                    sims_stage.record(start.elapsed(), paths.len());
+                    // a probe size without a profitable path is a result too, but it is counted and shown
+                    if let Err(error) = result {
+                        failures += 1;
+                        last_failure = Some(format!("{token} at {amount_in}: {error:#}"));
+                    }
+                }
+            }
+
+            tokens_stage.record(token_start.elapsed(), 1);
+        }
+    }
+
+    paths_stage.report("paths", "paths");
+    sims_stage.report("simulate", "sims");
+    tokens_stage.report("tokens", "tokens");
+    println!(
+        "~{:.1} tokens per {:?} block; {failures} probe(s) without a result",
+        tokens_stage.per_second() * AVAX_BLOCK_TIME.as_secs_f64(),
+        AVAX_BLOCK_TIME
+    );
+    if let Some(last_failure) = last_failure {
+        println!("last probe without a result: {last_failure}");
+    }
+
+    Ok(())
+}
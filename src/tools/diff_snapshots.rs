@@ -0,0 +1,167 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::PathBuf,
+};
+
+use clap::Parser;
+use ethers::types::Address;
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// One pool in an exported pool-cache snapshot. A snapshot file is a JSON array of these.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolSnapshot {
+    pub pool: Address,
+    pub protocol: String,
+    pub tokens: Vec<String>,
+    pub fee_bps: Option<u32>,
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Args {
+    /// Older snapshot
+    pub a: PathBuf,
+
+    /// Newer snapshot
+    pub b: PathBuf,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ProtocolDiff {
+    pub added: Vec<PoolSnapshot>,
+    pub removed: Vec<PoolSnapshot>,
+    /// (before, after)
+    pub changed: Vec<(PoolSnapshot, PoolSnapshot)>,
+}
+
+impl ProtocolDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+pub fn load(path: &PathBuf) -> Result<Vec<PoolSnapshot>> {
+    let data = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    serde_json::from_str(&data).with_context(|| format!("parse {}", path.display()))
+}
+
+/// Diff two snapshots, grouped by protocol. A pool that moved protocol shows as removed from one and
+/// added to the other. Token order is ignored when comparing pairs.
+pub fn diff(a: &[PoolSnapshot], b: &[PoolSnapshot]) -> BTreeMap<String, ProtocolDiff> {
+    let index = |snapshot: &[PoolSnapshot]| -> HashMap<(Address, String), PoolSnapshot> {
+        snapshot
+            .iter()
+            .map(|p| ((p.pool, p.protocol.clone()), p.clone()))
+            .collect()
+    };
+    let (a, b) = (index(a), index(b));
+    let same_pair = |x: &PoolSnapshot, y: &PoolSnapshot| {
+        let (mut tx, mut ty) = (x.tokens.clone(), y.tokens.clone());
+        tx.sort();
+        ty.sort();
+        tx == ty && x.fee_bps == y.fee_bps
+    };
+
+    let mut diffs: BTreeMap<String, ProtocolDiff> = BTreeMap::new();
+    for (key, before) in &a {
+        match b.get(key) {
+            None => diffs.entry(key.1.clone()).or_default().removed.push(before.clone()),
+            Some(after) if !same_pair(before, after) => diffs
+                .entry(key.1.clone())
+                .or_default()
+                .changed
+                .push((before.clone(), after.clone())),
+            Some(_) => {}
+        }
+    }
+    for (key, after) in &b {
+        if !a.contains_key(key) {
+            diffs.entry(key.1.clone()).or_default().added.push(after.clone());
+        }
+    }
+
+    diffs.retain(|_, d| !d.is_empty());
+    for d in diffs.values_mut() {
+        d.added.sort_by_key(|p| p.pool);
+        d.removed.sort_by_key(|p| p.pool);
+        d.changed.sort_by_key(|(p, _)| p.pool);
+    }
+    diffs
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    let a = load(&args.a)?;
+    let b = load(&args.b)?;
+    let diffs = diff(&a, &b);
+
+    println!("{}: {} pools, {}: {} pools", args.a.display(), a.len(), args.b.display(), b.len());
+    if diffs.is_empty() {
+        println!("no differences");
+        return Ok(());
+    }
+
+    for (protocol, d) in diffs {
+        println!(
+            "\n== {protocol}: +{} -{} ~{}",
+            d.added.len(),
+            d.removed.len(),
+            d.changed.len()
+        );
+        for p in d.added {
+            println!("  + {:?} {:?} fee={:?}", p.pool, p.tokens, p.fee_bps);
+        }
+        for p in d.removed {
+            println!("  - {:?} {:?} fee={:?}", p.pool, p.tokens, p.fee_bps);
+        }
+        for (before, after) in d.changed {
+            println!(
+                "  ~ {:?} {:?} fee={:?} -> {:?} fee={:?}",
+                before.pool, before.tokens, before.fee_bps, after.tokens, after.fee_bps
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(id: u64, protocol: &str, tokens: &[&str], fee_bps: u32) -> PoolSnapshot {
+        PoolSnapshot {
+            pool: Address::from_low_u64_be(id),
+            protocol: protocol.to_string(),
+            tokens: tokens.iter().map(|t| t.to_string()).collect(),
+            fee_bps: Some(fee_bps),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let a = vec![
+            pool(1, "TraderJoe", &["a", "b"], 30),
+            pool(2, "TraderJoe", &["a", "c"], 30),
+            pool(3, "Pangolin", &["a", "b"], 30),
+        ];
+        let b = vec![
+            // same pair, different order
+            pool(1, "TraderJoe", &["b", "a"], 30),
+            pool(2, "TraderJoe", &["a", "c"], 25),
+            pool(4, "Pangolin", &["a", "d"], 30),
+        ];
+
+        let diffs = diff(&a, &b);
+        assert_eq!(diffs.len(), 2);
+
+        let tj = &diffs["TraderJoe"];
+        assert!(tj.added.is_empty() && tj.removed.is_empty());
+        assert_eq!(tj.changed.len(), 1);
+        assert_eq!(tj.changed[0].1.fee_bps, Some(25));
+
+        let pangolin = &diffs["Pangolin"];
+        assert_eq!(pangolin.added, vec![pool(4, "Pangolin", &["a", "d"], 30)]);
+        assert_eq!(pangolin.removed, vec![pool(3, "Pangolin", &["a", "b"], 30)]);
+    }
+}
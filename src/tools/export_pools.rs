@@ -0,0 +1,306 @@
+use std::{collections::HashMap, fs, io::Write, path::PathBuf};
+
+use clap::Parser;
+use dex_indexer::{types::Protocol, DexIndexer};
+use ethers::{
+    abi::{self, ParamType},
+    providers::{Http, Middleware, Provider},
+    types::{transaction::eip2718::TypedTransaction, Address, TransactionRequest, U256},
+    utils::id,
+};
+use eyre::{Context, Result};
+use serde::Serialize;
+
+use crate::{
+    config::{parse_protocol, pool_fee_bps, pool_fee_override, SUPPORTED_PROTOCOLS},
+    dex::{ReserveCache, DEFAULT_FEE_BPS},
+    tools::diff_snapshots::PoolSnapshot,
+    utils::token_config::TokenConfig,
+    HttpConfig,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    #[default]
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Debug, Parser)]
+pub struct Args {
+    /// Only pools of this protocol. Every supported protocol when unset.
+    #[arg(long)]
+    pub protocol: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = Format::Csv)]
+    pub format: Format,
+
+    /// Output file
+    #[arg(short, long)]
+    pub output: PathBuf,
+
+    /// Block reserves are read at. Defaults to the latest block at start.
+    #[arg(long)]
+    pub block: Option<u64>,
+
+    #[command(flatten)]
+    pub http_config: HttpConfig,
+}
+
+/// One exported pool. Token and reserve columns are empty for pools without `token0()` / `getReserves()`
+/// (e.g. Platypus), and symbol/decimals for tokens that don't implement them. `--format json` writes
+/// these as [`PoolSnapshot`]s so `diff-snapshots` can compare two exports.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PoolRow {
+    pub pool: Address,
+    pub protocol: String,
+    pub token0: Option<Address>,
+    pub token0_symbol: Option<String>,
+    pub token0_decimals: Option<u8>,
+    pub token1: Option<Address>,
+    pub token1_symbol: Option<String>,
+    pub token1_decimals: Option<u8>,
+    /// Raw amounts, as decimal strings so spreadsheets don't round them
+    pub reserve0: Option<String>,
+    pub reserve1: Option<String>,
+    /// Empty when the fee isn't known, e.g. Platypus pools without a configured override
+    pub fee_bps: Option<u32>,
+}
+
+const CSV_HEADER: &str = "pool,protocol,token0,token0_symbol,token0_decimals,token1,token1_symbol,token1_decimals,\
+reserve0,reserve1,fee_bps";
+
+impl PoolRow {
+    fn csv_line(&self) -> String {
+        let opt = |value: Option<String>| value.map(|v| csv_field(&v)).unwrap_or_default();
+        [
+            format!("{:?}", self.pool),
+            csv_field(&self.protocol),
+            opt(self.token0.map(|t| format!("{t:?}"))),
+            opt(self.token0_symbol.clone()),
+            opt(self.token0_decimals.map(|d| d.to_string())),
+            opt(self.token1.map(|t| format!("{t:?}"))),
+            opt(self.token1_symbol.clone()),
+            opt(self.token1_decimals.map(|d| d.to_string())),
+            opt(self.reserve0.clone()),
+            opt(self.reserve1.clone()),
+            opt(self.fee_bps.map(|f| f.to_string())),
+        ]
+        .join(",")
+    }
+}
+
+impl From<&PoolRow> for PoolSnapshot {
+    fn from(row: &PoolRow) -> Self {
+        Self {
+            pool: row.pool,
+            protocol: row.protocol.clone(),
+            tokens: [row.token0, row.token1].into_iter().flatten().map(|t| format!("{t:?}")).collect(),
+            fee_bps: row.fee_bps,
+        }
+    }
+}
+
+/// Fee `pool` actually charges: the configured override, else the constant-product fee for V2 forks.
+/// `None` for protocols whose fee the bot doesn't know (e.g. Platypus, whose fee depends on coverage ratios).
+fn known_fee_bps(protocol: &Protocol, pool: Address) -> Option<u32> {
+    match protocol {
+        Protocol::TraderJoe | Protocol::Pangolin | Protocol::SushiSwap => {
+            Some(pool_fee_bps(pool, DEFAULT_FEE_BPS as u16) as u32)
+        }
+        _ => pool_fee_override(pool).map(u32::from),
+    }
+}
+
+/// Quote a CSV field when it holds a separator, quote or line break (RFC 4180).
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn write_csv(mut out: impl Write, rows: &[PoolRow]) -> Result<()> {
+    writeln!(out, "{CSV_HEADER}")?;
+    for row in rows {
+        writeln!(out, "{}", row.csv_line())?;
+    }
+    Ok(())
+}
+
+/// Symbol and decimals per token, from the token config first and the token contract otherwise.
+struct TokenMetadata {
+    known: TokenConfig,
+    fetched: HashMap<Address, (Option<String>, Option<u8>)>,
+}
+
+impl TokenMetadata {
+    fn new() -> Self {
+        Self {
+            known: TokenConfig::new(),
+            fetched: HashMap::new(),
+        }
+    }
+
+    async fn get(&mut self, provider: &Provider<Http>, token: Address) -> (Option<String>, Option<u8>) {
+        if let Some(info) = self.known.get_token_by_address(&format!("{token:?}")) {
+            return (Some(info.symbol.clone()), Some(info.decimals));
+        }
+        if let Some(metadata) = self.fetched.get(&token) {
+            return metadata.clone();
+        }
+
+        let symbol = erc20_call(provider, token, "symbol()", ParamType::String)
+            .await
+            .and_then(|t| t.into_string());
+        let decimals = erc20_call(provider, token, "decimals()", ParamType::Uint(8))
+            .await
+            .and_then(|t| t.into_uint())
+            .filter(|d| *d <= U256::from(u8::MAX))
+            .map(|d| d.as_u32() as u8);
+        self.fetched.insert(token, (symbol.clone(), decimals));
+        (symbol, decimals)
+    }
+}
+
+/// `None` when the call reverts or returns something else than `kind` (e.g. a bytes32 symbol).
+async fn erc20_call(provider: &Provider<Http>, token: Address, signature: &str, kind: ParamType) -> Option<abi::Token> {
+    let tx: TypedTransaction = TransactionRequest::new().to(token).data(id(signature).to_vec()).into();
+    let output = provider.call(&tx, None).await.ok()?;
+    abi::decode(&[kind], &output).ok()?.pop()
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    let protocols: Vec<Protocol> = match &args.protocol {
+        Some(name) => vec![parse_protocol(name)?],
+        None => SUPPORTED_PROTOCOLS.to_vec(),
+    };
+
+    let provider = Provider::<Http>::try_from(args.http_config.rpc_url.as_str())?;
+    let block = match args.block {
+        Some(block) => block,
+        None => provider.get_block_number().await?.as_u64(),
+    };
+    let indexer = DexIndexer::new(&args.http_config.rpc_url).await?;
+
+    let mut pools = vec![];
+    for protocol in &protocols {
+        for pool in indexer.get_all_pools(protocol)? {
+            let address = pool.pool.to_string().parse::<Address>()?;
+            pools.push((address, protocol.to_string(), known_fee_bps(protocol, address)));
+        }
+    }
+    pools.sort();
+    pools.dedup();
+
+    let mut cache = ReserveCache::new();
+    let addresses = pools.iter().map(|(pool, ..)| *pool).collect::<Vec<_>>();
+    cache.prefetch(&provider, &addresses, block).await;
+
+    let mut metadata = TokenMetadata::new();
+    let mut rows = Vec::with_capacity(pools.len());
+    for (pool, protocol, fee_bps) in pools {
+        let mut row = PoolRow {
+            pool,
+            protocol,
+            fee_bps,
+            ..Default::default()
+        };
+        if let Ok((token0, token1)) = cache.tokens(&provider, pool).await {
+            (row.token0_symbol, row.token0_decimals) = metadata.get(&provider, token0).await;
+            (row.token1_symbol, row.token1_decimals) = metadata.get(&provider, token1).await;
+            (row.token0, row.token1) = (Some(token0), Some(token1));
+        }
+        if let Ok((reserve0, reserve1)) = cache.get(&provider, pool, block).await {
+            (row.reserve0, row.reserve1) = (Some(reserve0.to_string()), Some(reserve1.to_string()));
+        }
+        rows.push(row);
+    }
+
+    let file = fs::File::create(&args.output).with_context(|| format!("create {}", args.output.display()))?;
+    let out = std::io::BufWriter::new(file);
+    match args.format {
+        Format::Csv => write_csv(out, &rows)?,
+        Format::Json => serde_json::to_writer_pretty(out, &rows.iter().map(PoolSnapshot::from).collect::<Vec<_>>())?,
+    }
+
+    println!("exported {} pools at block {block} to {}", rows.len(), args.output.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_csv() {
+        let rows = vec![
+            PoolRow {
+                pool: Address::from_low_u64_be(1),
+                protocol: "TraderJoe".to_string(),
+                token0: Some(Address::from_low_u64_be(2)),
+                token0_symbol: Some("A,\"B\"".to_string()),
+                token0_decimals: Some(18),
+                token1: Some(Address::from_low_u64_be(3)),
+                token1_symbol: None,
+                token1_decimals: None,
+                reserve0: Some("1000".to_string()),
+                reserve1: Some("2000".to_string()),
+                fee_bps: Some(30),
+            },
+            PoolRow {
+                pool: Address::from_low_u64_be(4),
+                protocol: "Platypus".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let mut out = vec![];
+        write_csv(&mut out, &rows).unwrap();
+        let lines = String::from_utf8(out).unwrap().lines().map(str::to_string).collect::<Vec<_>>();
+
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[0].split(',').count(), 11);
+        assert_eq!(
+            lines[1],
+            format!(
+                "{:?},TraderJoe,{:?},\"A,\"\"B\"\"\",18,{:?},,,1000,2000,30",
+                Address::from_low_u64_be(1),
+                Address::from_low_u64_be(2),
+                Address::from_low_u64_be(3)
+            )
+        );
+        assert_eq!(lines[2], format!("{:?},Platypus,,,,,,,,,", Address::from_low_u64_be(4)));
+    }
+
+    #[test]
+    fn test_json_rows_load_as_snapshots() {
+        let rows = vec![
+            PoolRow {
+                pool: Address::from_low_u64_be(1),
+                protocol: "TraderJoe".to_string(),
+                token0: Some(Address::from_low_u64_be(2)),
+                token1: Some(Address::from_low_u64_be(3)),
+                fee_bps: Some(30),
+                ..Default::default()
+            },
+            PoolRow {
+                pool: Address::from_low_u64_be(4),
+                protocol: "Platypus".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let json = serde_json::to_string(&rows.iter().map(PoolSnapshot::from).collect::<Vec<_>>()).unwrap();
+        let snapshots: Vec<PoolSnapshot> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            snapshots[0].tokens,
+            vec![format!("{:?}", Address::from_low_u64_be(2)), format!("{:?}", Address::from_low_u64_be(3))]
+        );
+        assert_eq!(snapshots[0].fee_bps, Some(30));
+        assert!(snapshots[1].tokens.is_empty());
+        assert_eq!(snapshots[1].fee_bps, None);
+    }
+}
@@ -86,3 +86,52 @@ pub fn init_console_logger_with_directives(level: Option<LevelFilter>, directive
         .with(env_filter)
         .init();
 }
+
+/// Spans exported over OTLP, whatever the console shows. The exporter's own gRPC stack is dropped so exporting
+/// a batch doesn't emit spans that get exported in the next one.
+const OTLP_DIRECTIVES: &str = "info,h2=off,hyper=off,tonic=off";
+
+/// Flushes buffered spans and shuts the OTLP exporter down when dropped. Hold it until the process exits.
+#[must_use = "spans buffered at exit are lost when the guard is dropped early"]
+pub struct OtlpGuard {
+    enabled: bool,
+}
+
+impl Drop for OtlpGuard {
+    fn drop(&mut self) {
+        if self.enabled {
+            opentelemetry::global::shutdown_tracer_provider();
+        }
+    }
+}
+
+/// Console logger filtered by `directives`, plus, with `otlp_endpoint`, spans at info and above exported over
+/// OTLP/gRPC as service `service_name` so one opportunity can be followed stage by stage in a trace UI.
+pub fn init_with_otlp(directives: &str, service_name: &str, otlp_endpoint: Option<&str>) -> eyre::Result<OtlpGuard> {
+    let console_layer = fmt::layer().with_filter(EnvFilter::new(directives));
+
+    let otlp_layer = match otlp_endpoint {
+        Some(endpoint) => {
+            let tracer = opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+                .with_trace_config(opentelemetry_sdk::trace::config().with_resource(opentelemetry_sdk::Resource::new(
+                    vec![opentelemetry::KeyValue::new("service.name", service_name.to_string())],
+                )))
+                .install_batch(opentelemetry_sdk::runtime::Tokio)?;
+            Some(
+                tracing_opentelemetry::layer()
+                    .with_tracer(tracer)
+                    .with_filter(EnvFilter::new(OTLP_DIRECTIVES)),
+            )
+        }
+        None => None,
+    };
+    let enabled = otlp_layer.is_some();
+
+    tracing_subscriber::registry()
+        .with(console_layer)
+        .with(otlp_layer)
+        .init();
+    Ok(OtlpGuard { enabled })
+}
@@ -1,3 +1,7 @@
+pub mod bench;
+pub mod diff_snapshots;
+pub mod export_pools;
 pub mod logger;
 pub mod object_pool;
 pub mod pool_ids;
+pub mod scan;
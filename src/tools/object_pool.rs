@@ -25,6 +25,28 @@ impl<T> ObjectPool<T> {
         Self { objects }
     }
 
+    /// Like `new`, for objects whose setup can fail: the first error is returned once every thread is done.
+    pub fn try_new<F>(num_objects: usize, init_fn: F) -> eyre::Result<Self>
+    where
+        F: Fn() -> eyre::Result<T> + Send + Sync + 'static,
+        T: Send + Sync + 'static,
+    {
+        let init_fn = Arc::new(init_fn);
+        let mut handles = Vec::with_capacity(num_objects);
+
+        for _ in 0..num_objects {
+            let init_fn = init_fn.clone();
+            handles.push(std::thread::spawn(move || (init_fn)().map(Arc::new)));
+        }
+
+        let objects = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        Ok(Self { objects })
+    }
+
     // get the one with the least refcount
     pub fn get(&self) -> Arc<T> {
         self.objects
@@ -0,0 +1,207 @@
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::Address,
+};
+use eyre::{Context, Result};
+use object_pool::ObjectPool;
+use serde::Serialize;
+use tracing::{debug, info};
+
+use crate::{
+    common::{get_chain_id, price_oracle::AVAX_BLOCK_TIME},
+    config::is_wavax,
+    dex::{Defi, Path, ReserveCache, UniswapV2Calculator},
+    simulator::{HttpSimulator, Simulator},
+    utils::token_config::TokenConfig,
+    HttpConfig,
+};
+
+#[derive(Clone, Debug, Parser)]
+pub struct Args {
+    /// Tokens to scan, comma separated. Every token of the token config but WAVAX when empty.
+    #[arg(long, value_delimiter = ',')]
+    pub tokens: Vec<String>,
+
+    /// Report paths whose marginal spread at the block, net of fees, is at least this many bps
+    #[arg(long, default_value_t = 10)]
+    pub min_spread_bps: u32,
+
+    #[arg(long, default_value_t = 2)]
+    pub max_hops: usize,
+
+    /// Append every finding to this file as a JSON line, e.g. to build a heatmap
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Scan a single block and exit instead of following the chain
+    #[arg(long, default_value_t = false)]
+    pub once: bool,
+
+    #[command(flatten)]
+    pub http_config: HttpConfig,
+}
+
+/// A path whose reserves showed an edge at `block`. Amounts are raw units of `token`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub block: u64,
+    pub token: String,
+    pub symbol: Option<String>,
+    pub pools: Vec<Address>,
+    pub protocols: Vec<String>,
+    pub spread_bps: f64,
+    pub amount_in: String,
+    pub profit: String,
+}
+
+/// Routing and sizing from reserves only: paths are enumerated once per token, then every block costs one
+/// batched reserve read and closed-form math per path. Nothing is simulated and no tx is built, so
+/// findings are leads, not executable arbs (fees on transfer, non-V2 pools and gas are ignored).
+pub async fn run(args: Args) -> Result<()> {
+    crate::tools::logger::init_console_logger(None);
+    let rpc_url = args.http_config.rpc_url.clone();
+    let chain_id = get_chain_id(&rpc_url).await?;
+    let provider = Provider::<Http>::try_from(rpc_url.as_str())?;
+
+    // only used to construct the searcher: nothing here simulates
+    let simulator_pool: ObjectPool<Box<dyn Simulator>> = {
+        let rpc_url = rpc_url.clone();
+        ObjectPool::new(1, move || {
+            let rpc_url = rpc_url.clone();
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(async { Box::new(HttpSimulator::new(&rpc_url, Some(chain_id)).await.unwrap()) as Box<dyn Simulator> })
+        })
+    };
+    let defi = Defi::new(&rpc_url, Arc::new(simulator_pool)).await?;
+
+    let token_config = TokenConfig::new();
+    let tokens = if args.tokens.is_empty() {
+        let mut tokens = token_config.tokens.keys().filter(|t| !is_wavax(t)).cloned().collect::<Vec<_>>();
+        tokens.sort();
+        tokens
+    } else {
+        args.tokens.iter().map(|t| t.to_lowercase()).collect()
+    };
+
+    let start = Instant::now();
+    let mut paths: HashMap<String, Vec<Path>> = HashMap::new();
+    for token in &tokens {
+        match defi.find_sell_paths_with_hops(token, args.max_hops).await {
+            Ok(token_paths) => {
+                paths.insert(token.clone(), token_paths);
+            }
+            Err(error) => debug!(token, "no paths: {error:#}"),
+        }
+    }
+    let all_paths = paths.values().flatten().cloned().collect::<Vec<_>>();
+    info!(
+        tokens = tokens.len(),
+        paths = all_paths.len(),
+        elapsed = ?start.elapsed(),
+        "routes enumerated"
+    );
+
+    let mut output = match &args.output {
+        Some(path) => Some(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("open {}", path.display()))?,
+        ),
+        None => None,
+    };
+
+    let mut cache = ReserveCache::new();
+    let mut last_block = 0;
+    loop {
+        let block = provider.get_block_number().await?.as_u64();
+        if block == last_block {
+            tokio::time::sleep(AVAX_BLOCK_TIME / 4).await;
+            continue;
+        }
+        last_block = block;
+
+        let start = Instant::now();
+        defi.prefetch_reserves(&all_paths, block, &mut cache).await;
+        let mut findings = 0;
+        for (token, token_paths) in &paths {
+            for path in token_paths {
+                let Some(mut finding) = evaluate(&defi, token, path, block, &mut cache, args.min_spread_bps).await
+                else {
+                    continue;
+                };
+                finding.symbol = token_config.get_token_by_address(token).map(|t| t.symbol.clone());
+                findings += 1;
+                info!(
+                    block,
+                    token = finding.symbol.as_deref().unwrap_or(token),
+                    protocols = ?finding.protocols,
+                    spread_bps = format!("{:.1}", finding.spread_bps),
+                    amount_in = %finding.amount_in,
+                    profit = %finding.profit,
+                    "spread found"
+                );
+                if let Some(output) = &mut output {
+                    writeln!(output, "{}", serde_json::to_string(&finding)?)?;
+                }
+            }
+        }
+
+        let elapsed = start.elapsed();
+        info!(
+            block,
+            tokens = paths.len(),
+            paths = all_paths.len(),
+            findings,
+            ?elapsed,
+            tokens_per_sec = format!("{:.0}", paths.len() as f64 / elapsed.as_secs_f64().max(f64::EPSILON)),
+            "block scanned"
+        );
+
+        if args.once {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// The finding for `path` at `block` when its spread clears `min_spread_bps`. Paths through pools without
+/// reserves are skipped.
+async fn evaluate(
+    defi: &Defi,
+    token: &str,
+    path: &Path,
+    block: u64,
+    cache: &mut ReserveCache,
+    min_spread_bps: u32,
+) -> Option<Finding> {
+    let pools = defi.cached_path_reserves(path, block, cache).await.ok()?;
+    let spread_bps = UniswapV2Calculator::path_spread_bps(&pools).ok()?;
+    if spread_bps < min_spread_bps as f64 {
+        return None;
+    }
+    let (amount_in, profit) = UniswapV2Calculator::get_optimal_profit(&pools)?;
+
+    Some(Finding {
+        block,
+        token: token.to_string(),
+        symbol: None,
+        pools: path.path.iter().map(|dex| dex.pool_address()).collect(),
+        protocols: path.path.iter().map(|dex| dex.protocol().to_string()).collect(),
+        spread_bps,
+        amount_in: amount_in.to_string(),
+        profit: profit.to_string(),
+    })
+}
@@ -1,13 +1,81 @@
 use std::fmt;
-use ethers::types::{TransactionRequest, TransactionReceipt, Log, H256};
+use ethers::types::{
+    transaction::{eip2718::TypedTransaction, eip2930::AccessList},
+    Address, Eip2930TransactionRequest, Log, TransactionReceipt, TransactionRequest, H256, U256,
+};
 use serde::{Deserialize, Serialize};
 use crate::engine::executor::telegram_message::Message;
 
 #[derive(Debug, Clone)]
 pub enum Action {
     NotifyViaTelegram(Message), 
-    ExecutePublicTx(TransactionRequest),
-    MevRelaySubmitBid((TransactionRequest, u64, H256)),
+    ExecutePublicTx(QuotedTx),
+    MevRelaySubmitBid((QuotedTx, U256, H256)),
+}
+
+/// A transaction together with the block number its quote was computed at.
+#[derive(Debug, Clone)]
+pub struct QuotedTx {
+    pub tx: TransactionRequest,
+    pub quoted_at_block: u64,
+    /// Access list from simulating `tx`; when set the tx is sent as EIP-2930.
+    pub access_list: Option<AccessList>,
+    /// Token the arb trades, to put it on cooldown once the tx is submitted.
+    pub token: Option<String>,
+    /// Token and `amount_in` an own-funds (non-flashloan) arb spends from the sender's balance, checked
+    /// against the position limit before sending.
+    pub own_funds: Option<(Address, U256)>,
+}
+
+impl QuotedTx {
+    pub fn new(tx: TransactionRequest, quoted_at_block: u64) -> Self {
+        Self {
+            tx,
+            quoted_at_block,
+            access_list: None,
+            token: None,
+            own_funds: None,
+        }
+    }
+
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    pub fn with_own_funds(mut self, token_in: Address, amount_in: U256) -> Self {
+        self.own_funds = Some((token_in, amount_in));
+        self
+    }
+
+    pub fn with_access_list(mut self, access_list: Option<AccessList>) -> Self {
+        self.access_list = access_list;
+        self
+    }
+
+    /// The tx to sign: EIP-2930 if an access list was attached, legacy otherwise.
+    pub fn typed_tx(&self) -> TypedTransaction {
+        match &self.access_list {
+            Some(access_list) => Eip2930TransactionRequest::new(self.tx.clone(), access_list.clone()).into(),
+            None => self.tx.clone().into(),
+        }
+    }
+}
+
+impl Action {
+    /// The transaction the action carries, if any.
+    pub fn quoted_tx(&self) -> Option<&QuotedTx> {
+        match self {
+            Action::ExecutePublicTx(quoted) => Some(quoted),
+            Action::MevRelaySubmitBid((quoted, _, _)) => Some(quoted),
+            Action::NotifyViaTelegram(_) => None,
+        }
+    }
+
+    /// The block the action's quote was computed at, if it carries a transaction.
+    pub fn quoted_at_block(&self) -> Option<u64> {
+        self.quoted_tx().map(|quoted| quoted.quoted_at_block)
+    }
 }
 
 impl From<Message> for Action {
@@ -16,8 +84,8 @@ impl From<Message> for Action {
     }
 }
 
-impl From<TransactionRequest> for Action {
-    fn from(tx: TransactionRequest) -> Self {
+impl From<QuotedTx> for Action {
+    fn from(tx: QuotedTx) -> Self {
         Self::ExecutePublicTx(tx)
     }
 }
@@ -26,6 +94,17 @@ impl From<TransactionRequest> for Action {
 pub enum Event {
     PublicTx(TransactionReceipt, Vec<Log>),
     PendingTx(ethers::types::Transaction),
+    /// SIGUSR1 arrived: log the in-flight opportunities.
+    DumpRequested,
+    System(SystemEvent),
+}
+
+/// Health of the bot's inputs rather than chain activity, for alerting.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SystemEvent {
+    /// The AVAX/USD feed hasn't updated within its max age; `age` is `None` if it was never read.
+    PriceFeedStale { age: Option<std::time::Duration> },
+    PriceFeedRecovered { avax_usd: f64 },
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
@@ -34,7 +113,7 @@ pub enum Source {
     Mempool,
     MevRelay {
         opp_tx_hash: H256,
-        bid_amount: u64,
+        bid_amount: U256,
         start: u64,
         deadline: u64,
         arb_found: u64,
@@ -86,7 +165,7 @@ impl Source {
         }
     }
 
-    pub fn with_bid_amount(self, bid_amount: u64) -> Self {
+    pub fn with_bid_amount(self, bid_amount: U256) -> Self {
         match self {
             Source::MevRelay { 
                 opp_tx_hash,
@@ -105,3 +184,54 @@ impl Source {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::transaction::eip2930::AccessListItem;
+
+    fn quoted() -> QuotedTx {
+        let tx = TransactionRequest::new()
+            .to(Address::repeat_byte(0x11))
+            .data(vec![0xde, 0xad])
+            .gas(300_000)
+            .gas_price(25_000_000_000u64)
+            .nonce(7)
+            .chain_id(43114);
+        QuotedTx::new(tx, 100)
+    }
+
+    fn access_list() -> AccessList {
+        AccessList(vec![AccessListItem {
+            address: Address::repeat_byte(0x22),
+            storage_keys: vec![H256::from_low_u64_be(8), H256::from_low_u64_be(9)],
+        }])
+    }
+
+    #[test]
+    fn test_typed_tx_without_access_list_is_legacy() {
+        let tx = quoted().typed_tx();
+        assert!(matches!(tx, TypedTransaction::Legacy(_)));
+        assert_eq!(tx.access_list(), None);
+        // legacy txs are plain RLP lists, no type byte
+        assert!(tx.rlp()[0] >= 0xc0);
+    }
+
+    #[test]
+    fn test_typed_tx_with_access_list_is_eip2930() {
+        let quoted = quoted().with_access_list(Some(access_list()));
+        let mut tx = quoted.typed_tx();
+
+        let TypedTransaction::Eip2930(inner) = &tx else {
+            panic!("expected an EIP-2930 tx, got {tx:?}");
+        };
+        assert_eq!(inner.access_list, access_list());
+        assert_eq!(inner.tx, quoted.tx);
+        assert_eq!(tx.rlp()[0], 0x01);
+
+        // re-pricing before sending keeps the tx type and its access list
+        tx.set_gas_price(30_000_000_000u64);
+        assert_eq!(tx.gas_price(), Some(U256::from(30_000_000_000u64)));
+        assert_eq!(tx.access_list(), Some(&access_list()));
+    }
+}
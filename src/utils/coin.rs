@@ -9,6 +9,8 @@ use ethers::{
 };
 use std::sync::Arc;
 
+use crate::utils::math::u256_to_f64;
+
 pub const AVAX_NATIVE_ADDRESS: Address = Address::zero(); // 0x0 represents native AVAX
 
 pub async fn get_gas_balance(
@@ -90,23 +92,12 @@ pub fn is_native_token(token_address: &Address) -> bool {
 }
 
 pub fn format_avax_with_symbol(value: U256) -> String {
-    let one_avax = U256::from(10u64.pow(18)); // 1 AVAX = 10^18 wei
-    
-    // Convert to f64 for display (losing some precision for very large numbers)
-    let value_f64 = value.as_u128() as f64;
-    let one_avax_f64 = one_avax.as_u128() as f64;
-    let avax_value = value_f64 / one_avax_f64;
-
-    format!("{:.6} AVAX", avax_value)
+    format!("{:.6} AVAX", wei_to_avax(value))
 }
 
 pub fn format_token_with_decimals(value: U256, decimals: u8, symbol: &str) -> String {
-    let divisor = U256::from(10u64.pow(decimals as u32));
-    
-    // Convert to f64 for display
-    let value_f64 = value.as_u128() as f64;
-    let divisor_f64 = divisor.as_u128() as f64;
-    let token_value = value_f64 / divisor_f64;
+    // Convert to f64 for display (losing some precision for very large numbers)
+    let token_value = u256_to_f64(value) / 10f64.powi(decimals as i32);
 
     format!("{:.6} {}", token_value, symbol)
 }
@@ -121,8 +112,7 @@ pub fn parse_avax_amount(amount_str: &str) -> Result<U256> {
 }
 
 pub fn wei_to_avax(wei: U256) -> f64 {
-    let wei_f64 = wei.as_u128() as f64;
-    wei_f64 / 1e18
+    u256_to_f64(wei) / 1e18
 }
 
 pub fn avax_to_wei(avax: f64) -> U256 {
@@ -143,6 +133,9 @@ mod tests {
         let half_avax = one_avax / 2;
         let result = format_avax_with_symbol(half_avax);
         assert_eq!(result, "0.500000 AVAX");
+
+        // beyond u64::MAX wei
+        assert_eq!(format_avax_with_symbol(one_avax * 20), "20.000000 AVAX");
     }
 
     #[test]
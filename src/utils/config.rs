@@ -1,32 +1,994 @@
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use sui_sdk::SUI_COIN_TYPE;
+use dex_indexer::types::Protocol;
+use ethers::types::{Address, U256};
+use eyre::{eyre, Result};
+use once_cell::sync::Lazy;
+
+use crate::{common::price_oracle::price_oracle, utils::math::u256_to_f64};
 
 pub const GAS_BUDGET: u64 = 10_000_000_000;
 pub const MAX_SQRT_PRICE_X64: u128 = 79226673515401279992447579055;
 pub const MIN_SQRT_PRICE_X64: u128 = 4295048016;
 
-pub fn pegged_coin_types() -> HashSet<&'static str> {
-    HashSet::from_iter([
-        SUI_COIN_TYPE,
-        // USDC
-        "0x5d4b302506645c37ff133b98c4b50a5ae14841659738d6d733d59d0d217a93bf::coin::COIN",
-        // USDT
-        "0xc060006111016b8a020ad5b33834984a437aaa7d3c74c18e09a95d48aceab08c::coin::COIN",
-        // WETH
-        "0xaf8cd5edc19c4512f4259f0bee101a40d41ebed738ade5874359610ef8eeced5::coin::COIN",
-        // USDC
-        "0xb231fcda8bbddb31f2ef02e6161444aec64a514e2c89279584ac9806ce9cf037::coin::COIN",
-        // USDC
-        "0xdba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7::usdc::USDC",
-        // Bucket USD
-        "0xce7ff77a83ea0cb6fd39bd8748e2ec89a3f41e8efdc3f4eb123e0ca37b184db2::buck::BUCK",
-    ])
+/// Stablecoins treated as pegged to ~$1. Used as a routing shortcut (pegged coins route straight to
+/// WAVAX) and to value profit without a price oracle. Overridable at runtime via `Tunables::set_pegged_coin_types`.
+pub const DEFAULT_PEGGED_COIN_TYPES: [&str; 5] = [
+    // USDC
+    "0xb97ef9ef8734c71904d8002f8b6bc66dd9c48a6e",
+    // USDC.e
+    "0xa7d7079b0fead91f3e65f86e8915cb59c1a4c664",
+    // USDT
+    "0x9702230a8ea53601f5cd2dc00fdbc13d4df4a8c7",
+    // USDT.e
+    "0xc7198437980c041c805a1edcba50c1ce5db95118",
+    // DAI.e
+    "0xd586e7f844cea2f87f50152665bcbc2c279d8d70",
+];
+
+/// Settings a running bot can change on reload. Readers take one snapshot and see every setting from the same
+/// reload; a reload swaps in a whole new snapshot with a single store.
+#[derive(Debug, Clone)]
+pub struct Tunables {
+    /// Stablecoins treated as pegged to ~$1, lowercase.
+    pegged_coin_types: HashSet<String>,
+    /// Per-pool swap fee corrections (bps), consulted before the protocol default. Meant for hotfixing a single
+    /// mispriced pool while the adapter is being fixed.
+    pool_fee_overrides: HashMap<Address, u16>,
+    /// Canonical names of the protocols pools are built for. `None` enables every supported protocol.
+    enabled_protocols: Option<HashSet<String>>,
+    /// Per-provider flashloan premiums (bps of the borrowed amount), keyed by lowercase provider name: a
+    /// protocol's canonical name, or `FALLBACK_FLASHLOAN_PROVIDER`.
+    flashloan_premiums: HashMap<String, u32>,
+    /// Address routers credit referral rebates to. The V2 adapters append it to their router calls, and the
+    /// rebates it is estimated to earn on those hops count towards profit.
+    referral_address: Option<Address>,
+    /// Per-protocol referral rebates, as the share of the swap fee paid back in bps of the fee, keyed by
+    /// lowercase protocol name.
+    referral_rebates: HashMap<String, u32>,
+    /// Debug mode: before building a final tx, compare the path's pool reserves at the quote block with the
+    /// latest block and log divergences.
+    check_reserves: bool,
+    /// Price trades whose simulation reported a zero or negative gas cost as is, instead of discarding them.
+    trust_nonpositive_gas_cost: bool,
+    /// Keep a token's first-hop pools by simulated output for a mid-grid trade size rather than by liquidity
+    /// when truncating the candidates. Costs one simulation per candidate pool.
+    rank_pools_by_output: bool,
+    /// Log one `arb::decision` record per evaluated opportunity, with its profit breakdown and why it was or
+    /// wasn't traded. Costs a reserves read per hop for the price impact.
+    log_decisions: bool,
+    /// Also evaluate each opportunity funded from the sender's own balance rather than a flashloan. Only useful
+    /// when the sender holds the traded tokens, and doubles the sell-side simulations of every trial.
+    own_funds: bool,
+    /// How much more an own-funds route must net than the flashloan route, in bps of `amount_in`, to be
+    /// picked. A flashloan pays a premium but never puts principal at risk, so close calls go to the flashloan.
+    flashloan_preference_bps: u32,
+    /// Output a swap tx that sets its own minimum accepts below the adapter's quote, in bps of the quote.
+    swap_slippage_bps: u32,
+    /// Marginal spread, in bps net of fees, a cyclic path needs at the quote block to be simulated at all. Near
+    /// mirrored pools otherwise show tiny rounding "arbs" that never clear gas.
+    min_spread_bps: Option<u32>,
+    /// Spread, in bps net of fees, at which a token's direct WAVAX pools on two protocols are traded as a 2-leg
+    /// cycle without the full path search.
+    direct_pair_spread_bps: Option<u32>,
+    /// Cap on the candidate paths fully simulated per opportunity.
+    max_paths_evaluated: Option<usize>,
+    /// Consecutive `getReserves` reads finding no code after which a pool is taken out of routing; 0 never
+    /// delists.
+    delist_after_failures: usize,
+    /// Paths trading on fewer protocols than this are dropped before simulation; round trips within one venue
+    /// can't beat its fees.
+    min_distinct_protocols: usize,
+    /// Wall-clock budget for expanding one token's routes. Past it the search returns the routes found so far.
+    route_search_budget: Option<Duration>,
+    /// How long a token's new opportunities are suppressed after one of its arbs was submitted, and after one
+    /// failed to submit.
+    arb_cooldown_success: Option<Duration>,
+    arb_cooldown_failure: Option<Duration>,
+    /// Payment a private relay's block builder requires to include an arb: a share of its profit in bps, plus a
+    /// fixed amount in wei of the gas token. 0 and 0 when arbs are sent publicly.
+    builder_payment_bps: u32,
+    builder_payment_wei: u64,
+    /// Attach an EIP-2930 access list, created by simulating the final tx, to every arb tx.
+    use_access_list: bool,
+    /// Trade sizes probed before refining, ascending.
+    probe_amounts: Vec<U256>,
+    /// Per-token probe grids, keyed by lowercase token address. Shallow tokens want smaller probes, deep ones
+    /// larger.
+    token_probe_amounts: HashMap<String, Vec<U256>>,
+}
+
+impl Default for Tunables {
+    fn default() -> Self {
+        Self {
+            pegged_coin_types: DEFAULT_PEGGED_COIN_TYPES.iter().map(|c| c.to_string()).collect(),
+            pool_fee_overrides: HashMap::new(),
+            enabled_protocols: None,
+            flashloan_premiums: HashMap::new(),
+            referral_address: None,
+            referral_rebates: HashMap::new(),
+            check_reserves: false,
+            trust_nonpositive_gas_cost: false,
+            rank_pools_by_output: false,
+            log_decisions: false,
+            own_funds: false,
+            flashloan_preference_bps: DEFAULT_FLASHLOAN_PREFERENCE_BPS,
+            swap_slippage_bps: DEFAULT_SWAP_SLIPPAGE_BPS,
+            min_spread_bps: None,
+            direct_pair_spread_bps: None,
+            max_paths_evaluated: None,
+            delist_after_failures: 0,
+            min_distinct_protocols: DEFAULT_MIN_DISTINCT_PROTOCOLS,
+            route_search_budget: None,
+            arb_cooldown_success: None,
+            arb_cooldown_failure: None,
+            builder_payment_bps: 0,
+            builder_payment_wei: 0,
+            use_access_list: false,
+            probe_amounts: default_probe_amounts(),
+            token_probe_amounts: HashMap::new(),
+        }
+    }
+}
+
+impl Tunables {
+    /// Replace the peg set, e.g. from `--pegged-coins` / `PEGGED_COINS`. An empty list keeps the current set.
+    pub fn set_pegged_coin_types<I, S>(&mut self, coin_types: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let coin_types: HashSet<String> = coin_types
+            .into_iter()
+            .map(|c| c.as_ref().trim().to_lowercase())
+            .filter(|c| !c.is_empty())
+            .collect();
+        if !coin_types.is_empty() {
+            self.pegged_coin_types = coin_types;
+        }
+    }
+
+    pub fn set_pool_fee_override(&mut self, pool: Address, fee_bps: u16) {
+        self.pool_fee_overrides.insert(pool, fee_bps);
+    }
+
+    pub fn set_pool_fee_overrides(&mut self, overrides: HashMap<Address, u16>) {
+        self.pool_fee_overrides = overrides;
+    }
+
+    /// Restrict pool discovery to `protocols`. An empty list enables all of them.
+    pub fn set_enabled_protocols(&mut self, protocols: &[Protocol]) {
+        self.enabled_protocols = (!protocols.is_empty()).then(|| protocols.iter().map(|p| p.to_string()).collect());
+    }
+
+    pub fn set_flashloan_premiums(&mut self, premiums: HashMap<String, u32>) {
+        self.flashloan_premiums = premiums.into_iter().map(|(provider, bps)| (provider.to_lowercase(), bps)).collect();
+    }
+
+    pub fn set_referral_address(&mut self, address: Option<Address>) {
+        self.referral_address = address;
+    }
+
+    pub fn set_referral_rebates(&mut self, rebates: HashMap<String, u32>) {
+        self.referral_rebates = rebates.into_iter().map(|(protocol, bps)| (protocol.to_lowercase(), bps)).collect();
+    }
+
+    pub fn set_check_reserves(&mut self, enabled: bool) {
+        self.check_reserves = enabled;
+    }
+
+    pub fn set_trust_nonpositive_gas_cost(&mut self, enabled: bool) {
+        self.trust_nonpositive_gas_cost = enabled;
+    }
+
+    pub fn set_rank_pools_by_output(&mut self, enabled: bool) {
+        self.rank_pools_by_output = enabled;
+    }
+
+    pub fn set_log_decisions(&mut self, enabled: bool) {
+        self.log_decisions = enabled;
+    }
+
+    pub fn set_own_funds(&mut self, enabled: bool) {
+        self.own_funds = enabled;
+    }
+
+    pub fn set_flashloan_preference_bps(&mut self, bps: u32) {
+        self.flashloan_preference_bps = bps;
+    }
+
+    pub fn set_swap_slippage_bps(&mut self, bps: u32) {
+        self.swap_slippage_bps = bps.min(10_000);
+    }
+
+    pub fn set_min_spread_bps(&mut self, bps: Option<u32>) {
+        self.min_spread_bps = bps;
+    }
+
+    pub fn set_direct_pair_spread_bps(&mut self, bps: Option<u32>) {
+        self.direct_pair_spread_bps = bps;
+    }
+
+    /// `None` or 0 evaluates all paths.
+    pub fn set_max_paths_evaluated(&mut self, max: Option<usize>) {
+        self.max_paths_evaluated = max.filter(|max| *max > 0);
+    }
+
+    pub fn set_delist_after_failures(&mut self, failures: Option<usize>) {
+        self.delist_after_failures = failures.unwrap_or_default();
+    }
+
+    pub fn set_min_distinct_protocols(&mut self, min: usize) {
+        self.min_distinct_protocols = min;
+    }
+
+    /// `None` or 0 searches until done.
+    pub fn set_route_search_budget_ms(&mut self, ms: Option<u64>) {
+        self.route_search_budget = ms.filter(|ms| *ms > 0).map(Duration::from_millis);
+    }
+
+    /// 0 disables either cooldown.
+    pub fn set_arb_cooldown_ms(&mut self, success_ms: u64, failure_ms: u64) {
+        let cooldown = |ms: u64| (ms > 0).then(|| Duration::from_millis(ms));
+        self.arb_cooldown_success = cooldown(success_ms);
+        self.arb_cooldown_failure = cooldown(failure_ms);
+    }
+
+    pub fn set_builder_payment(&mut self, bps: u32, fixed_wei: u64) {
+        self.builder_payment_bps = bps.min(10_000);
+        self.builder_payment_wei = fixed_wei;
+    }
+
+    pub fn set_use_access_list(&mut self, enabled: bool) {
+        self.use_access_list = enabled;
+    }
+
+    /// Replace the global grid, e.g. from `--probe-amounts` / `PROBE_AMOUNTS`. An empty list restores the
+    /// default decade grid.
+    pub fn set_probe_amounts(&mut self, amounts: &[U256]) {
+        self.probe_amounts = if amounts.is_empty() {
+            default_probe_amounts()
+        } else {
+            sorted_probe_amounts(amounts.to_vec())
+        };
+    }
+
+    pub fn set_token_probe_amounts(&mut self, overrides: HashMap<String, Vec<U256>>) {
+        self.token_probe_amounts = overrides
+            .into_iter()
+            .map(|(token, amounts)| (token.to_lowercase(), sorted_probe_amounts(amounts)))
+            .collect();
+    }
+}
+
+static TUNABLES: Lazy<RwLock<Arc<Tunables>>> = Lazy::new(Default::default);
+
+/// The current tunables. Hold on to the snapshot to read several settings from the same reload.
+pub fn tunables() -> Arc<Tunables> {
+    TUNABLES.read().unwrap().clone()
+}
+
+/// Swap in a whole new snapshot, e.g. a reload built from the current one.
+pub fn set_tunables(tunables: Tunables) {
+    *TUNABLES.write().unwrap() = Arc::new(tunables);
+}
+
+/// Change some tunables in place, leaving the rest as they are.
+pub fn update_tunables(update: impl FnOnce(&mut Tunables)) {
+    let mut current = TUNABLES.write().unwrap();
+    let mut tunables = Tunables::clone(&current);
+    update(&mut tunables);
+    *current = Arc::new(tunables);
+}
+
+pub fn pegged_coin_types() -> HashSet<String> {
+    tunables().pegged_coin_types.clone()
+}
+
+pub fn is_pegged_coin(coin_type: &str) -> bool {
+    tunables().pegged_coin_types.contains(&coin_type.to_lowercase())
+}
+
+/// Value `amount` of a pegged stablecoin at $1 per whole token. Returns `None` for non-pegged coins.
+pub fn pegged_usd_value(coin_type: &str, amount: U256, decimals: u8) -> Option<f64> {
+    if !is_pegged_coin(coin_type) {
+        return None;
+    }
+
+    Some(u256_to_f64(amount) / 10f64.powi(decimals as i32))
+}
+
+/// Wrapped-native (WAVAX) contract on C-Chain mainnet. Forks and test subnets can point elsewhere via
+/// `set_wavax_address`; everything else reads `wavax_address()`.
+pub const DEFAULT_WAVAX_ADDRESS: &str = "0xb31f66aa3c1e785363f0875a1b74e27b85fd66c7";
+
+static WAVAX_ADDRESS: Lazy<RwLock<String>> = Lazy::new(|| RwLock::new(DEFAULT_WAVAX_ADDRESS.to_string()));
+
+/// The configured wrapped-native address, lowercase.
+pub fn wavax_address() -> String {
+    WAVAX_ADDRESS.read().unwrap().clone()
+}
+
+pub fn is_wavax(coin_type: &str) -> bool {
+    WAVAX_ADDRESS.read().unwrap().eq_ignore_ascii_case(coin_type)
+}
+
+pub fn set_wavax_address(address: &str) {
+    *WAVAX_ADDRESS.write().unwrap() = address.trim().to_lowercase();
+}
+
+/// Canonical Multicall3 deployment, at the same address on C-Chain and most EVM chains.
+pub const DEFAULT_MULTICALL_ADDRESS: &str = "0xca11bde05977b3631167028862be2a173976ca11";
+
+static MULTICALL_ADDRESS: Lazy<RwLock<Option<Address>>> =
+    Lazy::new(|| RwLock::new(Some(DEFAULT_MULTICALL_ADDRESS.parse().unwrap())));
+
+/// Multicall3 contract batched reserve reads go through; `None` sends them as individual concurrent calls.
+pub fn multicall_address() -> Option<Address> {
+    *MULTICALL_ADDRESS.read().unwrap()
+}
+
+pub fn set_multicall_address(address: Option<Address>) {
+    *MULTICALL_ADDRESS.write().unwrap() = address;
+}
+
+/// Wrapped form of the token gas is paid in, when it isn't the wrapped native (subnets with a custom fee
+/// currency).
+static GAS_TOKEN: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
+
+/// The token gas is paid in, lowercase. Defaults to the wrapped native.
+pub fn gas_token() -> String {
+    GAS_TOKEN.read().unwrap().clone().unwrap_or_else(wavax_address)
+}
+
+pub fn set_gas_token(address: Option<&str>) {
+    *GAS_TOKEN.write().unwrap() = address.map(|a| a.trim().to_lowercase());
+}
+
+/// Token arbs are scored in. Gas is paid in the gas token and converted into this token at `gas_token_price`
+/// (whole profit-token units per 1 gas token).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfitToken {
+    pub address: String,
+    pub decimals: u8,
+    pub gas_token_price: f64,
+}
+
+impl Default for ProfitToken {
+    fn default() -> Self {
+        Self {
+            address: wavax_address(),
+            decimals: 18,
+            gas_token_price: 1.0,
+        }
+    }
+}
+
+impl ProfitToken {
+    pub fn new(address: &str, decimals: u8, gas_token_price: f64) -> Self {
+        Self {
+            address: address.to_lowercase(),
+            decimals,
+            gas_token_price,
+        }
+    }
+
+    pub fn is_profit_token(&self, coin_type: &str) -> bool {
+        self.address == coin_type.to_lowercase()
+    }
+
+    /// Whole profit-token units per 1 gas token. A USD-pegged profit token against AVAX gas uses the live
+    /// oracle price while it is fresh; everything else uses the configured price.
+    pub fn gas_token_price(&self) -> f64 {
+        self.price_of(&gas_token())
+    }
+
+    fn price_of(&self, gas_token: &str) -> f64 {
+        if is_wavax(gas_token) && is_pegged_coin(&self.address) {
+            if let Some(avax_usd) = price_oracle().avax_usd() {
+                return avax_usd;
+            }
+        }
+        self.gas_token_price
+    }
+
+    /// Convert a gas cost in wei of the gas token into raw units of the profit token.
+    pub fn gas_cost_in_token(&self, gas_cost_wei: i64) -> i128 {
+        self.convert_gas_cost(&gas_token(), gas_cost_wei)
+    }
+
+    fn convert_gas_cost(&self, gas_token: &str, gas_cost_wei: i64) -> i128 {
+        if self.address.eq_ignore_ascii_case(gas_token) {
+            return gas_cost_wei as i128;
+        }
+
+        let gas = gas_cost_wei as f64 / 1e18;
+        (gas * self.price_of(gas_token) * 10f64.powi(self.decimals as i32)).ceil() as i128
+    }
+
+    /// Convert raw units of the profit token into wei of the gas token, the inverse of `gas_cost_in_token`.
+    pub fn token_in_gas_wei(&self, amount: U256) -> U256 {
+        if self.address.eq_ignore_ascii_case(&gas_token()) {
+            return amount;
+        }
+
+        let tokens = u256_to_f64(amount) / 10f64.powi(self.decimals as i32);
+        U256::from((tokens / self.gas_token_price() * 1e18) as u128)
+    }
+}
+
+static PROFIT_TOKEN: Lazy<RwLock<ProfitToken>> = Lazy::new(|| RwLock::new(ProfitToken::default()));
+
+pub fn profit_token() -> ProfitToken {
+    PROFIT_TOKEN.read().unwrap().clone()
+}
+
+pub fn set_profit_token(profit_token: ProfitToken) {
+    *PROFIT_TOKEN.write().unwrap() = profit_token;
+}
+
+pub fn pool_fee_override(pool: Address) -> Option<u16> {
+    tunables().pool_fee_overrides.get(&pool).copied()
+}
+
+/// Fee for `pool`, preferring a configured override over `default_fee_bps`.
+pub fn pool_fee_bps(pool: Address, default_fee_bps: u16) -> u16 {
+    pool_fee_override(pool).unwrap_or(default_fee_bps)
+}
+
+/// Parse `<pool>:<fee_bps>` entries, e.g. from `--pool-fee-overrides` / `POOL_FEE_OVERRIDES`.
+pub fn parse_pool_fee_overrides<S: AsRef<str>>(entries: &[S]) -> Result<HashMap<Address, u16>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let entry = entry.as_ref().trim();
+            let (pool, fee) = entry
+                .split_once(':')
+                .ok_or_else(|| eyre!("invalid pool fee override {entry:?}, expected <pool>:<fee_bps>"))?;
+            let pool = pool.trim().parse::<Address>().map_err(|e| eyre!("invalid pool {pool:?}: {e}"))?;
+            let fee = fee.trim().parse::<u16>().map_err(|e| eyre!("invalid fee {fee:?}: {e}"))?;
+            eyre::ensure!(fee < 10_000, "fee {fee} bps for {pool:?} is not below 100%");
+            Ok((pool, fee))
+        })
+        .collect()
+}
+
+/// Hand-picked routes, keyed by lowercase token address: the pools of a cycle from the token back to itself,
+/// in trade order. A token with one skips the path search.
+static ROUTE_OVERRIDES: Lazy<RwLock<HashMap<String, Vec<Address>>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+pub fn route_override(token: &str) -> Option<Vec<Address>> {
+    ROUTE_OVERRIDES.read().unwrap().get(&token.to_lowercase()).cloned()
+}
+
+pub fn route_overrides() -> HashMap<String, Vec<Address>> {
+    ROUTE_OVERRIDES.read().unwrap().clone()
+}
+
+pub fn set_route_overrides(overrides: HashMap<String, Vec<Address>>) {
+    *ROUTE_OVERRIDES.write().unwrap() = overrides;
+}
+
+/// Drop `token`'s route override, so it goes back to the path search.
+pub fn remove_route_override(token: &str) {
+    ROUTE_OVERRIDES.write().unwrap().remove(&token.to_lowercase());
+}
+
+/// Parse `<token>:<pool>/<pool>/...` entries, e.g. from `--route-overrides` / `ROUTE_OVERRIDES`.
+pub fn parse_route_overrides<S: AsRef<str>>(entries: &[S]) -> Result<HashMap<String, Vec<Address>>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let entry = entry.as_ref().trim();
+            let (token, pools) = entry
+                .split_once(':')
+                .ok_or_else(|| eyre!("invalid route override {entry:?}, expected <token>:<pool>/<pool>/..."))?;
+            let token = token.trim().parse::<Address>().map_err(|e| eyre!("invalid token {token:?}: {e}"))?;
+            let pools = pools
+                .split('/')
+                .map(|pool| pool.trim().parse::<Address>().map_err(|e| eyre!("invalid pool {pool:?}: {e}")))
+                .collect::<Result<Vec<_>>>()?;
+            eyre::ensure!(pools.len() >= 2, "route override for {token:?} needs at least 2 pools to be a cycle");
+            Ok((format!("{token:?}"), pools))
+        })
+        .collect()
+}
+
+/// Aave's flashloan premium, charged by the lender flashloan paths fall back to when their first pool
+/// can't lend.
+pub const DEFAULT_FLASHLOAN_PREMIUM_BPS: u32 = 9;
+
+/// Name of the fallback flashloan lender in `--flashloan-premiums`.
+pub const FALLBACK_FLASHLOAN_PROVIDER: &str = "Aave";
+
+pub fn flashloan_premium_override(provider: &str) -> Option<u32> {
+    tunables().flashloan_premiums.get(&provider.to_lowercase()).copied()
+}
+
+/// Parse `<provider>:<premium_bps>` entries, e.g. from `--flashloan-premiums` / `FLASHLOAN_PREMIUMS`.
+pub fn parse_flashloan_premiums<S: AsRef<str>>(entries: &[S]) -> Result<HashMap<String, u32>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let entry = entry.as_ref().trim();
+            let (provider, premium) = entry
+                .split_once(':')
+                .ok_or_else(|| eyre!("invalid flashloan premium {entry:?}, expected <provider>:<premium_bps>"))?;
+            let provider = provider.trim();
+            if !provider.eq_ignore_ascii_case(FALLBACK_FLASHLOAN_PROVIDER) {
+                parse_protocol(provider)?;
+            }
+            let premium = premium.trim().parse::<u32>().map_err(|e| eyre!("invalid premium {premium:?}: {e}"))?;
+            eyre::ensure!(premium < 10_000, "premium {premium} bps for {provider} is not below 100%");
+            Ok((provider.to_string(), premium))
+        })
+        .collect()
+}
+
+pub fn referral_address() -> Option<Address> {
+    tunables().referral_address
+}
+
+/// Share of each protocol's swap fee rebated to the referral address, in bps of the fee; empty without a
+/// referral address.
+pub fn referral_rebates() -> HashMap<String, u32> {
+    let tunables = tunables();
+    if tunables.referral_address.is_none() {
+        return HashMap::new();
+    }
+    tunables.referral_rebates.clone()
+}
+
+/// Parse `<protocol>:<share_bps>` entries, e.g. from `--referral-rebates` / `REFERRAL_REBATES`.
+pub fn parse_referral_rebates<S: AsRef<str>>(entries: &[S]) -> Result<HashMap<String, u32>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let entry = entry.as_ref().trim();
+            let (protocol, share) = entry
+                .split_once(':')
+                .ok_or_else(|| eyre!("invalid referral rebate {entry:?}, expected <protocol>:<share_bps>"))?;
+            let protocol = parse_protocol(protocol.trim())?;
+            let share = share.trim().parse::<u32>().map_err(|e| eyre!("invalid rebate share {share:?}: {e}"))?;
+            eyre::ensure!(share <= 10_000, "rebate share {share} bps for {protocol} is above the whole fee");
+            Ok((protocol.to_string(), share))
+        })
+        .collect()
+}
+
+/// Avalanche protocols the bot builds adapters for. A protocol's canonical name is its `Display` form, the
+/// same name the DEX registry and metrics key it by.
+pub const SUPPORTED_PROTOCOLS: [Protocol; 4] = [
+    Protocol::TraderJoe,
+    Protocol::Pangolin,
+    Protocol::SushiSwap,
+    Protocol::Platypus,
+];
+
+/// Supported protocol named `name`, matched case-insensitively against canonical names.
+pub fn parse_protocol(name: &str) -> Result<Protocol> {
+    let name = name.trim();
+    SUPPORTED_PROTOCOLS
+        .into_iter()
+        .find(|protocol| protocol.to_string().eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            let valid = SUPPORTED_PROTOCOLS.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ");
+            eyre!("unknown protocol {name:?}, expected one of: {valid}")
+        })
+}
+
+/// Parse protocol names, e.g. from `--protocols` / `PROTOCOLS`.
+pub fn parse_protocols<S: AsRef<str>>(names: &[S]) -> Result<Vec<Protocol>> {
+    names.iter().map(|name| parse_protocol(name.as_ref())).collect()
+}
+
+/// `#[serde(with = "crate::utils::config::protocol_serde")]` for a `Protocol` field stored by canonical name.
+pub mod protocol_serde {
+    use dex_indexer::types::Protocol;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(protocol: &Protocol, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(protocol)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Protocol, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        super::parse_protocol(&name).map_err(|e| de::Error::custom(format!("{e:#}")))
+    }
+}
+
+/// Whether a built-in adapter's pools are searched: those enabled by `--protocols`, or every supported
+/// protocol when it is empty. Protocols with a registered `DexFactory` are decided by `DexRegistry::is_enabled`.
+pub fn is_protocol_enabled(protocol: &Protocol) -> bool {
+    match tunables().enabled_protocols.as_ref() {
+        Some(enabled) => enabled.contains(&protocol.to_string()),
+        None => SUPPORTED_PROTOCOLS.contains(protocol),
+    }
+}
+
+pub fn check_reserves() -> bool {
+    tunables().check_reserves
+}
+
+pub fn trust_nonpositive_gas_cost() -> bool {
+    tunables().trust_nonpositive_gas_cost
+}
+
+pub fn rank_pools_by_output() -> bool {
+    tunables().rank_pools_by_output
+}
+
+pub fn log_decisions() -> bool {
+    tunables().log_decisions
+}
+
+pub fn own_funds() -> bool {
+    tunables().own_funds
+}
+
+pub const DEFAULT_FLASHLOAN_PREFERENCE_BPS: u32 = 10;
+
+pub fn flashloan_preference_bps() -> u32 {
+    tunables().flashloan_preference_bps
+}
+
+pub const DEFAULT_SWAP_SLIPPAGE_BPS: u32 = 50;
+
+pub fn swap_slippage_bps() -> u32 {
+    tunables().swap_slippage_bps
+}
+
+/// How long a swap tx that sets its own deadline stays valid after it is built.
+pub const SWAP_DEADLINE: Duration = Duration::from_secs(120);
+
+/// `quote` less `--swap-slippage-bps`: the minimum out of a swap tx that sets its own.
+pub fn min_amount_out(quote: U256) -> U256 {
+    quote * U256::from(10_000 - swap_slippage_bps()) / U256::from(10_000)
+}
+
+/// Deadline of a swap tx built now, as a unix timestamp `SWAP_DEADLINE` ahead.
+pub fn swap_deadline() -> Result<U256> {
+    let deadline = SystemTime::now().duration_since(UNIX_EPOCH)? + SWAP_DEADLINE;
+    Ok(U256::from(deadline.as_secs()))
+}
+
+pub fn min_spread_bps() -> Option<u32> {
+    tunables().min_spread_bps
+}
+
+pub fn direct_pair_spread_bps() -> Option<u32> {
+    tunables().direct_pair_spread_bps
+}
+
+pub fn max_paths_evaluated() -> Option<usize> {
+    tunables().max_paths_evaluated
+}
+
+pub fn delist_after_failures() -> usize {
+    tunables().delist_after_failures
+}
+
+/// Anvil forks that may run at once across all fork simulators; 0 is unlimited. Read once, when the first
+/// fork simulator starts.
+static MAX_ANVILS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn max_anvils() -> Option<usize> {
+    Some(MAX_ANVILS.load(Ordering::Relaxed)).filter(|max| *max > 0)
+}
+
+pub fn set_max_anvils(max: Option<usize>) {
+    MAX_ANVILS.store(max.unwrap_or_default(), Ordering::Relaxed);
+}
+
+pub const DEFAULT_ANVIL_MIN_FREE_MB: usize = 2048;
+
+/// Available host memory, in MB, below which no new anvil fork is spawned; 0 skips the check.
+static ANVIL_MIN_FREE_MB: AtomicUsize = AtomicUsize::new(DEFAULT_ANVIL_MIN_FREE_MB);
+
+pub fn anvil_min_free_mb() -> usize {
+    ANVIL_MIN_FREE_MB.load(Ordering::Relaxed)
+}
+
+pub fn set_anvil_min_free_mb(mb: usize) {
+    ANVIL_MIN_FREE_MB.store(mb, Ordering::Relaxed);
+}
+
+pub const DEFAULT_MIN_DISTINCT_PROTOCOLS: usize = 2;
+
+pub fn min_distinct_protocols() -> usize {
+    tunables().min_distinct_protocols
+}
+
+pub fn route_search_budget() -> Option<Duration> {
+    tunables().route_search_budget
+}
+
+pub fn arb_cooldown(success: bool) -> Option<Duration> {
+    let tunables = tunables();
+    if success {
+        tunables.arb_cooldown_success
+    } else {
+        tunables.arb_cooldown_failure
+    }
+}
+
+/// Builder payment owed on an arb making `profit` (raw profit-token units, net of gas), in the same units.
+pub fn builder_payment(profit: U256) -> U256 {
+    let tunables = tunables();
+    let share = profit * U256::from(tunables.builder_payment_bps) / U256::from(10_000);
+    let fixed_wei = tunables.builder_payment_wei.min(i64::MAX as u64) as i64;
+    share.saturating_add(U256::from(profit_token().gas_cost_in_token(fixed_wei).max(0) as u128))
+}
+
+pub fn use_access_list() -> bool {
+    tunables().use_access_list
+}
+
+/// Master switch against on-chain writes: while set, every executor refuses to sign or send and only logs
+/// what it would have done. Checked right before each sign/send, so no executor configuration can bypass it.
+static SAFE_MODE: AtomicBool = AtomicBool::new(false);
+
+pub fn safe_mode() -> bool {
+    SAFE_MODE.load(Ordering::Relaxed)
+}
+
+pub fn set_safe_mode(enabled: bool) {
+    SAFE_MODE.store(enabled, Ordering::Relaxed);
+}
+
+/// A sign/send refused because safe mode is on.
+#[derive(Debug)]
+pub struct WritesDisabled(pub String);
+
+impl std::fmt::Display for WritesDisabled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "safe mode: refused to {}", self.0)
+    }
+}
+
+impl std::error::Error for WritesDisabled {}
+
+/// Fails with `WritesDisabled` (after logging `what`) while safe mode is on.
+pub fn ensure_writes_enabled(what: &str) -> Result<()> {
+    if safe_mode() {
+        tracing::warn!("safe mode: not going to {what}");
+        return Err(WritesDisabled(what.to_string()).into());
+    }
+    Ok(())
+}
+
+/// Turns a `WritesDisabled` failure into `Ok(None)`, so executors end a refused action as a log-only
+/// outcome instead of an error.
+pub fn skip_if_writes_disabled<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(error) if error.chain().any(|e| e.is::<WritesDisabled>()) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Trade sizes probed before refining when none are configured, in raw units of the token traded in: a decade
+/// apart from 0.01 to 10M AVAX, so both shallow and deep pools get a probe near their optimum.
+pub fn default_probe_amounts() -> Vec<U256> {
+    (16..26).map(U256::exp10).collect()
+}
+
+/// Narrower grid for AVAX-sized trades, 0.1, 0.5, 1 and 5 AVAX: fewer simulations, but it misses the optimum
+/// of pools much shallower or deeper than that. Opt in with `--narrow-probe-amounts`.
+pub const NARROW_PROBE_AMOUNTS: [u64; 4] = [
+    100_000_000_000_000_000,
+    500_000_000_000_000_000,
+    1_000_000_000_000_000_000,
+    5_000_000_000_000_000_000,
+];
+
+/// Probe grid for `token`, ascending: its own override if configured, the global grid otherwise.
+pub fn probe_amounts(token: &str) -> Vec<U256> {
+    let tunables = tunables();
+    tunables
+        .token_probe_amounts
+        .get(&token.to_lowercase())
+        .unwrap_or(&tunables.probe_amounts)
+        .clone()
+}
+
+fn sorted_probe_amounts(mut amounts: Vec<U256>) -> Vec<U256> {
+    amounts.retain(|amount| !amount.is_zero());
+    amounts.sort_unstable();
+    amounts.dedup();
+    amounts
+}
+
+/// Parse a token amount in raw units, written in decimal. `U256::from_str` would read it as hex.
+pub fn parse_amount(amount: &str) -> Result<U256> {
+    let amount = amount.trim();
+    U256::from_dec_str(amount).map_err(|e| eyre!("invalid amount {amount:?}: {e}"))
+}
+
+/// Parse `<token>:<amount>/<amount>/...` entries (raw units), e.g. from `--token-probe-amounts` /
+/// `TOKEN_PROBE_AMOUNTS`.
+pub fn parse_token_probe_amounts<S: AsRef<str>>(entries: &[S]) -> Result<HashMap<String, Vec<U256>>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let entry = entry.as_ref().trim();
+            let (token, amounts) = entry.split_once(':').ok_or_else(|| {
+                eyre!("invalid token probe amounts {entry:?}, expected <token>:<amount>/<amount>/...")
+            })?;
+            let token = token.trim().parse::<Address>().map_err(|e| eyre!("invalid token {token:?}: {e}"))?;
+            let amounts = amounts
+                .split('/')
+                .map(|amount| match parse_amount(amount)? {
+                    amount if amount.is_zero() => Err(eyre!("probe amount for {token:?} must be positive")),
+                    amount => Ok(amount),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok((format!("{token:?}"), amounts))
+        })
+        .collect()
 }
 
 #[cfg(test)]
 pub mod tests {
+    use super::*;
 
     pub const TEST_HTTP_URL: &str = "";
     pub const TEST_ATTACKER: &str = "";
+
+    #[test]
+    fn test_skip_if_writes_disabled() {
+        let refused: Result<u64> = Err(eyre::Report::new(WritesDisabled("send tx".to_string())).wrap_err("execute arb"));
+        assert_eq!(skip_if_writes_disabled(refused).unwrap(), None);
+        assert_eq!(skip_if_writes_disabled(Ok(1)).unwrap(), Some(1));
+        assert!(skip_if_writes_disabled::<u64>(Err(eyre!("nonce too low"))).is_err());
+    }
+
+    #[test]
+    fn test_pegged_usd_value() {
+        // USDC.e, 6 decimals, mixed-case address
+        let usdc = "0xA7D7079b0FEaD91F3e65f86E8915Cb59c1a4C664";
+        assert_eq!(pegged_usd_value(usdc, U256::from(2_500_000), 6), Some(2.5));
+
+        // WAVAX is not pegged
+        let wavax = "0xB31f66AA3C1e785363F0875A1B74E27b85FD66c7";
+        assert_eq!(pegged_usd_value(wavax, U256::from(1_000_000), 18), None);
+    }
+
+    #[test]
+    fn test_gas_cost_in_profit_token() {
+        // WAVAX: gas is already denominated in the profit token
+        assert_eq!(ProfitToken::default().gas_cost_in_token(1_000_000), 1_000_000);
+
+        // 0.01 AVAX of gas at 25 USDC/AVAX is 0.25 USDC
+        let usdc = ProfitToken::new("0xB97EF9Ef8734C71904D8002F8b6Bc66Dd9c48a6E", 6, 25.0);
+        assert!(usdc.is_profit_token("0xb97ef9ef8734c71904d8002f8b6bc66dd9c48a6e"));
+        assert_eq!(usdc.gas_cost_in_token(10_000_000_000_000_000), 250_000);
+    }
+
+    #[test]
+    fn test_token_in_gas_wei() {
+        assert_eq!(ProfitToken::default().token_in_gas_wei(U256::from(1_000_000)), U256::from(1_000_000));
+
+        // 8 USDC at 32 USDC/AVAX is 0.25 AVAX
+        let usdc = ProfitToken::new("0xB97EF9Ef8734C71904D8002F8b6Bc66Dd9c48a6E", 6, 32.0);
+        assert_eq!(usdc.token_in_gas_wei(U256::from(8_000_000)), U256::exp10(16) * 25);
+    }
+
+    #[test]
+    fn test_gas_cost_in_custom_gas_token() {
+        // subnet whose fee currency is its own token, scored in WAVAX at 0.5 AVAX per gas token
+        let subnet_gas = "0x1111111111111111111111111111111111111111";
+        let wavax = ProfitToken::new(DEFAULT_WAVAX_ADDRESS, 18, 0.5);
+        assert_eq!(wavax.convert_gas_cost(subnet_gas, 1_000_000), 500_000);
+        assert_eq!(ProfitToken::new(subnet_gas, 18, 1.0).convert_gas_cost(subnet_gas, 1_000_000), 1_000_000);
+
+        // gas paid in WAVAX itself needs no conversion
+        assert_eq!(wavax.convert_gas_cost(DEFAULT_WAVAX_ADDRESS, 1_000_000), 1_000_000);
+    }
+
+    #[test]
+    fn test_parse_pool_fee_overrides() {
+        let overrides =
+            parse_pool_fee_overrides(&["0x0000000000000000000000000000000000000001:25"]).unwrap();
+        assert_eq!(overrides.get(&Address::from_low_u64_be(1)), Some(&25));
+
+        assert!(parse_pool_fee_overrides(&["0x0000000000000000000000000000000000000001"]).is_err());
+        assert!(parse_pool_fee_overrides(&["0x0000000000000000000000000000000000000001:10000"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_route_overrides() {
+        let overrides = parse_route_overrides(&[
+            "0xB97EF9Ef8734C71904D8002F8b6Bc66Dd9c48a6E:0x0000000000000000000000000000000000000001/\
+             0x0000000000000000000000000000000000000002",
+        ])
+        .unwrap();
+        assert_eq!(
+            overrides.get("0xb97ef9ef8734c71904d8002f8b6bc66dd9c48a6e"),
+            Some(&vec![Address::from_low_u64_be(1), Address::from_low_u64_be(2)])
+        );
+
+        // a single pool can't bring the token back
+        assert!(parse_route_overrides(&[
+            "0xb97ef9ef8734c71904d8002f8b6bc66dd9c48a6e:0x0000000000000000000000000000000000000001"
+        ])
+        .is_err());
+        assert!(parse_route_overrides(&["0x0000000000000000000000000000000000000001"]).is_err());
+    }
+
+    #[test]
+    fn test_protocol_names_round_trip() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Entry {
+            #[serde(with = "protocol_serde")]
+            protocol: Protocol,
+        }
+
+        for protocol in SUPPORTED_PROTOCOLS {
+            let name = protocol.to_string();
+            assert_eq!(parse_protocol(&name).unwrap().to_string(), name);
+            assert_eq!(parse_protocol(&name.to_uppercase()).unwrap().to_string(), name);
+
+            let json = serde_json::to_string(&Entry { protocol }).unwrap();
+            assert_eq!(json, format!(r#"{{"protocol":"{name}"}}"#));
+            assert_eq!(serde_json::from_str::<Entry>(&json).unwrap().protocol.to_string(), name);
+        }
+
+        // Sui protocols the indexer knows about aren't tradable here
+        assert!(parse_protocol(&Protocol::Cetus.to_string()).is_err());
+
+        let error = parse_protocol("UniswapV4").unwrap_err().to_string();
+        assert!(error.contains("UniswapV4") && error.contains(&Protocol::TraderJoe.to_string()), "{error}");
+        assert!(serde_json::from_str::<Entry>(r#"{"protocol":"UniswapV4"}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_flashloan_premiums() {
+        let premiums = parse_flashloan_premiums(&["aave:5", &format!("{}:30", Protocol::TraderJoe)]).unwrap();
+        assert_eq!(premiums.get("aave"), Some(&5));
+
+        assert!(parse_flashloan_premiums(&["aave"]).is_err());
+        assert!(parse_flashloan_premiums(&["aave:10000"]).is_err());
+        assert!(parse_flashloan_premiums(&["UniswapV4:5"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_referral_rebates() {
+        let rebates = parse_referral_rebates(&[format!("{}:2000", Protocol::Pangolin)]).unwrap();
+        assert_eq!(rebates.get(&Protocol::Pangolin.to_string()), Some(&2000));
+
+        assert!(parse_referral_rebates(&["Pangolin"]).is_err());
+        assert!(parse_referral_rebates(&[format!("{}:10001", Protocol::Pangolin)]).is_err());
+        assert!(parse_referral_rebates(&["UniswapV4:2000"]).is_err());
+    }
+
+    #[test]
+    fn test_token_probe_amounts() {
+        let token = "0x00000000000000000000000000000000000000Aa";
+        // 50 WAVAX does not fit in a u64
+        let overrides = parse_token_probe_amounts(&[format!("{token}:500/100/50000000000000000000/500")]).unwrap();
+        update_tunables(|tunables| tunables.set_token_probe_amounts(overrides));
+
+        // sorted and deduplicated, looked up case-insensitively
+        assert_eq!(probe_amounts(&token.to_uppercase().replace("0X", "0x")), vec![
+            U256::from(100),
+            U256::from(500),
+            U256::exp10(18) * 50
+        ]);
+        assert_eq!(
+            probe_amounts("0x00000000000000000000000000000000000000bb"),
+            default_probe_amounts()
+        );
+
+        assert!(parse_token_probe_amounts(&[format!("{token}:0")]).is_err());
+        assert!(parse_token_probe_amounts(&[format!("{token}:0x10")]).is_err());
+        assert!(parse_token_probe_amounts(&[token]).is_err());
+    }
 }
@@ -0,0 +1,78 @@
+use ethers::types::U256;
+use eyre::{eyre, Result};
+
+/// `value` as `i128`, or an error if it does not fit. Unlike `value.as_u128() as i128`, this never
+/// panics above `u128::MAX` and never wraps into a negative number above `i128::MAX`.
+pub fn u256_to_i128_checked(value: U256) -> Result<i128> {
+    if value > U256::from(i128::MAX as u128) {
+        return Err(eyre!("{value} does not fit in i128"));
+    }
+
+    Ok(value.as_u128() as i128)
+}
+
+/// Signed difference `a - b`, or an error if it does not fit in `i128`.
+pub fn i128_from_delta(a: U256, b: U256) -> Result<i128> {
+    if a >= b {
+        u256_to_i128_checked(a - b)
+    } else {
+        // i128::MIN has no positive counterpart, so the most negative accepted delta is -i128::MAX
+        u256_to_i128_checked(b - a).map(|delta| -delta)
+    }
+}
+
+/// `value` as `f64`, rounding to the nearest representable value. Unlike `value.as_u128() as f64`, this
+/// never panics above `u128::MAX`. For display and price ratios only, never for amounts sent on-chain.
+pub fn u256_to_f64(value: U256) -> f64 {
+    value.0.iter().rev().fold(0.0, |acc, &limb| acc * 2f64.powi(64) + limb as f64)
+}
+
+/// Nearest-rank `percentile` (0-100) of ascending `sorted`, or `None` if it is empty.
+pub fn nearest_rank<T: Copy>(sorted: &[T], percentile: usize) -> Option<T> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (sorted.len() * percentile.min(100)).div_ceil(100).max(1);
+    Some(sorted[rank - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_u256_to_i128_checked() {
+        assert_eq!(u256_to_i128_checked(U256::from(42)).unwrap(), 42);
+        assert_eq!(u256_to_i128_checked(U256::from(i128::MAX as u128)).unwrap(), i128::MAX);
+        assert!(u256_to_i128_checked(U256::from(i128::MAX as u128) + 1).is_err());
+        assert!(u256_to_i128_checked(U256::MAX).is_err());
+    }
+
+    #[test]
+    fn test_i128_from_delta() {
+        assert_eq!(i128_from_delta(U256::from(10), U256::from(3)).unwrap(), 7);
+        assert_eq!(i128_from_delta(U256::from(3), U256::from(10)).unwrap(), -7);
+        assert_eq!(i128_from_delta(U256::zero(), U256::from(i128::MAX as u128)).unwrap(), -i128::MAX);
+        assert!(i128_from_delta(U256::zero(), U256::MAX).is_err());
+    }
+
+    #[test]
+    fn test_u256_to_f64() {
+        assert_eq!(u256_to_f64(U256::zero()), 0.0);
+        assert_eq!(u256_to_f64(U256::from(u64::MAX) + 1), 2f64.powi(64));
+        // 20 WAVAX, beyond u64::MAX wei
+        assert_eq!(u256_to_f64(U256::exp10(18) * 20), 20e18);
+        assert_eq!(u256_to_f64(U256::MAX), 2f64.powi(256));
+    }
+
+    #[test]
+    fn test_nearest_rank() {
+        let sorted: Vec<u64> = (1..=20).collect();
+        assert_eq!(nearest_rank(&sorted, 50), Some(10));
+        assert_eq!(nearest_rank(&sorted, 95), Some(19));
+        assert_eq!(nearest_rank(&sorted, 100), Some(20));
+        assert_eq!(nearest_rank(&sorted, 0), Some(1));
+        assert_eq!(nearest_rank(&[7], 95), Some(7));
+        assert_eq!(nearest_rank::<u64>(&[], 50), None);
+    }
+}
@@ -1,7 +1,12 @@
 pub mod coin;
+pub mod config;
 pub mod heartbeat;
 pub mod link;
+pub mod math;
 pub mod object;
+pub mod sampling;
+pub mod secret;
+pub mod seen_cache;
 pub mod telegram;
 pub mod token_config;
 
@@ -0,0 +1,48 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Samples 1-in-N events for verbose logging. Events that turn out to be actionable should be logged
+/// regardless; this only thins out the long tail.
+pub struct LogSampler {
+    every: u64,
+    seen: AtomicU64,
+}
+
+impl LogSampler {
+    /// `every` <= 1 samples everything.
+    pub fn new(every: u64) -> Self {
+        Self {
+            every: every.max(1),
+            seen: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether this event should be logged in full.
+    pub fn sample(&self) -> bool {
+        self.seen.fetch_add(1, Ordering::Relaxed) % self.every == 0
+    }
+
+    /// Events seen so far, sampled or not.
+    pub fn seen(&self) -> u64 {
+        self.seen.load(Ordering::Relaxed)
+    }
+
+    pub fn every(&self) -> u64 {
+        self.every
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_one_in_n() {
+        let sampler = LogSampler::new(3);
+        let sampled: Vec<bool> = (0..6).map(|_| sampler.sample()).collect();
+        assert_eq!(sampled, vec![true, false, false, true, false, false]);
+        assert_eq!(sampler.seen(), 6);
+
+        let all = LogSampler::new(0);
+        assert!((0..3).all(|_| all.sample()));
+    }
+}
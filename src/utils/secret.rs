@@ -0,0 +1,39 @@
+use std::{convert::Infallible, fmt, str::FromStr};
+
+/// A command line secret (private key, key source, HMAC secret). Its `Debug` prints `[REDACTED]`, so it can
+/// sit in args that get dumped to the logs; `expose` is for the places that actually use the value.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl FromStr for Secret {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_redacts() {
+        let keys: Vec<Secret> = vec!["0xdeadbeef".parse().unwrap(), "env:SIGNER_KEY".parse().unwrap()];
+        let dump = format!("{keys:?} {:#?}", Some(keys[0].clone()));
+        assert!(!dump.contains("deadbeef") && !dump.contains("SIGNER_KEY"));
+        assert_eq!(keys[0].expose(), "0xdeadbeef");
+    }
+}
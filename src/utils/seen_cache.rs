@@ -0,0 +1,92 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+/// Bounded set of recently seen keys. Entries are forgotten after `ttl`, or oldest-first once `capacity`
+/// is reached, so duplicates are only caught within a short window.
+pub struct SeenCache<K> {
+    ttl: Duration,
+    capacity: usize,
+    keys: HashSet<K>,
+    order: VecDeque<(K, Instant)>,
+}
+
+impl<K: Hash + Eq + Clone> SeenCache<K> {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity: capacity.max(1),
+            keys: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Record `key`. Returns `false` if it was already seen within the window.
+    pub fn insert(&mut self, key: K) -> bool {
+        self.insert_at(key, Instant::now())
+    }
+
+    fn insert_at(&mut self, key: K, now: Instant) -> bool {
+        while let Some((oldest, seen_at)) = self.order.front() {
+            if now.saturating_duration_since(*seen_at) < self.ttl && self.order.len() < self.capacity {
+                break;
+            }
+            self.keys.remove(oldest);
+            self.order.pop_front();
+        }
+
+        if !self.keys.insert(key.clone()) {
+            return false;
+        }
+        self.order.push_back((key, now));
+        true
+    }
+
+    /// Forget every key `f` rejects, so it is accepted again before the window runs out.
+    pub fn retain(&mut self, mut f: impl FnMut(&K) -> bool) {
+        self.order.retain(|(key, _)| f(key));
+        self.keys.retain(|key| f(key));
+    }
+
+    /// Keys still in the window, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &K> {
+        self.order.iter().map(|(key, _)| key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_within_window() {
+        let mut cache = SeenCache::new(Duration::from_secs(10), 2);
+        let start = Instant::now();
+
+        assert!(cache.insert_at(1, start));
+        assert!(!cache.insert_at(1, start + Duration::from_secs(1)));
+
+        // expired
+        assert!(cache.insert_at(1, start + Duration::from_secs(11)));
+
+        // capacity evicts the oldest
+        assert!(cache.insert_at(2, start + Duration::from_secs(12)));
+        assert!(cache.insert_at(3, start + Duration::from_secs(12)));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.insert_at(1, start + Duration::from_secs(12)));
+
+        cache.retain(|key| *key != 3);
+        assert_eq!(cache.iter().copied().collect::<Vec<_>>(), [1]);
+        assert!(cache.insert_at(3, start + Duration::from_secs(13)));
+    }
+}
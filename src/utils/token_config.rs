@@ -35,6 +35,33 @@ impl TokenConfig {
             }
         );
         
+        tokens.insert(
+            "0xb97ef9ef8734c71904d8002f8b6bc66dd9c48a6e".to_lowercase(),
+            TokenInfo {
+                symbol: "USDC".to_string(),
+                decimals: 6,
+                address: "0xb97ef9ef8734c71904d8002f8b6bc66dd9c48a6e".to_string(),
+            }
+        );
+
+        tokens.insert(
+            "0x9702230a8ea53601f5cd2dc00fdbc13d4df4a8c7".to_lowercase(),
+            TokenInfo {
+                symbol: "USDT".to_string(),
+                decimals: 6,
+                address: "0x9702230a8ea53601f5cd2dc00fdbc13d4df4a8c7".to_string(),
+            }
+        );
+
+        tokens.insert(
+            "0xd586e7f844cea2f87f50152665bcbc2c279d8d70".to_lowercase(),
+            TokenInfo {
+                symbol: "DAI.e".to_string(),
+                decimals: 18,
+                address: "0xd586e7f844cea2f87f50152665bcbc2c279d8d70".to_string(),
+            }
+        );
+        
         tokens.insert(
             "0xb31f66aa3c1e785363f0875a1b74e27b85fd66c7".to_lowercase(),
             TokenInfo {
@@ -101,11 +128,8 @@ impl TokenConfig {
         erc20_selectors.insert([0x23, 0xb8, 0x72, 0xdd], "transferFrom".to_string());
         erc20_selectors.insert([0x09, 0x5e, 0xa7, 0xb3], "approve".to_string());
 
-        // DEX函数签名
+        // DEX函数签名（swap 调用由 dex::DecodedSwap 解析）
         let mut dex_selectors = HashMap::new();
-        dex_selectors.insert([0x38, 0xed, 0x17, 0x39], "swapExactTokensForTokens".to_string());
-        dex_selectors.insert([0x8a, 0x03, 0xb2, 0xd4], "swapExactTokensForETH".to_string());
-        dex_selectors.insert([0x7f, 0xf3, 0x6a, 0xb5], "swapExactETHForTokens".to_string());
         dex_selectors.insert([0xe8, 0xe3, 0x37, 0x00], "addLiquidity".to_string());
 
         Self {